@@ -0,0 +1,34 @@
+mod common;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+fn construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    group.sample_size(10);
+
+    for edges in common::SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(edges), &edges, |b, &edges| {
+            b.iter(|| common::build(edges))
+        });
+    }
+
+    group.finish();
+}
+
+fn stats(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stats");
+    group.sample_size(10);
+
+    for edges in common::SIZES {
+        let graph = common::build(edges);
+
+        group.bench_with_input(BenchmarkId::from_parameter(edges), &graph, |b, graph| {
+            b.iter(|| graph.stats())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, construction, stats);
+criterion_main!(benches);
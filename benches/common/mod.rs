@@ -0,0 +1,77 @@
+// Each bench uses a different part of this module
+#![allow(dead_code)]
+
+use node_graph::{Graph, InitialPorts, Node};
+
+#[derive(Debug, Clone, Copy)]
+pub enum BenchNode {
+    Source,
+    Sum,
+}
+
+impl Node for BenchNode {
+    type DataType = ();
+    type DataValue = f32;
+
+    fn initial_ports(&self) -> InitialPorts<Self> {
+        match self {
+            Self::Source => InitialPorts {
+                outputs: vec![("value", ())],
+                ..Default::default()
+            },
+            Self::Sum => InitialPorts {
+                inputs: vec![("a", (), 0.0), ("b", (), 0.0)],
+                outputs: vec![("sum", ())],
+            },
+        }
+    }
+}
+
+/// A graph with `edges` connections, where every node sums the outputs of
+/// the two nodes before it
+pub fn build(edges: usize) -> Graph<BenchNode> {
+    let nodes = edges / 2 + 2;
+
+    let mut graph = Graph::with_capacity(nodes, edges);
+
+    let mut ids = vec![
+        graph.create_node(BenchNode::Source),
+        graph.create_node(BenchNode::Source),
+    ];
+
+    for index in 2..nodes {
+        let node = graph.create_node(BenchNode::Sum);
+
+        graph.connect(ids[index - 2].output(0), node.input(0));
+        graph.connect(ids[index - 1].output(0), node.input(1));
+
+        ids.push(node);
+    }
+
+    graph
+}
+
+/// A graph with `edges` connections, where every node sums the node before
+/// it and a new source. Every node has a single dependant, so the graph is a
+/// tree rooted at the last node.
+pub fn build_tree(edges: usize) -> Graph<BenchNode> {
+    let sums = edges / 2;
+
+    let mut graph = Graph::with_capacity(sums * 2 + 1, edges);
+
+    let mut last = graph.create_node(BenchNode::Source);
+
+    for _ in 0..sums {
+        let source = graph.create_node(BenchNode::Source);
+        let node = graph.create_node(BenchNode::Sum);
+
+        graph.connect(last.output(0), node.input(0));
+        graph.connect(source.output(0), node.input(1));
+
+        last = node;
+    }
+
+    graph
+}
+
+pub const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
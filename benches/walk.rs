@@ -0,0 +1,51 @@
+mod common;
+
+use common::BenchNode;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use node_graph::{
+    analyzer::GraphAnalyzer,
+    walker::{GraphWalkContext, GraphWalker},
+};
+
+fn evaluate(node: &mut BenchNode, ctx: &mut GraphWalkContext<BenchNode>) {
+    match node {
+        BenchNode::Source => ctx.set(0, 1.0),
+        BenchNode::Sum => {
+            let sum = ctx.get(0) + ctx.get(1);
+            ctx.set(0, sum);
+        }
+    }
+}
+
+fn walk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("walk");
+    group.sample_size(10);
+
+    for edges in common::SIZES {
+        let graph = common::build_tree(edges);
+
+        group.bench_with_input(BenchmarkId::from_parameter(edges), &graph, |b, graph| {
+            b.iter(|| GraphWalker::new(graph, None).walk(evaluate))
+        });
+    }
+
+    group.finish();
+}
+
+fn execution_path(c: &mut Criterion) {
+    let mut group = c.benchmark_group("execution_path");
+    group.sample_size(10);
+
+    for edges in common::SIZES {
+        let graph = common::build_tree(edges);
+
+        group.bench_with_input(BenchmarkId::from_parameter(edges), &graph, |b, graph| {
+            b.iter(|| GraphAnalyzer::new(graph).generate_complete_execution_path())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, walk, execution_path);
+criterion_main!(benches);
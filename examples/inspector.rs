@@ -0,0 +1,137 @@
+//! Browse a graph in the terminal and step through a walk:
+//!
+//! ```text
+//! cargo run --example inspector --features tui,serde -- graph.json
+//! ```
+//!
+//! Without a path, a small demo graph is inspected. Pressing `s` after
+//! opening the demo shows each node's values as it is evaluated.
+
+use node_graph::{
+    Graph, InitialPorts, Node,
+    document::{DocumentNode, GraphDocument},
+    inspector::Inspector,
+    metadata::MetaValue,
+    migration::Migrations,
+    registry::{NodeRegistry, RegistryArgs},
+    walker::GraphWalkContext,
+};
+
+#[derive(Debug, Clone, Copy)]
+enum MyNode {
+    Constant(f32),
+    Add,
+    Multiply,
+    Print,
+}
+
+impl MyNode {
+    fn evaluate(&mut self, context: &mut GraphWalkContext<Self>) {
+        match self {
+            Self::Constant(value) => context.set(0, *value),
+            Self::Add => context.set(0, context.get(0) + context.get(1)),
+            Self::Multiply => context.set(0, context.get(0) * context.get(1)),
+            // Printing would draw over the inspector
+            Self::Print => {}
+        }
+    }
+}
+
+impl Node for MyNode {
+    type DataType = ();
+    type DataValue = f32;
+
+    fn initial_ports(&self) -> InitialPorts<Self> {
+        match self {
+            Self::Constant(_) => InitialPorts {
+                outputs: vec![("value", ())],
+                ..Default::default()
+            },
+            Self::Add | Self::Multiply => InitialPorts {
+                inputs: vec![("a", (), 0.0), ("b", (), 0.0)],
+                outputs: vec![("result", ())],
+            },
+            Self::Print => InitialPorts {
+                inputs: vec![("value", (), 0.0)],
+                ..Default::default()
+            },
+        }
+    }
+
+    fn variant_name(&self) -> &'static str {
+        match self {
+            Self::Constant(_) => "Constant",
+            Self::Add => "Add",
+            Self::Multiply => "Multiply",
+            Self::Print => "Print",
+        }
+    }
+}
+
+impl DocumentNode for MyNode {
+    fn kind(&self) -> &str {
+        self.variant_name()
+    }
+
+    fn args(&self) -> RegistryArgs {
+        let mut args = RegistryArgs::new();
+
+        if let Self::Constant(value) = self {
+            args.set("value", *value as f64);
+        }
+
+        args
+    }
+}
+
+fn registry() -> NodeRegistry<MyNode> {
+    let mut registry = NodeRegistry::new();
+
+    registry.register("Constant", |args| {
+        MyNode::Constant(
+            args.get("value")
+                .and_then(MetaValue::as_float)
+                .unwrap_or_default() as f32,
+        )
+    });
+    registry.register("Add", |_| MyNode::Add);
+    registry.register("Multiply", |_| MyNode::Multiply);
+    registry.register("Print", |_| MyNode::Print);
+
+    registry
+}
+
+fn demo() -> Graph<MyNode> {
+    let mut graph = Graph::new();
+
+    let constant_1 = graph.create_node(MyNode::Constant(5.0));
+    let constant_2 = graph.create_node(MyNode::Constant(7.0));
+    let constant_3 = graph.create_node(MyNode::Constant(2.0));
+    let multiply = graph.create_node(MyNode::Multiply);
+    let add = graph.create_node(MyNode::Add);
+    let print = graph.create_node(MyNode::Print);
+
+    graph.connect(constant_1.output(0), multiply.input("a"));
+    graph.connect(constant_2.output(0), multiply.input("b"));
+    graph.connect(multiply.output(0), add.input("a"));
+    graph.connect(constant_3.output(0), add.input("b"));
+    graph.connect(add.output(0), print.input(0));
+
+    graph
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let graph = match std::env::args().nth(1) {
+        Some(path) => {
+            let document: GraphDocument<(), f32> =
+                serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+            Graph::from_document(document, &registry(), &Migrations::new(0))?
+        }
+        None => demo(),
+    };
+
+    Inspector::new(&graph, MyNode::evaluate).run()?;
+
+    Ok(())
+}
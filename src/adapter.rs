@@ -0,0 +1,152 @@
+use std::fmt::Debug;
+
+use crate::{
+    ConnectError, ConnectionId, DataType, Graph, Node, NodeId,
+    reference::{InputPortReference, OutputPortReference},
+};
+
+type AdapterFactory<N> = Box<dyn Fn() -> N + Send + Sync>;
+
+/// Maps pairs of data types to nodes that convert between them, see
+/// [`Graph::connect_with_adapters`]
+pub struct AdapterRegistry<N: Node> {
+    adapters: Vec<(N::DataType, N::DataType, AdapterFactory<N>)>,
+}
+
+impl<N: Node> AdapterRegistry<N> {
+    pub fn new() -> Self {
+        Self {
+            adapters: Vec::new(),
+        }
+    }
+
+    /// Register a node that converts `from` into `to`. The node's first input
+    /// port receives the value to convert, and its first output port provides
+    /// the converted value.
+    pub fn register(
+        &mut self,
+        from: N::DataType,
+        to: N::DataType,
+        factory: impl Fn() -> N + Send + Sync + 'static,
+    ) {
+        self.adapters.retain(|(f, t, _)| *f != from || *t != to);
+        self.adapters.push((from, to, Box::new(factory)));
+    }
+
+    pub fn unregister(&mut self, from: N::DataType, to: N::DataType) {
+        self.adapters.retain(|(f, t, _)| *f != from || *t != to);
+    }
+
+    /// Find an adapter for converting `from` into `to`. Adapters registered for
+    /// exactly these types are preferred over ones that need implicit
+    /// conversions on either side.
+    pub fn find(
+        &self,
+        from: N::DataType,
+        to: N::DataType,
+    ) -> Option<&(dyn Fn() -> N + Send + Sync)> {
+        self.adapters
+            .iter()
+            .find(|(f, t, _)| *f == from && *t == to)
+            .or_else(|| {
+                self.adapters
+                    .iter()
+                    .find(|(f, t, _)| from.can_convert_to(*f) && t.can_convert_to(to))
+            })
+            .map(|(_, _, factory)| factory.as_ref())
+    }
+}
+
+impl<N: Node> Default for AdapterRegistry<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Node> Debug for AdapterRegistry<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.adapters.iter().map(|(from, to, _)| (from, to)))
+            .finish()
+    }
+}
+
+/// The result of [`Graph::connect_with_adapters`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdaptedConnection {
+    /// The ports were compatible and connected directly
+    Direct(ConnectionId),
+    /// An adapter node was inserted between the ports
+    Adapted {
+        node: NodeId,
+        input: ConnectionId,
+        output: ConnectionId,
+    },
+}
+
+impl<N: Node> Graph<N> {
+    pub fn adapters(&self) -> &AdapterRegistry<N> {
+        &self.adapters
+    }
+
+    pub fn adapters_mut(&mut self) -> &mut AdapterRegistry<N> {
+        &mut self.adapters
+    }
+
+    /// Connect two ports, inserting an adapter node from the
+    /// [registry](Self::adapters_mut) if their types are not convertable
+    pub fn connect_with_adapters(
+        &mut self,
+        start_port: impl OutputPortReference,
+        end_port: impl InputPortReference,
+    ) -> Result<AdaptedConnection, ConnectError<N::DataType>> {
+        let start_port = start_port
+            .resolve(self)
+            .ok_or(ConnectError::OutputPortNotFound)?;
+
+        let end_port = end_port
+            .resolve(self)
+            .ok_or(ConnectError::InputPortNotFound)?;
+
+        let (from, to) = match self.check_connection(start_port, end_port) {
+            Ok(()) => {
+                return Ok(AdaptedConnection::Direct(
+                    self.connect(start_port, end_port),
+                ));
+            }
+            Err(ConnectError::TypeMismatch { from, to }) => (from, to),
+            Err(error) => return Err(error),
+        };
+
+        let Some(factory) = self.adapters.find(from, to) else {
+            return Err(ConnectError::TypeMismatch { from, to });
+        };
+
+        let adapter = factory();
+
+        let ports = adapter.initial_ports();
+        let adapter_input = ports.inputs.first().expect("Adapter has no input port").1;
+        let adapter_output = ports.outputs.first().expect("Adapter has no output port").1;
+
+        if !from.can_convert_to(adapter_input) || !adapter_output.can_convert_to(to) {
+            panic!("Adapter ports do not match the types it was registered for");
+        }
+
+        // The adapter is a new node, so it can only create a cycle if connecting
+        // the ports directly would too
+
+        if !self.allow_cycles && self.would_create_cycle(start_port, end_port) {
+            return Err(ConnectError::WouldCreateCycle);
+        }
+
+        let node = self.create_node(adapter);
+        let input = self.connect(start_port, node.input(0));
+        let output = self.connect(node.output(0), end_port);
+
+        Ok(AdaptedConnection::Adapted {
+            node,
+            input,
+            output,
+        })
+    }
+}
@@ -0,0 +1,138 @@
+use std::{collections::HashMap, sync::Arc};
+
+use parking_lot::Mutex;
+
+use crate::{
+    Graph, Node, NodeId,
+    analyzer::{CatagorizedNodes, GraphAnalyzer, ReachabilityIndex},
+};
+
+/// Analyzer results kept by a [`Graph`] until a mutation changes them, see
+/// [`Graph::cached_execution_path`]
+#[derive(Debug, Default)]
+pub(crate) struct AnalysisCache {
+    inner: Mutex<CachedAnalysis>,
+}
+
+#[derive(Debug, Default)]
+struct CachedAnalysis {
+    categorized: Option<CatagorizedNodes>,
+    complete_path: Option<Vec<NodeId>>,
+    /// Execution paths by sorted exit nodes
+    paths: HashMap<Vec<NodeId>, Vec<NodeId>>,
+    reachability: Option<Arc<ReachabilityIndex>>,
+}
+
+impl AnalysisCache {
+    /// A node without connections was added. Execution paths for explicit
+    /// exit nodes can't include it, so they stay valid, and the reachability
+    /// index grows by the node instead of being dropped.
+    pub(crate) fn node_added(&mut self, node: NodeId) {
+        let inner = self.inner.get_mut();
+
+        inner.categorized = None;
+        inner.complete_path = None;
+
+        if let Some(index) = &mut inner.reachability {
+            Arc::make_mut(index).node_added(node);
+        }
+    }
+
+    /// A connection from `start` to `end` was added. The reachability index
+    /// is only kept if `start` already reached `end`, updating it would cost
+    /// more than the connection itself.
+    pub(crate) fn connection_added(&mut self, start: NodeId, end: NodeId) {
+        let inner = self.inner.get_mut();
+
+        let reachability = inner
+            .reachability
+            .take()
+            .filter(|index| index.is_reachable(start, end));
+
+        *inner = CachedAnalysis {
+            reachability,
+            ..Default::default()
+        };
+    }
+
+    /// A connection was removed. The reachability index is only kept if the
+    /// nodes it connected are `still_connected` by another connection.
+    pub(crate) fn connection_removed(&mut self, still_connected: bool) {
+        let inner = self.inner.get_mut();
+
+        let reachability = inner.reachability.take().filter(|_| still_connected);
+
+        *inner = CachedAnalysis {
+            reachability,
+            ..Default::default()
+        };
+    }
+
+    /// The reachability index, if one was computed since it was last dropped
+    pub(crate) fn reachability(&self) -> Option<Arc<ReachabilityIndex>> {
+        self.inner.lock().reachability.clone()
+    }
+
+    /// Connections or nodes were removed, or connections were added
+    pub(crate) fn invalidate(&mut self) {
+        *self.inner.get_mut() = CachedAnalysis::default();
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Like [`GraphAnalyzer::catagorize_nodes`], but only computed again
+    /// after nodes or connections change
+    pub fn cached_categorized_nodes(&self) -> CatagorizedNodes {
+        self.analysis
+            .inner
+            .lock()
+            .categorized
+            .get_or_insert_with(|| GraphAnalyzer::new(self).catagorize_nodes())
+            .clone()
+    }
+
+    /// Like [`GraphAnalyzer::generate_execution_path`], or
+    /// [`generate_complete_execution_path`](GraphAnalyzer::generate_complete_execution_path)
+    /// when `exit_nodes` is `None`, but only computed again after nodes or
+    /// connections change
+    pub fn cached_execution_path(&self, exit_nodes: Option<&[NodeId]>) -> Vec<NodeId> {
+        let Some(exit_nodes) = exit_nodes else {
+            if let Some(path) = &self.analysis.inner.lock().complete_path {
+                return path.clone();
+            }
+
+            let exit_nodes = self.cached_categorized_nodes().exit;
+            let path = GraphAnalyzer::new(self).generate_execution_path(&exit_nodes);
+
+            self.analysis.inner.lock().complete_path = Some(path.clone());
+
+            return path;
+        };
+
+        let mut key = exit_nodes.to_vec();
+        key.sort_unstable();
+        key.dedup();
+
+        if let Some(path) = self.analysis.inner.lock().paths.get(&key) {
+            return path.clone();
+        }
+
+        let path = GraphAnalyzer::new(self).generate_execution_path(exit_nodes);
+
+        self.analysis.inner.lock().paths.insert(key, path.clone());
+
+        path
+    }
+
+    /// Like [`GraphAnalyzer::reachability_index`], but only computed again
+    /// after connections change. While the index is cached,
+    /// [`Graph::would_create_cycle`] answers from it.
+    pub fn cached_reachability(&self) -> Arc<ReachabilityIndex> {
+        self.analysis
+            .inner
+            .lock()
+            .reachability
+            .get_or_insert_with(|| Arc::new(GraphAnalyzer::new(self).reachability_index()))
+            .clone()
+    }
+}
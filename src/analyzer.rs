@@ -1,6 +1,10 @@
+use std::collections::HashSet;
+
 use slotmap::SecondaryMap;
 
-use crate::{Graph, INVALID_STATE, Node, NodeId};
+use crate::{
+    ConnectionId, DataType, Graph, INVALID_STATE, InputPortId, Node, NodeId, OutputPortId,
+};
 
 /// This structure is guaranteed to contain the id of each node in the analyzed
 /// graph exactly once.
@@ -18,7 +22,7 @@ pub struct CatagorizedNodes {
 
 #[derive(Debug)]
 pub struct GraphAnalyzer<'a, N: Node> {
-    graph: &'a Graph<N>,
+    pub(crate) graph: &'a Graph<N>,
 }
 
 impl<'a, N: Node> GraphAnalyzer<'a, N> {
@@ -38,21 +42,21 @@ impl<'a, N: Node> GraphAnalyzer<'a, N> {
 
         for (id, node) in self.graph.node_data.iter() {
             let has_incoming_connections = node.inputs.iter().any(|(_, port)| {
-                self.graph
+                !self
+                    .graph
                     .get_input_port_info(*port)
                     .expect(INVALID_STATE)
                     .incoming_connections
-                    .len()
-                    > 0
+                    .is_empty()
             });
 
             let has_outgoing_connections = node.outputs.iter().any(|(_, port)| {
-                self.graph
+                !self
+                    .graph
                     .get_output_port_info(*port)
                     .expect(INVALID_STATE)
                     .outgoing_connections
-                    .len()
-                    > 0
+                    .is_empty()
             });
 
             match (has_incoming_connections, has_outgoing_connections) {
@@ -67,7 +71,8 @@ impl<'a, N: Node> GraphAnalyzer<'a, N> {
     }
 
     /// Returns all (non-loose) node ids in the order that ensures dependencies
-    /// are always processed before dependants
+    /// are always processed before dependants. Reroutes are left out, since
+    /// there is nothing to evaluate.
     pub fn generate_execution_path(&self, exit_nodes: &[NodeId]) -> Vec<NodeId> {
         let mut buffer = SecondaryMap::<NodeId, usize>::with_capacity(self.graph.node_data.len());
 
@@ -90,7 +95,12 @@ impl<'a, N: Node> GraphAnalyzer<'a, N> {
         let mut buffer = buffer.into_iter().collect::<Vec<(NodeId, usize)>>();
         buffer.sort_by_key(|(_, priority)| *priority);
 
-        buffer.iter().rev().map(|(id, _)| *id).collect()
+        buffer
+            .iter()
+            .rev()
+            .map(|(id, _)| *id)
+            .filter(|&id| !self.graph.node_data[id].reroute)
+            .collect()
     }
 
     /// Returns all (non-loose) node ids in the order that ensures dependencies
@@ -98,4 +108,613 @@ impl<'a, N: Node> GraphAnalyzer<'a, N> {
     pub fn generate_complete_execution_path(&self) -> Vec<NodeId> {
         self.generate_execution_path(&self.catagorize_nodes().exit)
     }
+
+    /// Returns the strongly connected components of the graph, each node is
+    /// part of exactly one component. Components with more than one node form
+    /// a cycle. Components are ordered so that dependencies come before their
+    /// dependants.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        // Iterative version of Tarjan's algorithm, following edges from nodes
+        // to their dependencies
+
+        let mut index = SecondaryMap::<NodeId, usize>::new();
+        let mut lowlink = SecondaryMap::<NodeId, usize>::new();
+        let mut on_stack = SecondaryMap::<NodeId, ()>::new();
+        let mut stack = Vec::new();
+        let mut components = Vec::new();
+        let mut next_index = 0;
+
+        for root in self.graph.node_data.keys() {
+            if index.contains_key(root) {
+                continue;
+            }
+
+            let mut call_stack = Vec::<(NodeId, Vec<NodeId>, usize)>::new();
+
+            index.insert(root, next_index);
+            lowlink.insert(root, next_index);
+            next_index += 1;
+            stack.push(root);
+            on_stack.insert(root, ());
+            call_stack.push((root, self.graph.get_direct_dependencies(root).collect(), 0));
+
+            while let Some((node, dependencies, cursor)) = call_stack.last_mut() {
+                let node = *node;
+
+                if let Some(&dependency) = dependencies.get(*cursor) {
+                    *cursor += 1;
+
+                    if !index.contains_key(dependency) {
+                        index.insert(dependency, next_index);
+                        lowlink.insert(dependency, next_index);
+                        next_index += 1;
+                        stack.push(dependency);
+                        on_stack.insert(dependency, ());
+                        call_stack.push((
+                            dependency,
+                            self.graph.get_direct_dependencies(dependency).collect(),
+                            0,
+                        ));
+                    } else if on_stack.contains_key(dependency) {
+                        lowlink[node] = lowlink[node].min(index[dependency]);
+                    }
+
+                    continue;
+                }
+
+                call_stack.pop();
+
+                if let Some(&(parent, ..)) = call_stack.last() {
+                    lowlink[parent] = lowlink[parent].min(lowlink[node]);
+                }
+
+                if lowlink[node] == index[node] {
+                    let mut component = Vec::new();
+
+                    while let Some(member) = stack.pop() {
+                        on_stack.remove(member);
+                        component.push(member);
+
+                        if member == node {
+                            break;
+                        }
+                    }
+
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Returns only the strongly connected components that form a cycle
+    pub fn cycles(&self) -> Vec<Vec<NodeId>> {
+        self.strongly_connected_components()
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .collect()
+    }
+
+    /// Returns all nodes grouped into layers, where the nodes in each layer
+    /// only depend on nodes in earlier layers. The first layer contains all
+    /// nodes without dependencies, including loose nodes.
+    ///
+    /// Nodes that are part of, or depend on, a cycle are not included.
+    pub fn layers(&self) -> Vec<Vec<NodeId>> {
+        let mut remaining =
+            SecondaryMap::<NodeId, usize>::with_capacity(self.graph.node_data.len());
+        let mut current = Vec::new();
+
+        for id in self.graph.node_data.keys() {
+            let count = self.graph.get_direct_dependencies(id).count();
+
+            if count == 0 {
+                current.push(id);
+            } else {
+                remaining.insert(id, count);
+            }
+        }
+
+        let mut layers = Vec::new();
+
+        while !current.is_empty() {
+            let mut next = Vec::new();
+
+            for &id in current.iter() {
+                for dependent in self.graph.get_direct_dependents(id) {
+                    let count = remaining.get_mut(dependent).expect(INVALID_STATE);
+                    *count -= 1;
+
+                    if *count == 0 {
+                        remaining.remove(dependent);
+                        next.push(dependent);
+                    }
+                }
+            }
+
+            layers.push(current);
+            current = next;
+        }
+
+        layers
+    }
+
+    /// Returns the length of the longest chain of dependencies leading up to
+    /// each node. Nodes without dependencies have a depth of 0.
+    ///
+    /// Nodes that are part of, or depend on, a cycle are not included.
+    pub fn depths(&self) -> SecondaryMap<NodeId, usize> {
+        let mut depths = SecondaryMap::with_capacity(self.graph.node_data.len());
+
+        for (depth, layer) in self.layers().into_iter().enumerate() {
+            for id in layer {
+                depths.insert(id, depth);
+            }
+        }
+
+        depths
+    }
+
+    /// Returns the length of the longest chain of dependencies leading up to
+    /// `node`, or `None` if the node is part of, or depends on, a cycle
+    pub fn depth(&self, node: NodeId) -> Option<usize> {
+        self.depths().get(node).copied()
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.layers().len().saturating_sub(1)
+    }
+
+    /// Returns the longest chain of nodes ending at `exit`, starting with a node
+    /// without dependencies. Returns an empty path if `exit` is part of, or
+    /// depends on, a cycle.
+    pub fn critical_path(&self, exit: NodeId) -> Vec<NodeId> {
+        self.critical_path_weighted(exit, |_, _| 1.0)
+    }
+
+    /// Like [`critical_path`](Self::critical_path), but the length of a chain
+    /// is the sum of `cost` over its nodes instead of the number of nodes
+    pub fn critical_path_weighted(
+        &self,
+        exit: NodeId,
+        cost: impl Fn(NodeId, &N) -> f64,
+    ) -> Vec<NodeId> {
+        let mut totals = SecondaryMap::<NodeId, (f64, Option<NodeId>)>::new();
+
+        for id in self.layers().into_iter().flatten() {
+            let own_cost = cost(id, &self.graph.get_node(id).expect(INVALID_STATE));
+
+            let heaviest = self
+                .graph
+                .get_direct_dependencies(id)
+                .map(|dependency| (dependency, totals[dependency].0))
+                .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            let total = match heaviest {
+                Some((dependency, total)) => (own_cost + total, Some(dependency)),
+                None => (own_cost, None),
+            };
+
+            totals.insert(id, total);
+        }
+
+        let mut path = Vec::new();
+        let mut current = totals.contains_key(exit).then_some(exit);
+
+        while let Some(id) = current {
+            path.push(id);
+            current = totals[id].1;
+        }
+
+        path.reverse();
+        path
+    }
+
+    /// Returns groups of nodes that are connected to each other, ignoring the
+    /// direction of connections. Loose nodes form a group of their own.
+    pub fn connected_components(&self) -> Vec<Vec<NodeId>> {
+        let mut visited = SecondaryMap::<NodeId, ()>::with_capacity(self.graph.node_data.len());
+        let mut components = Vec::new();
+
+        for root in self.graph.node_data.keys() {
+            if visited.insert(root, ()).is_some() {
+                continue;
+            }
+
+            let mut component = Vec::new();
+            let mut stack = vec![root];
+
+            while let Some(top) = stack.pop() {
+                component.push(top);
+
+                for neighbor in self
+                    .graph
+                    .get_direct_dependencies(top)
+                    .chain(self.graph.get_direct_dependents(top))
+                {
+                    if visited.insert(neighbor, ()).is_none() {
+                        stack.push(neighbor);
+                    }
+                }
+            }
+
+            components.push(component);
+        }
+
+        components
+    }
+
+    /// Returns whether `to` can be reached from `from` by following
+    /// connections from outputs to inputs. A node is always reachable from
+    /// itself.
+    pub fn is_reachable(&self, from: NodeId, to: NodeId) -> bool {
+        // A secondary map would allocate up to the highest node index, which
+        // costs more than the search when few nodes are visited
+        let mut visited = HashSet::new();
+        let mut stack = vec![from];
+
+        while let Some(top) = stack.pop() {
+            if top == to {
+                return true;
+            }
+
+            if visited.insert(top) {
+                stack.extend(self.graph.get_direct_dependents(top));
+            }
+        }
+
+        false
+    }
+
+    /// Precompute reachability between all nodes, for when many
+    /// [`is_reachable`](Self::is_reachable) queries are made without changing
+    /// the graph
+    pub fn reachability_index(&self) -> ReachabilityIndex {
+        let components = self.strongly_connected_components();
+        let words = components.len().div_ceil(64);
+
+        let mut component_of = SecondaryMap::with_capacity(self.graph.node_data.len());
+
+        for (index, component) in components.iter().enumerate() {
+            for &id in component {
+                component_of.insert(id, index);
+            }
+        }
+
+        // Components are ordered dependencies first, so walking them in
+        // reverse guarantees all dependents have been visited already
+
+        let mut reachable = vec![Vec::new(); components.len()];
+
+        for (index, component) in components.iter().enumerate().rev() {
+            let mut bits = vec![0u64; words];
+            bits[index / 64] |= 1 << (index % 64);
+
+            for &id in component {
+                for dependent in self.graph.get_direct_dependents(id) {
+                    let other = component_of[dependent];
+
+                    if other != index {
+                        for (word, other_word) in bits.iter_mut().zip(&reachable[other]) {
+                            *word |= other_word;
+                        }
+                    }
+                }
+            }
+
+            reachable[index] = bits;
+        }
+
+        ReachabilityIndex {
+            component_of,
+            reachable,
+        }
+    }
+
+    /// Resolve the types of [generic](DataType::is_generic) ports by
+    /// propagating concrete types across connections. Generic ports connected
+    /// to each other, directly or through a node, share a single type, which
+    /// is pinned by the first concrete port connected to any of them.
+    pub fn infer_types(&self) -> InferredTypes<N::DataType> {
+        let graph = self.graph;
+
+        // Union-find over nodes, where each set of nodes shares the type of
+        // their generic ports
+
+        let mut parent = SecondaryMap::<NodeId, NodeId>::with_capacity(graph.node_data.len());
+
+        for id in graph.node_data.keys() {
+            parent.insert(id, id);
+        }
+
+        fn find(parent: &mut SecondaryMap<NodeId, NodeId>, mut id: NodeId) -> NodeId {
+            while parent[id] != id {
+                let grandparent = parent[parent[id]];
+                parent[id] = grandparent;
+                id = grandparent;
+            }
+
+            id
+        }
+
+        for connection in graph.connections.values() {
+            let start = &graph.output_ports[connection.start_port];
+            let end = &graph.input_ports[connection.end_port];
+
+            if start.ty.is_generic() && end.ty.is_generic() {
+                let a = find(&mut parent, start.node);
+                let b = find(&mut parent, end.node);
+                parent[a] = b;
+            }
+        }
+
+        let mut pinned = SecondaryMap::<NodeId, N::DataType>::new();
+
+        for connection in graph.connections.values() {
+            let start = &graph.output_ports[connection.start_port];
+            let end = &graph.input_ports[connection.end_port];
+
+            let (node, ty) = match (start.ty.is_generic(), end.ty.is_generic()) {
+                (true, false) => (start.node, end.ty),
+                (false, true) => (end.node, start.ty),
+                _ => continue,
+            };
+
+            let root = find(&mut parent, node);
+
+            if !pinned.contains_key(root) {
+                pinned.insert(root, ty);
+            }
+        }
+
+        let mut resolve = |node: NodeId, ty: N::DataType| {
+            if ty.is_generic() {
+                pinned.get(find(&mut parent, node)).copied()
+            } else {
+                Some(ty)
+            }
+        };
+
+        let mut inferred = InferredTypes {
+            inputs: SecondaryMap::with_capacity(graph.input_ports.len()),
+            outputs: SecondaryMap::with_capacity(graph.output_ports.len()),
+            conflicts: Vec::new(),
+        };
+
+        for (id, port) in graph.input_ports.iter() {
+            if let Some(ty) = resolve(port.node, port.ty) {
+                inferred.inputs.insert(id, ty);
+            }
+        }
+
+        for (id, port) in graph.output_ports.iter() {
+            if let Some(ty) = resolve(port.node, port.ty) {
+                inferred.outputs.insert(id, ty);
+            }
+        }
+
+        for (id, connection) in graph.connections.iter() {
+            if let (Some(&from), Some(&to)) = (
+                inferred.outputs.get(connection.start_port),
+                inferred.inputs.get(connection.end_port),
+            ) && !from.can_convert_to(to)
+            {
+                inferred.conflicts.push(TypeConflict {
+                    connection: id,
+                    from,
+                    to,
+                });
+            }
+        }
+
+        inferred
+    }
+}
+
+/// The result of [`GraphAnalyzer::infer_types`]
+#[derive(Debug, Clone)]
+pub struct InferredTypes<T: DataType> {
+    /// The concrete type of every input port, generic ports that could not be
+    /// resolved are left out
+    pub inputs: SecondaryMap<InputPortId, T>,
+    /// The concrete type of every output port, generic ports that could not be
+    /// resolved are left out
+    pub outputs: SecondaryMap<OutputPortId, T>,
+    pub conflicts: Vec<TypeConflict<T>>,
+}
+
+/// A connection whose ports have incompatible types after inference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeConflict<T: DataType> {
+    pub connection: ConnectionId,
+    pub from: T,
+    pub to: T,
+}
+
+/// Precomputed reachability between nodes, see
+/// [`GraphAnalyzer::reachability_index`]. The index is not updated when
+/// connections change.
+#[derive(Debug, Clone)]
+pub struct ReachabilityIndex {
+    component_of: SecondaryMap<NodeId, usize>,
+    reachable: Vec<Vec<u64>>,
+}
+
+impl ReachabilityIndex {
+    /// Returns whether `to` can be reached from `from` by following
+    /// connections from outputs to inputs. Returns false for nodes that did
+    /// not exist when the index was created.
+    pub fn is_reachable(&self, from: NodeId, to: NodeId) -> bool {
+        let (Some(&from), Some(&to)) = (self.component_of.get(from), self.component_of.get(to))
+        else {
+            return false;
+        };
+
+        self.reachable[from][to / 64] & (1 << (to % 64)) != 0
+    }
+
+    /// Add a node without connections, which only reaches itself
+    pub(crate) fn node_added(&mut self, node: NodeId) {
+        let index = self.reachable.len();
+
+        // Rows only grow by a word every 64 components
+        if index.is_multiple_of(64) {
+            for bits in self.reachable.iter_mut() {
+                bits.push(0);
+            }
+        }
+
+        let mut bits = vec![0u64; index / 64 + 1];
+        bits[index / 64] |= 1 << (index % 64);
+
+        self.reachable.push(bits);
+        self.component_of.insert(node, index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ConnectError,
+        test_util::{TestNode, chain, link},
+    };
+
+    fn sorted(mut nodes: Vec<NodeId>) -> Vec<NodeId> {
+        nodes.sort();
+        nodes
+    }
+
+    #[test]
+    fn acyclic_graph_has_single_node_components_in_dependency_order() {
+        let (mut graph, nodes) = chain(4);
+        let extra = graph.create_node(TestNode::Value(1));
+        graph.connect(extra.output(0), nodes[2].input(1));
+
+        let analyzer = GraphAnalyzer::new(&graph);
+        let components = analyzer.strongly_connected_components();
+
+        assert_eq!(components.len(), 5);
+        assert!(components.iter().all(|component| component.len() == 1));
+        assert!(analyzer.cycles().is_empty());
+
+        let position = |node| components.iter().position(|c| c[0] == node).unwrap();
+
+        for pair in nodes.windows(2) {
+            assert!(position(pair[0]) < position(pair[1]));
+        }
+
+        assert!(position(extra) < position(nodes[2]));
+    }
+
+    #[test]
+    fn cycles_are_reported_as_one_component() {
+        let (mut graph, nodes) = chain(5);
+
+        // 1 -> 2 -> 3 -> 1, with 0 feeding into and 4 depending on the cycle
+        link(&mut graph, nodes[3], nodes[1]);
+        let loose = graph.create_node(TestNode::Value(0));
+
+        let analyzer = GraphAnalyzer::new(&graph);
+        let components = analyzer.strongly_connected_components();
+
+        assert_eq!(components.len(), 4);
+        assert_eq!(
+            analyzer
+                .cycles()
+                .into_iter()
+                .map(sorted)
+                .collect::<Vec<_>>(),
+            vec![sorted(nodes[1..4].to_vec())]
+        );
+
+        let position = |node| components.iter().position(|c| c.contains(&node)).unwrap();
+
+        assert!(position(nodes[0]) < position(nodes[1]));
+        assert!(position(nodes[1]) < position(nodes[4]));
+        assert_eq!(position(nodes[1]), position(nodes[3]));
+        assert!(components.iter().any(|c| c == &[loose]));
+
+        // Nodes in or after the cycle can't be layered
+        let layered = analyzer.layers().concat();
+
+        assert!(layered.contains(&nodes[0]));
+        assert!(layered.contains(&loose));
+        assert!(nodes[1..].iter().all(|node| !layered.contains(node)));
+    }
+
+    #[test]
+    fn separate_cycles_are_separate_components() {
+        let (mut graph, nodes) = chain(6);
+
+        link(&mut graph, nodes[1], nodes[0]);
+        link(&mut graph, nodes[5], nodes[3]);
+
+        let cycles = GraphAnalyzer::new(&graph)
+            .cycles()
+            .into_iter()
+            .map(sorted)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            cycles,
+            vec![sorted(nodes[0..2].to_vec()), sorted(nodes[3..6].to_vec())]
+        );
+    }
+
+    #[test]
+    fn connections_closing_a_cycle_are_rejected() {
+        let (mut graph, nodes) = chain(3);
+        graph.set_allow_cycles(false);
+
+        assert!(graph.would_create_cycle(nodes[2].output(0), nodes[0].input(1)));
+        assert!(!graph.would_create_cycle(nodes[0].output(0), nodes[2].input(1)));
+        assert!(matches!(
+            graph.check_connection(nodes[2].output(0), nodes[0].input(1)),
+            Err(ConnectError::WouldCreateCycle)
+        ));
+    }
+
+    #[test]
+    fn reachability_index_matches_search() {
+        // More than 64 components, so rows span several words
+        let (mut graph, nodes) = chain(70);
+        let side = graph.create_node(TestNode::Value(0));
+        graph.connect(side.output(0), nodes[40].input(1));
+        link(&mut graph, nodes[66], nodes[65]);
+
+        let analyzer = GraphAnalyzer::new(&graph);
+        let index = analyzer.reachability_index();
+
+        for &from in nodes.iter().chain([&side]) {
+            for &to in nodes.iter().chain([&side]) {
+                assert_eq!(
+                    index.is_reachable(from, to),
+                    analyzer.is_reachable(from, to),
+                    "{from:?} -> {to:?}"
+                );
+            }
+        }
+
+        assert!(index.is_reachable(side, nodes[69]));
+        assert!(!index.is_reachable(side, nodes[39]));
+        assert!(index.is_reachable(nodes[66], nodes[65]));
+    }
+
+    #[test]
+    fn cached_reachability_follows_new_nodes_and_connections() {
+        let (mut graph, nodes) = chain(3);
+        graph.cached_reachability();
+
+        let node = graph.create_node(TestNode::Sum);
+
+        assert!(graph.cached_reachability().is_reachable(node, node));
+        assert!(!graph.cached_reachability().is_reachable(nodes[0], node));
+
+        link(&mut graph, nodes[2], node);
+
+        assert!(graph.cached_reachability().is_reachable(nodes[0], node));
+        assert!(!graph.cached_reachability().is_reachable(node, nodes[0]));
+    }
 }
@@ -0,0 +1,254 @@
+use std::future::Future;
+
+use futures::{StreamExt, stream::FuturesUnordered};
+use slotmap::SecondaryMap;
+
+use crate::{Graph, Node, NodeId, OutputPortId, analyzer::GraphAnalyzer, walker::OutputCache};
+
+/// The inputs of a node evaluated by an [`AsyncGraphWalker`]. Unlike
+/// [`GraphWalkContext`](crate::walker::GraphWalkContext) it owns its values, so
+/// it can be moved into the future evaluating the node.
+#[derive(Debug, Clone)]
+pub struct AsyncNodeContext<V> {
+    node: NodeId,
+    /// The values of every connection to each input port, or its default
+    /// value when it is disconnected
+    inputs: Vec<Vec<V>>,
+    output_count: usize,
+}
+
+impl<V> AsyncNodeContext<V> {
+    /// The id of the node being evaluated
+    pub fn node(&self) -> NodeId {
+        self.node
+    }
+
+    /// The value of the input port at `index`
+    pub fn get(&self, index: usize) -> &V {
+        self.inputs[index]
+            .first()
+            .expect("No default value present for disconnected port")
+    }
+
+    /// The values of every connection to the input port at `index`
+    pub fn get_all(&self, index: usize) -> &[V] {
+        &self.inputs[index]
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.output_count
+    }
+
+    /// Empty outputs for this node, to be returned from its future
+    pub fn outputs(&self) -> NodeOutputs<V> {
+        NodeOutputs(
+            std::iter::repeat_with(|| None)
+                .take(self.output_count)
+                .collect(),
+        )
+    }
+}
+
+/// The values a node produced, by output port index
+#[derive(Debug, Clone)]
+pub struct NodeOutputs<V>(Vec<Option<V>>);
+
+impl<V> NodeOutputs<V> {
+    pub fn set(&mut self, index: usize, value: impl Into<V>) {
+        self.0[index] = Some(value.into());
+    }
+}
+
+/// Walks a graph like [`GraphWalker`](crate::walker::GraphWalker), but
+/// evaluates nodes with futures and runs nodes that don't depend on each other
+/// concurrently.
+///
+/// Nodes that aren't [pure](Node::is_pure) still run one at a time in path
+/// order, so their effects happen in the same order as with a regular walker.
+#[derive(Debug)]
+pub struct AsyncGraphWalker<'a, N: Node> {
+    graph: &'a Graph<N>,
+    path: Vec<NodeId>,
+    output_cache: OutputCache<N::DataValue>,
+    concurrency_limit: usize,
+}
+
+impl<'a, N: Node> AsyncGraphWalker<'a, N> {
+    /// If `exit_nodes` is left as `None`, exit nodes will automatically be
+    /// calculated
+    pub fn new(graph: &'a Graph<N>, exit_nodes: Option<&[NodeId]>) -> Self {
+        let analyzer = GraphAnalyzer::new(graph);
+
+        Self {
+            graph,
+            path: match exit_nodes {
+                Some(exit_nodes) => analyzer.generate_execution_path(exit_nodes),
+                None => analyzer.generate_complete_execution_path(),
+            },
+            output_cache: SecondaryMap::with_capacity(graph.node_data.len()),
+            concurrency_limit: usize::MAX,
+        }
+    }
+
+    /// The maximum number of nodes evaluated at once, unlimited by default
+    pub fn set_concurrency_limit(&mut self, limit: usize) {
+        assert!(limit > 0, "Concurrency limit must be at least 1");
+
+        self.concurrency_limit = limit;
+    }
+
+    pub fn path(&self) -> &[NodeId] {
+        &self.path
+    }
+
+    /// Evaluate every node on the path. `callback` is called once a node's
+    /// dependencies are done, and the future it returns is polled alongside
+    /// those of other nodes.
+    pub async fn walk<F, Fut>(&mut self, callback: F)
+    where
+        F: Fn(&N, AsyncNodeContext<N::DataValue>) -> Fut,
+        Fut: Future<Output = NodeOutputs<N::DataValue>>,
+    {
+        let graph = self.graph;
+
+        let mut index_of = SecondaryMap::with_capacity(self.path.len());
+
+        for (index, &node) in self.path.iter().enumerate() {
+            index_of.insert(node, index);
+        }
+
+        // Only dependencies earlier on the path count, later ones can only
+        // come from cycles
+
+        let mut waiting_on = vec![0usize; self.path.len()];
+        let mut dependents = vec![Vec::new(); self.path.len()];
+        let mut last_impure = None;
+
+        for (index, &node) in self.path.iter().enumerate() {
+            let mut dependencies = graph
+                .get_direct_dependencies(node)
+                .filter_map(|dependency| index_of.get(dependency).copied())
+                .filter(|&dependency| dependency < index)
+                .collect::<Vec<_>>();
+
+            if !graph.nodes[node].read().is_pure() {
+                dependencies.extend(last_impure.replace(index));
+            }
+
+            dependencies.sort_unstable();
+            dependencies.dedup();
+
+            waiting_on[index] = dependencies.len();
+
+            for dependency in dependencies {
+                dependents[dependency].push(index);
+            }
+        }
+
+        let mut ready = (0..self.path.len())
+            .filter(|&index| waiting_on[index] == 0)
+            .collect::<Vec<_>>();
+        ready.reverse();
+
+        let mut running = FuturesUnordered::new();
+
+        loop {
+            while running.len() < self.concurrency_limit
+                && let Some(index) = ready.pop()
+            {
+                let node = self.path[index];
+                let context = self.context(node);
+
+                #[cfg(feature = "tracing")]
+                let span = crate::walker::node_span(graph, node);
+
+                #[cfg(feature = "tracing")]
+                span.in_scope(|| crate::walker::trace_inputs(graph, node, &context.inputs));
+
+                let future = callback(&graph.nodes[node].read(), context);
+
+                #[cfg(feature = "tracing")]
+                let future = tracing::Instrument::instrument(
+                    async move {
+                        let outputs = future.await;
+                        crate::walker::trace_outputs(graph, node, &outputs.0);
+                        outputs
+                    },
+                    span,
+                );
+
+                running.push(async move { (index, future.await) });
+            }
+
+            let Some((index, outputs)) = running.next().await else {
+                break;
+            };
+
+            let node = self.path[index];
+
+            for (&(_, port), value) in graph.node_data[node].outputs.iter().zip(outputs.0) {
+                if let Some(value) = value {
+                    self.output_cache.insert(port, value);
+                }
+            }
+
+            // Keep path order among nodes that become ready together
+
+            for &dependent in dependents[index].iter().rev() {
+                waiting_on[dependent] -= 1;
+
+                if waiting_on[dependent] == 0 {
+                    ready.push(dependent);
+                }
+            }
+
+            ready.sort_unstable_by(|a, b| b.cmp(a));
+        }
+    }
+
+    fn context(&self, node: NodeId) -> AsyncNodeContext<N::DataValue> {
+        let data = &self.graph.node_data[node];
+
+        let inputs = data
+            .inputs
+            .iter()
+            .map(|&(_, port)| {
+                let values = self
+                    .graph
+                    .get_sources(port)
+                    .filter_map(|port| self.output_cache.get(port))
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                if values.is_empty() {
+                    self.graph.input_ports[port]
+                        .default
+                        .clone()
+                        .into_iter()
+                        .collect()
+                } else {
+                    values
+                }
+            })
+            .collect();
+
+        AsyncNodeContext {
+            node,
+            inputs,
+            output_count: data.outputs.len(),
+        }
+    }
+
+    /// The value computed for an output port
+    pub fn get_output(&self, port: OutputPortId) -> Option<&N::DataValue> {
+        self.output_cache.get(port)
+    }
+
+    pub fn release_cache(self) -> OutputCache<N::DataValue> {
+        self.output_cache
+    }
+}
@@ -0,0 +1,1699 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use itertools::Itertools;
+
+use crate::{
+    ConnectionId, Graph, INVALID_STATE, InputPortId, Node, NodeId, OutputPortId, cell::NodeCell,
+    group::GroupId, is_compatible, metadata::MetaValue, naming::NodeNamePolicy,
+    transaction::TransactionError,
+};
+
+/// A single structural change to a [`Graph`], see [`Graph::apply_batch`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "N: serde::Serialize, N::DataType: serde::Serialize, N::DataValue: serde::Serialize",
+        deserialize = "N: serde::Deserialize<'de>, N::DataType: serde::Deserialize<'de>, N::DataValue: serde::Deserialize<'de>"
+    ))
+)]
+pub enum GraphOp<N: Node> {
+    CreateNode(N),
+    /// Delete a node along with its ports and connections
+    DeleteNode(NodeId),
+    /// Connect two ports, which may have been created earlier in the same
+    /// batch, see [`BatchPort`]
+    Connect {
+        start_port: BatchPort<OutputPortId>,
+        end_port: BatchPort<InputPortId>,
+    },
+    Disconnect(ConnectionId),
+    /// `default` is `None` for an input that has no default value, like the
+    /// input of a reroute node
+    CreateInputPort {
+        node: NodeId,
+        name: String,
+        ty: N::DataType,
+        default: Option<N::DataValue>,
+    },
+    CreateOutputPort {
+        node: NodeId,
+        name: String,
+        ty: N::DataType,
+    },
+    DeleteInputPort(InputPortId),
+    DeleteOutputPort(OutputPortId),
+    SetDefaultValue {
+        port: InputPortId,
+        value: N::DataValue,
+    },
+    /// Change the type of an input port, keeping incompatible connections
+    SetInputPortType {
+        port: InputPortId,
+        ty: N::DataType,
+    },
+    /// Change the type of an output port, keeping incompatible connections
+    SetOutputPortType {
+        port: OutputPortId,
+        ty: N::DataType,
+    },
+    RenameInputPort {
+        port: InputPortId,
+        name: String,
+    },
+    RenameOutputPort {
+        port: OutputPortId,
+        name: String,
+    },
+    /// Move the input port at index `from` of a node to index `to`
+    MoveInputPort {
+        node: NodeId,
+        from: usize,
+        to: usize,
+    },
+    /// Move the output port at index `from` of a node to index `to`
+    MoveOutputPort {
+        node: NodeId,
+        from: usize,
+        to: usize,
+    },
+    /// Reorder the connections to an input port. Only connections that
+    /// existed before the batch can be ordered.
+    SetIncomingOrder {
+        port: InputPortId,
+        order: Vec<ConnectionId>,
+    },
+    SetMaxIncoming {
+        port: InputPortId,
+        max: Option<usize>,
+    },
+    SetMaxOutgoing {
+        port: OutputPortId,
+        max: Option<usize>,
+    },
+    /// Replace the value of a node, keeping its ports and connections
+    SetNode {
+        node: NodeId,
+        value: N,
+    },
+    /// Name a node, or remove its name with `None`
+    SetNodeName {
+        node: NodeId,
+        name: Option<String>,
+    },
+    /// Set a metadata entry of a node, or remove it with `None`
+    SetNodeMeta {
+        node: NodeId,
+        key: String,
+        value: Option<MetaValue>,
+    },
+    /// Create a group, moving `nodes` out of the groups they were in
+    CreateGroup {
+        name: String,
+        nodes: Vec<NodeId>,
+    },
+    RenameGroup {
+        group: GroupId,
+        name: String,
+    },
+    /// Move a node into a group, out of the group it was in before
+    AddToGroup {
+        group: GroupId,
+        node: NodeId,
+    },
+    RemoveFromGroup(NodeId),
+    /// Remove a group, keeping its nodes
+    Ungroup(GroupId),
+}
+
+impl<N: Node> GraphOp<N> {
+    /// Whether the operation can't be reverted in place, like removals,
+    /// which can't be undone without changing ids, and changes to groups
+    fn is_destructive(&self) -> bool {
+        matches!(
+            self,
+            Self::DeleteNode(_)
+                | Self::Disconnect(_)
+                | Self::DeleteInputPort(_)
+                | Self::DeleteOutputPort(_)
+                | Self::CreateGroup { .. }
+                | Self::AddToGroup { .. }
+                | Self::RemoveFromGroup(_)
+                | Self::Ungroup(_)
+        )
+    }
+}
+
+/// A port of a [`GraphOp::Connect`]. Ports created by the same batch don't
+/// have an id before it is applied, so they are named by the operation that
+/// creates them instead.
+///
+/// ```
+/// # use node_graph::{Graph, InitialPorts, Node, batch::{BatchPort, GraphOp}};
+/// # #[derive(Debug)]
+/// # enum MyNode {
+/// #     Constant(f32),
+/// #     Print,
+/// # }
+/// # impl Node for MyNode {
+/// #     type DataType = ();
+/// #     type DataValue = f32;
+/// #     fn initial_ports(&self) -> InitialPorts<Self> {
+/// #         match self {
+/// #             Self::Constant(_) => InitialPorts {
+/// #                 outputs: vec![("value", ())],
+/// #                 ..Default::default()
+/// #             },
+/// #             Self::Print => InitialPorts {
+/// #                 inputs: vec![("value", (), 0.0)],
+/// #                 ..Default::default()
+/// #             },
+/// #         }
+/// #     }
+/// # }
+/// # let mut graph = Graph::new();
+/// graph.apply_batch(vec![
+///     GraphOp::CreateNode(MyNode::Constant(1.0)),
+///     GraphOp::CreateNode(MyNode::Print),
+///     GraphOp::Connect {
+///         start_port: BatchPort::Created { op: 0, index: 0 },
+///         end_port: BatchPort::Created { op: 1, index: 0 },
+///     },
+/// ])?;
+/// # assert_eq!(graph.connection_count(), 1);
+/// # Ok::<(), node_graph::batch::BatchError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BatchPort<T> {
+    /// A port that existed before the batch
+    Id(T),
+    /// The `index`-th initial port of the node created by the
+    /// [`CreateNode`](GraphOp::CreateNode) at index `op` of the batch, or the
+    /// port created by a [`CreateInputPort`](GraphOp::CreateInputPort) or
+    /// [`CreateOutputPort`](GraphOp::CreateOutputPort) there if `index` is 0
+    Created { op: usize, index: usize },
+}
+
+impl<T> From<T> for BatchPort<T> {
+    fn from(id: T) -> Self {
+        Self::Id(id)
+    }
+}
+
+/// The ids created by [`Graph::apply_batch`], in the order of the operations
+/// that created them
+#[derive(Debug, Clone, Default)]
+pub struct BatchResult {
+    pub nodes: Vec<NodeId>,
+    pub connections: Vec<ConnectionId>,
+    pub input_ports: Vec<InputPortId>,
+    pub output_ports: Vec<OutputPortId>,
+    pub groups: Vec<GroupId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchError {
+    /// Index of the first operation that failed validation
+    pub index: usize,
+    pub error: TransactionError,
+}
+
+impl Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Operation {} is invalid: {}", self.index, self.error)
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+impl<N: Node> Graph<N> {
+    /// Apply many operations at once, so either every operation is applied
+    /// or none are. All operations are validated against the graph (and the
+    /// effects of earlier operations in the batch) before anything is
+    /// changed. Cycles are checked once, on the graph as the whole batch
+    /// leaves it, and reported at the first connection that is part of one.
+    ///
+    /// [Connection validators](Self::add_connection_validator) are consulted
+    /// while applying instead, so they see the graph as the earlier
+    /// operations left it, and the batch is undone if one rejects a
+    /// connection. Removals can't be undone without changing ids, so the
+    /// graph's structure is copied up front when validators are registered
+    /// and the batch removes something or changes groups before its last
+    /// connection.
+    ///
+    /// The analysis cache is invalidated and
+    /// [`connections_changed`](Node::connections_changed) is called once for
+    /// the whole batch, and not at all if the batch is rolled back.
+    pub fn apply_batch(&mut self, ops: Vec<GraphOp<N>>) -> Result<BatchResult, BatchError> {
+        self.validate_batch(&ops)?;
+
+        // Only validators can reject an operation from here on, and none are
+        // consulted after the last connection
+        let last_connect = ops
+            .iter()
+            .rposition(|op| matches!(op, GraphOp::Connect { .. }));
+        let structure = (!self.validators.is_empty()
+            && last_connect.is_some_and(|last| ops[..last].iter().any(GraphOp::is_destructive)))
+        .then(|| self.snapshot_structure());
+        let mutation_log_len = self.mutation_log_len();
+
+        let mut result = BatchResult::default();
+        let mut changes = BatchChanges::default();
+        let new_connections = ops
+            .iter()
+            .filter(|op| matches!(op, GraphOp::Connect { .. }))
+            .count();
+
+        self.connections.reserve(new_connections);
+        result.connections.reserve(new_connections);
+        let notifications = self.defer_notifications();
+
+        for (index, op) in ops.into_iter().enumerate() {
+            if let Err(error) = self.apply_op(index, op, &mut result, &mut changes) {
+                match structure {
+                    Some(structure) => {
+                        self.restore_structure(structure);
+
+                        for node in changes.created_nodes {
+                            self.nodes.remove(node);
+                        }
+
+                        for (id, node) in changes.deleted_nodes {
+                            self.nodes.insert(id, NodeCell::new(node));
+                        }
+
+                        // Node values aren't part of the structure
+                        for entry in changes.undo.into_iter().rev() {
+                            if let Undo::SetNode { node, value } = entry {
+                                *self.nodes[node].get_mut() = value;
+                            }
+                        }
+                    }
+                    None => self.undo(changes.undo),
+                }
+
+                self.truncate_mutation_log(mutation_log_len);
+                self.resume_notifications(notifications, false);
+                self.analysis.invalidate();
+                self.after_mutation();
+
+                return Err(BatchError { index, error });
+            }
+        }
+
+        self.analysis.invalidate();
+        self.resume_notifications(notifications, true);
+        self.after_mutation();
+
+        Ok(result)
+    }
+
+    fn apply_op(
+        &mut self,
+        index: usize,
+        op: GraphOp<N>,
+        result: &mut BatchResult,
+        changes: &mut BatchChanges<N>,
+    ) -> Result<(), TransactionError> {
+        match op {
+            GraphOp::CreateNode(node) => {
+                let id = self.create_node(node);
+                let data = &self.node_data[id];
+
+                changes.created.insert(
+                    index,
+                    CreatedIds::Node {
+                        inputs: data.inputs.iter().map(|&(_, port)| port).collect(),
+                        outputs: data.outputs.iter().map(|&(_, port)| port).collect(),
+                    },
+                );
+                changes.undo.push(Undo::CreateNode(id));
+                result.nodes.push(id);
+                changes.created_nodes.push(id);
+            }
+            GraphOp::DeleteNode(node) => {
+                // Only nodes that existed before the batch have an id to
+                // delete them by
+                let value = self.take_node(node).expect(INVALID_STATE);
+                changes.deleted_nodes.push((node, value));
+            }
+            GraphOp::Connect {
+                start_port,
+                end_port,
+            } => {
+                let start_port = changes.output(start_port);
+                let end_port = changes.input(end_port);
+
+                // Validators may look at cached analysis, which is only
+                // invalidated once per batch otherwise
+                if changes.connected > changes.validated {
+                    self.analysis.invalidate();
+                    changes.validated = changes.connected;
+                }
+
+                self.validators.validate(self, start_port, end_port)?;
+
+                let id = self.insert_connection(start_port, end_port);
+
+                changes.undo.push(Undo::Connect(id));
+                changes.connected += 1;
+                result.connections.push(id);
+                self.notify_connections_changed(&[
+                    self.output_ports[start_port].node,
+                    self.input_ports[end_port].node,
+                ]);
+            }
+            GraphOp::Disconnect(connection) => self.disconnect(connection).expect(INVALID_STATE),
+            GraphOp::CreateInputPort {
+                node,
+                name,
+                ty,
+                default,
+            } => {
+                let id = self.create_input_port_inner(node, &name, ty, default);
+
+                changes.created.insert(index, CreatedIds::Input(id));
+                changes.undo.push(Undo::CreateInputPort(id));
+                result.input_ports.push(id);
+            }
+            GraphOp::CreateOutputPort { node, name, ty } => {
+                let id = self.create_output_port(node, &name, ty);
+
+                changes.created.insert(index, CreatedIds::Output(id));
+                changes.undo.push(Undo::CreateOutputPort(id));
+                result.output_ports.push(id);
+            }
+            GraphOp::DeleteInputPort(port) => self.delete_input_port(port).expect(INVALID_STATE),
+            GraphOp::DeleteOutputPort(port) => self.delete_output_port(port).expect(INVALID_STATE),
+            GraphOp::SetDefaultValue { port, value } => {
+                changes.undo.push(Undo::SetDefaultValue {
+                    port,
+                    value: self.input_ports[port].default.clone(),
+                });
+                self.set_default_value(port, value);
+            }
+            GraphOp::SetInputPortType { port, ty } => {
+                changes.undo.push(Undo::SetInputPortType {
+                    port,
+                    ty: self.input_ports[port].ty,
+                });
+                self.set_input_port_type(port, ty, false);
+            }
+            GraphOp::SetOutputPortType { port, ty } => {
+                changes.undo.push(Undo::SetOutputPortType {
+                    port,
+                    ty: self.output_ports[port].ty,
+                });
+                self.set_output_port_type(port, ty, false);
+            }
+            GraphOp::RenameInputPort { port, name } => {
+                changes.undo.push(Undo::RenameInputPort {
+                    port,
+                    name: self.input_ports[port].name.to_string(),
+                });
+                self.rename_input_port(port, &name).expect(INVALID_STATE);
+            }
+            GraphOp::RenameOutputPort { port, name } => {
+                changes.undo.push(Undo::RenameOutputPort {
+                    port,
+                    name: self.output_ports[port].name.to_string(),
+                });
+                self.rename_output_port(port, &name).expect(INVALID_STATE);
+            }
+            GraphOp::MoveInputPort { node, from, to } => {
+                self.move_input_port(node, from, to).expect(INVALID_STATE);
+                changes.undo.push(Undo::MoveInputPort { node, from, to });
+            }
+            GraphOp::MoveOutputPort { node, from, to } => {
+                self.move_output_port(node, from, to).expect(INVALID_STATE);
+                changes.undo.push(Undo::MoveOutputPort { node, from, to });
+            }
+            GraphOp::SetIncomingOrder { port, order } => {
+                changes.undo.push(Undo::SetIncomingOrder {
+                    port,
+                    order: self.input_ports[port].incoming_connections.to_vec(),
+                });
+                self.set_incoming_order(port, &order).expect(INVALID_STATE);
+            }
+            GraphOp::SetMaxIncoming { port, max } => {
+                changes.undo.push(Undo::SetMaxIncoming {
+                    port,
+                    max: self.input_ports[port].max_incoming,
+                });
+                self.set_max_incoming(port, max);
+            }
+            GraphOp::SetMaxOutgoing { port, max } => {
+                changes.undo.push(Undo::SetMaxOutgoing {
+                    port,
+                    max: self.output_ports[port].max_outgoing,
+                });
+                self.set_max_outgoing(port, max);
+            }
+            GraphOp::SetNode { node, value } => {
+                let value = self.set_node(node, value);
+                changes.undo.push(Undo::SetNode { node, value });
+            }
+            GraphOp::SetNodeName { node, name } => {
+                changes.undo.push(Undo::SetNodeName {
+                    node,
+                    name: self.get_node_name(node).map(str::to_string),
+                });
+
+                match name {
+                    Some(name) => {
+                        self.set_node_name(node, &name).expect(INVALID_STATE);
+                    }
+                    None => {
+                        self.clear_node_name(node);
+                    }
+                }
+            }
+            GraphOp::SetNodeMeta { node, key, value } => {
+                let value = match value {
+                    Some(value) => self.set_node_meta(node, &key, value),
+                    None => self.remove_node_meta(node, &key),
+                };
+
+                changes.undo.push(Undo::SetNodeMeta { node, key, value });
+            }
+            GraphOp::CreateGroup { name, nodes } => {
+                result.groups.push(self.create_group(&name, nodes));
+            }
+            GraphOp::RenameGroup { group, name } => {
+                changes.undo.push(Undo::RenameGroup {
+                    group,
+                    name: self.groups[group].name().to_string(),
+                });
+                self.rename_group(group, &name);
+            }
+            GraphOp::AddToGroup { group, node } => self.add_to_group(group, node),
+            GraphOp::RemoveFromGroup(node) => {
+                self.remove_from_group(node);
+            }
+            GraphOp::Ungroup(group) => {
+                self.ungroup(group).expect(INVALID_STATE);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Revert the operations of a batch that didn't remove anything, latest
+    /// first
+    fn undo(&mut self, undo: Vec<Undo<N>>) {
+        for entry in undo.into_iter().rev() {
+            match entry {
+                Undo::CreateNode(node) => {
+                    self.take_node(node).expect(INVALID_STATE);
+                }
+                Undo::Connect(connection) => self.disconnect(connection).expect(INVALID_STATE),
+                Undo::CreateInputPort(port) => self.delete_input_port(port).expect(INVALID_STATE),
+                Undo::CreateOutputPort(port) => self.delete_output_port(port).expect(INVALID_STATE),
+                Undo::SetDefaultValue { port, value } => match value {
+                    Some(value) => self.set_default_value(port, value),
+                    None => self.input_ports[port].default = None,
+                },
+                Undo::SetInputPortType { port, ty } => {
+                    self.set_input_port_type(port, ty, false);
+                }
+                Undo::SetOutputPortType { port, ty } => {
+                    self.set_output_port_type(port, ty, false);
+                }
+                Undo::RenameInputPort { port, name } => {
+                    self.rename_input_port(port, &name).expect(INVALID_STATE);
+                }
+                Undo::RenameOutputPort { port, name } => {
+                    self.rename_output_port(port, &name).expect(INVALID_STATE);
+                }
+                Undo::MoveInputPort { node, from, to } => {
+                    self.move_input_port(node, to, from).expect(INVALID_STATE);
+                }
+                Undo::MoveOutputPort { node, from, to } => {
+                    self.move_output_port(node, to, from).expect(INVALID_STATE);
+                }
+                Undo::SetIncomingOrder { port, order } => {
+                    self.set_incoming_order(port, &order).expect(INVALID_STATE);
+                }
+                Undo::SetMaxIncoming { port, max } => self.set_max_incoming(port, max),
+                Undo::SetMaxOutgoing { port, max } => self.set_max_outgoing(port, max),
+                Undo::SetNode { node, value } => {
+                    self.set_node(node, value);
+                }
+                Undo::SetNodeName { node, name } => self.restore_node_name(node, name),
+                Undo::SetNodeMeta { node, key, value } => {
+                    match value {
+                        Some(value) => self.set_node_meta(node, &key, value),
+                        None => self.remove_node_meta(node, &key),
+                    };
+                }
+                Undo::RenameGroup { group, name } => self.rename_group(group, &name),
+            }
+        }
+    }
+
+    fn validate_batch(&self, ops: &[GraphOp<N>]) -> Result<(), BatchError> {
+        let mut batch = BatchValidation::new(self);
+
+        for (index, op) in ops.iter().enumerate() {
+            batch
+                .validate(index, op)
+                .map_err(|error| BatchError { index, error })?;
+        }
+
+        if !self.allow_cycles
+            && let Some(index) = batch.first_cycle()
+        {
+            return Err(BatchError {
+                index,
+                error: TransactionError::WouldCreateCycle,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// What [`Graph::apply_batch`] changed so far, to roll back or resolve
+/// [`BatchPort::Created`] with
+struct BatchChanges<N: Node> {
+    /// Ids created by each operation, by the operation's index
+    created: HashMap<usize, CreatedIds>,
+    /// How to revert every operation applied so far, which is enough when
+    /// nothing was removed
+    undo: Vec<Undo<N>>,
+    /// Nodes created by the batch
+    created_nodes: Vec<NodeId>,
+    /// Nodes that existed before the batch, with their values
+    deleted_nodes: Vec<(NodeId, N)>,
+    /// The number of connections made
+    connected: usize,
+    /// The value of `connected` when the analysis cache was last invalidated
+    validated: usize,
+}
+
+impl<N: Node> Default for BatchChanges<N> {
+    fn default() -> Self {
+        Self {
+            created: HashMap::new(),
+            undo: Vec::new(),
+            created_nodes: Vec::new(),
+            deleted_nodes: Vec::new(),
+            connected: 0,
+            validated: 0,
+        }
+    }
+}
+
+impl<N: Node> BatchChanges<N> {
+    fn input(&self, port: BatchPort<InputPortId>) -> InputPortId {
+        match port {
+            BatchPort::Id(id) => id,
+            BatchPort::Created { op, index } => match &self.created[&op] {
+                CreatedIds::Node { inputs, .. } => inputs[index],
+                CreatedIds::Input(id) => *id,
+                CreatedIds::Output(_) => unreachable!("{INVALID_STATE}"),
+            },
+        }
+    }
+
+    fn output(&self, port: BatchPort<OutputPortId>) -> OutputPortId {
+        match port {
+            BatchPort::Id(id) => id,
+            BatchPort::Created { op, index } => match &self.created[&op] {
+                CreatedIds::Node { outputs, .. } => outputs[index],
+                CreatedIds::Output(id) => *id,
+                CreatedIds::Input(_) => unreachable!("{INVALID_STATE}"),
+            },
+        }
+    }
+}
+
+enum CreatedIds {
+    /// The initial ports of a created node
+    Node {
+        inputs: Vec<InputPortId>,
+        outputs: Vec<OutputPortId>,
+    },
+    Input(InputPortId),
+    Output(OutputPortId),
+}
+
+/// The inverse of an applied operation
+enum Undo<N: Node> {
+    CreateNode(NodeId),
+    Connect(ConnectionId),
+    CreateInputPort(InputPortId),
+    CreateOutputPort(OutputPortId),
+    SetDefaultValue {
+        port: InputPortId,
+        value: Option<N::DataValue>,
+    },
+    SetInputPortType {
+        port: InputPortId,
+        ty: N::DataType,
+    },
+    SetOutputPortType {
+        port: OutputPortId,
+        ty: N::DataType,
+    },
+    RenameInputPort {
+        port: InputPortId,
+        name: String,
+    },
+    RenameOutputPort {
+        port: OutputPortId,
+        name: String,
+    },
+    /// Moved from `from` to `to`
+    MoveInputPort {
+        node: NodeId,
+        from: usize,
+        to: usize,
+    },
+    MoveOutputPort {
+        node: NodeId,
+        from: usize,
+        to: usize,
+    },
+    SetIncomingOrder {
+        port: InputPortId,
+        order: Vec<ConnectionId>,
+    },
+    SetMaxIncoming {
+        port: InputPortId,
+        max: Option<usize>,
+    },
+    SetMaxOutgoing {
+        port: OutputPortId,
+        max: Option<usize>,
+    },
+    SetNode {
+        node: NodeId,
+        value: N,
+    },
+    SetNodeName {
+        node: NodeId,
+        name: Option<String>,
+    },
+    SetNodeMeta {
+        node: NodeId,
+        key: String,
+        value: Option<MetaValue>,
+    },
+    RenameGroup {
+        group: GroupId,
+        name: String,
+    },
+}
+
+/// A node as seen while validating a batch, which may not exist yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BatchNode {
+    Id(NodeId),
+    /// Created by the operation at this index
+    Created(usize),
+}
+
+/// A connection made by the batch being validated
+struct NewEdge {
+    op: usize,
+    start_port: BatchPort<OutputPortId>,
+    end_port: BatchPort<InputPortId>,
+    start: BatchNode,
+    end: BatchNode,
+    removed: bool,
+}
+
+/// The graph as it will be after the validated part of a batch has been
+/// applied, without changing it
+struct BatchValidation<'a, N: Node> {
+    graph: &'a Graph<N>,
+    deleted_nodes: HashSet<NodeId>,
+    deleted_inputs: HashSet<BatchPort<InputPortId>>,
+    deleted_outputs: HashSet<BatchPort<OutputPortId>>,
+    deleted_connections: HashSet<ConnectionId>,
+    /// Initial port types of created nodes, by the creating operation
+    created_nodes: HashMap<usize, InitialTypes<N::DataType>>,
+    created_inputs: HashMap<usize, (NodeId, N::DataType)>,
+    created_outputs: HashMap<usize, (NodeId, N::DataType)>,
+    /// Ports created on existing nodes, by node
+    node_inputs: HashMap<NodeId, Vec<usize>>,
+    node_outputs: HashMap<NodeId, Vec<usize>>,
+    /// Names of the ports created on existing nodes
+    input_names: HashSet<(NodeId, &'a str)>,
+    output_names: HashSet<(NodeId, &'a str)>,
+    input_renames: HashMap<InputPortId, &'a str>,
+    output_renames: HashMap<OutputPortId, &'a str>,
+    input_types: HashMap<InputPortId, N::DataType>,
+    output_types: HashMap<OutputPortId, N::DataType>,
+    max_incoming: HashMap<InputPortId, Option<usize>>,
+    max_outgoing: HashMap<OutputPortId, Option<usize>>,
+    /// Names given or removed by the batch, and the nodes given each name
+    node_names: HashMap<NodeId, Option<&'a str>>,
+    named: HashMap<&'a str, HashSet<NodeId>>,
+    deleted_groups: HashSet<GroupId>,
+    /// Connection counts of ports that the batch changed
+    incoming: HashMap<BatchPort<InputPortId>, usize>,
+    outgoing: HashMap<BatchPort<OutputPortId>, usize>,
+    edges: Vec<NewEdge>,
+    edges_by_input: HashMap<BatchPort<InputPortId>, Vec<usize>>,
+    edges_by_output: HashMap<BatchPort<OutputPortId>, Vec<usize>>,
+    edges_by_start: HashMap<BatchNode, Vec<usize>>,
+}
+
+struct InitialTypes<T> {
+    inputs: Vec<T>,
+    outputs: Vec<T>,
+}
+
+/// A port as seen while validating a batch
+struct PortState<T> {
+    node: BatchNode,
+    ty: T,
+    max: Option<usize>,
+}
+
+impl<'a, N: Node> BatchValidation<'a, N> {
+    fn new(graph: &'a Graph<N>) -> Self {
+        Self {
+            graph,
+            deleted_nodes: HashSet::new(),
+            deleted_inputs: HashSet::new(),
+            deleted_outputs: HashSet::new(),
+            deleted_connections: HashSet::new(),
+            created_nodes: HashMap::new(),
+            created_inputs: HashMap::new(),
+            created_outputs: HashMap::new(),
+            node_inputs: HashMap::new(),
+            node_outputs: HashMap::new(),
+            input_names: HashSet::new(),
+            output_names: HashSet::new(),
+            input_renames: HashMap::new(),
+            output_renames: HashMap::new(),
+            input_types: HashMap::new(),
+            output_types: HashMap::new(),
+            max_incoming: HashMap::new(),
+            max_outgoing: HashMap::new(),
+            node_names: HashMap::new(),
+            named: HashMap::new(),
+            deleted_groups: HashSet::new(),
+            incoming: HashMap::new(),
+            outgoing: HashMap::new(),
+            edges: Vec::new(),
+            edges_by_input: HashMap::new(),
+            edges_by_output: HashMap::new(),
+            edges_by_start: HashMap::new(),
+        }
+    }
+
+    fn validate(&mut self, index: usize, op: &'a GraphOp<N>) -> Result<(), TransactionError> {
+        match op {
+            GraphOp::CreateNode(node) => {
+                let ports = node.initial_ports();
+
+                self.created_nodes.insert(
+                    index,
+                    InitialTypes {
+                        inputs: ports.inputs.iter().map(|&(_, ty, _)| ty).collect(),
+                        outputs: ports.outputs.iter().map(|&(_, ty)| ty).collect(),
+                    },
+                );
+            }
+            GraphOp::DeleteNode(node) => {
+                if !self.existing_node(*node) {
+                    return Err(TransactionError::NodeNotFound(*node));
+                }
+
+                self.deleted_nodes.insert(*node);
+                self.rename_node(*node, None);
+
+                let graph = self.graph;
+                let data = &graph.node_data[*node];
+                let created_inputs = self.node_inputs.remove(node).unwrap_or_default();
+                let created_outputs = self.node_outputs.remove(node).unwrap_or_default();
+
+                let inputs = data
+                    .inputs
+                    .iter()
+                    .map(|&(_, port)| BatchPort::Id(port))
+                    .chain(
+                        created_inputs
+                            .into_iter()
+                            .map(|op| BatchPort::Created { op, index: 0 }),
+                    );
+
+                for port in inputs.collect::<Vec<_>>() {
+                    self.delete_input(port);
+                }
+
+                let outputs = data
+                    .outputs
+                    .iter()
+                    .map(|&(_, port)| BatchPort::Id(port))
+                    .chain(
+                        created_outputs
+                            .into_iter()
+                            .map(|op| BatchPort::Created { op, index: 0 }),
+                    );
+
+                for port in outputs.collect::<Vec<_>>() {
+                    self.delete_output(port);
+                }
+            }
+            GraphOp::Connect {
+                start_port,
+                end_port,
+            } => {
+                let start = self
+                    .output(*start_port)
+                    .ok_or(TransactionError::OutputPortNotFound)?;
+                let end = self
+                    .input(*end_port)
+                    .ok_or(TransactionError::InputPortNotFound)?;
+
+                if start.node == end.node {
+                    return Err(TransactionError::SameNode);
+                }
+
+                if start
+                    .max
+                    .is_some_and(|max| self.outgoing_count(*start_port) >= max)
+                {
+                    return Err(TransactionError::OutputFull);
+                }
+
+                if end
+                    .max
+                    .is_some_and(|max| self.incoming_count(*end_port) >= max)
+                {
+                    return Err(TransactionError::InputFull);
+                }
+
+                if !is_compatible(start.ty, end.ty) {
+                    return Err(TransactionError::IncompatibleTypes);
+                }
+
+                let edge = self.edges.len();
+
+                *self.outgoing.entry(*start_port).or_insert_with(|| {
+                    existing_outgoing(self.graph, &self.deleted_connections, *start_port)
+                }) += 1;
+                *self.incoming.entry(*end_port).or_insert_with(|| {
+                    existing_incoming(self.graph, &self.deleted_connections, *end_port)
+                }) += 1;
+
+                self.edges_by_output
+                    .entry(*start_port)
+                    .or_default()
+                    .push(edge);
+                self.edges_by_input.entry(*end_port).or_default().push(edge);
+                self.edges_by_start
+                    .entry(start.node)
+                    .or_default()
+                    .push(edge);
+                self.edges.push(NewEdge {
+                    op: index,
+                    start_port: *start_port,
+                    end_port: *end_port,
+                    start: start.node,
+                    end: end.node,
+                    removed: false,
+                });
+            }
+            GraphOp::Disconnect(connection) => {
+                if !self.graph.connections.contains_key(*connection)
+                    || self.deleted_connections.contains(connection)
+                {
+                    return Err(TransactionError::ConnectionNotFound(*connection));
+                }
+
+                self.delete_connection(*connection);
+            }
+            GraphOp::CreateInputPort { node, name, ty, .. } => {
+                if !self.existing_node(*node) {
+                    return Err(TransactionError::NodeNotFound(*node));
+                }
+
+                if self.input_name_taken(*node, name, None) {
+                    return Err(TransactionError::DuplicateInputPort(name.clone()));
+                }
+
+                self.input_names.insert((*node, name));
+
+                self.created_inputs.insert(index, (*node, *ty));
+                self.node_inputs.entry(*node).or_default().push(index);
+            }
+            GraphOp::CreateOutputPort { node, name, ty } => {
+                if !self.existing_node(*node) {
+                    return Err(TransactionError::NodeNotFound(*node));
+                }
+
+                if self.output_name_taken(*node, name, None) {
+                    return Err(TransactionError::DuplicateOutputPort(name.clone()));
+                }
+
+                self.output_names.insert((*node, name));
+
+                self.created_outputs.insert(index, (*node, *ty));
+                self.node_outputs.entry(*node).or_default().push(index);
+            }
+            GraphOp::DeleteInputPort(port) => {
+                if !self.existing_input(*port) {
+                    return Err(TransactionError::InputPortNotFound);
+                }
+
+                self.delete_input(BatchPort::Id(*port));
+            }
+            GraphOp::DeleteOutputPort(port) => {
+                if !self.existing_output(*port) {
+                    return Err(TransactionError::OutputPortNotFound);
+                }
+
+                self.delete_output(BatchPort::Id(*port));
+            }
+            GraphOp::SetDefaultValue { port, .. } => {
+                if !self.existing_input(*port) {
+                    return Err(TransactionError::InputPortNotFound);
+                }
+            }
+            GraphOp::SetInputPortType { port, ty } => {
+                if !self.existing_input(*port) {
+                    return Err(TransactionError::InputPortNotFound);
+                }
+
+                self.input_types.insert(*port, *ty);
+            }
+            GraphOp::SetOutputPortType { port, ty } => {
+                if !self.existing_output(*port) {
+                    return Err(TransactionError::OutputPortNotFound);
+                }
+
+                self.output_types.insert(*port, *ty);
+            }
+            GraphOp::RenameInputPort { port, name } => {
+                if !self.existing_input(*port) {
+                    return Err(TransactionError::InputPortNotFound);
+                }
+
+                let node = self.graph.input_ports[*port].node;
+
+                if self.input_name_taken(node, name, Some(*port)) {
+                    return Err(TransactionError::DuplicateInputPort(name.clone()));
+                }
+
+                self.input_renames.insert(*port, name);
+            }
+            GraphOp::RenameOutputPort { port, name } => {
+                if !self.existing_output(*port) {
+                    return Err(TransactionError::OutputPortNotFound);
+                }
+
+                let node = self.graph.output_ports[*port].node;
+
+                if self.output_name_taken(node, name, Some(*port)) {
+                    return Err(TransactionError::DuplicateOutputPort(name.clone()));
+                }
+
+                self.output_renames.insert(*port, name);
+            }
+            GraphOp::MoveInputPort { node, from, to } => {
+                if !self.existing_node(*node) {
+                    return Err(TransactionError::NodeNotFound(*node));
+                }
+
+                let count = self.graph.node_data[*node]
+                    .inputs
+                    .iter()
+                    .filter(|&&(_, id)| !self.deleted_inputs.contains(&BatchPort::Id(id)))
+                    .count()
+                    + self.node_inputs.get(node).map_or(0, Vec::len);
+
+                if *from >= count || *to >= count {
+                    return Err(TransactionError::InputPortNotFound);
+                }
+            }
+            GraphOp::MoveOutputPort { node, from, to } => {
+                if !self.existing_node(*node) {
+                    return Err(TransactionError::NodeNotFound(*node));
+                }
+
+                let count = self.graph.node_data[*node]
+                    .outputs
+                    .iter()
+                    .filter(|&&(_, id)| !self.deleted_outputs.contains(&BatchPort::Id(id)))
+                    .count()
+                    + self.node_outputs.get(node).map_or(0, Vec::len);
+
+                if *from >= count || *to >= count {
+                    return Err(TransactionError::OutputPortNotFound);
+                }
+            }
+            GraphOp::SetIncomingOrder { port, order } => {
+                if !self.existing_input(*port) {
+                    return Err(TransactionError::InputPortNotFound);
+                }
+
+                let connections = self.graph.input_ports[*port]
+                    .incoming_connections
+                    .iter()
+                    .filter(|connection| !self.deleted_connections.contains(connection))
+                    .collect::<HashSet<_>>();
+                let connected = self
+                    .edges_by_input
+                    .get(&BatchPort::Id(*port))
+                    .is_some_and(|edges| edges.iter().any(|&edge| !self.edges[edge].removed));
+
+                if connected
+                    || order.len() != connections.len()
+                    || !order.iter().all(|id| connections.contains(id))
+                    || !order.iter().all_unique()
+                {
+                    return Err(TransactionError::InvalidConnectionOrder);
+                }
+            }
+            GraphOp::SetMaxIncoming { port, max } => {
+                if !self.existing_input(*port) {
+                    return Err(TransactionError::InputPortNotFound);
+                }
+
+                self.max_incoming.insert(*port, *max);
+            }
+            GraphOp::SetMaxOutgoing { port, max } => {
+                if !self.existing_output(*port) {
+                    return Err(TransactionError::OutputPortNotFound);
+                }
+
+                self.max_outgoing.insert(*port, *max);
+            }
+            GraphOp::SetNode { node, .. } | GraphOp::SetNodeMeta { node, .. } => {
+                if !self.existing_node(*node) {
+                    return Err(TransactionError::NodeNotFound(*node));
+                }
+            }
+            GraphOp::SetNodeName { node, name } => {
+                if !self.existing_node(*node) {
+                    return Err(TransactionError::NodeNotFound(*node));
+                }
+
+                if let Some(name) = name
+                    && self.graph.node_name_policy == NodeNamePolicy::Unique
+                    && let Some(other) = self.name_holder(name, *node)
+                {
+                    return Err(TransactionError::NameTaken(other));
+                }
+
+                self.rename_node(*node, name.as_deref());
+            }
+            GraphOp::CreateGroup { nodes, .. } => {
+                if let Some(&node) = nodes.iter().find(|&&node| !self.existing_node(node)) {
+                    return Err(TransactionError::NodeNotFound(node));
+                }
+            }
+            GraphOp::RenameGroup { group, .. } => {
+                if !self.existing_group(*group) {
+                    return Err(TransactionError::GroupNotFound(*group));
+                }
+            }
+            GraphOp::AddToGroup { group, node } => {
+                if !self.existing_group(*group) {
+                    return Err(TransactionError::GroupNotFound(*group));
+                }
+
+                if !self.existing_node(*node) {
+                    return Err(TransactionError::NodeNotFound(*node));
+                }
+            }
+            GraphOp::RemoveFromGroup(node) => {
+                if !self.existing_node(*node) {
+                    return Err(TransactionError::NodeNotFound(*node));
+                }
+            }
+            GraphOp::Ungroup(group) => {
+                if !self.existing_group(*group) {
+                    return Err(TransactionError::GroupNotFound(*group));
+                }
+
+                self.deleted_groups.insert(*group);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether an input port of `node` other than `except` is named `name`
+    fn input_name_taken(&self, node: NodeId, name: &str, except: Option<InputPortId>) -> bool {
+        self.input_names.contains(&(node, name))
+            || self.graph.node_data[node]
+                .inputs
+                .iter()
+                .any(|(port_name, id)| {
+                    Some(*id) != except
+                        && !self.deleted_inputs.contains(&BatchPort::Id(*id))
+                        && self.input_renames.get(id).copied().unwrap_or(port_name) == name
+                })
+    }
+
+    /// Whether an output port of `node` other than `except` is named `name`
+    fn output_name_taken(&self, node: NodeId, name: &str, except: Option<OutputPortId>) -> bool {
+        self.output_names.contains(&(node, name))
+            || self.graph.node_data[node]
+                .outputs
+                .iter()
+                .any(|(port_name, id)| {
+                    Some(*id) != except
+                        && !self.deleted_outputs.contains(&BatchPort::Id(*id))
+                        && self.output_renames.get(id).copied().unwrap_or(port_name) == name
+                })
+    }
+
+    /// A node other than `node` that will be named `name`
+    fn name_holder(&self, name: &str, node: NodeId) -> Option<NodeId> {
+        let renamed = self.named.get(name).into_iter().flatten().copied();
+        let existing = self
+            .graph
+            .node_names
+            .nodes_named(name)
+            .iter()
+            .copied()
+            .filter(|other| !self.node_names.contains_key(other));
+
+        renamed
+            .chain(existing)
+            .find(|&other| other != node && !self.deleted_nodes.contains(&other))
+    }
+
+    fn rename_node(&mut self, node: NodeId, name: Option<&'a str>) {
+        if let Some(Some(old)) = self.node_names.insert(node, name) {
+            self.named.get_mut(old).expect(INVALID_STATE).remove(&node);
+        }
+
+        if let Some(name) = name {
+            self.named.entry(name).or_default().insert(node);
+        }
+    }
+
+    fn existing_group(&self, group: GroupId) -> bool {
+        self.graph.groups.contains_key(group) && !self.deleted_groups.contains(&group)
+    }
+
+    fn existing_node(&self, node: NodeId) -> bool {
+        self.graph.node_data.contains_key(node) && !self.deleted_nodes.contains(&node)
+    }
+
+    fn existing_input(&self, port: InputPortId) -> bool {
+        self.graph.input_ports.contains_key(port)
+            && !self.deleted_inputs.contains(&BatchPort::Id(port))
+    }
+
+    fn existing_output(&self, port: OutputPortId) -> bool {
+        self.graph.output_ports.contains_key(port)
+            && !self.deleted_outputs.contains(&BatchPort::Id(port))
+    }
+
+    fn input(&self, port: BatchPort<InputPortId>) -> Option<PortState<N::DataType>> {
+        if self.deleted_inputs.contains(&port) {
+            return None;
+        }
+
+        match port {
+            BatchPort::Id(id) => {
+                let info = self.graph.input_ports.get(id)?;
+
+                Some(PortState {
+                    node: BatchNode::Id(info.node),
+                    ty: self.input_types.get(&id).copied().unwrap_or(info.ty),
+                    max: self
+                        .max_incoming
+                        .get(&id)
+                        .copied()
+                        .unwrap_or(info.max_incoming),
+                })
+            }
+            BatchPort::Created { op, index } => {
+                if let Some(InitialTypes { inputs, .. }) = self.created_nodes.get(&op) {
+                    return Some(PortState {
+                        node: BatchNode::Created(op),
+                        ty: *inputs.get(index)?,
+                        max: None,
+                    });
+                }
+
+                let &(node, ty) = self.created_inputs.get(&op).filter(|_| index == 0)?;
+
+                Some(PortState {
+                    node: BatchNode::Id(node),
+                    ty,
+                    max: None,
+                })
+            }
+        }
+    }
+
+    fn output(&self, port: BatchPort<OutputPortId>) -> Option<PortState<N::DataType>> {
+        if self.deleted_outputs.contains(&port) {
+            return None;
+        }
+
+        match port {
+            BatchPort::Id(id) => {
+                let info = self.graph.output_ports.get(id)?;
+
+                Some(PortState {
+                    node: BatchNode::Id(info.node),
+                    ty: self.output_types.get(&id).copied().unwrap_or(info.ty),
+                    max: self
+                        .max_outgoing
+                        .get(&id)
+                        .copied()
+                        .unwrap_or(info.max_outgoing),
+                })
+            }
+            BatchPort::Created { op, index } => {
+                if let Some(InitialTypes { outputs, .. }) = self.created_nodes.get(&op) {
+                    return Some(PortState {
+                        node: BatchNode::Created(op),
+                        ty: *outputs.get(index)?,
+                        max: None,
+                    });
+                }
+
+                let &(node, ty) = self.created_outputs.get(&op).filter(|_| index == 0)?;
+
+                Some(PortState {
+                    node: BatchNode::Id(node),
+                    ty,
+                    max: None,
+                })
+            }
+        }
+    }
+
+    fn incoming_count(&self, port: BatchPort<InputPortId>) -> usize {
+        self.incoming
+            .get(&port)
+            .copied()
+            .unwrap_or_else(|| existing_incoming(self.graph, &self.deleted_connections, port))
+    }
+
+    fn outgoing_count(&self, port: BatchPort<OutputPortId>) -> usize {
+        self.outgoing
+            .get(&port)
+            .copied()
+            .unwrap_or_else(|| existing_outgoing(self.graph, &self.deleted_connections, port))
+    }
+
+    fn delete_input(&mut self, port: BatchPort<InputPortId>) {
+        if !self.deleted_inputs.insert(port) {
+            return;
+        }
+
+        if let BatchPort::Id(id) = port {
+            for &connection in self.graph.input_ports[id].incoming_connections.iter() {
+                self.delete_connection(connection);
+            }
+        }
+
+        for edge in self.edges_by_input.remove(&port).unwrap_or_default() {
+            self.remove_edge(edge);
+        }
+    }
+
+    fn delete_output(&mut self, port: BatchPort<OutputPortId>) {
+        if !self.deleted_outputs.insert(port) {
+            return;
+        }
+
+        if let BatchPort::Id(id) = port {
+            for &connection in self.graph.output_ports[id].outgoing_connections.iter() {
+                self.delete_connection(connection);
+            }
+        }
+
+        for edge in self.edges_by_output.remove(&port).unwrap_or_default() {
+            self.remove_edge(edge);
+        }
+    }
+
+    /// Remove a connection that existed before the batch
+    fn delete_connection(&mut self, connection: ConnectionId) {
+        if !self.deleted_connections.insert(connection) {
+            return;
+        }
+
+        let data = &self.graph.connections[connection];
+        let (start_port, end_port) = (BatchPort::Id(data.start_port), BatchPort::Id(data.end_port));
+
+        // Counts that aren't tracked yet are computed without the connection
+        if let Some(count) = self.outgoing.get_mut(&start_port) {
+            *count -= 1;
+        }
+
+        if let Some(count) = self.incoming.get_mut(&end_port) {
+            *count -= 1;
+        }
+    }
+
+    fn remove_edge(&mut self, edge: usize) {
+        let edge = &mut self.edges[edge];
+
+        if edge.removed {
+            return;
+        }
+
+        edge.removed = true;
+
+        *self
+            .outgoing
+            .get_mut(&edge.start_port)
+            .expect(INVALID_STATE) -= 1;
+        *self.incoming.get_mut(&edge.end_port).expect(INVALID_STATE) -= 1;
+    }
+
+    /// The nodes `node` connects to once the batch is applied
+    fn dependents(&self, node: BatchNode) -> Vec<BatchNode> {
+        let mut dependents = Vec::new();
+
+        if let BatchNode::Id(id) = node {
+            for &(_, port) in self.graph.node_data[id].outputs.iter() {
+                if self.deleted_outputs.contains(&BatchPort::Id(port)) {
+                    continue;
+                }
+
+                for &connection in self.graph.output_ports[port].outgoing_connections.iter() {
+                    if !self.deleted_connections.contains(&connection) {
+                        let end_port = self.graph.connections[connection].end_port;
+                        dependents.push(BatchNode::Id(self.graph.input_ports[end_port].node));
+                    }
+                }
+            }
+        }
+
+        if let Some(edges) = self.edges_by_start.get(&node) {
+            dependents.extend(
+                edges
+                    .iter()
+                    .map(|&edge| &self.edges[edge])
+                    .filter(|edge| !edge.removed)
+                    .map(|edge| edge.end),
+            );
+        }
+
+        dependents
+    }
+
+    /// Returns the operation of the first new connection that is part of a
+    /// cycle once the batch is applied. Any new cycle goes through a new
+    /// connection, so only what those reach has to be sorted.
+    fn first_cycle(&self) -> Option<usize> {
+        let mut adjacent = HashMap::<BatchNode, Vec<BatchNode>>::new();
+        let mut stack = self
+            .edges
+            .iter()
+            .filter(|edge| !edge.removed)
+            .map(|edge| edge.end)
+            .collect::<Vec<_>>();
+
+        while let Some(node) = stack.pop() {
+            if let std::collections::hash_map::Entry::Vacant(entry) = adjacent.entry(node) {
+                let dependents = self.dependents(node);
+                stack.extend(dependents.iter().copied());
+                entry.insert(dependents);
+            }
+        }
+
+        // Kahn's algorithm, which leaves nodes on or after a cycle unsorted
+        let mut in_degree = HashMap::<BatchNode, usize>::with_capacity(adjacent.len());
+
+        for dependents in adjacent.values() {
+            for &dependent in dependents {
+                *in_degree.entry(dependent).or_default() += 1;
+            }
+        }
+
+        let mut ready = adjacent
+            .keys()
+            .copied()
+            .filter(|node| !in_degree.contains_key(node))
+            .collect::<Vec<_>>();
+        let mut sorted = 0;
+
+        while let Some(node) = ready.pop() {
+            sorted += 1;
+
+            for dependent in adjacent[&node].iter() {
+                let degree = in_degree.get_mut(dependent).expect(INVALID_STATE);
+                *degree -= 1;
+
+                if *degree == 0 {
+                    ready.push(*dependent);
+                }
+            }
+        }
+
+        if sorted == adjacent.len() {
+            return None;
+        }
+
+        // Only pay for finding the culprit when there is one. A cycle that
+        // existed before the batch doesn't count.
+        self.edges
+            .iter()
+            .filter(|edge| !edge.removed)
+            .find(|edge| reaches(&adjacent, edge.end, edge.start))
+            .map(|edge| edge.op)
+    }
+}
+
+fn reaches(adjacent: &HashMap<BatchNode, Vec<BatchNode>>, from: BatchNode, to: BatchNode) -> bool {
+    let mut visited = HashSet::new();
+    let mut stack = vec![from];
+
+    while let Some(node) = stack.pop() {
+        if node == to {
+            return true;
+        }
+
+        if visited.insert(node) {
+            stack.extend(adjacent.get(&node).into_iter().flatten().copied());
+        }
+    }
+
+    false
+}
+
+fn existing_incoming<N: Node>(
+    graph: &Graph<N>,
+    deleted: &HashSet<ConnectionId>,
+    port: BatchPort<InputPortId>,
+) -> usize {
+    match port {
+        BatchPort::Id(id) => graph.input_ports[id]
+            .incoming_connections
+            .iter()
+            .filter(|connection| !deleted.contains(connection))
+            .count(),
+        BatchPort::Created { .. } => 0,
+    }
+}
+
+fn existing_outgoing<N: Node>(
+    graph: &Graph<N>,
+    deleted: &HashSet<ConnectionId>,
+    port: BatchPort<OutputPortId>,
+) -> usize {
+    match port {
+        BatchPort::Id(id) => graph.output_ports[id]
+            .outgoing_connections
+            .iter()
+            .filter(|connection| !deleted.contains(connection))
+            .count(),
+        BatchPort::Created { .. } => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TestNode, chain, describe};
+
+    /// A chain of three sums with a value node on the side, whose
+    /// connections are all rejected by a validator
+    fn guarded_graph() -> (Graph<TestNode>, Vec<NodeId>, NodeId) {
+        let (mut graph, nodes) = chain(3);
+        let guarded = graph.create_node(TestNode::Sum);
+
+        graph.set_allow_cycles(false);
+        graph.add_connection_validator(move |graph, _, end| {
+            match graph.get_input_port_info(end).unwrap().node == guarded {
+                true => Err("guarded".to_string()),
+                false => Ok(()),
+            }
+        });
+
+        (graph, nodes, guarded)
+    }
+
+    #[test]
+    fn invalid_batch_changes_nothing() {
+        let (mut graph, nodes) = chain(3);
+        graph.set_allow_cycles(false);
+        graph.enable_mutation_log();
+
+        let before = describe(&graph);
+        let port = graph.get_input_port_at(nodes[1], 0).unwrap();
+        let connection = graph.get_incoming_connection_ids(port).next().unwrap();
+
+        let error = graph
+            .apply_batch(vec![
+                GraphOp::CreateNode(TestNode::Value(1)),
+                GraphOp::Connect {
+                    start_port: BatchPort::Created { op: 0, index: 0 },
+                    end_port: port.into(),
+                },
+                GraphOp::Disconnect(connection),
+                GraphOp::DeleteInputPort(port),
+                GraphOp::Connect {
+                    start_port: BatchPort::Created { op: 0, index: 0 },
+                    end_port: port.into(),
+                },
+            ])
+            .unwrap_err();
+
+        assert_eq!(error.index, 4);
+        assert_eq!(error.error, TransactionError::InputPortNotFound);
+        assert_eq!(describe(&graph), before);
+        assert_eq!(graph.mutation_log().unwrap().len(), 0);
+
+        // Connecting the last node back into the first closes a cycle
+        let error = graph
+            .apply_batch(vec![GraphOp::Connect {
+                start_port: graph.get_output_port_at(nodes[2], 0).unwrap().into(),
+                end_port: graph.get_input_port_at(nodes[0], 1).unwrap().into(),
+            }])
+            .unwrap_err();
+
+        assert_eq!(error.error, TransactionError::WouldCreateCycle);
+        assert_eq!(describe(&graph), before);
+    }
+
+    #[test]
+    fn rejected_connection_undoes_earlier_operations() {
+        let (mut graph, nodes, guarded) = guarded_graph();
+        graph.enable_mutation_log();
+
+        let before = describe(&graph);
+        let port = graph.get_input_port_at(nodes[0], 1).unwrap();
+
+        let error = graph
+            .apply_batch(vec![
+                GraphOp::CreateNode(TestNode::Value(1)),
+                GraphOp::CreateInputPort {
+                    node: nodes[0],
+                    name: "c".to_string(),
+                    ty: (),
+                    default: Some(3),
+                },
+                GraphOp::SetDefaultValue { port, value: 7 },
+                GraphOp::RenameInputPort {
+                    port,
+                    name: "renamed".to_string(),
+                },
+                GraphOp::MoveInputPort {
+                    node: nodes[0],
+                    from: 0,
+                    to: 2,
+                },
+                GraphOp::SetMaxIncoming { port, max: Some(1) },
+                GraphOp::SetNodeName {
+                    node: nodes[1],
+                    name: Some("middle".to_string()),
+                },
+                GraphOp::SetNode {
+                    node: nodes[2],
+                    value: TestNode::Value(2),
+                },
+                GraphOp::Connect {
+                    start_port: BatchPort::Created { op: 0, index: 0 },
+                    end_port: port.into(),
+                },
+                GraphOp::Connect {
+                    start_port: BatchPort::Created { op: 0, index: 0 },
+                    end_port: graph.get_input_port_at(guarded, 0).unwrap().into(),
+                },
+            ])
+            .unwrap_err();
+
+        assert_eq!(error.index, 9);
+        assert_eq!(
+            error.error,
+            TransactionError::Rejected("guarded".to_string())
+        );
+        assert_eq!(describe(&graph), before);
+        assert_eq!(graph.mutation_log().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn rejected_connection_restores_removed_elements() {
+        let (mut graph, nodes, guarded) = guarded_graph();
+
+        let before = describe(&graph);
+        let port = graph.get_input_port_at(nodes[2], 0).unwrap();
+        let connection = graph.get_incoming_connection_ids(port).next().unwrap();
+
+        let error = graph
+            .apply_batch(vec![
+                GraphOp::Disconnect(connection),
+                GraphOp::DeleteNode(nodes[0]),
+                GraphOp::CreateNode(TestNode::Value(1)),
+                GraphOp::Connect {
+                    start_port: BatchPort::Created { op: 2, index: 0 },
+                    end_port: port.into(),
+                },
+                GraphOp::Connect {
+                    start_port: BatchPort::Created { op: 2, index: 0 },
+                    end_port: graph.get_input_port_at(guarded, 1).unwrap().into(),
+                },
+            ])
+            .unwrap_err();
+
+        assert_eq!(error.index, 4);
+        assert_eq!(describe(&graph), before);
+        assert!(graph.get_connection(connection).is_some());
+        graph.debug_assert_consistent();
+    }
+
+    #[test]
+    fn connects_ports_created_in_the_batch() {
+        let (mut graph, nodes, _) = guarded_graph();
+
+        let result = graph
+            .apply_batch(vec![
+                GraphOp::CreateNode(TestNode::Value(1)),
+                GraphOp::CreateInputPort {
+                    node: nodes[2],
+                    name: "c".to_string(),
+                    ty: (),
+                    default: None,
+                },
+                GraphOp::Connect {
+                    start_port: BatchPort::Created { op: 0, index: 0 },
+                    end_port: BatchPort::Created { op: 1, index: 0 },
+                },
+            ])
+            .unwrap();
+
+        let connection = graph.get_connection(result.connections[0]).unwrap();
+
+        assert_eq!(result.nodes.len(), 1);
+        assert_eq!(
+            connection.start_port,
+            graph.get_output_port_at(result.nodes[0], 0).unwrap()
+        );
+        assert_eq!(connection.end_port, result.input_ports[0]);
+    }
+}
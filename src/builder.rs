@@ -0,0 +1,132 @@
+use crate::{
+    Graph, InputPortId, Node, NodeId, NodeTemplate, OutputPortId,
+    metadata::MetaValue,
+    reference::{NodeInputIdentifier, OutputPortReference},
+};
+
+/// Adds ports and connections to a new node one call at a time, see
+/// [`Graph::build_node`]
+#[derive(Debug)]
+pub struct NodeBuilder<'a, N: Node> {
+    graph: &'a mut Graph<N>,
+    node: NodeId,
+    /// The port [`meta`](Self::meta) applies to
+    last_port: Option<LastPort>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LastPort {
+    Input(InputPortId),
+    Output(OutputPortId),
+}
+
+impl<N: Node> Graph<N> {
+    /// Create a node and add ports to it with chained calls, for when the
+    /// number of ports isn't known up front like with
+    /// [`create_node_with`](Self::create_node_with). The node is created
+    /// right away, [`finish`](NodeBuilder::finish) only returns its id.
+    ///
+    /// ```
+    /// # use node_graph::{Graph, InitialPorts, Node};
+    /// # #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    /// # enum DataType {
+    /// #     Float,
+    /// # }
+    /// # impl node_graph::DataType for DataType {}
+    /// # #[derive(Debug, Clone)]
+    /// # enum Value {
+    /// #     Float(f64),
+    /// # }
+    /// # impl From<f64> for Value {
+    /// #     fn from(value: f64) -> Self {
+    /// #         Self::Float(value)
+    /// #     }
+    /// # }
+    /// # #[derive(Debug)]
+    /// # enum AudioNode {
+    /// #     Oscillator,
+    /// #     Gain,
+    /// # }
+    /// # impl Node for AudioNode {
+    /// #     type DataType = DataType;
+    /// #     type DataValue = Value;
+    /// #     fn initial_ports(&self) -> InitialPorts<Self> {
+    /// #         match self {
+    /// #             Self::Oscillator => InitialPorts {
+    /// #                 outputs: vec![("out", DataType::Float)],
+    /// #                 ..Default::default()
+    /// #             },
+    /// #             Self::Gain => InitialPorts {
+    /// #                 inputs: vec![("in", DataType::Float, 0.0.into())],
+    /// #                 ..Default::default()
+    /// #             },
+    /// #         }
+    /// #     }
+    /// # }
+    /// # let mut graph = Graph::<AudioNode>::new();
+    /// # let oscillator = graph.create_node(AudioNode::Oscillator);
+    /// let gain = graph
+    ///     .build_node(AudioNode::Gain)
+    ///     .input("gain", DataType::Float, 1.0.into())
+    ///     .meta("min", 0.0)
+    ///     .output("out", DataType::Float)
+    ///     .connect_from(oscillator.output(0), "in")
+    ///     .finish();
+    /// # assert_eq!(graph.get_input_ports(gain).unwrap().len(), 2);
+    /// # assert_eq!(graph.connection_count_of(gain), 1);
+    /// ```
+    pub fn build_node<T: NodeTemplate<N>>(&mut self, node: T) -> NodeBuilder<'_, N> {
+        let node = self.create_node(node);
+
+        NodeBuilder {
+            graph: self,
+            node,
+            last_port: None,
+        }
+    }
+}
+
+impl<N: Node> NodeBuilder<'_, N> {
+    pub fn input(mut self, name: &str, ty: N::DataType, default: N::DataValue) -> Self {
+        let port = self.graph.create_input_port(self.node, name, ty, default);
+        self.last_port = Some(LastPort::Input(port));
+        self
+    }
+
+    pub fn output(mut self, name: &str, ty: N::DataType) -> Self {
+        let port = self.graph.create_output_port(self.node, name, ty);
+        self.last_port = Some(LastPort::Output(port));
+        self
+    }
+
+    /// Set a metadata entry of the port added last
+    pub fn meta(self, key: &str, value: impl Into<MetaValue>) -> Self {
+        let metadata = match self.last_port.expect("No port was added yet") {
+            LastPort::Input(port) => self.graph.get_input_port_metadata_mut(port),
+            LastPort::Output(port) => self.graph.get_output_port_metadata_mut(port),
+        };
+
+        metadata.expect("Port does not exist").set(key, value);
+        self
+    }
+
+    /// Connect `start_port` to an input of the node, which has to exist
+    /// already
+    pub fn connect_from<'c>(
+        self,
+        start_port: impl OutputPortReference,
+        input: impl NodeInputIdentifier<'c>,
+    ) -> Self {
+        self.graph.connect(start_port, input.combine(self.node));
+        self
+    }
+
+    /// The node being built, for references to it before it is finished
+    pub fn id(&self) -> NodeId {
+        self.node
+    }
+
+    pub fn finish(self) -> NodeId {
+        self.node
+    }
+}
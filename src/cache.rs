@@ -0,0 +1,263 @@
+use slotmap::SecondaryMap;
+
+use crate::{
+    Graph, INVALID_STATE, Node, NodeId, OutputPortId,
+    memo::MemoKey,
+    stable_id::StableId,
+    walker::{GraphWalker, OutputCache},
+};
+
+/// Compares the output caches of two walks, see
+/// [`GraphWalker::retain_previous_cache`](crate::walker::GraphWalker::retain_previous_cache)
+pub struct OutputCacheDiff;
+
+impl OutputCacheDiff {
+    /// The ports whose value differs between `old` and `new` according to
+    /// `eq`, including ports that only have a value in one of them
+    pub fn compare<T>(
+        old: &OutputCache<T>,
+        new: &OutputCache<T>,
+        eq: impl Fn(&T, &T) -> bool,
+    ) -> Vec<OutputPortId> {
+        let mut changed = new
+            .iter()
+            .filter(|&(port, value)| match old.get(port) {
+                Some(old_value) => !eq(old_value, value),
+                None => true,
+            })
+            .map(|(port, _)| port)
+            .collect::<Vec<_>>();
+
+        changed.extend(old.keys().filter(|&port| !new.contains_key(port)));
+
+        changed
+    }
+
+    /// [`compare`](Self::compare) using [`PartialEq`]
+    pub fn compare_eq<T: PartialEq>(
+        old: &OutputCache<T>,
+        new: &OutputCache<T>,
+    ) -> Vec<OutputPortId> {
+        Self::compare(old, new, T::eq)
+    }
+}
+
+/// The values of a walker's output cache and memoizer, with nodes and ports
+/// referred to by [`StableId`] so they can be saved and loaded in another
+/// process. Created with [`GraphWalker::save_cache`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PersistedCache<V> {
+    /// The value of each output port
+    pub outputs: Vec<(StableId, V)>,
+    pub memoized: Vec<PersistedMemo<V>>,
+}
+
+/// A memoized node in a [`PersistedCache`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PersistedMemo<V> {
+    pub node: StableId,
+    /// The hash of the node's inputs. Hashes made by [`Hash`] may change
+    /// between Rust versions, in which case the node is evaluated again.
+    pub key: u64,
+    /// The node's input values, saved by
+    /// [checked](crate::memo::Memoizer::checked) memoizers
+    pub inputs: Option<Vec<Vec<V>>>,
+    pub outputs: Vec<(StableId, V)>,
+}
+
+impl<N: Node> Graph<N> {
+    /// Remove the values of all output ports of `node` from `cache`
+    pub fn invalidate_node<V>(&self, cache: &mut OutputCache<V>, node: NodeId) {
+        let node = self.node_data.get(node).expect("Node does not exist");
+
+        for (_, port) in node.outputs.iter() {
+            cache.remove(*port);
+        }
+    }
+
+    /// Remove the values of all output ports of `node` and of every node that
+    /// depends on it, directly or indirectly, from `cache`
+    pub fn invalidate_downstream_of<V>(&self, cache: &mut OutputCache<V>, node: NodeId) {
+        for node in self.downstream_nodes([node]) {
+            self.invalidate_node(cache, node);
+        }
+    }
+
+    /// Remove the value of `port` and the values of every node that depends
+    /// on it, directly or indirectly, from `cache`. Other outputs of the
+    /// port's node keep their values.
+    pub fn invalidate_port<V>(&self, cache: &mut OutputCache<V>, port: OutputPortId) {
+        cache.remove(port);
+
+        for node in self.downstream_nodes(self.port_dependents(port)) {
+            self.invalidate_node(cache, node);
+        }
+    }
+
+    /// The nodes with an input connected to `port`
+    fn port_dependents(&self, port: OutputPortId) -> impl Iterator<Item = NodeId> + '_ {
+        self.get_outgoing_connections(port)
+            .map(|input| self.input_ports.get(input).expect(INVALID_STATE).node)
+    }
+
+    /// `nodes` and all nodes depending on them, each returned once
+    fn downstream_nodes(&self, nodes: impl IntoIterator<Item = NodeId>) -> Vec<NodeId> {
+        let mut visited = SecondaryMap::<NodeId, ()>::new();
+        let mut stack = nodes.into_iter().collect::<Vec<_>>();
+        let mut downstream = Vec::new();
+
+        while let Some(top) = stack.pop() {
+            if visited.insert(top, ()).is_none() {
+                downstream.push(top);
+                stack.extend(self.get_direct_dependents(top));
+            }
+        }
+
+        downstream
+    }
+}
+
+impl<N: Node> GraphWalker<'_, N> {
+    /// Forget the outputs of `node` in the current cache and the memoizer, so
+    /// it is evaluated again on the next walk
+    pub fn invalidate_node(&mut self, node: NodeId) {
+        let (graph, cache, mut memoizer) = self.caches_mut();
+
+        graph.invalidate_node(cache, node);
+
+        if let Some(memoizer) = memoizer.as_mut() {
+            memoizer.invalidate(node);
+        }
+    }
+
+    /// Forget the outputs of `node` and of every node that depends on it in
+    /// the current cache and the memoizer, like after changing state of
+    /// `node` that the walker can't see
+    pub fn invalidate_downstream_of(&mut self, node: NodeId) {
+        let (graph, cache, mut memoizer) = self.caches_mut();
+
+        for node in graph.downstream_nodes([node]) {
+            graph.invalidate_node(cache, node);
+
+            if let Some(memoizer) = memoizer.as_mut() {
+                memoizer.invalidate(node);
+            }
+        }
+    }
+
+    /// Forget the value of `port` and the outputs of every node that depends
+    /// on it in the current cache and the memoizer. The memoized outputs of
+    /// the port's own node are forgotten too, since they include the port.
+    pub fn invalidate_port(&mut self, port: OutputPortId) {
+        let (graph, cache, mut memoizer) = self.caches_mut();
+
+        let owner = graph
+            .output_ports
+            .get(port)
+            .expect("Output port does not exist")
+            .node;
+
+        cache.remove(port);
+
+        if let Some(memoizer) = memoizer.as_mut() {
+            memoizer.invalidate(owner);
+        }
+
+        for node in graph.downstream_nodes(graph.port_dependents(port)) {
+            graph.invalidate_node(cache, node);
+
+            if let Some(memoizer) = memoizer.as_mut() {
+                memoizer.invalidate(node);
+            }
+        }
+    }
+
+    /// Copy the current output cache and the entries of the memoizer, if
+    /// there is one. Ports and nodes without a stable id are left out.
+    ///
+    /// Panics if the graph doesn't have stable ids enabled.
+    pub fn save_cache(&self) -> PersistedCache<N::DataValue> {
+        let stable_ids = self
+            .graph()
+            .stable_ids()
+            .expect("Stable ids are not enabled");
+
+        let outputs = |values: &mut dyn Iterator<Item = (OutputPortId, &N::DataValue)>| {
+            values
+                .filter_map(|(port, value)| {
+                    Some((stable_ids.output_ports().get(port)?, value.clone()))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let memoized = self
+            .memoizer()
+            .into_iter()
+            .flat_map(|memoizer| memoizer.entries())
+            .filter_map(|(node, entry)| {
+                Some(PersistedMemo {
+                    node: stable_ids.nodes().get(node)?,
+                    key: entry.key.hash,
+                    inputs: entry.key.inputs.clone(),
+                    outputs: outputs(&mut entry.outputs.iter().map(|(port, value)| (*port, value))),
+                })
+            })
+            .collect();
+
+        PersistedCache {
+            outputs: outputs(&mut self.output_cache().iter()),
+            memoized,
+        }
+    }
+
+    /// Add the values of a saved cache to the current output cache, and its
+    /// memoized nodes to the memoizer if there is one. With a memoizer, the
+    /// next walk only evaluates nodes whose inputs changed since the cache
+    /// was saved. Values of nodes and ports that no longer exist are skipped.
+    ///
+    /// Panics if the graph doesn't have stable ids enabled.
+    pub fn load_cache(&mut self, cache: PersistedCache<N::DataValue>) {
+        let stable_ids = self
+            .graph()
+            .stable_ids()
+            .expect("Stable ids are not enabled");
+
+        let resolve = |values: Vec<(StableId, N::DataValue)>| {
+            values
+                .into_iter()
+                .filter_map(|(port, value)| Some((stable_ids.output_ports().resolve(port)?, value)))
+                .collect::<Vec<_>>()
+        };
+
+        let outputs = resolve(cache.outputs);
+
+        let memoized = cache
+            .memoized
+            .into_iter()
+            .filter_map(|memo| {
+                let node = stable_ids.nodes().resolve(memo.node)?;
+
+                Some((
+                    node,
+                    // Saved outputs are taken to match the current value
+                    MemoKey {
+                        hash: memo.key,
+                        revision: self.graph().node_data[node].revision,
+                        inputs: memo.inputs,
+                    },
+                    resolve(memo.outputs),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        self.output_cache_mut().extend(outputs);
+
+        if let Some(memoizer) = self.memoizer_mut() {
+            for (node, key, values) in memoized {
+                memoizer.insert(node, key, values);
+            }
+        }
+    }
+}
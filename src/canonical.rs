@@ -0,0 +1,124 @@
+use std::{collections::BTreeMap, fmt::Debug, fmt::Write};
+
+use slotmap::SecondaryMap;
+
+use crate::{Graph, INVALID_STATE, Node, NodeId, analyzer::GraphAnalyzer};
+
+impl<N: Node + Debug> Graph<N> {
+    /// A listing of all nodes with their ports, defaults and connections that
+    /// doesn't depend on ids, meant for snapshot tests and diffing.
+    ///
+    /// Nodes are sorted by depth, then by name, value and what their inputs
+    /// are connected to, and labelled `n0`, `n1`... in that order. Nodes that
+    /// are equal in all of those stay in the order they were created in.
+    ///
+    /// ```text
+    /// n0 Constant(2.0)
+    ///   out value: Float
+    /// n1 "result" Add
+    ///   in a: Float = 0.0 <- n0.value
+    ///   in b: Float = 1.0
+    ///   out sum: Float
+    /// ```
+    pub fn to_canonical_string(&self) -> String {
+        let depths = GraphAnalyzer::new(self).depths();
+
+        // Nodes in a cycle have no depth, they go last
+        let mut by_depth = BTreeMap::<usize, Vec<NodeId>>::new();
+
+        for node in self.node_data.keys() {
+            let depth = depths.get(node).copied().unwrap_or(usize::MAX);
+            by_depth.entry(depth).or_default().push(node);
+        }
+
+        let mut labels = SecondaryMap::<NodeId, usize>::with_capacity(self.node_count());
+        let mut order = Vec::with_capacity(self.node_count());
+
+        for nodes in by_depth.into_values() {
+            // Dependencies have a lower depth, so they are labelled already
+            let mut keyed = nodes
+                .into_iter()
+                .map(|node| {
+                    let incoming = self.node_data[node]
+                        .inputs
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(index, &(_, port))| {
+                            self.get_incoming_connections(port)
+                                .map(move |start| (index, start))
+                        })
+                        .map(|(index, start)| {
+                            let start_node = self.output_ports[start].node;
+                            let output = self.output_port_index(start);
+
+                            (index, labels.get(start_node).copied(), output)
+                        })
+                        .collect::<Vec<_>>();
+
+                    let key = (
+                        self.get_node_name(node),
+                        format!("{:?}", *self.nodes[node].read()),
+                        incoming,
+                    );
+
+                    (key, node)
+                })
+                .collect::<Vec<_>>();
+
+            keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            for (_, node) in keyed {
+                labels.insert(node, order.len());
+                order.push(node);
+            }
+        }
+
+        let mut out = String::new();
+
+        for (label, &node) in order.iter().enumerate() {
+            let value = self.nodes[node].read();
+
+            match self.get_node_name(node) {
+                Some(name) => writeln!(out, "n{label} {name:?} {:?}", *value),
+                None => writeln!(out, "n{label} {:?}", *value),
+            }
+            .expect(INVALID_STATE);
+
+            for (name, port) in self.node_data[node].inputs.iter() {
+                let info = &self.input_ports[*port];
+
+                write!(out, "  in {name}: {:?}", info.ty).expect(INVALID_STATE);
+
+                if let Some(default) = &info.default {
+                    write!(out, " = {default:?}").expect(INVALID_STATE);
+                }
+
+                let mut sources = self
+                    .get_incoming_connections(*port)
+                    .map(|start| {
+                        let start_node = self.output_ports[start].node;
+                        (labels[start_node], self.output_port_index(start))
+                    })
+                    .collect::<Vec<_>>();
+
+                sources.sort_unstable();
+
+                for (start_label, output) in sources {
+                    let start_node = order[start_label];
+                    let start_name = &self.node_data[start_node].outputs[output].0;
+
+                    write!(out, " <- n{start_label}.{start_name}").expect(INVALID_STATE);
+                }
+
+                out.push('\n');
+            }
+
+            for (name, port) in self.node_data[node].outputs.iter() {
+                writeln!(out, "  out {name}: {:?}", self.output_ports[*port].ty)
+                    .expect(INVALID_STATE);
+            }
+        }
+
+        out
+    }
+}
@@ -0,0 +1,111 @@
+//! The cell every node is stored in. By default this is a
+//! [`parking_lot::RwLock`], so nodes can be accessed from multiple threads.
+//! With the `cell` feature it is a [`RefCell`](std::cell::RefCell) instead,
+//! which avoids the cost of locking for single-threaded users, at the cost of
+//! [`Graph`](crate::Graph) no longer being `Sync`.
+
+#[cfg(not(feature = "cell"))]
+mod inner {
+    use parking_lot::RwLock;
+
+    pub type NodeRef<'a, T> = parking_lot::RwLockReadGuard<'a, T>;
+    pub type NodeRefMut<'a, T> = parking_lot::RwLockWriteGuard<'a, T>;
+    pub type MappedNodeRef<'a, T> = parking_lot::MappedRwLockReadGuard<'a, T>;
+    pub type MappedNodeRefMut<'a, T> = parking_lot::MappedRwLockWriteGuard<'a, T>;
+
+    /// Narrow a borrowed node down to part of it, or `None` if `f` returns
+    /// `None`
+    pub fn try_map<'a, T, U>(
+        node: NodeRef<'a, T>,
+        f: impl FnOnce(&T) -> Option<&U>,
+    ) -> Option<MappedNodeRef<'a, U>> {
+        parking_lot::RwLockReadGuard::try_map(node, f).ok()
+    }
+
+    pub fn try_map_mut<'a, T, U>(
+        node: NodeRefMut<'a, T>,
+        f: impl FnOnce(&mut T) -> Option<&mut U>,
+    ) -> Option<MappedNodeRefMut<'a, U>> {
+        parking_lot::RwLockWriteGuard::try_map(node, f).ok()
+    }
+
+    #[derive(Debug, Default)]
+    pub struct NodeCell<T>(RwLock<T>);
+
+    impl<T> NodeCell<T> {
+        pub fn new(value: T) -> Self {
+            Self(RwLock::new(value))
+        }
+
+        pub fn read(&self) -> NodeRef<'_, T> {
+            self.0.read()
+        }
+
+        pub fn write(&self) -> NodeRefMut<'_, T> {
+            self.0.write()
+        }
+
+        pub fn get_mut(&mut self) -> &mut T {
+            self.0.get_mut()
+        }
+
+        pub fn into_inner(self) -> T {
+            self.0.into_inner()
+        }
+    }
+}
+
+#[cfg(feature = "cell")]
+mod inner {
+    use std::cell::RefCell;
+
+    pub type NodeRef<'a, T> = std::cell::Ref<'a, T>;
+    pub type NodeRefMut<'a, T> = std::cell::RefMut<'a, T>;
+    pub type MappedNodeRef<'a, T> = std::cell::Ref<'a, T>;
+    pub type MappedNodeRefMut<'a, T> = std::cell::RefMut<'a, T>;
+
+    /// Narrow a borrowed node down to part of it, or `None` if `f` returns
+    /// `None`
+    pub fn try_map<'a, T, U>(
+        node: NodeRef<'a, T>,
+        f: impl FnOnce(&T) -> Option<&U>,
+    ) -> Option<MappedNodeRef<'a, U>> {
+        std::cell::Ref::filter_map(node, f).ok()
+    }
+
+    pub fn try_map_mut<'a, T, U>(
+        node: NodeRefMut<'a, T>,
+        f: impl FnOnce(&mut T) -> Option<&mut U>,
+    ) -> Option<MappedNodeRefMut<'a, U>> {
+        std::cell::RefMut::filter_map(node, f).ok()
+    }
+
+    #[derive(Debug, Default)]
+    pub struct NodeCell<T>(RefCell<T>);
+
+    impl<T> NodeCell<T> {
+        pub fn new(value: T) -> Self {
+            Self(RefCell::new(value))
+        }
+
+        pub fn read(&self) -> NodeRef<'_, T> {
+            self.0.borrow()
+        }
+
+        pub fn write(&self) -> NodeRefMut<'_, T> {
+            self.0.borrow_mut()
+        }
+
+        pub fn get_mut(&mut self) -> &mut T {
+            self.0.get_mut()
+        }
+
+        pub fn into_inner(self) -> T {
+            self.0.into_inner()
+        }
+    }
+}
+
+pub use inner::{
+    MappedNodeRef, MappedNodeRefMut, NodeCell, NodeRef, NodeRefMut, try_map, try_map_mut,
+};
@@ -0,0 +1,930 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use itertools::Itertools;
+use slotmap::SecondaryMap;
+
+use crate::{
+    ConnectError, ConnectionId, Graph, INVALID_STATE, InputPortId, Node, NodeId, OutputPortId,
+};
+
+/// Identifies a node, connection or operation across every replica of a
+/// graph. Ids are ordered like Lamport timestamps, so later operations have
+/// greater ids, with the peer breaking ties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElementId {
+    pub counter: u64,
+    pub peer: u64,
+}
+
+/// A port by node and name, which is the same on every replica
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PortAddress {
+    pub node: ElementId,
+    pub name: String,
+}
+
+/// A change to a [`Replica`], to be sent to the other replicas. Operations
+/// can be applied in any order and more than once: operations on elements
+/// that don't exist yet wait until they do, and deleted elements are
+/// remembered so operations arriving late can't bring them back.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "N: serde::Serialize, N::DataType: serde::Serialize, N::DataValue: serde::Serialize",
+        deserialize = "N: serde::Deserialize<'de>, N::DataType: serde::Deserialize<'de>, N::DataValue: serde::Deserialize<'de>"
+    ))
+)]
+pub enum CollabOp<N: Node> {
+    /// Create a node with its initial ports, whose id is `id`
+    CreateNode {
+        id: ElementId,
+        node: N,
+    },
+    DeleteNode {
+        id: ElementId,
+        node: ElementId,
+    },
+    /// Connect two ports, the connection's id is `id`
+    Connect {
+        id: ElementId,
+        start: PortAddress,
+        end: PortAddress,
+    },
+    Disconnect {
+        id: ElementId,
+        connection: ElementId,
+    },
+    CreateInputPort {
+        id: ElementId,
+        port: PortAddress,
+        ty: N::DataType,
+        default: N::DataValue,
+    },
+    CreateOutputPort {
+        id: ElementId,
+        port: PortAddress,
+        ty: N::DataType,
+    },
+    /// Set the default value of an input port. The operation with the
+    /// greatest id wins.
+    SetDefaultValue {
+        id: ElementId,
+        port: PortAddress,
+        value: N::DataValue,
+    },
+}
+
+impl<N: Node> CollabOp<N> {
+    pub fn id(&self) -> ElementId {
+        match self {
+            Self::CreateNode { id, .. }
+            | Self::DeleteNode { id, .. }
+            | Self::Connect { id, .. }
+            | Self::Disconnect { id, .. }
+            | Self::CreateInputPort { id, .. }
+            | Self::CreateOutputPort { id, .. }
+            | Self::SetDefaultValue { id, .. } => *id,
+        }
+    }
+}
+
+/// Which connections win when they conflict, like two peers connecting the
+/// same input or connecting two nodes in opposite directions. Connections are
+/// made in the order of their ids, each one only if it fits alongside those
+/// made before it, so every replica that has seen the same operations keeps
+/// the same connections no matter the order they arrived in. Every replica of
+/// a graph has to use the same policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Connections with smaller ids win, so a connection that conflicts with
+    /// an older one is rejected
+    #[default]
+    Reject,
+    /// Connections with greater ids win, replacing the older connections
+    /// they conflict with
+    LastWriterWins,
+}
+
+/// Returned by [`Replica::apply`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApplyOutcome<T: crate::DataType> {
+    Applied,
+    /// Waiting for a node or port it refers to. It is applied once that
+    /// arrives.
+    Pending,
+    /// It was applied before, refers to something that was deleted, or lost
+    /// to another operation
+    Ignored,
+    /// The connection conflicts with connections that win under the
+    /// [`ConflictPolicy`], or can't be made at all. It is made once
+    /// whatever it conflicts with is deleted.
+    Rejected(ConnectError<T>),
+}
+
+/// A copy of a graph edited by several peers at once, which stay in sync by
+/// sending each other the [operations](CollabOp) returned by local edits.
+/// The graph is only changed through the replica, so every change can be
+/// sent to the other peers.
+#[derive(Debug)]
+pub struct Replica<N: Node> {
+    graph: Graph<N>,
+    peer: u64,
+    policy: ConflictPolicy,
+    /// The greatest counter seen so far
+    clock: u64,
+    nodes: HashMap<ElementId, NodeId>,
+    node_ids: SecondaryMap<NodeId, ElementId>,
+    connections: HashMap<ElementId, ConnectionId>,
+    connection_ids: SecondaryMap<ConnectionId, ElementId>,
+    /// Every connection that wasn't deleted, including those that lost a
+    /// conflict and aren't in the graph
+    candidates: BTreeMap<ElementId, (PortAddress, PortAddress)>,
+    /// The operations that created ports, the greatest id wins
+    input_ports: HashMap<PortAddress, ElementId>,
+    output_ports: HashMap<PortAddress, ElementId>,
+    /// Deleted nodes and connections
+    tombstones: HashSet<ElementId>,
+    /// The operation that last set each default value
+    defaults: HashMap<PortAddress, ElementId>,
+    /// Ids of every operation in `history`
+    seen: HashSet<ElementId>,
+    pending: Vec<CollabOp<N>>,
+    history: Vec<CollabOp<N>>,
+}
+
+impl<N: Node> Replica<N> {
+    /// An empty graph edited by `peer`, which has to be unique among the
+    /// peers editing it
+    pub fn new(peer: u64) -> Self {
+        Self {
+            graph: Graph::new(),
+            peer,
+            policy: ConflictPolicy::default(),
+            clock: 0,
+            nodes: HashMap::new(),
+            node_ids: SecondaryMap::new(),
+            connections: HashMap::new(),
+            connection_ids: SecondaryMap::new(),
+            candidates: BTreeMap::new(),
+            input_ports: HashMap::new(),
+            output_ports: HashMap::new(),
+            tombstones: HashSet::new(),
+            defaults: HashMap::new(),
+            seen: HashSet::new(),
+            pending: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn graph(&self) -> &Graph<N> {
+        &self.graph
+    }
+
+    pub fn into_graph(self) -> Graph<N> {
+        self.graph
+    }
+
+    pub fn peer(&self) -> u64 {
+        self.peer
+    }
+
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        self.policy
+    }
+
+    /// Every operation received or made here, in the order they arrived.
+    /// Applying them to another replica brings it up to date.
+    pub fn history(&self) -> &[CollabOp<N>] {
+        &self.history
+    }
+
+    /// Operations waiting for the nodes or ports they refer to
+    pub fn pending(&self) -> &[CollabOp<N>] {
+        &self.pending
+    }
+
+    pub fn node(&self, id: ElementId) -> Option<NodeId> {
+        self.nodes.get(&id).copied()
+    }
+
+    pub fn node_element(&self, node: NodeId) -> Option<ElementId> {
+        self.node_ids.get(node).copied()
+    }
+
+    pub fn connection(&self, id: ElementId) -> Option<ConnectionId> {
+        self.connections.get(&id).copied()
+    }
+
+    pub fn connection_element(&self, connection: ConnectionId) -> Option<ElementId> {
+        self.connection_ids.get(connection).copied()
+    }
+}
+
+impl<N: Node + Clone> Replica<N> {
+    /// Change how conflicting connections are resolved, remaking the
+    /// connections that win under the new policy
+    pub fn set_conflict_policy(&mut self, policy: ConflictPolicy) {
+        if self.policy != policy {
+            self.policy = policy;
+            self.remake_all_connections();
+        }
+    }
+
+    /// Apply an operation from another peer, along with any pending
+    /// operations that were waiting for it
+    pub fn apply(&mut self, op: CollabOp<N>) -> ApplyOutcome<N::DataType> {
+        if !self.seen.insert(op.id()) {
+            return ApplyOutcome::Ignored;
+        }
+
+        self.clock = self.clock.max(op.id().counter);
+        self.history.push(op.clone());
+
+        let outcome = self.apply_op(op);
+
+        if outcome == ApplyOutcome::Applied {
+            self.apply_pending();
+        }
+
+        outcome
+    }
+
+    /// Apply every operation of `other` that this replica hasn't seen yet,
+    /// returning the connections that were rejected
+    pub fn merge(&mut self, other: &Replica<N>) -> Vec<(ElementId, ConnectError<N::DataType>)> {
+        let mut rejected = Vec::new();
+
+        for op in other.history.iter() {
+            if let ApplyOutcome::Rejected(error) = self.apply(op.clone()) {
+                rejected.push((op.id(), error));
+            }
+        }
+
+        // Later operations may have removed what they conflicted with
+        rejected.retain(|(id, _)| !self.connections.contains_key(id));
+        rejected
+    }
+
+    pub fn delete_node(&mut self, node: NodeId) -> Option<CollabOp<N>> {
+        let op = CollabOp::DeleteNode {
+            id: self.next_id(),
+            node: self.node_element(node)?,
+        };
+
+        self.apply_local(op)
+    }
+
+    /// Connect two ports, checked like [`Graph::connect`]
+    pub fn connect(
+        &mut self,
+        start_port: OutputPortId,
+        end_port: InputPortId,
+    ) -> Result<(ConnectionId, CollabOp<N>), ConnectError<N::DataType>> {
+        self.graph.check_connection(start_port, end_port)?;
+
+        let id = self.next_id();
+        let op = CollabOp::Connect {
+            id,
+            start: self.output_address(start_port),
+            end: self.input_address(end_port),
+        };
+
+        let op = self.apply_local(op).expect(INVALID_STATE);
+
+        Ok((self.connections[&id], op))
+    }
+
+    pub fn disconnect(&mut self, connection: ConnectionId) -> Option<CollabOp<N>> {
+        let op = CollabOp::Disconnect {
+            id: self.next_id(),
+            connection: self.connection_element(connection)?,
+        };
+
+        self.apply_local(op)
+    }
+
+    /// Panics if the node already has an input port called `name`
+    pub fn create_input_port(
+        &mut self,
+        node: NodeId,
+        name: &str,
+        ty: N::DataType,
+        default: N::DataValue,
+    ) -> (InputPortId, CollabOp<N>) {
+        assert!(
+            self.graph.get_input_port(node, name).is_none(),
+            "An input port with this name already exists"
+        );
+
+        let op = CollabOp::CreateInputPort {
+            id: self.next_id(),
+            port: PortAddress {
+                node: self.node_element(node).expect("Node does not exist"),
+                name: name.to_string(),
+            },
+            ty,
+            default,
+        };
+
+        let op = self.apply_local(op).expect(INVALID_STATE);
+
+        (
+            self.graph.get_input_port(node, name).expect(INVALID_STATE),
+            op,
+        )
+    }
+
+    /// Panics if the node already has an output port called `name`
+    pub fn create_output_port(
+        &mut self,
+        node: NodeId,
+        name: &str,
+        ty: N::DataType,
+    ) -> (OutputPortId, CollabOp<N>) {
+        assert!(
+            self.graph.get_output_port(node, name).is_none(),
+            "An output port with this name already exists"
+        );
+
+        let op = CollabOp::CreateOutputPort {
+            id: self.next_id(),
+            port: PortAddress {
+                node: self.node_element(node).expect("Node does not exist"),
+                name: name.to_string(),
+            },
+            ty,
+        };
+
+        let op = self.apply_local(op).expect(INVALID_STATE);
+
+        (
+            self.graph.get_output_port(node, name).expect(INVALID_STATE),
+            op,
+        )
+    }
+
+    pub fn set_default_value(&mut self, port: InputPortId, value: N::DataValue) -> CollabOp<N> {
+        let op = CollabOp::SetDefaultValue {
+            id: self.next_id(),
+            port: self.input_address(port),
+            value,
+        };
+
+        self.apply_local(op).expect(INVALID_STATE)
+    }
+
+    fn next_id(&mut self) -> ElementId {
+        self.clock += 1;
+
+        ElementId {
+            counter: self.clock,
+            peer: self.peer,
+        }
+    }
+
+    fn input_address(&self, port: InputPortId) -> PortAddress {
+        let port = self
+            .graph
+            .get_input_port_info(port)
+            .expect("Input port does not exist");
+
+        PortAddress {
+            node: self.node_ids[port.node],
+            name: port.name.to_string(),
+        }
+    }
+
+    fn output_address(&self, port: OutputPortId) -> PortAddress {
+        let port = self
+            .graph
+            .get_output_port_info(port)
+            .expect("Output port does not exist");
+
+        PortAddress {
+            node: self.node_ids[port.node],
+            name: port.name.to_string(),
+        }
+    }
+
+    /// Apply an operation made here, which can't be pending or conflict.
+    /// Returns it if it changed anything.
+    fn apply_local(&mut self, op: CollabOp<N>) -> Option<CollabOp<N>> {
+        match self.apply(op.clone()) {
+            ApplyOutcome::Applied => Some(op),
+            _ => None,
+        }
+    }
+
+    fn apply_pending(&mut self) {
+        loop {
+            let mut progress = false;
+
+            for op in std::mem::take(&mut self.pending) {
+                match self.apply_op(op) {
+                    ApplyOutcome::Pending => {}
+                    _ => progress = true,
+                }
+            }
+
+            if !progress {
+                break;
+            }
+        }
+    }
+
+    fn apply_op(&mut self, op: CollabOp<N>) -> ApplyOutcome<N::DataType> {
+        match self.resolve(&op) {
+            Resolved::Ready => {}
+            Resolved::Missing => {
+                self.pending.push(op);
+                return ApplyOutcome::Pending;
+            }
+            Resolved::Deleted => return ApplyOutcome::Ignored,
+        }
+
+        match op {
+            CollabOp::CreateNode { id, node } => {
+                let node = self.graph.create_node(node);
+                self.nodes.insert(id, node);
+                self.node_ids.insert(node, id);
+            }
+            CollabOp::DeleteNode { node: element, .. } => {
+                self.tombstones.insert(element);
+
+                let Some(node) = self.nodes.remove(&element) else {
+                    return ApplyOutcome::Ignored;
+                };
+
+                let connections = self
+                    .candidates
+                    .iter()
+                    .filter(|(_, (start, end))| start.node == element || end.node == element)
+                    .map(|(&id, _)| id)
+                    .collect_vec();
+
+                let removed = connections
+                    .into_iter()
+                    .filter(|&id| self.remove_connection(id))
+                    .collect_vec();
+
+                self.node_ids.remove(node);
+                self.graph.take_node(node).expect(INVALID_STATE);
+
+                if let Some(first) = self.first_in_order(removed) {
+                    self.retry_rejected(first);
+                }
+            }
+            CollabOp::Connect { id, start, end } => {
+                return self.connect_remote(id, start, end);
+            }
+            CollabOp::Disconnect {
+                connection: element,
+                ..
+            } => {
+                if !self.remove_connection(element) {
+                    return ApplyOutcome::Ignored;
+                }
+
+                self.retry_rejected(element);
+            }
+            CollabOp::CreateInputPort {
+                id,
+                port,
+                ty,
+                default,
+            } => {
+                let node = self.nodes[&port.node];
+
+                match self.graph.get_input_port(node, &port.name) {
+                    None => {
+                        self.graph.create_input_port(node, &port.name, ty, default);
+                        self.defaults.insert(port.clone(), id);
+                    }
+                    Some(input) => {
+                        // Initial ports and ports created later win
+                        if self.input_ports.get(&port).is_none_or(|&last| last > id) {
+                            return ApplyOutcome::Ignored;
+                        }
+
+                        if self.defaults.get(&port).is_none_or(|&last| last < id) {
+                            self.graph.set_default_value(input, default);
+                            self.defaults.insert(port.clone(), id);
+                        }
+
+                        if self.graph.input_ports[input].ty != ty {
+                            self.graph.set_input_port_type(input, ty, false);
+                            self.remake_all_connections();
+                        }
+                    }
+                }
+
+                self.input_ports.insert(port, id);
+            }
+            CollabOp::CreateOutputPort { id, port, ty } => {
+                let node = self.nodes[&port.node];
+
+                match self.graph.get_output_port(node, &port.name) {
+                    None => {
+                        self.graph.create_output_port(node, &port.name, ty);
+                    }
+                    Some(output) => {
+                        if self.output_ports.get(&port).is_none_or(|&last| last > id) {
+                            return ApplyOutcome::Ignored;
+                        }
+
+                        if self.graph.output_ports[output].ty != ty {
+                            self.graph.set_output_port_type(output, ty, false);
+                            self.remake_all_connections();
+                        }
+                    }
+                }
+
+                self.output_ports.insert(port, id);
+            }
+            CollabOp::SetDefaultValue { id, port, value } => {
+                if self.defaults.get(&port).is_some_and(|&last| last > id) {
+                    return ApplyOutcome::Ignored;
+                }
+
+                let input = self
+                    .graph
+                    .get_input_port(self.nodes[&port.node], &port.name)
+                    .expect(INVALID_STATE);
+
+                self.graph.set_default_value(input, value);
+                self.defaults.insert(port, id);
+            }
+        }
+
+        ApplyOutcome::Applied
+    }
+
+    fn connect_remote(
+        &mut self,
+        id: ElementId,
+        start: PortAddress,
+        end: PortAddress,
+    ) -> ApplyOutcome<N::DataType> {
+        self.candidates.insert(id, (start, end));
+
+        let (start_port, end_port) = self.candidate_ports(id);
+
+        // A connection that fits alongside every other one can't change
+        // which of them win
+        if self.graph.check_connection(start_port, end_port).is_ok() {
+            self.make_connection(id, start_port, end_port);
+            return ApplyOutcome::Applied;
+        }
+
+        self.remake_connections(id);
+
+        if self.connections.contains_key(&id) {
+            return ApplyOutcome::Applied;
+        }
+
+        ApplyOutcome::Rejected(
+            self.graph
+                .check_connection(start_port, end_port)
+                .expect_err(INVALID_STATE),
+        )
+    }
+
+    fn candidate_ports(&self, id: ElementId) -> (OutputPortId, InputPortId) {
+        let (start, end) = &self.candidates[&id];
+
+        let start_port = self
+            .graph
+            .get_output_port(self.nodes[&start.node], &start.name)
+            .expect(INVALID_STATE);
+        let end_port = self
+            .graph
+            .get_input_port(self.nodes[&end.node], &end.name)
+            .expect(INVALID_STATE);
+
+        (start_port, end_port)
+    }
+
+    fn make_connection(&mut self, id: ElementId, start_port: OutputPortId, end_port: InputPortId) {
+        let connection = self.graph.connect(start_port, end_port);
+        self.connections.insert(id, connection);
+        self.connection_ids.insert(connection, id);
+    }
+
+    /// Delete a connection for good, returning whether it was in the graph
+    fn remove_connection(&mut self, id: ElementId) -> bool {
+        self.tombstones.insert(id);
+        self.candidates.remove(&id);
+
+        let Some(connection) = self.connections.remove(&id) else {
+            return false;
+        };
+
+        self.connection_ids.remove(connection);
+        self.graph.disconnect(connection).expect(INVALID_STATE);
+        true
+    }
+
+    /// The connections from `first` on, in the order the policy makes them
+    fn connection_order(&self, first: ElementId) -> Vec<ElementId> {
+        match self.policy {
+            ConflictPolicy::Reject => self.candidates.range(first..).map(|(&id, _)| id).collect(),
+            ConflictPolicy::LastWriterWins => self
+                .candidates
+                .range(..=first)
+                .rev()
+                .map(|(&id, _)| id)
+                .collect(),
+        }
+    }
+
+    fn first_in_order(&self, ids: impl IntoIterator<Item = ElementId>) -> Option<ElementId> {
+        match self.policy {
+            ConflictPolicy::Reject => ids.into_iter().min(),
+            ConflictPolicy::LastWriterWins => ids.into_iter().max(),
+        }
+    }
+
+    /// Remake the connections from `first` on after a connection before them
+    /// was removed, which may make room for some that were rejected
+    fn retry_rejected(&mut self, first: ElementId) {
+        let rejected = self
+            .connection_order(first)
+            .into_iter()
+            .any(|id| !self.connections.contains_key(&id));
+
+        if rejected {
+            self.remake_connections(first);
+        }
+    }
+
+    fn remake_all_connections(&mut self) {
+        if let Some(first) = self.first_in_order(self.candidates.keys().copied()) {
+            self.remake_connections(first);
+        }
+    }
+
+    /// Disconnect the connections from `first` on, then connect each one in
+    /// order that fits alongside those before it. The connections before
+    /// `first` stay, as they never depend on later ones.
+    fn remake_connections(&mut self, first: ElementId) {
+        let order = self.connection_order(first);
+
+        for id in order.iter() {
+            if let Some(connection) = self.connections.remove(id) {
+                self.connection_ids.remove(connection);
+                self.graph.disconnect(connection).expect(INVALID_STATE);
+            }
+        }
+
+        for id in order {
+            let (start_port, end_port) = self.candidate_ports(id);
+
+            if self.graph.check_connection(start_port, end_port).is_ok() {
+                self.make_connection(id, start_port, end_port);
+            }
+        }
+    }
+
+    /// Whether everything `op` refers to exists
+    fn resolve(&self, op: &CollabOp<N>) -> Resolved {
+        let node = |id: &ElementId| {
+            if self.tombstones.contains(id) {
+                Resolved::Deleted
+            } else if self.nodes.contains_key(id) {
+                Resolved::Ready
+            } else {
+                Resolved::Missing
+            }
+        };
+
+        let input = |port: &PortAddress| match node(&port.node) {
+            Resolved::Ready => match self
+                .graph
+                .get_input_port(self.nodes[&port.node], &port.name)
+            {
+                Some(_) => Resolved::Ready,
+                None => Resolved::Missing,
+            },
+            other => other,
+        };
+
+        let output = |port: &PortAddress| match node(&port.node) {
+            Resolved::Ready => match self
+                .graph
+                .get_output_port(self.nodes[&port.node], &port.name)
+            {
+                Some(_) => Resolved::Ready,
+                None => Resolved::Missing,
+            },
+            other => other,
+        };
+
+        match op {
+            CollabOp::CreateNode { id, .. } if self.tombstones.contains(id) => Resolved::Deleted,
+            CollabOp::CreateNode { .. } | CollabOp::DeleteNode { .. } => Resolved::Ready,
+            CollabOp::Connect { id, .. } if self.tombstones.contains(id) => Resolved::Deleted,
+            CollabOp::Connect { start, end, .. } => match (output(start), input(end)) {
+                (Resolved::Deleted, _) | (_, Resolved::Deleted) => Resolved::Deleted,
+                (Resolved::Ready, Resolved::Ready) => Resolved::Ready,
+                _ => Resolved::Missing,
+            },
+            CollabOp::Disconnect { .. } => Resolved::Ready,
+            CollabOp::CreateInputPort { port, .. } | CollabOp::CreateOutputPort { port, .. } => {
+                node(&port.node)
+            }
+            CollabOp::SetDefaultValue { port, .. } => input(port),
+        }
+    }
+
+    /// Create a node with its [initial ports](Node::initial_ports)
+    pub fn create_node(&mut self, node: N) -> (NodeId, CollabOp<N>) {
+        let id = self.next_id();
+        let op = self
+            .apply_local(CollabOp::CreateNode { id, node })
+            .expect(INVALID_STATE);
+
+        (self.nodes[&id], op)
+    }
+}
+
+enum Resolved {
+    Ready,
+    /// Waiting for a node or port to be created
+    Missing,
+    /// Refers to something that was deleted
+    Deleted,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestNode;
+
+    /// Everything replicas have to agree on, by element id and port address
+    fn state(replica: &Replica<TestNode>) -> Vec<String> {
+        let graph = replica.graph();
+
+        let nodes = graph.node_data.iter().map(|(node, data)| {
+            let inputs = data
+                .inputs
+                .iter()
+                .map(|(name, port)| (name.to_string(), graph.input_ports[*port].default))
+                .collect_vec();
+
+            format!(
+                "{:?} {:?} {inputs:?}",
+                replica.node_element(node).unwrap(),
+                graph.nodes[node].read()
+            )
+        });
+
+        let connections = graph.connections.iter().map(|(connection, data)| {
+            format!(
+                "{:?} {:?} -> {:?}",
+                replica.connection_element(connection).unwrap(),
+                replica.output_address(data.start_port),
+                replica.input_address(data.end_port)
+            )
+        });
+
+        nodes.chain(connections).sorted().collect()
+    }
+
+    fn input(replica: &Replica<TestNode>, node: ElementId, index: usize) -> InputPortId {
+        let node = replica.node(node).unwrap();
+        replica.graph().get_input_port_at(node, index).unwrap()
+    }
+
+    fn output(replica: &Replica<TestNode>, node: ElementId) -> OutputPortId {
+        let node = replica.node(node).unwrap();
+        replica.graph().get_output_port_at(node, 0).unwrap()
+    }
+
+    /// Two replicas sharing a value node and two sum nodes
+    fn replicas(policy: ConflictPolicy) -> (Replica<TestNode>, Replica<TestNode>, [ElementId; 3]) {
+        let mut a = Replica::new(1);
+        let mut b = Replica::new(2);
+        a.set_conflict_policy(policy);
+        b.set_conflict_policy(policy);
+
+        let nodes = [TestNode::Value(1), TestNode::Sum, TestNode::Sum].map(|node| {
+            let (_, op) = a.create_node(node);
+            op.id()
+        });
+
+        b.merge(&a);
+
+        (a, b, nodes)
+    }
+
+    /// Make conflicting edits on both replicas and merge them both ways.
+    /// Returns the ids of the connections closing the cycle on `a` and `b`.
+    fn concurrent_edits(
+        a: &mut Replica<TestNode>,
+        b: &mut Replica<TestNode>,
+        [value, first, second]: [ElementId; 3],
+    ) -> (ElementId, ElementId) {
+        // Both replicas connect the sums, in opposite directions
+        let (_, forward) = a.connect(output(a, first), input(a, second, 0)).unwrap();
+        let (_, backward) = b.connect(output(b, second), input(b, first, 0)).unwrap();
+
+        // The value node is connected on one replica and deleted on the other
+        a.connect(output(a, value), input(a, first, 1)).unwrap();
+        b.delete_node(b.node(value).unwrap()).unwrap();
+
+        // Both set the same default
+        a.set_default_value(input(a, second, 1), 5);
+        b.set_default_value(input(b, second, 1), 7);
+
+        let (_, node) = b.create_node(TestNode::Sum);
+        b.connect(output(b, second), input(b, node.id(), 0))
+            .unwrap();
+
+        a.merge(b);
+        b.merge(a);
+
+        (forward.id(), backward.id())
+    }
+
+    #[test]
+    fn concurrent_edits_converge() {
+        let (mut a, mut b, nodes) = replicas(ConflictPolicy::Reject);
+        let (forward, backward) = concurrent_edits(&mut a, &mut b, nodes);
+
+        assert_eq!(state(&a), state(&b));
+
+        // The older connection wins the cycle, the deleted node stays deleted
+        // and the later default wins
+        assert!(a.connection(forward.min(backward)).is_some());
+        assert!(a.connection(forward.max(backward)).is_none());
+        assert!(a.node(nodes[0]).is_none());
+        assert_eq!(a.graph().node_count(), 3);
+        assert_eq!(a.graph().connection_count(), 2);
+        assert_eq!(
+            a.graph()
+                .get_input_port_info(input(&a, nodes[2], 1))
+                .unwrap()
+                .default,
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn last_writer_wins_converges() {
+        let (mut a, mut b, nodes) = replicas(ConflictPolicy::LastWriterWins);
+        let (forward, backward) = concurrent_edits(&mut a, &mut b, nodes);
+
+        assert_eq!(state(&a), state(&b));
+        assert!(a.connection(forward.max(backward)).is_some());
+        assert!(a.connection(forward.min(backward)).is_none());
+    }
+
+    #[test]
+    fn operations_converge_in_any_order() {
+        let (mut a, mut b, nodes) = replicas(ConflictPolicy::Reject);
+        concurrent_edits(&mut a, &mut b, nodes);
+
+        let mut reversed = Replica::new(3);
+
+        for op in a.history().iter().rev() {
+            reversed.apply(op.clone());
+        }
+
+        assert!(reversed.pending().is_empty());
+        assert_eq!(state(&reversed), state(&a));
+
+        // Applying everything again changes nothing
+        for op in b.history() {
+            assert_eq!(reversed.apply(op.clone()), ApplyOutcome::Ignored);
+        }
+
+        assert_eq!(state(&reversed), state(&a));
+    }
+
+    #[test]
+    fn operations_wait_for_their_nodes() {
+        let mut a = Replica::new(1);
+        let mut b = Replica::new(2);
+
+        let (first, create_first) = a.create_node(TestNode::Value(1));
+        let (second, create_second) = a.create_node(TestNode::Sum);
+        let (_, connect) = a
+            .connect(
+                a.graph().get_output_port_at(first, 0).unwrap(),
+                a.graph().get_input_port_at(second, 0).unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(b.apply(connect), ApplyOutcome::Pending);
+        assert_eq!(b.apply(create_second), ApplyOutcome::Applied);
+        assert_eq!(b.pending().len(), 1);
+        assert_eq!(b.apply(create_first), ApplyOutcome::Applied);
+
+        assert!(b.pending().is_empty());
+        assert_eq!(state(&a), state(&b));
+    }
+}
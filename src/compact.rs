@@ -0,0 +1,339 @@
+use std::fmt::Debug;
+
+use slotmap::{Key, SecondaryMap, SlotMap, new_key_type};
+
+use crate::{
+    ConnectionId, Graph, INVALID_STATE, InputPortId, Node, NodeData, NodeId, OutputPortId,
+    PortList, port_names::NamedPorts,
+};
+
+new_key_type! { pub struct CompactListenerId; }
+
+/// Maps the ids a graph had before [`Graph::compact`] to the ones it has
+/// after. Ids of everything that was removed before compacting are missing.
+#[derive(Debug, Clone, Default)]
+pub struct IdRemapping {
+    pub nodes: SecondaryMap<NodeId, NodeId>,
+    pub input_ports: SecondaryMap<InputPortId, InputPortId>,
+    pub output_ports: SecondaryMap<OutputPortId, OutputPortId>,
+    pub connections: SecondaryMap<ConnectionId, ConnectionId>,
+}
+
+/// A kind of id that is handed out again by [`Graph::compact`]
+pub trait CompactedKey: Key {
+    fn mapping(remapping: &IdRemapping) -> &SecondaryMap<Self, Self>;
+}
+
+impl CompactedKey for NodeId {
+    fn mapping(remapping: &IdRemapping) -> &SecondaryMap<Self, Self> {
+        &remapping.nodes
+    }
+}
+
+impl CompactedKey for InputPortId {
+    fn mapping(remapping: &IdRemapping) -> &SecondaryMap<Self, Self> {
+        &remapping.input_ports
+    }
+}
+
+impl CompactedKey for OutputPortId {
+    fn mapping(remapping: &IdRemapping) -> &SecondaryMap<Self, Self> {
+        &remapping.output_ports
+    }
+}
+
+impl CompactedKey for ConnectionId {
+    fn mapping(remapping: &IdRemapping) -> &SecondaryMap<Self, Self> {
+        &remapping.connections
+    }
+}
+
+impl IdRemapping {
+    /// The new id of `old`
+    pub fn get<K: CompactedKey>(&self, old: K) -> Option<K> {
+        K::mapping(self).get(old).copied()
+    }
+
+    /// Move the values of `map` from old ids to new ones. Values of ids that
+    /// no longer exist are dropped.
+    pub fn remap<K: CompactedKey, V>(&self, map: SecondaryMap<K, V>) -> SecondaryMap<K, V> {
+        map.into_iter()
+            .filter_map(|(old, value)| Some((self.get(old)?, value)))
+            .collect()
+    }
+
+    fn expect<K: CompactedKey>(&self, old: K) -> K {
+        self.get(old).expect(INVALID_STATE)
+    }
+}
+
+type CompactListener = Box<dyn Fn(&IdRemapping) + Send + Sync>;
+
+/// Callbacks called after every compaction, see [`Graph::on_compact`]
+#[derive(Default)]
+pub(crate) struct CompactListeners {
+    listeners: SlotMap<CompactListenerId, CompactListener>,
+}
+
+impl Debug for CompactListeners {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.listeners.keys()).finish()
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Rebuild the storage of the graph without the gaps left by removed
+    /// nodes, ports and connections. Every id changes, the returned
+    /// remapping maps the old ones to the new ones. Old ids must not be used
+    /// anymore, they may point to something else now.
+    ///
+    /// Ids stored outside the graph can be updated with the remapping, or
+    /// from a listener registered with [`on_compact`](Self::on_compact).
+    /// [`StableId`](crate::stable_id::StableId)s and group ids are kept.
+    pub fn compact(&mut self) -> IdRemapping {
+        let mut remapping = IdRemapping::default();
+
+        // Move everything into new slot maps in the same order, then update
+        // the ids they refer to each other with
+
+        let mut node_data = SlotMap::with_capacity_and_key(self.node_data.len());
+
+        for (old, data) in std::mem::take(&mut self.node_data).drain() {
+            remapping.nodes.insert(old, node_data.insert(data));
+        }
+
+        let mut input_ports = SlotMap::with_capacity_and_key(self.input_ports.len());
+
+        for (old, port) in std::mem::take(&mut self.input_ports).drain() {
+            remapping.input_ports.insert(old, input_ports.insert(port));
+        }
+
+        let mut output_ports = SlotMap::with_capacity_and_key(self.output_ports.len());
+
+        for (old, port) in std::mem::take(&mut self.output_ports).drain() {
+            remapping
+                .output_ports
+                .insert(old, output_ports.insert(port));
+        }
+
+        let mut connections = SlotMap::with_capacity_and_key(self.connections.len());
+
+        for (old, connection) in std::mem::take(&mut self.connections).drain() {
+            remapping
+                .connections
+                .insert(old, connections.insert(connection));
+        }
+
+        for data in node_data.values_mut() {
+            let NodeData {
+                inputs,
+                outputs,
+                reroute,
+                metadata,
+                revision,
+            } = std::mem::take(data);
+
+            let inputs = inputs
+                .iter()
+                .map(|(name, port)| (name.clone(), remapping.expect(*port)))
+                .collect::<PortList<_>>();
+
+            let outputs = outputs
+                .iter()
+                .map(|(name, port)| (name.clone(), remapping.expect(*port)))
+                .collect::<PortList<_>>();
+
+            *data = NodeData {
+                inputs: NamedPorts::from_list(inputs),
+                outputs: NamedPorts::from_list(outputs),
+                reroute,
+                metadata,
+                revision,
+            };
+        }
+
+        for port in input_ports.values_mut().chain(output_ports.values_mut()) {
+            port.node = remapping.expect(port.node);
+
+            for connection in port
+                .incoming_connections
+                .iter_mut()
+                .chain(port.outgoing_connections.iter_mut())
+            {
+                *connection = remapping.expect(*connection);
+            }
+        }
+
+        for connection in connections.values_mut() {
+            connection.start_port = remapping.expect(connection.start_port);
+            connection.end_port = remapping.expect(connection.end_port);
+        }
+
+        self.node_data = node_data;
+        self.input_ports = input_ports;
+        self.output_ports = output_ports;
+        self.connections = connections;
+
+        self.nodes = remapping.remap(std::mem::take(&mut self.nodes));
+
+        self.variadic_inputs = remapping.remap(std::mem::take(&mut self.variadic_inputs));
+
+        for group in self.variadic_inputs.values_mut().flatten() {
+            for port in group.ports.iter_mut() {
+                *port = remapping.expect(*port);
+            }
+        }
+
+        self.node_names.remap(&remapping);
+        self.parameters.remap(&remapping);
+        self.node_groups = remapping.remap(std::mem::take(&mut self.node_groups));
+
+        for group in self.groups.values_mut() {
+            group.remap(&remapping);
+        }
+
+        self.observers.remap(&remapping);
+
+        if let Some(stable_ids) = &mut self.stable_ids {
+            stable_ids.remap(&remapping);
+        }
+
+        self.analysis.invalidate();
+        self.after_mutation();
+
+        for listener in self.compact_listeners.listeners.values() {
+            listener(&remapping);
+        }
+
+        remapping
+    }
+
+    /// Call `listener` with the remapping after every
+    /// [`compact`](Self::compact), so ids stored elsewhere can be updated
+    pub fn on_compact(
+        &mut self,
+        listener: impl Fn(&IdRemapping) + Send + Sync + 'static,
+    ) -> CompactListenerId {
+        self.compact_listeners.listeners.insert(Box::new(listener))
+    }
+
+    #[must_use]
+    pub fn remove_compact_listener(&mut self, listener: CompactListenerId) -> Option<()> {
+        self.compact_listeners
+            .listeners
+            .remove(listener)
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::test_util::{TestNode, chain, link};
+
+    /// The index of the slot an id points to
+    fn slot<K: Key>(key: K) -> u64 {
+        key.data().as_ffi() & 0xffff_ffff
+    }
+
+    #[test]
+    fn compact_remaps_every_id() {
+        let (mut graph, nodes) = chain(6);
+        graph.enable_stable_ids();
+
+        link(&mut graph, nodes[0], nodes[5]);
+        graph.set_node_name(nodes[4], "four").unwrap();
+        let group = graph.create_group("tail", [nodes[4], nodes[5]]);
+
+        graph.take_node(nodes[1]);
+        graph.take_node(nodes[2]);
+        graph.delete_input_port(nodes[5].input(1)).unwrap();
+
+        let kept = [nodes[0], nodes[3], nodes[4], nodes[5]];
+        let stable = kept.map(|node| graph.stable_ids().unwrap().nodes().get(node));
+        let connections = graph
+            .connections
+            .iter()
+            .map(|(id, connection)| {
+                let start = graph.output_ports[connection.start_port].node;
+                let end = graph.input_ports[connection.end_port].node;
+
+                (
+                    id,
+                    start,
+                    end,
+                    graph.input_ports[connection.end_port].name.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let remapping = graph.compact();
+        graph.debug_assert_consistent();
+
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(remapping.get(nodes[1]), None);
+        assert_eq!(remapping.get(nodes[2]), None);
+
+        for (node, stable) in kept.into_iter().zip(stable) {
+            let new = remapping.get(node).unwrap();
+
+            assert!(slot(new) <= 4);
+            assert_eq!(graph.get_node(new).as_deref(), Some(&TestNode::Sum));
+            assert_eq!(graph.stable_ids().unwrap().nodes().get(new), stable);
+        }
+
+        let four = remapping.get(nodes[4]).unwrap();
+        let five = remapping.get(nodes[5]).unwrap();
+
+        assert_eq!(graph.get_node_by_name("four"), Some(four));
+        assert_eq!(graph.group_of(four), Some(group));
+        assert_eq!(graph.group_of(five), Some(group));
+        assert_eq!(graph.get_input_ports(five).unwrap().len(), 1);
+
+        assert_eq!(graph.connection_count(), connections.len());
+
+        for (old, start, end, port_name) in connections {
+            let connection = graph.get_connection(remapping.get(old).unwrap()).unwrap();
+
+            assert!(slot(remapping.get(old).unwrap()) <= graph.connection_count() as u64);
+            assert_eq!(
+                graph.output_ports[connection.start_port].node,
+                remapping.get(start).unwrap()
+            );
+            assert_eq!(
+                graph.input_ports[connection.end_port].node,
+                remapping.get(end).unwrap()
+            );
+            assert_eq!(graph.input_ports[connection.end_port].name, port_name);
+        }
+    }
+
+    #[test]
+    fn listeners_and_maps_follow_the_remapping() {
+        let (mut graph, nodes) = chain(3);
+        let seen = Arc::new(Mutex::new(None));
+
+        let listener = graph.on_compact({
+            let seen = seen.clone();
+            move |remapping| *seen.lock().unwrap() = Some(remapping.clone())
+        });
+
+        let mut labels = SecondaryMap::new();
+        labels.insert(nodes[0], "first");
+        labels.insert(nodes[2], "last");
+
+        graph.take_node(nodes[0]);
+        let remapping = graph.compact();
+        let labels = remapping.remap(labels);
+
+        let seen = seen.lock().unwrap().take().unwrap();
+
+        assert_eq!(seen.get(nodes[2]), remapping.get(nodes[2]));
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[remapping.get(nodes[2]).unwrap()], "last");
+
+        assert!(graph.remove_compact_listener(listener).is_some());
+    }
+}
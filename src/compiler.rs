@@ -0,0 +1,459 @@
+use std::ops::Range;
+
+use slotmap::SecondaryMap;
+
+use crate::{Graph, InputPortId, Node, NodeId, OutputPortId, analyzer::GraphAnalyzer};
+
+/// A value slot in a [`Program`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Register(pub usize);
+
+/// The registers assigned to every port on an execution path. Every output
+/// port gets its own register, connected input ports read the register of the
+/// output they are connected to, and disconnected input ports get a register
+/// holding their default value.
+#[derive(Debug, Clone)]
+pub(crate) struct RegisterLayout<V> {
+    /// The evaluated nodes in order, with their input and output registers as
+    /// ranges into `operands`
+    pub(crate) nodes: Vec<(NodeId, Range<usize>, Range<usize>)>,
+    pub(crate) operands: Vec<Register>,
+    pub(crate) initial_values: Vec<Option<V>>,
+    pub(crate) input_registers: SecondaryMap<InputPortId, Register>,
+    pub(crate) output_registers: SecondaryMap<OutputPortId, Register>,
+}
+
+impl<N: Node> Graph<N> {
+    /// Assign registers to all ports of the nodes needed for `exit_nodes`, or
+    /// all nodes when `None`
+    pub(crate) fn register_layout(
+        &self,
+        exit_nodes: Option<&[NodeId]>,
+    ) -> RegisterLayout<N::DataValue> {
+        let analyzer = GraphAnalyzer::new(self);
+        let path = match exit_nodes {
+            Some(exit_nodes) => analyzer.generate_execution_path(exit_nodes),
+            None => analyzer.generate_complete_execution_path(),
+        };
+
+        let mut layout = RegisterLayout {
+            nodes: Vec::with_capacity(path.len()),
+            operands: Vec::new(),
+            initial_values: Vec::new(),
+            input_registers: SecondaryMap::new(),
+            output_registers: SecondaryMap::new(),
+        };
+
+        // Outputs first, so inputs can refer to nodes later on the path in
+        // graphs with cycles
+
+        for &node in path.iter() {
+            for &(_, port) in self.node_data[node].outputs.iter() {
+                layout
+                    .output_registers
+                    .insert(port, Register(layout.initial_values.len()));
+                layout.initial_values.push(None);
+            }
+        }
+
+        for &node in path.iter() {
+            let data = &self.node_data[node];
+
+            let inputs_start = layout.operands.len();
+
+            for &(_, port) in data.inputs.iter() {
+                let connected = self
+                    .get_sources(port)
+                    .find_map(|output| layout.output_registers.get(output).copied());
+
+                let register = connected.unwrap_or_else(|| {
+                    let register = Register(layout.initial_values.len());
+                    layout
+                        .initial_values
+                        .push(self.input_ports[port].default.clone());
+                    register
+                });
+
+                layout.input_registers.insert(port, register);
+                layout.operands.push(register);
+            }
+
+            let outputs_start = layout.operands.len();
+
+            for &(_, port) in data.outputs.iter() {
+                layout.operands.push(layout.output_registers[port]);
+            }
+
+            layout.nodes.push((
+                node,
+                inputs_start..outputs_start,
+                outputs_start..layout.operands.len(),
+            ));
+        }
+
+        layout
+    }
+
+    /// Lower the nodes needed for `exit_nodes`, or all nodes when `None`, to a
+    /// flat [`Program`]. `emit` turns each node into an operation, which is
+    /// interpreted by the callback passed to [`Vm::run`].
+    ///
+    /// The program is a copy, it doesn't change along with the graph.
+    pub fn compile_program<Op>(
+        &self,
+        exit_nodes: Option<&[NodeId]>,
+        emit: impl Fn(&N) -> Op,
+    ) -> Program<Op, N::DataValue> {
+        let layout = self.register_layout(exit_nodes);
+
+        let instructions = layout
+            .nodes
+            .iter()
+            .map(|(node, inputs, outputs)| Instruction {
+                op: emit(&self.nodes[*node].read()),
+                inputs: inputs.clone(),
+                outputs: outputs.clone(),
+            })
+            .collect();
+
+        Program {
+            instructions,
+            operands: layout.operands,
+            initial_values: layout.initial_values,
+            input_registers: layout.input_registers,
+            output_registers: layout.output_registers,
+        }
+    }
+}
+
+/// A single operation in a [`Program`], along with the registers it reads
+/// and writes
+#[derive(Debug, Clone)]
+pub struct Instruction<Op> {
+    pub op: Op,
+    inputs: Range<usize>,
+    outputs: Range<usize>,
+}
+
+/// A graph lowered to a list of instructions, see [`Graph::compile_program`]
+#[derive(Debug, Clone)]
+pub struct Program<Op, V> {
+    instructions: Vec<Instruction<Op>>,
+    operands: Vec<Register>,
+    initial_values: Vec<Option<V>>,
+    input_registers: SecondaryMap<InputPortId, Register>,
+    output_registers: SecondaryMap<OutputPortId, Register>,
+}
+
+impl<Op, V: Clone> Program<Op, V> {
+    pub fn instructions(&self) -> &[Instruction<Op>] {
+        &self.instructions
+    }
+
+    pub fn register_count(&self) -> usize {
+        self.initial_values.len()
+    }
+
+    /// The registers `instruction` reads, one per input port of its node
+    pub fn inputs_of(&self, instruction: &Instruction<Op>) -> &[Register] {
+        &self.operands[instruction.inputs.clone()]
+    }
+
+    /// The registers `instruction` writes, one per output port of its node
+    pub fn outputs_of(&self, instruction: &Instruction<Op>) -> &[Register] {
+        &self.operands[instruction.outputs.clone()]
+    }
+
+    /// The register an input port reads from. For disconnected ports this
+    /// holds the default value, which can be replaced with [`Vm::set`] to feed
+    /// the program new inputs.
+    pub fn input_register(&self, port: InputPortId) -> Option<Register> {
+        self.input_registers.get(port).copied()
+    }
+
+    /// The register an output port writes to
+    pub fn output_register(&self, port: OutputPortId) -> Option<Register> {
+        self.output_registers.get(port).copied()
+    }
+}
+
+/// Executes [`Program`]s, keeping the registers between runs
+#[derive(Debug, Clone)]
+pub struct Vm<V> {
+    registers: Vec<Option<V>>,
+}
+
+impl<V: Clone> Vm<V> {
+    /// Create a VM with registers for `program`, holding its default values
+    pub fn new<Op>(program: &Program<Op, V>) -> Self {
+        Self {
+            registers: program.initial_values.clone(),
+        }
+    }
+
+    /// Reset all registers to the default values of `program`
+    pub fn reset<Op>(&mut self, program: &Program<Op, V>) {
+        self.registers.clone_from(&program.initial_values);
+    }
+
+    pub fn get(&self, register: Register) -> Option<&V> {
+        self.registers.get(register.0)?.as_ref()
+    }
+
+    pub fn set(&mut self, register: Register, value: V) {
+        self.registers[register.0] = Some(value);
+    }
+
+    /// Execute every instruction in order, calling `execute` with each
+    /// operation and a frame to read its inputs and write its outputs
+    pub fn run<Op>(
+        &mut self,
+        program: &Program<Op, V>,
+        mut execute: impl FnMut(&Op, &mut Frame<V>),
+    ) {
+        for instruction in program.instructions.iter() {
+            let mut frame = Frame {
+                registers: &mut self.registers,
+                inputs: &program.operands[instruction.inputs.clone()],
+                outputs: &program.operands[instruction.outputs.clone()],
+            };
+
+            execute(&instruction.op, &mut frame);
+        }
+    }
+}
+
+/// The registers of the instruction being executed, indexed by port index
+#[derive(Debug)]
+pub struct Frame<'a, V> {
+    registers: &'a mut [Option<V>],
+    inputs: &'a [Register],
+    outputs: &'a [Register],
+}
+
+impl<'a, V> Frame<'a, V> {
+    /// The value of the input port at `index`
+    pub fn get(&self, index: usize) -> &V {
+        self.try_get(index)
+            .expect("No value present for input port")
+    }
+
+    pub fn try_get(&self, index: usize) -> Option<&V> {
+        self.registers[self.inputs[index].0].as_ref()
+    }
+
+    /// Set the value of the output port at `index`
+    pub fn set(&mut self, index: usize, value: V) {
+        self.registers[self.outputs[index].0] = Some(value);
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.outputs.len()
+    }
+}
+
+/// The frame passed to the closures of a [`CompiledGraph`]
+pub type ValueSlots<'a, V> = Frame<'a, V>;
+
+type CompiledNode<V> = Box<dyn Fn(&mut ValueSlots<V>)>;
+
+impl<N: Node> Graph<N> {
+    /// Turn the nodes needed for `exit_nodes`, or all nodes when `None`, into
+    /// a chain of closures created by `factory`. Ports are resolved to slots
+    /// up front, so running the chain doesn't touch the graph.
+    ///
+    /// This is a lighter alternative to [`compile_program`](Self::compile_program)
+    /// for when nodes don't need to be inspected after compiling.
+    pub fn compile(
+        &self,
+        exit_nodes: Option<&[NodeId]>,
+        factory: impl Fn(&N) -> CompiledNode<N::DataValue>,
+    ) -> CompiledGraph<N::DataValue> {
+        let layout = self.register_layout(exit_nodes);
+
+        let nodes = layout
+            .nodes
+            .into_iter()
+            .map(|(node, inputs, outputs)| (factory(&self.nodes[node].read()), inputs, outputs))
+            .collect();
+
+        CompiledGraph {
+            nodes,
+            operands: layout.operands,
+            slots: layout.initial_values.clone(),
+            initial_values: layout.initial_values,
+            input_registers: layout.input_registers,
+            output_registers: layout.output_registers,
+        }
+    }
+}
+
+/// A graph compiled to closures, see [`Graph::compile`]. Values are kept
+/// between runs.
+pub struct CompiledGraph<V> {
+    nodes: Vec<(CompiledNode<V>, Range<usize>, Range<usize>)>,
+    operands: Vec<Register>,
+    slots: Vec<Option<V>>,
+    initial_values: Vec<Option<V>>,
+    input_registers: SecondaryMap<InputPortId, Register>,
+    output_registers: SecondaryMap<OutputPortId, Register>,
+}
+
+impl<V: Clone> CompiledGraph<V> {
+    /// Run every closure in order
+    pub fn run(&mut self) {
+        for (node, inputs, outputs) in self.nodes.iter() {
+            node(&mut Frame {
+                registers: &mut self.slots,
+                inputs: &self.operands[inputs.clone()],
+                outputs: &self.operands[outputs.clone()],
+            });
+        }
+    }
+
+    /// Replace the value of a disconnected input port, which holds its
+    /// default value until then
+    pub fn set_input(&mut self, port: InputPortId, value: V) {
+        let register = self
+            .input_registers
+            .get(port)
+            .expect("Input port is not part of the compiled graph");
+
+        self.slots[register.0] = Some(value);
+    }
+
+    /// The value last written to an output port
+    pub fn get_output(&self, port: OutputPortId) -> Option<&V> {
+        self.slots[self.output_registers.get(port)?.0].as_ref()
+    }
+
+    /// Restore the default values of all input ports and clear all outputs
+    pub fn reset(&mut self) {
+        self.slots.clone_from(&self.initial_values);
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl<V> std::fmt::Debug for CompiledGraph<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompiledGraph")
+            .field("nodes", &self.nodes.len())
+            .field("slots", &self.slots.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestNode;
+
+    /// `(2 + 3) + b`, with `b` left at its default of 10. Returns the graph,
+    /// the last sum and the first sum.
+    fn sample() -> (Graph<TestNode>, NodeId, NodeId) {
+        let mut graph = Graph::new();
+
+        let two = graph.create_node(TestNode::Value(2));
+        let three = graph.create_node(TestNode::Value(3));
+        let inner = graph.create_node(TestNode::Sum);
+        let outer = graph.create_node(TestNode::Sum);
+
+        graph.connect(two.output(0), inner.input(0));
+        graph.connect(three.output(0), inner.input(1));
+        graph.connect(inner.output(0), outer.input(0));
+        graph.set_default_value(outer.input(1), 10);
+
+        (graph, outer, inner)
+    }
+
+    fn execute(op: &TestNode, frame: &mut Frame<i64>) {
+        match op {
+            TestNode::Value(value) => frame.set(0, *value),
+            TestNode::Sum => frame.set(0, frame.get(0) + frame.get(1)),
+        }
+    }
+
+    #[test]
+    fn programs_evaluate_in_dependency_order() {
+        let (graph, outer, inner) = sample();
+        let program = graph.compile_program(None, TestNode::clone);
+
+        assert_eq!(program.instructions().len(), 4);
+
+        // Connected inputs read the register of their output
+        let inner_output = program
+            .output_register(graph.get_output_port_at(inner, 0).unwrap())
+            .unwrap();
+        let outer_input = graph.get_input_port_at(outer, 0).unwrap();
+
+        assert_eq!(program.input_register(outer_input), Some(inner_output));
+
+        let mut vm = Vm::new(&program);
+        vm.run(&program, execute);
+
+        let result = program
+            .output_register(graph.get_output_port_at(outer, 0).unwrap())
+            .unwrap();
+
+        assert_eq!(vm.get(result), Some(&15));
+
+        // Disconnected inputs can be fed new values
+        let default = program
+            .input_register(graph.get_input_port_at(outer, 1).unwrap())
+            .unwrap();
+
+        vm.set(default, 20);
+        vm.run(&program, execute);
+        assert_eq!(vm.get(result), Some(&25));
+
+        vm.reset(&program);
+        assert_eq!(vm.get(result), None);
+        assert_eq!(vm.get(default), Some(&10));
+    }
+
+    #[test]
+    fn programs_only_contain_the_nodes_exits_need() {
+        let (graph, outer, inner) = sample();
+        let program = graph.compile_program(Some(&[inner]), TestNode::clone);
+
+        assert_eq!(program.instructions().len(), 3);
+        assert_eq!(
+            program.output_register(graph.get_output_port_at(outer, 0).unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn compiled_graphs_match_programs() {
+        let (graph, outer, _) = sample();
+
+        let mut compiled = graph.compile(None, |node| {
+            let node = node.clone();
+            Box::new(move |slots| execute(&node, slots))
+        });
+
+        let result = graph.get_output_port_at(outer, 0).unwrap();
+
+        compiled.run();
+        assert_eq!(compiled.get_output(result), Some(&15));
+
+        compiled.set_input(graph.get_input_port_at(outer, 1).unwrap(), -5);
+        compiled.run();
+        assert_eq!(compiled.get_output(result), Some(&0));
+
+        compiled.reset();
+        assert_eq!(compiled.get_output(result), None);
+        assert_eq!(compiled.len(), 4);
+    }
+}
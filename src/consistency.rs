@@ -0,0 +1,176 @@
+use std::fmt::Display;
+
+use slotmap::SecondaryMap;
+
+use crate::{Graph, Node};
+
+/// Returned by [`Graph::check_consistency`], describes the first broken
+/// invariant that was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inconsistency(pub String);
+
+impl Display for Inconsistency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Graph is inconsistent: {}", self.0)
+    }
+}
+
+impl std::error::Error for Inconsistency {}
+
+macro_rules! ensure {
+    ($condition:expr, $($message:tt)*) => {
+        if !$condition {
+            return Err(Inconsistency(format!($($message)*)));
+        }
+    };
+}
+
+impl<N: Node> Graph<N> {
+    /// Verify that nodes, ports, connections and names all agree with each
+    /// other. An inconsistent graph is always a bug in this crate.
+    pub fn check_consistency(&self) -> Result<(), Inconsistency> {
+        ensure!(
+            self.nodes.len() == self.node_data.len(),
+            "{} node values for {} nodes",
+            self.nodes.len(),
+            self.node_data.len(),
+        );
+
+        let mut listed_inputs = SecondaryMap::with_capacity(self.input_ports.len());
+        let mut listed_outputs = SecondaryMap::with_capacity(self.output_ports.len());
+
+        for (node, data) in self.node_data.iter() {
+            ensure!(self.nodes.contains_key(node), "{node:?} has no value");
+
+            for (index, (name, port)) in data.inputs.iter().enumerate() {
+                let info = self.input_ports.get(*port);
+
+                ensure!(info.is_some(), "{node:?} lists missing input {port:?}");
+                ensure!(
+                    info.is_some_and(|info| info.node == node),
+                    "{node:?} lists input {port:?} of another node",
+                );
+                ensure!(
+                    listed_inputs.insert(*port, ()).is_none(),
+                    "Input {port:?} is listed twice",
+                );
+
+                let first = data.inputs.iter().position(|(other, _)| other == name);
+                let indexed = data.inputs.by_name(name);
+
+                ensure!(
+                    first != Some(index) || indexed == Some(*port),
+                    "Input {name:?} of {node:?} is indexed as {indexed:?} instead of {port:?}",
+                );
+            }
+
+            for (index, (name, port)) in data.outputs.iter().enumerate() {
+                let info = self.output_ports.get(*port);
+
+                ensure!(info.is_some(), "{node:?} lists missing output {port:?}");
+                ensure!(
+                    info.is_some_and(|info| info.node == node),
+                    "{node:?} lists output {port:?} of another node",
+                );
+                ensure!(
+                    listed_outputs.insert(*port, ()).is_none(),
+                    "Output {port:?} is listed twice",
+                );
+
+                let first = data.outputs.iter().position(|(other, _)| other == name);
+                let indexed = data.outputs.by_name(name);
+
+                ensure!(
+                    first != Some(index) || indexed == Some(*port),
+                    "Output {name:?} of {node:?} is indexed as {indexed:?} instead of {port:?}",
+                );
+            }
+        }
+
+        for (id, port) in self.input_ports.iter() {
+            ensure!(
+                listed_inputs.contains_key(id),
+                "Input {id:?} is not listed by its node {:?}",
+                port.node,
+            );
+            ensure!(
+                port.outgoing_connections.is_empty(),
+                "Input {id:?} has outgoing connections",
+            );
+
+            for &connection in port.incoming_connections.iter() {
+                let end = self.connections.get(connection).map(|c| c.end_port);
+
+                ensure!(
+                    end == Some(id),
+                    "Input {id:?} lists {connection:?}, which ends at {end:?}",
+                );
+            }
+        }
+
+        for (id, port) in self.output_ports.iter() {
+            ensure!(
+                listed_outputs.contains_key(id),
+                "Output {id:?} is not listed by its node {:?}",
+                port.node,
+            );
+            ensure!(
+                port.incoming_connections.is_empty(),
+                "Output {id:?} has incoming connections",
+            );
+
+            for &connection in port.outgoing_connections.iter() {
+                let start = self.connections.get(connection).map(|c| c.start_port);
+
+                ensure!(
+                    start == Some(id),
+                    "Output {id:?} lists {connection:?}, which starts at {start:?}",
+                );
+            }
+        }
+
+        for (id, connection) in self.connections.iter() {
+            let listed_at_start = self
+                .output_ports
+                .get(connection.start_port)
+                .is_some_and(|port| port.outgoing_connections.contains(&id));
+
+            let listed_at_end = self
+                .input_ports
+                .get(connection.end_port)
+                .is_some_and(|port| port.incoming_connections.contains(&id));
+
+            ensure!(listed_at_start, "{id:?} is not listed by its output");
+            ensure!(listed_at_end, "{id:?} is not listed by its input");
+        }
+
+        self.node_names
+            .check_consistency(|node| self.node_data.contains_key(node))
+            .map_err(Inconsistency)?;
+
+        self.parameters
+            .check_consistency(|port| self.input_ports.contains_key(port))
+            .map_err(Inconsistency)
+    }
+
+    /// Panic if [`check_consistency`](Self::check_consistency) fails. Does
+    /// nothing in release builds.
+    ///
+    /// With the `consistency-checks` feature, this runs after every change to
+    /// the graph, so a bug is caught by the change that caused it instead of
+    /// a later read.
+    #[track_caller]
+    pub fn debug_assert_consistent(&self) {
+        if cfg!(debug_assertions)
+            && let Err(error) = self.check_consistency()
+        {
+            panic!("{error}");
+        }
+    }
+
+    #[inline]
+    pub(crate) fn after_mutation(&self) {
+        #[cfg(feature = "consistency-checks")]
+        self.debug_assert_consistent();
+    }
+}
@@ -0,0 +1,254 @@
+use slotmap::SecondaryMap;
+
+use crate::{
+    Connection, Graph, INVALID_STATE, Node, NodeData, NodeId, Port, PortList, batch::GraphOp,
+    cell::NodeCell, metadata::Metadata, port_names::NamedPorts, variadic::VariadicInput,
+};
+
+/// A detached copy of a set of nodes and the connections between them, used
+/// to duplicate nodes within a graph or move them to another one
+#[derive(Debug, Clone)]
+pub(crate) struct CopiedNodes<N: Node + Clone> {
+    nodes: Vec<CopiedNode<N>>,
+    /// Connections as (start node, output index, end node, input index,
+    /// metadata), where nodes are indices into `nodes`
+    connections: Vec<(usize, usize, usize, usize, Metadata)>,
+}
+
+#[derive(Debug, Clone)]
+struct CopiedNode<N: Node + Clone> {
+    original: NodeId,
+    value: N,
+    name: Option<String>,
+    reroute: bool,
+    metadata: Metadata,
+    inputs: Vec<Port<N>>,
+    outputs: Vec<Port<N>>,
+    /// Variadic inputs, with their ports as indices into `inputs`
+    variadic_inputs: Vec<(VariadicInput<N>, Vec<usize>)>,
+}
+
+impl<N: Node + Clone> Graph<N> {
+    /// Copy `nodes` along with their ports and the connections between them.
+    /// Connections to nodes outside of the set are left out.
+    pub(crate) fn copy_nodes(&self, nodes: &[NodeId]) -> CopiedNodes<N> {
+        let mut index_of = SecondaryMap::with_capacity(nodes.len());
+
+        for (index, &id) in nodes.iter().enumerate() {
+            index_of.insert(id, index);
+        }
+
+        let mut copied = CopiedNodes {
+            nodes: Vec::with_capacity(nodes.len()),
+            connections: Vec::new(),
+        };
+
+        for &id in nodes {
+            let data = self.node_data.get(id).expect("Node does not exist");
+
+            let copy_port = |port: &Port<N>| {
+                let mut port = port.clone();
+                port.incoming_connections.clear();
+                port.outgoing_connections.clear();
+                port
+            };
+
+            let inputs = data
+                .inputs
+                .iter()
+                .map(|&(_, port)| copy_port(&self.input_ports[port]))
+                .collect();
+
+            let outputs = data
+                .outputs
+                .iter()
+                .map(|&(_, port)| copy_port(&self.output_ports[port]))
+                .collect();
+
+            let variadic_inputs = self
+                .variadic_inputs
+                .get(id)
+                .into_iter()
+                .flatten()
+                .map(|group| {
+                    let ports = group
+                        .ports
+                        .iter()
+                        .map(|&port| {
+                            data.inputs
+                                .iter()
+                                .position(|&(_, other)| other == port)
+                                .expect(INVALID_STATE)
+                        })
+                        .collect();
+
+                    (group.clone(), ports)
+                })
+                .collect();
+
+            copied.nodes.push(CopiedNode {
+                original: id,
+                value: self.nodes[id].read().clone(),
+                name: self.get_node_name(id).map(str::to_string),
+                reroute: data.reroute,
+                metadata: data.metadata.clone(),
+                inputs,
+                outputs,
+                variadic_inputs,
+            });
+        }
+
+        for (end_index, &id) in nodes.iter().enumerate() {
+            for (input_index, &(_, port)) in self.node_data[id].inputs.iter().enumerate() {
+                for &connection in self.input_ports[port].incoming_connections.iter() {
+                    let connection = &self.connections[connection];
+                    let start_port = connection.start_port;
+                    let start = &self.output_ports[start_port];
+
+                    let Some(&start_index) = index_of.get(start.node) else {
+                        continue;
+                    };
+
+                    let output_index = self.node_data[start.node]
+                        .outputs
+                        .iter()
+                        .position(|&(_, other)| other == start_port)
+                        .expect(INVALID_STATE);
+
+                    copied.connections.push((
+                        start_index,
+                        output_index,
+                        end_index,
+                        input_index,
+                        connection.metadata.clone(),
+                    ));
+                }
+            }
+        }
+
+        copied
+    }
+
+    /// Insert copied nodes into this graph, returning a map from the original
+    /// node ids to the new ones.
+    ///
+    /// Like [`restore`](Self::restore), this does not notify nodes; they
+    /// already hold the state of the originals.
+    pub(crate) fn paste_nodes(&mut self, copied: CopiedNodes<N>) -> SecondaryMap<NodeId, NodeId> {
+        let mut mapping = SecondaryMap::with_capacity(copied.nodes.len());
+        let mut new_nodes = Vec::with_capacity(copied.nodes.len());
+
+        for node in copied.nodes {
+            let id = self.node_data.insert(NodeData::default());
+
+            let inputs = node
+                .inputs
+                .into_iter()
+                .map(|mut port| {
+                    // The nodes may come from another graph
+                    port.node = id;
+                    port.name = self.port_names.intern(&port.name);
+                    (port.name.clone(), self.input_ports.insert(port))
+                })
+                .collect::<PortList<_>>();
+
+            let outputs = node
+                .outputs
+                .into_iter()
+                .map(|mut port| {
+                    // The nodes may come from another graph
+                    port.node = id;
+                    port.name = self.port_names.intern(&port.name);
+                    (port.name.clone(), self.output_ports.insert(port))
+                })
+                .collect::<PortList<_>>();
+
+            if !node.variadic_inputs.is_empty() {
+                let groups = node
+                    .variadic_inputs
+                    .into_iter()
+                    .map(|(mut group, ports)| {
+                        group.ports = ports.into_iter().map(|index| inputs[index].1).collect();
+                        group
+                    })
+                    .collect();
+
+                self.variadic_inputs.insert(id, groups);
+            }
+
+            self.node_data[id] = NodeData {
+                inputs: NamedPorts::from_list(inputs),
+                outputs: NamedPorts::from_list(outputs),
+                reroute: node.reroute,
+                metadata: node.metadata,
+                revision: 0,
+            };
+            self.nodes.insert(id, NodeCell::new(node.value));
+
+            if self.mutation_logging().is_some() {
+                self.log_node_with_ports(id);
+
+                // The copied default values may differ from the initial ones
+                let ports = self.node_data[id].inputs.iter().map(|&(_, port)| port);
+
+                for port in ports.collect::<Vec<_>>() {
+                    if let Some(value) = self.input_ports[port].default.clone() {
+                        self.log_mutation(GraphOp::SetDefaultValue { port, value });
+                    }
+                }
+            }
+
+            if let Some(stable_ids) = &mut self.stable_ids {
+                stable_ids.track_node(id, &self.node_data[id]);
+            }
+
+            if let Some(name) = node.name {
+                // Under a unique naming policy, the copy stays unnamed
+                let _ = self.set_node_name(id, &name);
+            }
+
+            mapping.insert(node.original, id);
+            new_nodes.push(id);
+        }
+
+        for (start_index, output_index, end_index, input_index, metadata) in copied.connections {
+            let start_port = self.node_data[new_nodes[start_index]].outputs[output_index].1;
+            let end_port = self.node_data[new_nodes[end_index]].inputs[input_index].1;
+
+            let connection = self.connections.insert(Connection {
+                start_port,
+                end_port,
+                metadata,
+            });
+
+            self.output_ports[start_port]
+                .outgoing_connections
+                .push(connection);
+            self.input_ports[end_port]
+                .incoming_connections
+                .push(connection);
+
+            if let Some(stable_ids) = &mut self.stable_ids {
+                stable_ids.track_connection(connection);
+            }
+
+            self.log_mutation(GraphOp::Connect {
+                start_port: start_port.into(),
+                end_port: end_port.into(),
+            });
+        }
+
+        self.analysis.invalidate();
+
+        // Connections to nodes that weren't copied are gone, so variadic inputs
+        // may have to shrink
+
+        for node in new_nodes {
+            self.update_variadic_inputs(node);
+        }
+
+        self.after_mutation();
+
+        mapping
+    }
+}
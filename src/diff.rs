@@ -0,0 +1,293 @@
+use std::collections::{HashMap, HashSet};
+
+use slotmap::SecondaryMap;
+
+use crate::{Graph, INVALID_STATE, Node, NodeId};
+
+/// A connection described by node ids and port names, so it can be compared
+/// between graphs where port ids differ
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConnectionDiff {
+    pub start_node: NodeId,
+    pub start_port: String,
+    pub end_node: NodeId,
+    pub end_port: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DefaultChange<V> {
+    pub old_node: NodeId,
+    pub new_node: NodeId,
+    pub port: String,
+    pub old: Option<V>,
+    pub new: Option<V>,
+}
+
+/// The differences between two graphs, as returned by [`diff`], [`diff_stable`]
+/// or [`diff_by`].
+/// Ids in `removed_*` fields refer to the old graph, all other ids refer to the
+/// new graph.
+#[derive(Debug, Clone)]
+pub struct GraphDiff<N: Node> {
+    pub added_nodes: Vec<NodeId>,
+    pub removed_nodes: Vec<NodeId>,
+    pub changed_defaults: Vec<DefaultChange<N::DataValue>>,
+    pub added_connections: Vec<ConnectionDiff>,
+    pub removed_connections: Vec<ConnectionDiff>,
+}
+
+impl<N: Node> GraphDiff<N> {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.changed_defaults.is_empty()
+            && self.added_connections.is_empty()
+            && self.removed_connections.is_empty()
+    }
+}
+
+/// Compare two graphs, matching nodes by their [`NodeId`]. This is only useful
+/// for two versions of the same graph, for example one of them restored from a
+/// [`snapshot`](Graph::snapshot) of the other.
+pub fn diff<N: Node>(old: &Graph<N>, new: &Graph<N>) -> GraphDiff<N>
+where
+    N::DataValue: PartialEq,
+{
+    let matching = old
+        .node_data
+        .keys()
+        .filter(|&id| new.node_data.contains_key(id))
+        .map(|id| (id, id))
+        .collect();
+
+    diff_with_matching(old, new, matching)
+}
+
+/// Compare two graphs, matching nodes by their [`StableId`](crate::stable_id::StableId).
+/// Both graphs need to have [stable ids enabled](Graph::enable_stable_ids).
+pub fn diff_stable<N: Node>(old: &Graph<N>, new: &Graph<N>) -> GraphDiff<N>
+where
+    N::DataValue: PartialEq,
+{
+    let old_ids = old.stable_ids().expect("Stable ids are not enabled");
+    let new_ids = new.stable_ids().expect("Stable ids are not enabled");
+
+    let matching = old_ids
+        .nodes()
+        .iter()
+        .filter_map(|(id, stable)| Some((id, new_ids.nodes().resolve(stable)?)))
+        .collect();
+
+    diff_with_matching(old, new, matching)
+}
+
+/// Compare two graphs, matching each node in `old` to the first unmatched node
+/// in `new` for which `matcher` returns true
+pub fn diff_by<N: Node>(
+    old: &Graph<N>,
+    new: &Graph<N>,
+    matcher: impl Fn(&N, &N) -> bool,
+) -> GraphDiff<N>
+where
+    N::DataValue: PartialEq,
+{
+    let mut matching = SecondaryMap::new();
+    let mut taken = SecondaryMap::<NodeId, ()>::new();
+
+    for (old_id, old_node) in old.nodes.iter() {
+        let old_node = old_node.read();
+
+        let found = new.nodes.iter().find(|&(new_id, new_node)| {
+            !taken.contains_key(new_id) && matcher(&old_node, &new_node.read())
+        });
+
+        if let Some((new_id, _)) = found {
+            matching.insert(old_id, new_id);
+            taken.insert(new_id, ());
+        }
+    }
+
+    diff_with_matching(old, new, matching)
+}
+
+/// Compare two graphs using a precomputed mapping from node ids in `old` to
+/// node ids in `new`. Unmapped nodes are considered removed or added.
+pub fn diff_with_matching<N: Node>(
+    old: &Graph<N>,
+    new: &Graph<N>,
+    matching: SecondaryMap<NodeId, NodeId>,
+) -> GraphDiff<N>
+where
+    N::DataValue: PartialEq,
+{
+    let matched_new = matching.values().copied().collect::<HashSet<NodeId>>();
+
+    let removed_nodes = old
+        .node_data
+        .keys()
+        .filter(|&id| !matching.contains_key(id))
+        .collect();
+
+    let added_nodes = new
+        .node_data
+        .keys()
+        .filter(|id| !matched_new.contains(id))
+        .collect();
+
+    let mut changed_defaults = Vec::new();
+
+    for (old_id, &new_id) in matching.iter() {
+        let old_data = old
+            .node_data
+            .get(old_id)
+            .expect("Matched node does not exist");
+        let new_data = new
+            .node_data
+            .get(new_id)
+            .expect("Matched node does not exist");
+
+        for (name, old_port) in old_data.inputs.iter() {
+            let Some((_, new_port)) = new_data.inputs.iter().find(|(n, _)| n == name) else {
+                continue;
+            };
+
+            let old_default = &old.input_ports.get(*old_port).expect(INVALID_STATE).default;
+            let new_default = &new.input_ports.get(*new_port).expect(INVALID_STATE).default;
+
+            if old_default != new_default {
+                changed_defaults.push(DefaultChange {
+                    old_node: old_id,
+                    new_node: new_id,
+                    port: name.to_string(),
+                    old: old_default.clone(),
+                    new: new_default.clone(),
+                });
+            }
+        }
+    }
+
+    let old_connections = connection_diffs(old).collect::<Vec<_>>();
+    let new_connections = connection_diffs(new).collect::<Vec<_>>();
+    // Parallel connections are allowed, so connections are counted instead of
+    // collected into a set
+    let mut unmatched = HashMap::<ConnectionDiff, usize>::new();
+    for connection in &new_connections {
+        *unmatched.entry(connection.clone()).or_default() += 1;
+    }
+
+    let mut kept = HashMap::<ConnectionDiff, usize>::new();
+    let mut removed_connections = Vec::new();
+
+    for connection in old_connections {
+        let mapped = matching
+            .get(connection.start_node)
+            .zip(matching.get(connection.end_node))
+            .map(|(&start_node, &end_node)| ConnectionDiff {
+                start_node,
+                start_port: connection.start_port.clone(),
+                end_node,
+                end_port: connection.end_port.clone(),
+            })
+            .filter(|mapped| unmatched.get(mapped).is_some_and(|&count| count > 0));
+
+        match mapped {
+            Some(mapped) => {
+                *unmatched.get_mut(&mapped).expect(INVALID_STATE) -= 1;
+                *kept.entry(mapped).or_default() += 1;
+            }
+            None => removed_connections.push(connection),
+        }
+    }
+
+    let added_connections = new_connections
+        .into_iter()
+        .filter(|connection| match kept.get_mut(connection) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect();
+
+    GraphDiff {
+        added_nodes,
+        removed_nodes,
+        changed_defaults,
+        added_connections,
+        removed_connections,
+    }
+}
+
+fn connection_diffs<N: Node>(graph: &Graph<N>) -> impl Iterator<Item = ConnectionDiff> + '_ {
+    graph.connections.values().map(|connection| {
+        let start = graph
+            .output_ports
+            .get(connection.start_port)
+            .expect(INVALID_STATE);
+
+        let end = graph
+            .input_ports
+            .get(connection.end_port)
+            .expect(INVALID_STATE);
+
+        ConnectionDiff {
+            start_node: start.node,
+            start_port: start.name.to_string(),
+            end_node: end.node,
+            end_port: end.name.to_string(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TestNode, link};
+
+    fn pair() -> (Graph<TestNode>, NodeId, NodeId) {
+        let mut graph = Graph::new();
+        let start = graph.create_node(TestNode::Value(1));
+        let end = graph.create_node(TestNode::Sum);
+        (graph, start, end)
+    }
+
+    fn copy(graph: &Graph<TestNode>) -> Graph<TestNode> {
+        let mut copy = Graph::new();
+        copy.restore(graph.snapshot());
+        copy
+    }
+
+    #[test]
+    fn parallel_connections_are_counted() {
+        let (mut old, start, end) = pair();
+        link(&mut old, start, end);
+
+        let mut new = copy(&old);
+        link(&mut new, start, end);
+        link(&mut new, start, end);
+
+        let added = diff(&old, &new);
+        assert_eq!(added.added_connections.len(), 2);
+        assert!(added.removed_connections.is_empty());
+        assert!(
+            added
+                .added_connections
+                .iter()
+                .all(|connection| { connection.start_node == start && connection.end_node == end })
+        );
+
+        let removed = diff(&new, &old);
+        assert!(removed.added_connections.is_empty());
+        assert_eq!(removed.removed_connections.len(), 2);
+    }
+
+    #[test]
+    fn identical_graphs_have_no_diff() {
+        let (mut graph, start, end) = pair();
+        link(&mut graph, start, end);
+        link(&mut graph, start, end);
+
+        assert!(diff(&graph, &copy(&graph)).is_empty());
+    }
+}
@@ -0,0 +1,890 @@
+use std::{collections::HashMap, fmt::Display};
+
+use slotmap::SecondaryMap;
+
+use crate::{
+    ConnectError, DataType, Graph, INVALID_STATE, Node, NodeId,
+    metadata::Metadata,
+    migration::GraphMigrator,
+    naming::NameTaken,
+    parameter::ExposeParameterError,
+    registry::{NodeRegistry, RegistryArgs},
+    stable_id::StableId,
+    variadic::VariadicInput,
+};
+
+/// A node that can be saved in a [`GraphDocument`], as the name of its kind
+/// in a [`NodeRegistry`] along with the arguments that recreate it
+pub trait DocumentNode: Node {
+    /// The name this node's kind is registered under
+    fn kind(&self) -> &str;
+
+    /// The arguments the registry constructor needs to recreate this node
+    fn args(&self) -> RegistryArgs {
+        RegistryArgs::new()
+    }
+}
+
+/// A graph in a form that can be saved and loaded with serde. Nodes are
+/// stored by kind name and ports by name, so documents stay readable after
+/// node types change, if need be with help from a
+/// [migration](crate::migration).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: serde::Serialize, V: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, V: serde::Deserialize<'de>"
+    ))
+)]
+pub struct GraphDocument<T, V> {
+    /// The version of the document format the graph was saved with, see
+    /// [`GraphMigrator`]
+    pub version: u32,
+    pub nodes: Vec<NodeEntry<T, V>>,
+    pub connections: Vec<ConnectionEntry>,
+    /// See [`Graph::metadata`]
+    pub metadata: Metadata,
+    /// Whether the ids of nodes, ports and connections are the graph's
+    /// [`StableId`]s, which are restored when loading
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stable_ids: bool,
+    /// See [`Graph::create_group`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub groups: Vec<GroupEntry>,
+    /// See [`Graph::expose_parameter`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parameters: Vec<ParameterEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: serde::Serialize, V: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, V: serde::Deserialize<'de>"
+    ))
+)]
+pub struct NodeEntry<T, V> {
+    /// Identifies the node within the document
+    pub id: u64,
+    /// See [`DocumentNode::kind`]
+    pub kind: String,
+    pub args: RegistryArgs,
+    pub name: Option<String>,
+    /// See [`Graph::get_node_metadata`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: Metadata,
+    /// See [`Graph::is_reroute`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub reroute: bool,
+    pub inputs: Vec<InputEntry<T, V>>,
+    pub outputs: Vec<OutputEntry<T>>,
+    /// See [`Graph::create_variadic_input`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub variadic_inputs: Vec<VariadicEntry<T, V>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: serde::Serialize, V: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, V: serde::Deserialize<'de>"
+    ))
+)]
+pub struct InputEntry<T, V> {
+    pub name: String,
+    pub ty: T,
+    pub default: Option<V>,
+    pub metadata: Metadata,
+    /// The port's [`StableId`], if the document has them
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub id: Option<u64>,
+    /// See [`Graph::set_max_incoming`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_incoming: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutputEntry<T> {
+    pub name: String,
+    pub ty: T,
+    pub metadata: Metadata,
+    /// The port's [`StableId`], if the document has them
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub id: Option<u64>,
+    /// See [`Graph::set_max_outgoing`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_outgoing: Option<usize>,
+}
+
+/// A group of input ports that grows as it is connected, see
+/// [`Graph::create_variadic_input`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: serde::Serialize, V: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, V: serde::Deserialize<'de>"
+    ))
+)]
+pub struct VariadicEntry<T, V> {
+    pub base_name: String,
+    pub ty: T,
+    pub default: V,
+    /// The names of the node's inputs that are part of the group, in order
+    pub ports: Vec<String>,
+}
+
+/// A port of a node in a [`GraphDocument`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PortEntry {
+    pub node: u64,
+    pub port: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectionEntry {
+    pub start: PortEntry,
+    pub end: PortEntry,
+    pub metadata: Metadata,
+    /// The connection's [`StableId`], if the document has them
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub id: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroupEntry {
+    pub name: String,
+    pub nodes: Vec<u64>,
+}
+
+/// An input port exposed as a parameter of the graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParameterEntry {
+    pub name: String,
+    pub port: PortEntry,
+}
+
+/// A node type that can stand in for nodes of kinds the registry doesn't
+/// know, like nodes from a newer version of an application or a plugin that
+/// isn't loaded, see [`Graph::from_document_with`]
+pub trait PlaceholderNode: DocumentNode {
+    /// A placeholder for a node of `kind`. It should have no initial ports,
+    /// since the saved ports are added to it, and return `kind` and `args`
+    /// from [`DocumentNode::kind`] and [`DocumentNode::args`] so it is saved
+    /// unchanged.
+    fn placeholder(kind: &str, args: &RegistryArgs) -> Self;
+
+    fn is_placeholder(&self) -> bool;
+}
+
+/// What [`Graph::from_document_with`] does with nodes of kinds the registry
+/// doesn't know
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownKindPolicy {
+    /// Fail with [`LoadError::UnknownKind`]
+    #[default]
+    Fail,
+    /// Load them as [placeholders](PlaceholderNode), keeping their ports and
+    /// connections
+    Preserve,
+}
+
+/// Returned by [`Graph::from_document`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError<T: DataType> {
+    /// The document was saved with a newer version than the migrator knows
+    NewerVersion { version: u32, current: u32 },
+    /// Migrating the document from `version` to the next version failed
+    Migration { version: u32, message: String },
+    /// The registry has no node kind with this name
+    UnknownKind { node: u64, kind: String },
+    /// Two nodes have the same id
+    DuplicateNode(u64),
+    /// The graph's [naming policy](Graph::set_node_name_policy) doesn't allow
+    /// this name twice
+    NameTaken { node: u64, name: String },
+    /// A connection refers to a node or port that isn't in the document
+    MissingPort(PortEntry),
+    /// Connection `index` of the document can't be made
+    Connection {
+        index: usize,
+        error: ConnectError<T>,
+    },
+    Parameter {
+        name: String,
+        error: ExposeParameterError,
+    },
+}
+
+impl<T: DataType> Display for LoadError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NewerVersion { version, current } => write!(
+                f,
+                "Document version {version} is newer than the supported version {current}"
+            ),
+            Self::Migration { version, message } => {
+                write!(f, "Migrating from version {version} failed: {message}")
+            }
+            Self::UnknownKind { node, kind } => {
+                write!(f, "Node {node} has unknown kind {kind:?}")
+            }
+            Self::DuplicateNode(node) => write!(f, "Node id {node} is used more than once"),
+            Self::NameTaken { node, name } => {
+                write!(f, "Node {node} can't be named {name:?}, the name is taken")
+            }
+            Self::MissingPort(port) => {
+                write!(f, "Node {} has no port {:?}", port.node, port.port)
+            }
+            Self::Connection { index, error } => {
+                write!(f, "Connection {index} is invalid: {error}")
+            }
+            Self::Parameter { name, error } => {
+                write!(f, "Parameter {name:?} can't be exposed: {error}")
+            }
+        }
+    }
+}
+
+impl<T: DataType> std::error::Error for LoadError<T> {}
+
+impl<T, V> GraphDocument<T, V> {
+    pub fn node(&self, id: u64) -> Option<&NodeEntry<T, V>> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    pub fn node_mut(&mut self, id: u64) -> Option<&mut NodeEntry<T, V>> {
+        self.nodes.iter_mut().find(|node| node.id == id)
+    }
+
+    /// All nodes of kind `kind`, for fixing their arguments or defaults
+    pub fn nodes_of_kind_mut<'a>(
+        &'a mut self,
+        kind: &'a str,
+    ) -> impl Iterator<Item = &'a mut NodeEntry<T, V>> + 'a {
+        self.nodes.iter_mut().filter(move |node| node.kind == kind)
+    }
+
+    /// Rename a node kind, returning how many nodes were changed
+    pub fn rename_kind(&mut self, from: &str, to: &str) -> usize {
+        self.nodes_of_kind_mut(from)
+            .map(|node| node.kind = to.to_string())
+            .count()
+    }
+
+    /// Rename an input port of every node of kind `kind`, along with the
+    /// connections and parameters referring to it
+    pub fn rename_input(&mut self, kind: &str, from: &str, to: &str) {
+        for node in self.nodes.iter_mut().filter(|node| node.kind == kind) {
+            for input in node.inputs.iter_mut().filter(|input| input.name == from) {
+                input.name = to.to_string();
+            }
+
+            for variadic in node.variadic_inputs.iter_mut() {
+                for port in variadic.ports.iter_mut().filter(|port| *port == from) {
+                    *port = to.to_string();
+                }
+            }
+
+            let ports = self
+                .connections
+                .iter_mut()
+                .map(|connection| &mut connection.end)
+                .chain(
+                    self.parameters
+                        .iter_mut()
+                        .map(|parameter| &mut parameter.port),
+                );
+
+            for port in ports {
+                if port.node == node.id && port.port == from {
+                    port.port = to.to_string();
+                }
+            }
+        }
+    }
+
+    /// Rename an output port of every node of kind `kind`, along with the
+    /// connections from it
+    pub fn rename_output(&mut self, kind: &str, from: &str, to: &str) {
+        for node in self.nodes.iter_mut().filter(|node| node.kind == kind) {
+            for output in node.outputs.iter_mut().filter(|output| output.name == from) {
+                output.name = to.to_string();
+            }
+
+            for connection in self.connections.iter_mut() {
+                if connection.start.node == node.id && connection.start.port == from {
+                    connection.start.port = to.to_string();
+                }
+            }
+        }
+    }
+
+    /// Remove a node along with its connections and parameters
+    pub fn remove_node(&mut self, id: u64) -> Option<NodeEntry<T, V>> {
+        let index = self.nodes.iter().position(|node| node.id == id)?;
+
+        self.connections
+            .retain(|connection| connection.start.node != id && connection.end.node != id);
+        self.parameters
+            .retain(|parameter| parameter.port.node != id);
+
+        for group in self.groups.iter_mut() {
+            group.nodes.retain(|&node| node != id);
+        }
+
+        Some(self.nodes.remove(index))
+    }
+}
+
+impl<N: DocumentNode> Graph<N> {
+    /// Convert the graph to a document that can be saved, stamped with
+    /// `version`. Usually that is the
+    /// [current version](GraphMigrator::current_version) of the migrator
+    /// that will load it.
+    pub fn to_document(&self, version: u32) -> GraphDocument<N::DataType, N::DataValue> {
+        let stable_ids = self.stable_ids.as_ref();
+
+        let ids = match stable_ids {
+            Some(stable_ids) => {
+                // Nodes can only lack a stable id after another node was
+                // assigned theirs, those are numbered after the others
+                let mut next = stable_ids
+                    .nodes()
+                    .iter()
+                    .map(|(_, id)| id.0 + 1)
+                    .max()
+                    .unwrap_or(0);
+
+                self.node_data
+                    .keys()
+                    .map(|node| {
+                        let id = stable_ids.nodes().get(node).map_or_else(
+                            || {
+                                next += 1;
+                                next - 1
+                            },
+                            |id| id.0,
+                        );
+
+                        (node, id)
+                    })
+                    .collect::<SecondaryMap<_, _>>()
+            }
+            None => self
+                .node_data
+                .keys()
+                .enumerate()
+                .map(|(index, node)| (node, index as u64))
+                .collect::<SecondaryMap<_, _>>(),
+        };
+
+        let nodes = self
+            .node_data
+            .iter()
+            .map(|(id, data)| {
+                let node = self.nodes[id].read();
+
+                NodeEntry {
+                    id: ids[id],
+                    kind: node.kind().to_string(),
+                    args: node.args(),
+                    name: self.get_node_name(id).map(str::to_string),
+                    metadata: data.metadata.clone(),
+                    reroute: data.reroute,
+                    inputs: data
+                        .inputs
+                        .iter()
+                        .map(|(name, port)| InputEntry {
+                            name: name.to_string(),
+                            ty: self.input_ports[*port].ty,
+                            default: self.input_ports[*port].default.clone(),
+                            metadata: self.input_ports[*port].metadata.clone(),
+                            id: stable_ids
+                                .and_then(|stable_ids| stable_ids.input_ports().get(*port))
+                                .map(|id| id.0),
+                            max_incoming: self.input_ports[*port].max_incoming,
+                        })
+                        .collect(),
+                    outputs: data
+                        .outputs
+                        .iter()
+                        .map(|(name, port)| OutputEntry {
+                            name: name.to_string(),
+                            ty: self.output_ports[*port].ty,
+                            metadata: self.output_ports[*port].metadata.clone(),
+                            id: stable_ids
+                                .and_then(|stable_ids| stable_ids.output_ports().get(*port))
+                                .map(|id| id.0),
+                            max_outgoing: self.output_ports[*port].max_outgoing,
+                        })
+                        .collect(),
+                    variadic_inputs: self
+                        .variadic_inputs
+                        .get(id)
+                        .into_iter()
+                        .flatten()
+                        .map(|group| VariadicEntry {
+                            base_name: group.base_name.clone(),
+                            ty: group.ty,
+                            default: group.default.clone(),
+                            ports: group
+                                .ports
+                                .iter()
+                                .map(|&port| self.input_ports[port].name.to_string())
+                                .collect(),
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let port_entry = |node, name: &str| PortEntry {
+            node: ids[node],
+            port: name.to_string(),
+        };
+
+        let connections = self
+            .connections
+            .iter()
+            .map(|(id, connection)| {
+                let start = &self.output_ports[connection.start_port];
+                let end = &self.input_ports[connection.end_port];
+
+                ConnectionEntry {
+                    start: port_entry(start.node, &start.name),
+                    end: port_entry(end.node, &end.name),
+                    metadata: connection.metadata.clone(),
+                    id: stable_ids
+                        .and_then(|stable_ids| stable_ids.connections().get(id))
+                        .map(|id| id.0),
+                }
+            })
+            .collect();
+
+        let groups = self
+            .groups()
+            .map(|(_, group)| GroupEntry {
+                name: group.name().to_string(),
+                nodes: group.nodes().iter().map(|&node| ids[node]).collect(),
+            })
+            .collect();
+
+        let parameters = self
+            .parameters()
+            .map(|(name, port)| {
+                let port = &self.input_ports[port];
+
+                ParameterEntry {
+                    name: name.to_string(),
+                    port: port_entry(port.node, &port.name),
+                }
+            })
+            .collect();
+
+        GraphDocument {
+            version,
+            nodes,
+            connections,
+            metadata: self.metadata.clone(),
+            stable_ids: stable_ids.is_some(),
+            groups,
+            parameters,
+        }
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Load a graph from a document, first migrating it to the current
+    /// version with `migrator`. Nodes are created by kind with `registry`,
+    /// which also [installs](NodeRegistry::install) its adapters and
+    /// validators. Ports in the document that the new nodes don't have are
+    /// added, and saved defaults replace the initial ones. Port types come
+    /// from the nodes, not from the document. If the document was saved with
+    /// stable ids, the loaded graph has stable ids enabled and keeps them.
+    pub fn from_document(
+        document: GraphDocument<N::DataType, N::DataValue>,
+        registry: &NodeRegistry<N>,
+        migrator: &impl GraphMigrator<N::DataType, N::DataValue>,
+    ) -> Result<Self, LoadError<N::DataType>> {
+        Self::load_document(document, registry, migrator, None)
+    }
+
+    /// Load a document, creating nodes of unknown kinds with `placeholder`
+    /// if given
+    fn load_document(
+        mut document: GraphDocument<N::DataType, N::DataValue>,
+        registry: &NodeRegistry<N>,
+        migrator: &impl GraphMigrator<N::DataType, N::DataValue>,
+        placeholder: Option<fn(&str, &RegistryArgs) -> N>,
+    ) -> Result<Self, LoadError<N::DataType>> {
+        migrator.migrate_document(&mut document)?;
+
+        let mut graph = Self::new();
+        registry.install(&mut graph);
+        graph.metadata = document.metadata;
+
+        if document.stable_ids {
+            graph.enable_stable_ids();
+
+            // Generated ids must not take the ids of elements loaded later
+            let ports = document.nodes.iter().flat_map(|node| {
+                let inputs = node.inputs.iter().map(|input| input.id);
+                let outputs = node.outputs.iter().map(|output| output.id);
+
+                inputs.chain(outputs).flatten()
+            });
+
+            let last = document
+                .nodes
+                .iter()
+                .map(|node| node.id)
+                .chain(ports)
+                .chain(
+                    document
+                        .connections
+                        .iter()
+                        .filter_map(|connection| connection.id),
+                )
+                .max();
+
+            if let (Some(stable_ids), Some(last)) = (&mut graph.stable_ids, last) {
+                stable_ids.reserve(StableId(last));
+            }
+        }
+
+        let mut ids = HashMap::new();
+        let mut variadic_inputs = Vec::new();
+
+        for mut entry in document.nodes {
+            if ids.contains_key(&entry.id) {
+                return Err(LoadError::DuplicateNode(entry.id));
+            }
+
+            let node = registry
+                .create(&entry.kind, &entry.args)
+                .or_else(|| Some(placeholder?(&entry.kind, &entry.args)))
+                .ok_or_else(|| LoadError::UnknownKind {
+                    node: entry.id,
+                    kind: entry.kind.clone(),
+                })?;
+
+            let node = graph.create_node(node);
+            ids.insert(entry.id, node);
+
+            if let Some(stable_ids) = &mut graph.stable_ids {
+                stable_ids.assign_node(node, StableId(entry.id));
+            }
+
+            // Variadic inputs are set up once connected, so connecting them
+            // doesn't add ports
+            variadic_inputs.push((node, std::mem::take(&mut entry.variadic_inputs)));
+
+            graph.restore_entry(node, entry)?;
+        }
+
+        for (index, connection) in document.connections.into_iter().enumerate() {
+            let start_port = ids
+                .get(&connection.start.node)
+                .and_then(|&node| graph.get_output_port(node, &connection.start.port))
+                .ok_or_else(|| LoadError::MissingPort(connection.start.clone()))?;
+            let end_port = ids
+                .get(&connection.end.node)
+                .and_then(|&node| graph.get_input_port(node, &connection.end.port))
+                .ok_or_else(|| LoadError::MissingPort(connection.end.clone()))?;
+
+            graph
+                .check_connection(start_port, end_port)
+                .map_err(|error| LoadError::Connection { index, error })?;
+
+            let id = graph.connect(start_port, end_port);
+            graph.connections[id].metadata = connection.metadata;
+
+            if let (Some(stable_ids), Some(stable_id)) = (&mut graph.stable_ids, connection.id) {
+                stable_ids.assign_connection(id, StableId(stable_id));
+            }
+        }
+
+        for (node, groups) in variadic_inputs {
+            for group in groups {
+                let ports = group
+                    .ports
+                    .iter()
+                    .filter_map(|name| graph.get_input_port(node, name))
+                    .collect();
+
+                graph
+                    .variadic_inputs
+                    .entry(node)
+                    .expect(INVALID_STATE)
+                    .or_default()
+                    .push(VariadicInput {
+                        base_name: group.base_name,
+                        ty: group.ty,
+                        default: group.default,
+                        ports,
+                    });
+            }
+
+            graph.update_variadic_inputs(node);
+        }
+
+        for parameter in document.parameters {
+            let port = ids
+                .get(&parameter.port.node)
+                .and_then(|&node| graph.get_input_port(node, &parameter.port.port))
+                .ok_or_else(|| LoadError::MissingPort(parameter.port.clone()))?;
+
+            graph
+                .expose_parameter(port, &parameter.name)
+                .map_err(|error| LoadError::Parameter {
+                    name: parameter.name,
+                    error,
+                })?;
+        }
+
+        for group in document.groups {
+            let nodes = group.nodes.iter().filter_map(|id| ids.get(id).copied());
+            graph.create_group(&group.name, nodes);
+        }
+
+        Ok(graph)
+    }
+
+    /// Give a newly created node the name and ports saved in `entry`
+    fn restore_entry(
+        &mut self,
+        node: NodeId,
+        entry: NodeEntry<N::DataType, N::DataValue>,
+    ) -> Result<(), LoadError<N::DataType>> {
+        if let Some(name) = &entry.name {
+            self.set_node_name(node, name)
+                .map_err(|NameTaken(_)| LoadError::NameTaken {
+                    node: entry.id,
+                    name: name.clone(),
+                })?;
+        }
+
+        self.node_data[node].metadata = entry.metadata;
+        self.node_data[node].reroute = entry.reroute;
+
+        for input in entry.inputs {
+            let port = match self.get_input_port(node, &input.name) {
+                Some(port) => {
+                    if let Some(default) = input.default {
+                        self.set_default_value(port, default);
+                    }
+
+                    port
+                }
+                None => self.create_input_port_inner(node, &input.name, input.ty, input.default),
+            };
+
+            self.input_ports[port].metadata = input.metadata;
+            self.input_ports[port].max_incoming = input.max_incoming;
+
+            if let (Some(stable_ids), Some(id)) = (&mut self.stable_ids, input.id) {
+                stable_ids.assign_input_port(port, StableId(id));
+            }
+        }
+
+        for output in entry.outputs {
+            let port = match self.get_output_port(node, &output.name) {
+                Some(port) => port,
+                None => self.create_output_port(node, &output.name, output.ty),
+            };
+
+            self.output_ports[port].metadata = output.metadata;
+            self.output_ports[port].max_outgoing = output.max_outgoing;
+
+            if let (Some(stable_ids), Some(id)) = (&mut self.stable_ids, output.id) {
+                stable_ids.assign_output_port(port, StableId(id));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<N: PlaceholderNode> Graph<N> {
+    /// Load a graph like [`from_document`](Self::from_document), handling
+    /// nodes of unknown kinds according to `policy`
+    pub fn from_document_with(
+        document: GraphDocument<N::DataType, N::DataValue>,
+        registry: &NodeRegistry<N>,
+        migrator: &impl GraphMigrator<N::DataType, N::DataValue>,
+        policy: UnknownKindPolicy,
+    ) -> Result<Self, LoadError<N::DataType>> {
+        let placeholder = match policy {
+            UnknownKindPolicy::Fail => None,
+            UnknownKindPolicy::Preserve => Some(N::placeholder as fn(&str, &RegistryArgs) -> N),
+        };
+
+        Self::load_document(document, registry, migrator, placeholder)
+    }
+
+    /// All [placeholder](PlaceholderNode) nodes
+    pub fn placeholders(&self) -> Vec<NodeId> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.read().is_placeholder())
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Replace every placeholder whose kind `registry` knows by now with a
+    /// real node, keeping its id, ports and connections. Ports the real node
+    /// would have been created with aren't added. Returns the replaced
+    /// nodes.
+    pub fn resolve_placeholders(&mut self, registry: &NodeRegistry<N>) -> Vec<NodeId> {
+        let mut resolved = Vec::new();
+
+        for id in self.placeholders() {
+            let node = {
+                let placeholder = self.nodes[id].read();
+                registry.create(placeholder.kind(), &placeholder.args())
+            };
+
+            if let Some(node) = node {
+                self.set_node(id, node);
+                resolved.push(id);
+            }
+        }
+
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        migration::Migrations,
+        test_util::{TestNode, link, registry},
+    };
+
+    /// A graph using most of what documents store
+    fn sample() -> Graph<TestNode> {
+        let mut graph = Graph::new();
+
+        let value = graph.create_node(TestNode::Value(3));
+        let sum = graph.create_node(TestNode::Sum);
+        let total = graph.create_node(TestNode::Sum);
+
+        link(&mut graph, value, sum);
+        let connection = link(&mut graph, sum, total);
+
+        graph.set_default_value(total.input(1), 4);
+        graph.create_input_port(sum, "c", (), 9);
+        graph.create_variadic_input(total, "extra", (), 0);
+        graph.set_max_incoming(total.input(0), Some(1));
+
+        graph.set_node_name(sum, "middle").unwrap();
+        graph.set_node_meta(value, "x", 1.5);
+        graph.set_connection_meta(connection, "color", "red");
+        graph.set_meta("author", "test");
+        graph.create_group("sums", [sum, total]);
+        graph.expose_parameter(sum.input(1), "gain").unwrap();
+
+        graph
+    }
+
+    fn load(
+        document: GraphDocument<(), i64>,
+        migrations: &Migrations<(), i64>,
+    ) -> Result<Graph<TestNode>, LoadError<()>> {
+        Graph::from_document(document, &registry(), migrations)
+    }
+
+    #[test]
+    fn documents_round_trip() {
+        let document = sample().to_document(1);
+        let loaded = load(document.clone(), &Migrations::new(1)).unwrap();
+
+        assert_eq!(loaded.to_document(1), document);
+        assert_eq!(loaded.node_count(), 3);
+        assert_eq!(loaded.connection_count(), 2);
+        assert!(loaded.get_node_by_name("middle").is_some());
+    }
+
+    #[test]
+    fn documents_with_stable_ids_round_trip() {
+        let mut graph = sample();
+        graph.enable_stable_ids();
+
+        // Leave a gap in the ids
+        let first = graph.node_data.keys().next().unwrap();
+        graph.take_node(first);
+
+        let document = graph.to_document(1);
+        let loaded = load(document.clone(), &Migrations::new(1)).unwrap();
+
+        assert!(document.stable_ids);
+        assert_eq!(loaded.to_document(1), document);
+
+        let ids = |graph: &Graph<TestNode>| {
+            let mut ids = graph
+                .stable_ids()
+                .unwrap()
+                .nodes()
+                .iter()
+                .map(|(_, id)| id.0)
+                .collect::<Vec<_>>();
+
+            ids.sort_unstable();
+            ids
+        };
+
+        assert_eq!(ids(&loaded), ids(&graph));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn documents_round_trip_through_json() {
+        let document = sample().to_document(1);
+        let json = serde_json::to_string(&document).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<GraphDocument<(), i64>>(&json).unwrap(),
+            document
+        );
+    }
+
+    #[test]
+    fn old_documents_are_migrated() {
+        let mut document = sample().to_document(0);
+        document.rename_kind("sum", "add");
+        document.rename_input("add", "a", "x");
+
+        let mut migrations = Migrations::new(1);
+        migrations.register(0, |document| {
+            document.rename_kind("add", "sum");
+            document.rename_input("sum", "x", "a");
+            Ok(())
+        });
+
+        let loaded = load(document, &migrations).unwrap();
+
+        assert_eq!(loaded.to_document(1), sample().to_document(1));
+
+        assert_eq!(
+            load(sample().to_document(2), &migrations).unwrap_err(),
+            LoadError::NewerVersion {
+                version: 2,
+                current: 1
+            }
+        );
+    }
+}
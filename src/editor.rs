@@ -0,0 +1,310 @@
+use egui::{
+    Align2, Color32, FontId, Id, Pos2, Rect, Sense, Shape, Stroke, StrokeKind, Ui, Vec2,
+    epaint::CubicBezierShape,
+};
+
+use crate::{
+    ConnectionId, Graph, InputPortId, Node, NodeId, OutputPortId,
+    layout::{Layout, LayoutOptions},
+};
+
+const NODE_WIDTH: f32 = 140.0;
+const HEADER_HEIGHT: f32 = 24.0;
+const ROW_HEIGHT: f32 = 20.0;
+const PORT_RADIUS: f32 = 5.0;
+
+/// A change requested by the user in a [`GraphEditor`]. The editor never
+/// changes the graph itself, so commands can go through undo stacks,
+/// transactions or a network first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphCommand {
+    /// Move a node to a new [position](Graph::node_position)
+    MoveNode {
+        node: NodeId,
+        position: (f64, f64),
+    },
+    /// Connect two ports, only emitted for connections that
+    /// [can be made](Graph::can_connect)
+    Connect {
+        start_port: OutputPortId,
+        end_port: InputPortId,
+    },
+    Disconnect(ConnectionId),
+}
+
+impl GraphCommand {
+    pub fn apply<N: Node>(self, graph: &mut Graph<N>) {
+        match self {
+            Self::MoveNode { node, position } => graph.set_node_position(node, position),
+            Self::Connect {
+                start_port,
+                end_port,
+            } => {
+                graph.connect(start_port, end_port);
+            }
+            Self::Disconnect(connection) => {
+                let _ = graph.disconnect(connection);
+            }
+        }
+    }
+}
+
+/// A minimal node editor for egui. Nodes are drawn at their
+/// [positions](Graph::node_position), or where
+/// [`compute_layout`](Graph::compute_layout) puts them if they have none.
+///
+/// - Drag a node by its title to move it
+/// - Drag from an output port to an input port to connect them, the input
+///   lights up green or red depending on whether the connection is allowed
+/// - Right click an input port to disconnect it
+/// - Drag the background to pan
+///
+/// ```
+/// # use node_graph::{Graph, Node, editor::GraphEditor};
+/// # fn show<N: Node>(ui: &mut egui::Ui, editor: &mut GraphEditor, mut graph: Graph<N>) {
+/// for command in editor.show(ui, &graph) {
+///     command.apply(&mut graph);
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GraphEditor {
+    id: Id,
+    offset: Vec2,
+    /// The output port a wire is being dragged from
+    wire: Option<OutputPortId>,
+}
+
+impl GraphEditor {
+    /// `id` has to be unique among the editors shown at the same time
+    pub fn new(id: impl std::hash::Hash) -> Self {
+        Self {
+            id: Id::new(id),
+            offset: Vec2::ZERO,
+            wire: None,
+        }
+    }
+
+    /// Show the graph in all the space left in `ui`, returning the changes
+    /// the user made
+    pub fn show<N: Node>(&mut self, ui: &mut Ui, graph: &Graph<N>) -> Vec<GraphCommand> {
+        let mut commands = Vec::new();
+
+        let (background, painter) = ui.allocate_painter(ui.available_size(), Sense::drag());
+
+        if background.dragged() {
+            self.offset += background.drag_delta();
+        }
+
+        let origin = background.rect.min + self.offset;
+        let visuals = ui.visuals().clone();
+        let pointer = ui.input(|input| input.pointer.hover_pos());
+
+        let layout = graph
+            .node_data
+            .keys()
+            .any(|node| graph.node_position(node).is_none())
+            .then(|| graph.compute_layout(&LayoutOptions::default()));
+
+        let rects = graph
+            .node_data
+            .iter()
+            .map(|(node, data)| {
+                let rows = data.inputs.len().max(data.outputs.len()) as f32;
+                let (x, y) = position(graph, layout.as_ref(), node);
+                let rect = Rect::from_min_size(
+                    origin + Vec2::new(x as f32, y as f32),
+                    Vec2::new(NODE_WIDTH, HEADER_HEIGHT + rows * ROW_HEIGHT + 4.0),
+                );
+
+                (node, rect)
+            })
+            .collect::<Vec<_>>();
+
+        let input_pos = |port: InputPortId| {
+            let info = &graph.input_ports[port];
+            let index = graph.input_port_index(port);
+            let rect = rects
+                .iter()
+                .find(|(node, _)| *node == info.node)
+                .map(|(_, rect)| *rect);
+
+            rect.map(|rect| Pos2::new(rect.left(), row_y(rect, index)))
+        };
+
+        let output_pos = |port: OutputPortId| {
+            let info = &graph.output_ports[port];
+            let index = graph.output_port_index(port);
+            let rect = rects
+                .iter()
+                .find(|(node, _)| *node == info.node)
+                .map(|(_, rect)| *rect);
+
+            rect.map(|rect| Pos2::new(rect.right(), row_y(rect, index)))
+        };
+
+        let wire_stroke = Stroke::new(2.0, visuals.widgets.active.fg_stroke.color);
+
+        for connection in graph.connections.values() {
+            if let (Some(start), Some(end)) = (
+                output_pos(connection.start_port),
+                input_pos(connection.end_port),
+            ) {
+                painter.add(wire(start, end, wire_stroke));
+            }
+        }
+
+        for &(node, rect) in rects.iter() {
+            let data = &graph.node_data[node];
+
+            painter.rect(
+                rect,
+                4.0,
+                visuals.window_fill,
+                visuals.window_stroke,
+                StrokeKind::Inside,
+            );
+
+            let title = Rect::from_min_size(rect.min, Vec2::new(rect.width(), HEADER_HEIGHT));
+            painter.text(
+                title.left_center() + Vec2::new(6.0, 0.0),
+                Align2::LEFT_CENTER,
+                graph.node_title(node),
+                FontId::proportional(14.0),
+                visuals.strong_text_color(),
+            );
+
+            let response = ui.interact(title, self.id.with(node), Sense::drag());
+
+            if response.dragged() && response.drag_delta() != Vec2::ZERO {
+                let (x, y) = position(graph, layout.as_ref(), node);
+                let delta = response.drag_delta();
+
+                commands.push(GraphCommand::MoveNode {
+                    node,
+                    position: (x + delta.x as f64, y + delta.y as f64),
+                });
+            }
+
+            for (index, (name, port)) in data.inputs.iter().enumerate() {
+                let center = Pos2::new(rect.left(), row_y(rect, index));
+                let response = ui.interact(
+                    Rect::from_center_size(center, Vec2::splat(PORT_RADIUS * 3.0)),
+                    self.id.with(("input", *port)),
+                    Sense::click(),
+                );
+
+                let color = match self.wire {
+                    Some(start) if response.hovered() || hovers(pointer, center) => {
+                        match graph.can_connect(start, *port) {
+                            true => Color32::GREEN,
+                            false => Color32::RED,
+                        }
+                    }
+                    _ => visuals.widgets.inactive.fg_stroke.color,
+                };
+
+                if response.secondary_clicked() {
+                    commands.extend(
+                        graph.input_ports[*port]
+                            .incoming_connections
+                            .iter()
+                            .map(|&connection| GraphCommand::Disconnect(connection)),
+                    );
+                }
+
+                painter.circle_filled(center, PORT_RADIUS, color);
+                painter.text(
+                    center + Vec2::new(PORT_RADIUS + 4.0, 0.0),
+                    Align2::LEFT_CENTER,
+                    name,
+                    FontId::proportional(12.0),
+                    visuals.text_color(),
+                );
+            }
+
+            for (index, (name, port)) in data.outputs.iter().enumerate() {
+                let center = Pos2::new(rect.right(), row_y(rect, index));
+                let response = ui.interact(
+                    Rect::from_center_size(center, Vec2::splat(PORT_RADIUS * 3.0)),
+                    self.id.with(("output", *port)),
+                    Sense::drag(),
+                );
+
+                if response.drag_started() {
+                    self.wire = Some(*port);
+                }
+
+                painter.circle_filled(
+                    center,
+                    PORT_RADIUS,
+                    visuals.widgets.inactive.fg_stroke.color,
+                );
+                painter.text(
+                    center - Vec2::new(PORT_RADIUS + 4.0, 0.0),
+                    Align2::RIGHT_CENTER,
+                    name,
+                    FontId::proportional(12.0),
+                    visuals.text_color(),
+                );
+            }
+        }
+
+        if let Some(start_port) = self.wire {
+            if let (Some(start), Some(end)) = (output_pos(start_port), pointer) {
+                painter.add(wire(start, end, wire_stroke));
+            }
+
+            if ui.input(|input| input.pointer.any_released()) {
+                self.wire = None;
+
+                let target = graph.input_ports.iter().find(|&(port, _)| {
+                    input_pos(port).is_some_and(|center| hovers(pointer, center))
+                });
+
+                if let Some((end_port, _)) = target
+                    && graph.can_connect(start_port, end_port)
+                {
+                    commands.push(GraphCommand::Connect {
+                        start_port,
+                        end_port,
+                    });
+                }
+            }
+        }
+
+        commands
+    }
+}
+
+fn position<N: Node>(graph: &Graph<N>, layout: Option<&Layout>, node: NodeId) -> (f64, f64) {
+    graph
+        .node_position(node)
+        .or_else(|| layout?.get(node))
+        .unwrap_or_default()
+}
+
+fn row_y(rect: Rect, index: usize) -> f32 {
+    rect.top() + HEADER_HEIGHT + (index as f32 + 0.5) * ROW_HEIGHT
+}
+
+fn hovers(pointer: Option<Pos2>, center: Pos2) -> bool {
+    pointer.is_some_and(|pointer| pointer.distance(center) <= PORT_RADIUS * 2.0)
+}
+
+fn wire(start: Pos2, end: Pos2, stroke: Stroke) -> Shape {
+    let bend = ((end.x - start.x).abs() / 2.0).max(40.0);
+
+    CubicBezierShape::from_points_stroke(
+        [
+            start,
+            start + Vec2::new(bend, 0.0),
+            end - Vec2::new(bend, 0.0),
+            end,
+        ],
+        false,
+        Color32::TRANSPARENT,
+        stroke,
+    )
+    .into()
+}
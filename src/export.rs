@@ -0,0 +1,3 @@
+//! Drawing graphs for use outside of the crate, like in documentation
+
+pub mod svg;
@@ -0,0 +1,267 @@
+use std::fmt::Write;
+
+use crate::{Graph, Node, NodeId, layout::Layout};
+
+/// Sizes and colors used by [`render`]. Colors are any SVG color, like
+/// `#1e1e1e` or `white`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgStyle {
+    pub node_width: f64,
+    pub header_height: f64,
+    pub row_height: f64,
+    pub port_radius: f64,
+    /// Space around the drawing
+    pub margin: f64,
+    pub font_family: String,
+    pub font_size: f64,
+    /// `None` for a transparent background
+    pub background: Option<String>,
+    pub node_fill: String,
+    pub header_fill: String,
+    pub node_stroke: String,
+    pub text_color: String,
+    pub port_color: String,
+    pub wire_color: String,
+    pub wire_width: f64,
+}
+
+impl Default for SvgStyle {
+    fn default() -> Self {
+        Self {
+            node_width: 140.0,
+            header_height: 24.0,
+            row_height: 20.0,
+            port_radius: 5.0,
+            margin: 20.0,
+            font_family: "sans-serif".to_string(),
+            font_size: 12.0,
+            background: Some("#1e1e1e".to_string()),
+            node_fill: "#2d2d2d".to_string(),
+            header_fill: "#3c3c3c".to_string(),
+            node_stroke: "#555555".to_string(),
+            text_color: "#e0e0e0".to_string(),
+            port_color: "#a0a0a0".to_string(),
+            wire_color: "#8ab4f8".to_string(),
+            wire_width: 2.0,
+        }
+    }
+}
+
+/// Draw `graph` as a standalone SVG document, with nodes as boxes at their
+/// positions in `layout`, titled by their name or variant, port labels and
+/// connections as bezier curves. Nodes missing from `layout` are drawn at
+/// their [editor position](Graph::node_position), or at the origin.
+///
+/// ```
+/// # use node_graph::{
+/// #     Graph, InitialPorts, Node,
+/// #     export::{self, svg::SvgStyle},
+/// #     layout::LayoutOptions,
+/// # };
+/// # #[derive(Debug)]
+/// # struct Passthrough;
+/// # impl Node for Passthrough {
+/// #     type DataType = ();
+/// #     type DataValue = f32;
+/// #     fn initial_ports(&self) -> InitialPorts<Self> {
+/// #         InitialPorts {
+/// #             inputs: vec![("in", (), 0.0)],
+/// #             outputs: vec![("out", ())],
+/// #         }
+/// #     }
+/// # }
+/// # let mut graph = Graph::<Passthrough>::new();
+/// # let first = graph.create_node(Passthrough);
+/// # let second = graph.create_node(Passthrough);
+/// # graph.connect(first.output(0), second.input(0));
+/// let layout = graph.compute_layout(&LayoutOptions::default());
+/// let svg = export::svg::render(&graph, &layout, &SvgStyle::default());
+/// # assert!(svg.starts_with("<svg"));
+/// ```
+pub fn render<N: Node>(graph: &Graph<N>, layout: &Layout, style: &SvgStyle) -> String {
+    let boxes = graph
+        .node_data
+        .iter()
+        .map(|(node, data)| {
+            let (x, y) = layout
+                .get(node)
+                .or_else(|| graph.node_position(node))
+                .unwrap_or_default();
+            let rows = data.inputs.len().max(data.outputs.len()) as f64;
+            let height = style.header_height + rows * style.row_height + style.row_height / 4.0;
+
+            (node, (x, y, height))
+        })
+        .collect::<Vec<_>>();
+
+    let bounds = boxes.iter().fold(None, |bounds, &(_, (x, y, height))| {
+        let (min_x, min_y, max_x, max_y) = bounds.unwrap_or((
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ));
+
+        Some((
+            f64::min(min_x, x),
+            f64::min(min_y, y),
+            f64::max(max_x, x + style.node_width),
+            f64::max(max_y, y + height),
+        ))
+    });
+
+    let (min_x, min_y, max_x, max_y) = bounds.unwrap_or_default();
+    let (left, top) = (min_x - style.margin, min_y - style.margin);
+    let (width, height) = (
+        max_x - min_x + style.margin * 2.0,
+        max_y - min_y + style.margin * 2.0,
+    );
+
+    let position = |node: NodeId| {
+        boxes
+            .iter()
+            .find(|(other, _)| *other == node)
+            .map(|&(_, (x, y, _))| (x, y))
+            .unwrap_or_default()
+    };
+
+    let row_y =
+        |y: f64, index: usize| y + style.header_height + (index as f64 + 0.5) * style.row_height;
+
+    let mut svg = String::new();
+
+    // Writing to a String can't fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="{left} {top} {width} {height}" font-family="{}" font-size="{}">"#,
+        escape(&style.font_family),
+        style.font_size,
+    );
+
+    if let Some(background) = &style.background {
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{left}" y="{top}" width="{width}" height="{height}" fill="{}"/>"#,
+            escape(background),
+        );
+    }
+
+    let _ = writeln!(
+        svg,
+        r#"<g fill="none" stroke="{}" stroke-width="{}">"#,
+        escape(&style.wire_color),
+        style.wire_width,
+    );
+
+    for connection in graph.connections.values() {
+        let (start_x, start_y) = position(graph.output_ports[connection.start_port].node);
+        let (end_x, end_y) = position(graph.input_ports[connection.end_port].node);
+
+        let (x1, y1) = (
+            start_x + style.node_width,
+            row_y(start_y, graph.output_port_index(connection.start_port)),
+        );
+        let (x2, y2) = (
+            end_x,
+            row_y(end_y, graph.input_port_index(connection.end_port)),
+        );
+        let bend = ((x2 - x1).abs() / 2.0).max(40.0);
+
+        let _ = writeln!(
+            svg,
+            r#"<path d="M {x1} {y1} C {} {y1} {} {y2} {x2} {y2}"/>"#,
+            x1 + bend,
+            x2 - bend,
+        );
+    }
+
+    let _ = writeln!(svg, "</g>");
+
+    for &(node, (x, y, height)) in boxes.iter() {
+        let data = &graph.node_data[node];
+
+        let _ = writeln!(svg, "<g>");
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{x}" y="{y}" width="{}" height="{height}" rx="4" fill="{}" stroke="{}"/>"#,
+            style.node_width,
+            escape(&style.node_fill),
+            escape(&style.node_stroke),
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{x}" y="{y}" width="{}" height="{}" rx="4" fill="{}"/>"#,
+            style.node_width,
+            style.header_height,
+            escape(&style.header_fill),
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" dominant-baseline="middle" font-weight="bold" fill="{}">{}</text>"#,
+            x + 6.0,
+            y + style.header_height / 2.0,
+            escape(&style.text_color),
+            escape(&graph.node_title(node)),
+        );
+
+        for (index, (name, _)) in data.inputs.iter().enumerate() {
+            let port_y = row_y(y, index);
+
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{x}" cy="{port_y}" r="{}" fill="{}"/>"#,
+                style.port_radius,
+                escape(&style.port_color),
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{port_y}" dominant-baseline="middle" fill="{}">{}</text>"#,
+                x + style.port_radius + 4.0,
+                escape(&style.text_color),
+                escape(name),
+            );
+        }
+
+        for (index, (name, _)) in data.outputs.iter().enumerate() {
+            let port_x = x + style.node_width;
+            let port_y = row_y(y, index);
+
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{port_x}" cy="{port_y}" r="{}" fill="{}"/>"#,
+                style.port_radius,
+                escape(&style.port_color),
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{port_y}" dominant-baseline="middle" text-anchor="end" fill="{}">{}</text>"#,
+                port_x - style.port_radius - 4.0,
+                escape(&style.text_color),
+                escape(name),
+            );
+        }
+
+        let _ = writeln!(svg, "</g>");
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Escape text for use in SVG text and attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            char => escaped.push(char),
+        }
+    }
+
+    escaped
+}
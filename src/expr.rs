@@ -0,0 +1,501 @@
+//! Math expressions like `a * sin(t) + 2`, and nodes that evaluate them with
+//! an input port per variable
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{Graph, Node, NodeId, walker::GraphWalkContext};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+impl BinaryOp {
+    fn apply(self, lhs: f64, rhs: f64) -> f64 {
+        match self {
+            Self::Add => lhs + rhs,
+            Self::Sub => lhs - rhs,
+            Self::Mul => lhs * rhs,
+            Self::Div => lhs / rhs,
+            Self::Rem => lhs % rhs,
+            Self::Pow => lhs.powf(rhs),
+        }
+    }
+}
+
+/// A parsed expression, see [`Expr::parse`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(String),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+/// The functions expressions can call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Log10,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Floor,
+    Ceil,
+    Round,
+    Min,
+    Max,
+    Pow,
+    Clamp,
+}
+
+impl Function {
+    const ALL: [(&'static str, Function); 19] = [
+        ("abs", Self::Abs),
+        ("sqrt", Self::Sqrt),
+        ("exp", Self::Exp),
+        ("ln", Self::Ln),
+        ("log10", Self::Log10),
+        ("sin", Self::Sin),
+        ("cos", Self::Cos),
+        ("tan", Self::Tan),
+        ("asin", Self::Asin),
+        ("acos", Self::Acos),
+        ("atan", Self::Atan),
+        ("atan2", Self::Atan2),
+        ("floor", Self::Floor),
+        ("ceil", Self::Ceil),
+        ("round", Self::Round),
+        ("min", Self::Min),
+        ("max", Self::Max),
+        ("pow", Self::Pow),
+        ("clamp", Self::Clamp),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(other, _)| *other == name)
+            .map(|(_, function)| *function)
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(_, other)| *other == self)
+            .map(|(name, _)| *name)
+            .expect("Every function is listed")
+    }
+
+    pub fn arity(self) -> usize {
+        match self {
+            Self::Atan2 | Self::Min | Self::Max | Self::Pow => 2,
+            Self::Clamp => 3,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        match self {
+            Self::Abs => args[0].abs(),
+            Self::Sqrt => args[0].sqrt(),
+            Self::Exp => args[0].exp(),
+            Self::Ln => args[0].ln(),
+            Self::Log10 => args[0].log10(),
+            Self::Sin => args[0].sin(),
+            Self::Cos => args[0].cos(),
+            Self::Tan => args[0].tan(),
+            Self::Asin => args[0].asin(),
+            Self::Acos => args[0].acos(),
+            Self::Atan => args[0].atan(),
+            Self::Atan2 => args[0].atan2(args[1]),
+            Self::Floor => args[0].floor(),
+            Self::Ceil => args[0].ceil(),
+            Self::Round => args[0].round(),
+            Self::Min => args[0].min(args[1]),
+            Self::Max => args[0].max(args[1]),
+            Self::Pow => args[0].powf(args[1]),
+            Self::Clamp => args[0].clamp(args[1].min(args[2]), args[2].max(args[1])),
+        }
+    }
+}
+
+/// Returned by [`Expr::parse`], `position` is the byte offset of the problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseExprError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl Display for ParseExprError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseExprError {}
+
+impl Expr {
+    /// Parse an expression made of numbers, variables, `+ - * / % ^`,
+    /// parentheses and calls to [`Function`]s. `pi` and `e` are constants,
+    /// every other name is a variable.
+    pub fn parse(source: &str) -> Result<Self, ParseExprError> {
+        let mut parser = Parser {
+            source,
+            position: 0,
+        };
+
+        let expr = parser.parse_sum()?;
+
+        parser.skip_whitespace();
+
+        if parser.position < source.len() {
+            return Err(parser.error("Unexpected character"));
+        }
+
+        Ok(expr)
+    }
+
+    /// The names of all variables, in order of first use
+    pub fn variables(&self) -> Vec<&str> {
+        let mut variables = Vec::new();
+        self.collect_variables(&mut variables);
+        variables
+    }
+
+    fn collect_variables<'a>(&'a self, variables: &mut Vec<&'a str>) {
+        match self {
+            Self::Number(_) => {}
+            Self::Variable(name) => {
+                if !variables.contains(&name.as_str()) {
+                    variables.push(name);
+                }
+            }
+            Self::Neg(expr) => expr.collect_variables(variables),
+            Self::Binary(_, lhs, rhs) => {
+                lhs.collect_variables(variables);
+                rhs.collect_variables(variables);
+            }
+            Self::Call(_, args) => {
+                for arg in args {
+                    arg.collect_variables(variables);
+                }
+            }
+        }
+    }
+
+    /// Compute the value of the expression, with `variable` providing the
+    /// value of each variable
+    pub fn eval(&self, variable: &impl Fn(&str) -> f64) -> f64 {
+        match self {
+            Self::Number(value) => *value,
+            Self::Variable(name) => variable(name),
+            Self::Neg(expr) => -expr.eval(variable),
+            Self::Binary(op, lhs, rhs) => op.apply(lhs.eval(variable), rhs.eval(variable)),
+            Self::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(variable))
+                    .collect::<Vec<_>>();
+
+                function.apply(&args)
+            }
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ParseExprError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Self::parse(source)
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: impl Into<String>) -> ParseExprError {
+        ParseExprError {
+            position: self.position,
+            message: message.into(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skip whitespace, then consume `c` if it comes next
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+
+        if self.rest().starts_with(c) {
+            self.position += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseExprError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("Expected '{c}'")))
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, ParseExprError> {
+        let mut expr = self.parse_product()?;
+
+        loop {
+            let op = if self.eat('+') {
+                BinaryOp::Add
+            } else if self.eat('-') {
+                BinaryOp::Sub
+            } else {
+                return Ok(expr);
+            };
+
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.parse_product()?));
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, ParseExprError> {
+        let mut expr = self.parse_unary()?;
+
+        loop {
+            let op = if self.eat('*') {
+                BinaryOp::Mul
+            } else if self.eat('/') {
+                BinaryOp::Div
+            } else if self.eat('%') {
+                BinaryOp::Rem
+            } else {
+                return Ok(expr);
+            };
+
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseExprError> {
+        if self.eat('-') {
+            Ok(Expr::Neg(Box::new(self.parse_unary()?)))
+        } else if self.eat('+') {
+            self.parse_unary()
+        } else {
+            self.parse_power()
+        }
+    }
+
+    /// `^` binds tighter than a leading `-` and is right associative, so
+    /// `-a^b^c` is `-(a^(b^c))`
+    fn parse_power(&mut self) -> Result<Expr, ParseExprError> {
+        let base = self.parse_primary()?;
+
+        if self.eat('^') {
+            let exponent = self.parse_unary()?;
+
+            Ok(Expr::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(exponent),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseExprError> {
+        self.skip_whitespace();
+
+        let start = self.position;
+        let rest = self.rest();
+
+        let Some(first) = rest.chars().next() else {
+            return Err(self.error("Unexpected end of expression"));
+        };
+
+        if first == '(' {
+            self.position += 1;
+            let expr = self.parse_sum()?;
+            self.expect(')')?;
+
+            return Ok(expr);
+        }
+
+        if first.is_ascii_digit() || first == '.' {
+            let mut end = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+
+            // An exponent like `1e-3`
+            if rest[end..].starts_with(['e', 'E']) {
+                let exponent = rest[end + 1..]
+                    .strip_prefix(['+', '-'])
+                    .map_or(end + 1, |_| end + 2);
+                let digits = rest[exponent..]
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len() - exponent);
+
+                if digits > 0 {
+                    end = exponent + digits;
+                }
+            }
+
+            let value = rest[..end]
+                .parse()
+                .map_err(|_| self.error(format!("Invalid number {:?}", &rest[..end])))?;
+
+            self.position += end;
+
+            return Ok(Expr::Number(value));
+        }
+
+        if first.is_alphabetic() || first == '_' {
+            let end = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+
+            let name = &rest[..end];
+            self.position += end;
+
+            if !self.eat('(') {
+                return Ok(match name {
+                    "pi" => Expr::Number(std::f64::consts::PI),
+                    "e" => Expr::Number(std::f64::consts::E),
+                    _ => Expr::Variable(name.to_string()),
+                });
+            }
+
+            let function = Function::from_name(name).ok_or_else(|| ParseExprError {
+                position: start,
+                message: format!("Unknown function {name:?}"),
+            })?;
+
+            let mut args = Vec::new();
+
+            if !self.eat(')') {
+                loop {
+                    args.push(self.parse_sum()?);
+
+                    if self.eat(')') {
+                        break;
+                    }
+
+                    self.expect(',')?;
+                }
+            }
+
+            if args.len() != function.arity() {
+                return Err(ParseExprError {
+                    position: start,
+                    message: format!(
+                        "{name} takes {} arguments, but {} were given",
+                        function.arity(),
+                        args.len()
+                    ),
+                });
+            }
+
+            return Ok(Expr::Call(function, args));
+        }
+
+        Err(self.error("Unexpected character"))
+    }
+}
+
+/// A node type that can contain [`ExprNode`]s, describing how its values
+/// convert to and from the `f64`s expressions are computed with
+pub trait ExprHost: Node {
+    /// The data type of the ports of expression nodes
+    fn expr_type() -> Self::DataType;
+
+    fn to_f64(value: &Self::DataValue) -> f64;
+
+    fn from_f64(value: f64) -> Self::DataValue;
+}
+
+/// An expression with an input port for each variable, in order of first
+/// use, and a single output port called `result`. Wrap it in a variant of the
+/// node type, which should have no initial ports, and add it with
+/// [`Graph::create_expr_node`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExprNode {
+    source: String,
+    expr: Expr,
+}
+
+impl ExprNode {
+    pub fn new(source: impl Into<String>) -> Result<Self, ParseExprError> {
+        let source = source.into();
+        let expr = Expr::parse(&source)?;
+
+        Ok(Self { source, expr })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Compute the expression from the inputs in `context` and set the
+    /// `result` output
+    pub fn evaluate<N: ExprHost>(&self, context: &mut GraphWalkContext<'_, '_, N>) {
+        let result = self.expr.eval(&|name| N::to_f64(&context.get(name)));
+
+        context.set("result", N::from_f64(result));
+    }
+}
+
+impl<N: ExprHost> Graph<N> {
+    /// Create a node wrapping `expr`, with an input port for each variable
+    /// defaulting to zero
+    pub fn create_expr_node(&mut self, expr: ExprNode) -> NodeId
+    where
+        ExprNode: Into<N>,
+    {
+        let variables = expr
+            .expr
+            .variables()
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        let node = self.create_node(expr);
+
+        for name in variables {
+            self.create_input_port(node, &name, N::expr_type(), N::from_f64(0.0));
+        }
+
+        self.create_output_port(node, "result", N::expr_type());
+
+        node
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    Graph, InputPortId, Node, NodeId, OutputPortId,
+    walker::{GraphWalkContext, GraphWalker, WalkStatus},
+};
+
+/// A graph evaluated like a function, see [`Graph::as_function`]
+#[derive(Debug)]
+pub struct GraphFunction<'a, N: Node> {
+    walker: GraphWalker<'a, N>,
+    inputs: Vec<InputPortId>,
+    outputs: Vec<OutputPortId>,
+}
+
+impl<N: Node> Graph<N> {
+    /// Treat the part of the graph that `exit_outputs` depend on as a
+    /// function, whose arguments are the values of `entry_inputs` and whose
+    /// results are the values of `exit_outputs`. Entry inputs should be
+    /// disconnected, their arguments replace their default values.
+    pub fn as_function(
+        &self,
+        exit_outputs: &[OutputPortId],
+        entry_inputs: &[InputPortId],
+    ) -> GraphFunction<'_, N> {
+        for &port in entry_inputs {
+            assert!(
+                self.input_ports.contains_key(port),
+                "Input port does not exist"
+            );
+        }
+
+        let mut exit_nodes = Vec::<NodeId>::with_capacity(exit_outputs.len());
+
+        for &port in exit_outputs {
+            let node = self
+                .output_ports
+                .get(port)
+                .expect("Output port does not exist")
+                .node;
+
+            if !exit_nodes.contains(&node) {
+                exit_nodes.push(node);
+            }
+        }
+
+        GraphFunction {
+            walker: GraphWalker::new(self, Some(&exit_nodes)),
+            inputs: entry_inputs.to_vec(),
+            outputs: exit_outputs.to_vec(),
+        }
+    }
+}
+
+impl<'a, N: Node> GraphFunction<'a, N> {
+    /// Evaluate the graph with `arguments` for the entry inputs, in order,
+    /// using `callback` to evaluate each node like [`GraphWalker::walk`].
+    /// Returns the value of each exit output, or `None` for outputs their
+    /// node didn't set. Pauses requested by nodes are ignored.
+    pub fn call<F: for<'b> Fn(&mut N, &mut GraphWalkContext<'a, 'b, N>)>(
+        &mut self,
+        arguments: &[N::DataValue],
+        callback: F,
+    ) -> Vec<Option<N::DataValue>> {
+        assert_eq!(
+            arguments.len(),
+            self.inputs.len(),
+            "Expected one argument per entry input"
+        );
+
+        for (&port, argument) in self.inputs.iter().zip(arguments) {
+            self.walker.set_input(port, argument.clone());
+        }
+
+        let mut status = self.walker.walk(&callback);
+
+        while status != WalkStatus::Finished {
+            status = self.walker.resume(&callback);
+        }
+
+        self.outputs
+            .iter()
+            .map(|&port| self.walker.get_output(port).cloned())
+            .collect()
+    }
+
+    pub fn entry_inputs(&self) -> &[InputPortId] {
+        &self.inputs
+    }
+
+    pub fn exit_outputs(&self) -> &[OutputPortId] {
+        &self.outputs
+    }
+
+    pub fn walker(&self) -> &GraphWalker<'a, N> {
+        &self.walker
+    }
+
+    pub fn walker_mut(&mut self) -> &mut GraphWalker<'a, N> {
+        &mut self.walker
+    }
+}
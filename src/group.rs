@@ -0,0 +1,175 @@
+use slotmap::new_key_type;
+
+use crate::{Graph, INVALID_STATE, Node, NodeId, batch::GraphOp, compact::IdRemapping};
+
+new_key_type! { pub struct GroupId; }
+
+/// A named set of nodes, like a frame in an editor. Every node is in at most
+/// one group.
+#[derive(Debug, Clone)]
+pub struct Group {
+    name: String,
+    nodes: Vec<NodeId>,
+}
+
+impl Group {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn nodes(&self) -> &[NodeId] {
+        &self.nodes
+    }
+
+    pub(crate) fn remap(&mut self, remapping: &IdRemapping) {
+        for node in self.nodes.iter_mut() {
+            *node = remapping.get(*node).expect(INVALID_STATE);
+        }
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Create a group containing `nodes`. Nodes that were already in another
+    /// group are moved to the new one.
+    pub fn create_group(&mut self, name: &str, nodes: impl IntoIterator<Item = NodeId>) -> GroupId {
+        let nodes = nodes.into_iter().collect::<Vec<_>>();
+        let group = self.groups.insert(Group {
+            name: name.to_string(),
+            nodes: Vec::new(),
+        });
+
+        self.unlogged(|graph| {
+            for &node in nodes.iter() {
+                graph.add_to_group(group, node);
+            }
+        });
+
+        self.log_mutation(GraphOp::CreateGroup {
+            name: name.to_string(),
+            nodes,
+        });
+
+        group
+    }
+
+    pub fn get_group(&self, group: GroupId) -> Option<&Group> {
+        self.groups.get(group)
+    }
+
+    pub fn groups(&self) -> impl Iterator<Item = (GroupId, &Group)> + '_ {
+        self.groups.iter()
+    }
+
+    /// The group `node` is in
+    pub fn group_of(&self, node: NodeId) -> Option<GroupId> {
+        self.node_groups.get(node).copied()
+    }
+
+    pub fn rename_group(&mut self, group: GroupId, name: &str) {
+        self.groups
+            .get_mut(group)
+            .expect("Group does not exist")
+            .name = name.to_string();
+
+        if self.mutation_logging().is_some() {
+            self.log_mutation(GraphOp::RenameGroup {
+                group,
+                name: name.to_string(),
+            });
+        }
+    }
+
+    /// Add `node` to `group`, removing it from the group it was in before
+    pub fn add_to_group(&mut self, group: GroupId, node: NodeId) {
+        if !self.node_data.contains_key(node) {
+            panic!("Node does not exist");
+        }
+
+        if !self.groups.contains_key(group) {
+            panic!("Group does not exist");
+        }
+
+        if self.group_of(node) == Some(group) {
+            return;
+        }
+
+        self.unlogged(|graph| graph.remove_from_group(node));
+
+        self.groups[group].nodes.push(node);
+        self.node_groups.insert(node, group);
+        self.log_mutation(GraphOp::AddToGroup { group, node });
+    }
+
+    /// Remove `node` from its group, returning the group it was in
+    pub fn remove_from_group(&mut self, node: NodeId) -> Option<GroupId> {
+        let group = self.node_groups.remove(node)?;
+
+        self.groups
+            .get_mut(group)
+            .expect(INVALID_STATE)
+            .nodes
+            .retain(|&other| other != node);
+        self.log_mutation(GraphOp::RemoveFromGroup(node));
+
+        Some(group)
+    }
+
+    /// Remove a group, keeping its nodes. Returns the nodes that were in it.
+    #[must_use]
+    pub fn ungroup(&mut self, group_id: GroupId) -> Option<Vec<NodeId>> {
+        let group = self.groups.remove(group_id)?;
+
+        for &node in group.nodes.iter() {
+            self.node_groups.remove(node);
+        }
+
+        self.log_mutation(GraphOp::Ungroup(group_id));
+
+        Some(group.nodes)
+    }
+
+    /// Remove a group along with all of its nodes
+    #[must_use]
+    pub fn delete_group(&mut self, group: GroupId) -> Option<()> {
+        for node in self.ungroup(group)? {
+            self.take_node(node).expect(INVALID_STATE);
+        }
+
+        Some(())
+    }
+}
+
+impl<N: Node + Clone> Graph<N> {
+    /// Copy all nodes in a group and the connections between them into a new
+    /// group. Connections to nodes outside the group are not copied.
+    #[must_use]
+    pub fn duplicate_group(&mut self, group: GroupId) -> Option<GroupId> {
+        let Group { name, nodes } = self.groups.get(group)?;
+
+        let name = name.clone();
+        let copied = self.copy_nodes(nodes);
+        let mapping = self.paste_nodes(copied);
+
+        let nodes = self.groups[group]
+            .nodes
+            .iter()
+            .map(|&node| mapping[node])
+            .collect::<Vec<_>>();
+
+        Some(self.create_group(&name, nodes))
+    }
+
+    /// Move all nodes in a group to a new graph, along with the connections
+    /// between them. Connections to nodes outside the group are removed.
+    #[must_use]
+    pub fn extract_group(&mut self, group: GroupId) -> Option<Graph<N>> {
+        let nodes = self.groups.get(group)?.nodes.clone();
+
+        let mut graph = Graph::new();
+        graph.paste_nodes(self.copy_nodes(&nodes));
+
+        self.delete_group(group).expect(INVALID_STATE);
+
+        Some(graph)
+    }
+}
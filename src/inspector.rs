@@ -0,0 +1,373 @@
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use crate::{
+    Graph, Node, NodeId,
+    analyzer::GraphAnalyzer,
+    walker::{GraphWalkContext, GraphWalker},
+};
+
+/// What the right panel of an [`Inspector`] shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Panel {
+    Node,
+    Analysis,
+}
+
+/// A terminal UI for looking through a graph, useful on servers without a
+/// graphical editor. Nodes are listed in evaluation order; selecting one shows
+/// its ports, connections and the values of the current walk.
+///
+/// | Key | Action |
+/// | --- | --- |
+/// | Up/Down, k/j | Select a node |
+/// | Tab | Switch between node details and analysis |
+/// | s | Evaluate the next node of the walk |
+/// | f | Finish the walk |
+/// | r | Restart the walk |
+/// | q, Esc | Quit |
+///
+/// ```no_run
+/// # use node_graph::{
+/// #     Graph, InitialPorts, Node, document::DocumentNode, inspector::Inspector,
+/// #     migration::Migrations, registry::NodeRegistry, walker::GraphWalkContext,
+/// # };
+/// # #[derive(Debug)]
+/// # struct MyNode;
+/// # impl MyNode {
+/// #     fn evaluate(&mut self, context: &mut GraphWalkContext<Self>) {
+/// #         context.set(0, 1.0);
+/// #     }
+/// # }
+/// # impl Node for MyNode {
+/// #     type DataType = ();
+/// #     type DataValue = f32;
+/// #     fn initial_ports(&self) -> InitialPorts<Self> {
+/// #         InitialPorts {
+/// #             outputs: vec![("value", ())],
+/// #             ..Default::default()
+/// #         }
+/// #     }
+/// # }
+/// # impl DocumentNode for MyNode {
+/// #     fn kind(&self) -> &str {
+/// #         "MyNode"
+/// #     }
+/// # }
+/// # let mut registry = NodeRegistry::new();
+/// # registry.register("MyNode", |_| MyNode);
+/// # let document = Graph::<MyNode>::new().to_document(0);
+/// # let migrations = Migrations::new(0);
+/// let graph = Graph::from_document(document, &registry, &migrations)?;
+/// Inspector::new(&graph, MyNode::evaluate).run()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Inspector<'a, N: Node, F> {
+    graph: &'a Graph<N>,
+    evaluate: F,
+    /// All nodes, dependencies first
+    nodes: Vec<NodeId>,
+    selected: usize,
+    panel: Panel,
+    walker: GraphWalker<'a, N>,
+    /// The nodes evaluated by the current walk
+    evaluated: Vec<NodeId>,
+    quit: bool,
+}
+
+impl<'a, N, F> Inspector<'a, N, F>
+where
+    N: Node,
+    F: for<'b> Fn(&mut N, &mut GraphWalkContext<'a, 'b, N>),
+{
+    /// Inspect `graph`, stepping through walks with `evaluate` like
+    /// [`GraphWalker::walk`]
+    pub fn new(graph: &'a Graph<N>, evaluate: F) -> Self {
+        let mut nodes = GraphAnalyzer::new(graph)
+            .layers()
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        for node in graph.node_data.keys() {
+            if !nodes.contains(&node) {
+                nodes.push(node);
+            }
+        }
+
+        Self {
+            graph,
+            evaluate,
+            nodes,
+            selected: 0,
+            panel: Panel::Node,
+            walker: GraphWalker::new(graph, None),
+            evaluated: Vec::new(),
+            quit: false,
+        }
+    }
+
+    /// Take over the terminal until the user quits
+    pub fn run(&mut self) -> std::io::Result<()> {
+        let mut terminal = ratatui::init();
+        let result = self.run_in(&mut terminal);
+
+        ratatui::restore();
+        result
+    }
+
+    fn run_in(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                self.handle_key(key.code);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The node selected in the list
+    pub fn selected(&self) -> Option<NodeId> {
+        self.nodes.get(self.selected).copied()
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// React to a key press, for driving the inspector from another event
+    /// loop
+    pub fn handle_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.nodes.len().saturating_sub(1))
+            }
+            KeyCode::Tab => {
+                self.panel = match self.panel {
+                    Panel::Node => Panel::Analysis,
+                    Panel::Analysis => Panel::Node,
+                }
+            }
+            KeyCode::Char('s') => self.step(),
+            KeyCode::Char('f') => {
+                while !self.walker.is_finished() {
+                    self.step();
+                }
+            }
+            KeyCode::Char('r') => {
+                self.walker = GraphWalker::new(self.graph, None);
+                self.evaluated.clear();
+            }
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            _ => {}
+        }
+    }
+
+    fn step(&mut self) {
+        if let Some(step) = self.walker.step(&self.evaluate) {
+            self.evaluated.push(step.node);
+
+            // Follow the walk, so the values it just wrote are on screen
+            if let Some(index) = self.nodes.iter().position(|&node| node == step.node) {
+                self.selected = index;
+            }
+        }
+    }
+
+    /// Draw the inspector into a frame, for embedding it in another
+    /// application
+    pub fn draw(&self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        let [list, details] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Fill(1)]).areas(main);
+
+        self.draw_list(frame, list);
+
+        match self.panel {
+            Panel::Node => self.draw_node(frame, details),
+            Panel::Analysis => self.draw_analysis(frame, details),
+        }
+
+        let walk = match self.walker.is_finished() {
+            true => "finished".to_string(),
+            false => format!("{}/{}", self.walker.position(), self.walker.path().len()),
+        };
+
+        frame.render_widget(
+            Line::from(format!(
+                " walk {walk} | ↑↓ select  tab analysis  s step  f finish  r restart  q quit"
+            ))
+            .dim(),
+            help,
+        );
+    }
+
+    fn draw_list(&self, frame: &mut Frame, area: Rect) {
+        let next = self.walker.path().get(self.walker.position()).copied();
+
+        let items = self
+            .nodes
+            .iter()
+            .map(|&node| {
+                let marker = if Some(node) == next {
+                    "▶ "
+                } else if self.evaluated.contains(&node) {
+                    "✓ "
+                } else {
+                    "  "
+                };
+
+                ListItem::new(format!("{marker}{}", self.graph.node_title(node)))
+            })
+            .collect::<Vec<_>>();
+
+        let mut state = ListState::default().with_selected(Some(self.selected));
+
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(format!(" Nodes ({}) ", self.nodes.len())))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            area,
+            &mut state,
+        );
+    }
+
+    fn draw_node(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(" Node ");
+
+        let Some(node) = self.selected() else {
+            frame.render_widget(Paragraph::new("The graph is empty").block(block), area);
+            return;
+        };
+
+        let graph = self.graph;
+        let data = &graph.node_data[node];
+
+        let mut lines = vec![
+            Line::from(graph.node_title(node).bold()),
+            Line::from(graph.nodes[node].read().variant_name()).dim(),
+            Line::default(),
+            Line::from("Inputs".underlined()),
+        ];
+
+        for &(ref name, port) in data.inputs.iter() {
+            let info = &graph.input_ports[port];
+
+            let mut spans = vec![Span::raw(format!("  {name}: {:?}", info.ty))];
+
+            if let Some(default) = &info.default {
+                spans.push(Span::raw(format!(" = {default:?}")).dim());
+            }
+
+            for &connection in info.incoming_connections.iter() {
+                let port = graph.connections[connection].start_port;
+                let start = &graph.output_ports[port];
+
+                spans.push(Span::raw(format!(
+                    " <- {}.{}",
+                    graph.node_title(start.node),
+                    start.name
+                )));
+
+                if let Some(value) = self.walker.get_output(port) {
+                    spans.push(Span::raw(format!(" = {value:?}")).green());
+                }
+            }
+
+            lines.push(Line::from(spans));
+        }
+
+        lines.push(Line::default());
+        lines.push(Line::from("Outputs".underlined()));
+
+        for &(ref name, port) in data.outputs.iter() {
+            let info = &graph.output_ports[port];
+
+            let mut spans = vec![Span::raw(format!("  {name}: {:?}", info.ty))];
+
+            if let Some(value) = self.walker.get_output(port) {
+                spans.push(Span::raw(format!(" = {value:?}")).green());
+            }
+
+            for &connection in info.outgoing_connections.iter() {
+                let end = graph.connections[connection].end_port;
+                let end = &graph.input_ports[end];
+
+                spans.push(Span::raw(format!(
+                    " -> {}.{}",
+                    graph.node_title(end.node),
+                    end.name
+                )));
+            }
+
+            lines.push(Line::from(spans));
+        }
+
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(block)
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+
+    fn draw_analysis(&self, frame: &mut Frame, area: Rect) {
+        let graph = self.graph;
+        let analyzer = GraphAnalyzer::new(graph);
+        let stats = graph.stats();
+        let categories = analyzer.catagorize_nodes();
+        let cycles = analyzer.cycles();
+
+        let mut lines = vec![
+            Line::from(format!("Nodes:        {}", stats.nodes)),
+            Line::from(format!("Connections:  {}", stats.connections)),
+            Line::from(format!(
+                "Ports:        {} in, {} out",
+                stats.input_ports, stats.output_ports
+            )),
+            Line::from(format!("Max depth:    {}", stats.max_depth)),
+            Line::from(format!("Avg. degree:  {:.2}", stats.average_degree)),
+            Line::default(),
+            Line::from(format!(
+                "Entry {}, exit {}, net {}, loose {}",
+                categories.entry.len(),
+                categories.exit.len(),
+                categories.net.len(),
+                categories.loose.len()
+            )),
+            Line::default(),
+        ];
+
+        if cycles.is_empty() {
+            lines.push(Line::from("No cycles").green());
+        }
+
+        for cycle in cycles {
+            let names = cycle
+                .iter()
+                .map(|&node| graph.node_title(node))
+                .collect::<Vec<_>>();
+
+            lines.push(Line::from(format!("Cycle: {}", names.join(" -> "))).red());
+        }
+
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title(" Analysis "))
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+}
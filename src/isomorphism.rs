@@ -0,0 +1,247 @@
+use std::collections::{HashSet, VecDeque};
+
+use slotmap::SecondaryMap;
+
+use crate::{Graph, Node, NodeId};
+
+/// A connection as (start node, output index, end node, input index), which
+/// can be compared between graphs once the nodes are matched up
+type Edge = (NodeId, usize, NodeId, usize);
+
+impl<N: Node + PartialEq> Graph<N>
+where
+    N::DataValue: PartialEq,
+{
+    /// Whether both graphs contain the same nodes, ports, defaults and
+    /// connections, ignoring ids. Named nodes are matched by name, unnamed
+    /// nodes are matched with the first equal unnamed node in `other`.
+    ///
+    /// Because of that, graphs that only differ in the order equal nodes were
+    /// created in may not be structurally equal, see
+    /// [`is_isomorphic_to`](Self::is_isomorphic_to).
+    pub fn structurally_eq(&self, other: &Graph<N>) -> bool {
+        if self.node_count() != other.node_count()
+            || self.connection_count() != other.connection_count()
+        {
+            return false;
+        }
+
+        let mut matching = SecondaryMap::with_capacity(self.node_count());
+        let mut taken = SecondaryMap::with_capacity(other.node_count());
+
+        for node in self.node_data.keys() {
+            let name = self.get_node_name(node);
+
+            let candidates: Box<dyn Iterator<Item = NodeId>> = match name {
+                Some(name) => Box::new(other.get_nodes_by_name(name)),
+                None => Box::new(
+                    other
+                        .node_data
+                        .keys()
+                        .filter(|&id| other.get_node_name(id).is_none()),
+                ),
+            };
+
+            let found = candidates
+                .filter(|&id| !taken.contains_key(id))
+                .find(|&id| self.node_matches(node, other, id));
+
+            let Some(found) = found else {
+                return false;
+            };
+
+            matching.insert(node, found);
+            taken.insert(found, ());
+        }
+
+        let mut edges = self
+            .edges()
+            .into_iter()
+            .map(|(start, output, end, input)| (matching[start], output, matching[end], input))
+            .collect::<Vec<_>>();
+        let mut other_edges = other.edges();
+
+        edges.sort_unstable();
+        other_edges.sort_unstable();
+
+        edges == other_edges
+    }
+
+    /// Whether the nodes of this graph can be matched up with the nodes of
+    /// `other` so that all nodes, ports, defaults and connections are equal.
+    /// Unlike [`structurally_eq`](Self::structurally_eq) this ignores node
+    /// names and tries every possible matching, which can be slow for graphs
+    /// with many equal nodes.
+    pub fn is_isomorphic_to(&self, other: &Graph<N>) -> bool {
+        if self.node_count() != other.node_count()
+            || self.connection_count() != other.connection_count()
+        {
+            return false;
+        }
+
+        let edges = self.edges();
+        let other_edges = other.edges().into_iter().collect::<HashSet<_>>();
+
+        let mut adjacent = SecondaryMap::<NodeId, Vec<Edge>>::with_capacity(self.node_count());
+
+        for node in self.node_data.keys() {
+            adjacent.insert(node, Vec::new());
+        }
+
+        for &edge in edges.iter() {
+            adjacent[edge.0].push(edge);
+
+            if edge.2 != edge.0 {
+                adjacent[edge.2].push(edge);
+            }
+        }
+
+        // Visit nodes breadth first, so most nodes are matched right after a
+        // neighbour and wrong candidates are rejected early
+
+        let mut order = Vec::with_capacity(self.node_count());
+        let mut visited = SecondaryMap::with_capacity(self.node_count());
+
+        for root in self.node_data.keys() {
+            if visited.insert(root, ()).is_some() {
+                continue;
+            }
+
+            let mut queue = VecDeque::from([root]);
+
+            while let Some(node) = queue.pop_front() {
+                order.push(node);
+
+                for &(start, _, end, _) in adjacent[node].iter() {
+                    for next in [start, end] {
+                        if visited.insert(next, ()).is_none() {
+                            queue.push_back(next);
+                        }
+                    }
+                }
+            }
+        }
+
+        let candidates = order
+            .iter()
+            .map(|&node| {
+                other
+                    .node_data
+                    .keys()
+                    .filter(|&id| self.node_matches(node, other, id))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut matching = SecondaryMap::<NodeId, NodeId>::with_capacity(self.node_count());
+        let mut used = SecondaryMap::with_capacity(other.node_count());
+
+        // The candidate to try next at every depth of the search
+        let mut next = vec![0; order.len()];
+        let mut depth = 0;
+
+        while depth < order.len() {
+            let node = order[depth];
+
+            if let Some(previous) = matching.remove(node) {
+                used.remove(previous);
+            }
+
+            let consistent = |matching: &SecondaryMap<NodeId, NodeId>, candidate: NodeId| {
+                let map = |id: NodeId| match id == node {
+                    true => Some(candidate),
+                    false => matching.get(id).copied(),
+                };
+
+                adjacent[node].iter().all(|&(start, output, end, input)| {
+                    match (map(start), map(end)) {
+                        (Some(start), Some(end)) => {
+                            other_edges.contains(&(start, output, end, input))
+                        }
+                        _ => true,
+                    }
+                })
+            };
+
+            let found = candidates[depth][next[depth]..]
+                .iter()
+                .position(|&id| !used.contains_key(id) && consistent(&matching, id));
+
+            match found {
+                Some(offset) => {
+                    let candidate = candidates[depth][next[depth] + offset];
+
+                    next[depth] += offset + 1;
+                    matching.insert(node, candidate);
+                    used.insert(candidate, ());
+                    depth += 1;
+                }
+                None if depth == 0 => return false,
+                None => {
+                    next[depth] = 0;
+                    depth -= 1;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Whether two nodes have equal values and equal ports with the same
+    /// number of connections
+    fn node_matches(&self, node: NodeId, other: &Graph<N>, other_node: NodeId) -> bool {
+        if *self.nodes[node].read() != *other.nodes[other_node].read() {
+            return false;
+        }
+
+        let data = &self.node_data[node];
+        let other_data = &other.node_data[other_node];
+
+        if data.inputs.len() != other_data.inputs.len()
+            || data.outputs.len() != other_data.outputs.len()
+        {
+            return false;
+        }
+
+        let inputs_match = data.inputs.iter().zip(other_data.inputs.iter()).all(
+            |((name, port), (other_name, other_port))| {
+                let port = &self.input_ports[*port];
+                let other_port = &other.input_ports[*other_port];
+
+                name == other_name
+                    && port.ty == other_port.ty
+                    && port.default == other_port.default
+                    && port.incoming_connections.len() == other_port.incoming_connections.len()
+            },
+        );
+
+        let outputs_match = data.outputs.iter().zip(other_data.outputs.iter()).all(
+            |((name, port), (other_name, other_port))| {
+                let port = &self.output_ports[*port];
+                let other_port = &other.output_ports[*other_port];
+
+                name == other_name
+                    && port.ty == other_port.ty
+                    && port.outgoing_connections.len() == other_port.outgoing_connections.len()
+            },
+        );
+
+        inputs_match && outputs_match
+    }
+}
+
+impl<N: Node> Graph<N> {
+    fn edges(&self) -> Vec<Edge> {
+        self.connections
+            .values()
+            .map(|connection| {
+                (
+                    self.output_ports[connection.start_port].node,
+                    self.output_port_index(connection.start_port),
+                    self.input_ports[connection.end_port].node,
+                    self.input_port_index(connection.end_port),
+                )
+            })
+            .collect()
+    }
+}
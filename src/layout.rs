@@ -0,0 +1,310 @@
+use slotmap::SecondaryMap;
+
+use crate::{Graph, Node, NodeId, analyzer::GraphAnalyzer};
+
+/// Settings for [`Graph::compute_layout`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutOptions {
+    /// Horizontal distance between layers
+    pub layer_spacing: f64,
+    /// Vertical distance between nodes in the same layer
+    pub node_spacing: f64,
+    /// How many times to sweep over the layers reordering nodes, more sweeps
+    /// can remove more crossings
+    pub sweeps: usize,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            layer_spacing: 200.0,
+            node_spacing: 100.0,
+            sweeps: 8,
+        }
+    }
+}
+
+/// Positions of the nodes of a graph, see [`Graph::compute_layout`]
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    positions: SecondaryMap<NodeId, (f64, f64)>,
+    /// The node ids in each layer, from top to bottom
+    layers: Vec<Vec<NodeId>>,
+}
+
+impl Layout {
+    pub fn get(&self, node: NodeId) -> Option<(f64, f64)> {
+        self.positions.get(node).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, (f64, f64))> + '_ {
+        self.positions
+            .iter()
+            .map(|(node, &position)| (node, position))
+    }
+
+    /// The nodes in each layer from left to right, each sorted from top to
+    /// bottom
+    pub fn layers(&self) -> &[Vec<NodeId>] {
+        &self.layers
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+/// A node in the layered graph, either a real node or a dummy node placed on
+/// connections spanning several layers so they take part in ordering
+struct Vertex {
+    node: Option<NodeId>,
+    layer: usize,
+    predecessors: Vec<usize>,
+    successors: Vec<usize>,
+}
+
+impl<N: Node> Graph<N> {
+    /// Compute positions for all nodes, flowing from left to right: nodes
+    /// are placed in [layers](GraphAnalyzer::layers) by their dependencies,
+    /// and the nodes in each layer are reordered to reduce the number of
+    /// crossing connections. Nodes in cycles are placed in a layer after all
+    /// others.
+    pub fn compute_layout(&self, options: &LayoutOptions) -> Layout {
+        let mut layers = GraphAnalyzer::new(self).layers();
+
+        let placed = layers.iter().map(Vec::len).sum::<usize>();
+
+        if placed < self.node_data.len() {
+            let mut depths = SecondaryMap::with_capacity(placed);
+
+            for (depth, layer) in layers.iter().enumerate() {
+                for &node in layer {
+                    depths.insert(node, depth);
+                }
+            }
+
+            layers.push(
+                self.node_data
+                    .keys()
+                    .filter(|&node| !depths.contains_key(node))
+                    .collect(),
+            );
+        }
+
+        let mut vertices = Vec::with_capacity(self.node_data.len());
+        let mut vertex_of = SecondaryMap::with_capacity(self.node_data.len());
+        let mut order = vec![Vec::new(); layers.len()];
+
+        for (layer, nodes) in layers.iter().enumerate() {
+            for &node in nodes {
+                vertex_of.insert(node, vertices.len());
+                order[layer].push(vertices.len());
+                vertices.push(Vertex {
+                    node: Some(node),
+                    layer,
+                    predecessors: Vec::new(),
+                    successors: Vec::new(),
+                });
+            }
+        }
+
+        let mut edges = self
+            .connections
+            .values()
+            .map(|connection| {
+                (
+                    vertex_of[self.output_ports[connection.start_port].node],
+                    vertex_of[self.input_ports[connection.end_port].node],
+                )
+            })
+            .collect::<Vec<_>>();
+
+        edges.sort_unstable();
+        edges.dedup();
+
+        for (start, end) in edges {
+            let end_layer = vertices[end].layer;
+            let mut previous = start;
+
+            // Connections within a layer or going back a layer only happen
+            // in cycles, they don't affect the order
+            if end_layer <= vertices[start].layer {
+                continue;
+            }
+
+            let start_layer = vertices[start].layer;
+
+            for (layer, layer_order) in order
+                .iter_mut()
+                .enumerate()
+                .take(end_layer)
+                .skip(start_layer + 1)
+            {
+                let dummy = vertices.len();
+
+                layer_order.push(dummy);
+                vertices.push(Vertex {
+                    node: None,
+                    layer,
+                    predecessors: vec![previous],
+                    successors: Vec::new(),
+                });
+                vertices[previous].successors.push(dummy);
+                previous = dummy;
+            }
+
+            vertices[previous].successors.push(end);
+            vertices[end].predecessors.push(previous);
+        }
+
+        minimize_crossings(&vertices, &mut order, options.sweeps);
+
+        let mut positions = SecondaryMap::with_capacity(self.node_data.len());
+
+        for (layer, vertices_in_layer) in order.iter().enumerate() {
+            let center = (vertices_in_layer.len() as f64 - 1.0) / 2.0;
+
+            for (index, &vertex) in vertices_in_layer.iter().enumerate() {
+                if let Some(node) = vertices[vertex].node {
+                    positions.insert(
+                        node,
+                        (
+                            layer as f64 * options.layer_spacing,
+                            (index as f64 - center) * options.node_spacing,
+                        ),
+                    );
+                }
+            }
+        }
+
+        let layers = order
+            .iter()
+            .map(|layer| {
+                layer
+                    .iter()
+                    .filter_map(|&vertex| vertices[vertex].node)
+                    .collect()
+            })
+            .collect();
+
+        Layout { positions, layers }
+    }
+
+    /// [Compute a layout](Self::compute_layout) and store it as the
+    /// [positions](Self::set_node_position) of the nodes
+    pub fn auto_layout(&mut self, options: &LayoutOptions) -> Layout {
+        let layout = self.compute_layout(options);
+
+        for (node, position) in layout.iter() {
+            self.set_node_position(node, position);
+        }
+
+        layout
+    }
+}
+
+/// Reorder the vertices within each layer with the barycenter heuristic,
+/// sweeping down and up the layers and keeping the order with the fewest
+/// crossings
+fn minimize_crossings(vertices: &[Vertex], order: &mut [Vec<usize>], sweeps: usize) {
+    let mut best = order.to_vec();
+    let mut best_crossings = crossings(vertices, order);
+
+    for sweep in 0..sweeps {
+        let downwards = sweep % 2 == 0;
+
+        for step in 1..order.len() {
+            let (layer, neighbor_layer) = if downwards {
+                (step, step - 1)
+            } else {
+                (order.len() - 1 - step, order.len() - step)
+            };
+
+            let mut index_of = vec![0; vertices.len()];
+
+            for (index, &vertex) in order[neighbor_layer].iter().enumerate() {
+                index_of[vertex] = index;
+            }
+
+            let barycenters = order[layer]
+                .iter()
+                .enumerate()
+                .map(|(index, &vertex)| {
+                    let neighbors = match downwards {
+                        true => &vertices[vertex].predecessors,
+                        false => &vertices[vertex].successors,
+                    };
+
+                    // Vertices without neighbors stay where they are
+                    if neighbors.is_empty() {
+                        return (vertex, index as f64);
+                    }
+
+                    let sum = neighbors
+                        .iter()
+                        .map(|&neighbor| index_of[neighbor] as f64)
+                        .sum::<f64>();
+
+                    (vertex, sum / neighbors.len() as f64)
+                })
+                .collect::<Vec<_>>();
+
+            let mut sorted = barycenters;
+            sorted.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+            order[layer] = sorted.into_iter().map(|(vertex, _)| vertex).collect();
+        }
+
+        let count = crossings(vertices, order);
+
+        if count < best_crossings {
+            best_crossings = count;
+            best = order.to_vec();
+        }
+
+        if best_crossings == 0 {
+            break;
+        }
+    }
+
+    order.clone_from_slice(&best);
+}
+
+/// The number of pairs of edges between adjacent layers that cross
+fn crossings(vertices: &[Vertex], order: &[Vec<usize>]) -> usize {
+    let mut index_of = vec![0; vertices.len()];
+
+    for layer in order {
+        for (index, &vertex) in layer.iter().enumerate() {
+            index_of[vertex] = index;
+        }
+    }
+
+    let mut total = 0;
+
+    for layer in order {
+        let edges = layer
+            .iter()
+            .flat_map(|&vertex| {
+                vertices[vertex]
+                    .successors
+                    .iter()
+                    .map(move |&successor| (vertex, successor))
+            })
+            .map(|(start, end)| (index_of[start], index_of[end]))
+            .collect::<Vec<_>>();
+
+        for (i, &(start_a, end_a)) in edges.iter().enumerate() {
+            for &(start_b, end_b) in &edges[i + 1..] {
+                if (start_a < start_b && end_a > end_b) || (start_a > start_b && end_a < end_b) {
+                    total += 1;
+                }
+            }
+        }
+    }
+
+    total
+}
@@ -1,16 +1,107 @@
+pub mod adapter;
+pub mod analysis_cache;
 pub mod analyzer;
+#[cfg(feature = "async")]
+pub mod async_walker;
+pub mod batch;
+pub mod builder;
+pub mod cache;
+mod canonical;
+pub mod cell;
+pub mod collab;
+pub mod compact;
+pub mod compiler;
+pub mod consistency;
+mod copy;
+pub mod diff;
+pub mod document;
+#[cfg(feature = "egui")]
+pub mod editor;
+pub mod export;
+#[cfg(feature = "expr")]
+pub mod expr;
+pub mod function;
+pub mod group;
+#[cfg(feature = "tui")]
+pub mod inspector;
+mod isomorphism;
+pub mod layout;
+pub mod lint;
 pub mod macros;
+pub mod memo;
+pub mod metadata;
+pub mod metrics;
+pub mod migration;
+pub mod mutation_log;
+pub mod naming;
+pub mod observer;
+pub mod parameter;
+pub mod pass;
+pub mod path;
+pub mod plugin;
+mod port_names;
+pub mod pretty;
 pub mod reference;
+pub mod registry;
+pub mod reroute;
+#[cfg(feature = "runtime")]
+pub mod runtime;
+pub mod scheduler;
+#[cfg(feature = "rhai")]
+pub mod script;
+pub mod snapshot;
+pub mod stable_id;
+pub mod stats;
+mod structural_hash;
+pub mod subgraph;
+pub mod template;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod trace;
+pub mod transaction;
+pub mod typed;
+pub mod validator;
+pub mod variadic;
+pub mod view;
 pub mod walker;
-
-use std::fmt::Debug;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod workspace;
+
+use std::{
+    fmt::{Debug, Display},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
 
 use itertools::Itertools;
-use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use slotmap::{Key, SecondaryMap, SlotMap, new_key_type};
 
-use crate::reference::{
-    InputPortReference, NodeInputIdentifier, NodeOutputIdentifier, OutputPortReference,
+use crate::{
+    adapter::AdapterRegistry,
+    analysis_cache::AnalysisCache,
+    analyzer::GraphAnalyzer,
+    batch::GraphOp,
+    cell::{NodeCell, NodeRef, NodeRefMut},
+    compact::CompactListeners,
+    group::{Group, GroupId},
+    metadata::Metadata,
+    mutation_log::MutationLog,
+    naming::{NodeNamePolicy, NodeNames},
+    observer::OutputObservers,
+    parameter::Parameters,
+    port_names::{NameInterner, NamedPorts},
+    reference::{
+        InputPortReference, NodeInputIdentifier, NodeOutputIdentifier, OutputPortReference,
+    },
+    stable_id::StableIdMap,
+    validator::ConnectionValidators,
+    variadic::VariadicInput,
+    view::{DeferredNotifications, GraphView},
 };
 
 pub(crate) const INVALID_STATE: &str = "Graph is in invalid state, this is a bug";
@@ -33,10 +124,27 @@ impl NodeId {
 #[derive(Debug)]
 pub struct Graph<N: Node> {
     node_data: SlotMap<NodeId, NodeData>,
-    nodes: SecondaryMap<NodeId, RwLock<N>>,
+    nodes: SecondaryMap<NodeId, NodeCell<N>>,
     connections: SlotMap<ConnectionId, Connection>,
     input_ports: SlotMap<InputPortId, Port<N>>,
     output_ports: SlotMap<OutputPortId, Port<N>>,
+    stable_ids: Option<StableIdMap>,
+    allow_cycles: bool,
+    adapters: AdapterRegistry<N>,
+    validators: ConnectionValidators<N>,
+    variadic_inputs: SecondaryMap<NodeId, Vec<VariadicInput<N>>>,
+    node_names: NodeNames,
+    node_name_policy: NodeNamePolicy,
+    parameters: Parameters,
+    groups: SlotMap<GroupId, Group>,
+    node_groups: SecondaryMap<NodeId, GroupId>,
+    metadata: Metadata,
+    observers: OutputObservers<N>,
+    port_names: NameInterner,
+    analysis: AnalysisCache,
+    compact_listeners: CompactListeners,
+    mutation_log: Option<MutationLog<N>>,
+    deferred_notifications: DeferredNotifications,
 }
 
 impl<N: Node> Graph<N> {
@@ -47,23 +155,306 @@ impl<N: Node> Graph<N> {
             connections: SlotMap::with_key(),
             input_ports: SlotMap::with_key(),
             output_ports: SlotMap::with_key(),
+            stable_ids: None,
+            allow_cycles: false,
+            adapters: AdapterRegistry::new(),
+            validators: ConnectionValidators::new(),
+            variadic_inputs: SecondaryMap::new(),
+            node_names: NodeNames::default(),
+            node_name_policy: NodeNamePolicy::default(),
+            parameters: Parameters::default(),
+            groups: SlotMap::with_key(),
+            node_groups: SecondaryMap::new(),
+            metadata: Metadata::new(),
+            observers: OutputObservers::new(),
+            port_names: NameInterner::default(),
+            analysis: AnalysisCache::default(),
+            compact_listeners: CompactListeners::default(),
+            mutation_log: None,
+            deferred_notifications: DeferredNotifications::default(),
+        }
+    }
+
+    /// Create a graph with room for `nodes` nodes and `connections`
+    /// connections before reallocating
+    pub fn with_capacity(nodes: usize, connections: usize) -> Self {
+        let mut graph = Self::new();
+        graph.reserve(nodes, connections);
+        graph
+    }
+
+    /// Reserve room for at least `nodes` more nodes and `connections` more
+    /// connections
+    pub fn reserve(&mut self, nodes: usize, connections: usize) {
+        self.node_data.reserve(nodes);
+        self.nodes.set_capacity(self.node_data.capacity());
+        self.connections.reserve(connections);
+    }
+
+    /// Reserve room for at least `inputs` more input ports and `outputs` more
+    /// output ports
+    pub fn reserve_ports(&mut self, inputs: usize, outputs: usize) {
+        self.input_ports.reserve(inputs);
+        self.output_ports.reserve(outputs);
+    }
+
+    /// Free unused capacity in the port and connection lists of every node
+    /// and port, along with port names no port uses anymore. With the
+    /// `smallvec` feature, lists that fit are moved back inline.
+    pub fn shrink_to_fit(&mut self) {
+        self.port_names.shrink_to_fit();
+
+        for data in self.node_data.values_mut() {
+            data.inputs.shrink_to_fit();
+            data.outputs.shrink_to_fit();
+        }
+
+        for port in self.input_ports.values_mut() {
+            port.incoming_connections.shrink_to_fit();
+        }
+
+        for port in self.output_ports.values_mut() {
+            port.outgoing_connections.shrink_to_fit();
         }
     }
 
-    pub fn get_node(&self, node: NodeId) -> Option<RwLockReadGuard<'_, N>> {
+    pub fn node_count(&self) -> usize {
+        self.node_data.len()
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn input_port_count(&self) -> usize {
+        self.input_ports.len()
+    }
+
+    pub fn output_port_count(&self) -> usize {
+        self.output_ports.len()
+    }
+
+    pub fn contains_node(&self, node: NodeId) -> bool {
+        self.node_data.contains_key(node)
+    }
+
+    pub fn contains_connection(&self, connection: ConnectionId) -> bool {
+        self.connections.contains_key(connection)
+    }
+
+    pub fn contains_input_port(&self, port: impl InputPortReference) -> bool {
+        port.resolve(self)
+            .is_some_and(|port| self.input_ports.contains_key(port))
+    }
+
+    pub fn contains_output_port(&self, port: impl OutputPortReference) -> bool {
+        port.resolve(self)
+            .is_some_and(|port| self.output_ports.contains_key(port))
+    }
+
+    pub fn get_node(&self, node: NodeId) -> Option<NodeRef<'_, N>> {
         Some(self.nodes.get(node)?.read())
     }
 
-    pub fn get_node_mut(&self, node: NodeId) -> Option<RwLockWriteGuard<'_, N>> {
+    pub fn get_node_mut(&self, node: NodeId) -> Option<NodeRefMut<'_, N>> {
         Some(self.nodes.get(node)?.write())
     }
 
+    /// Remove a node along with its ports and connections, returning its value
+    pub fn take_node(&mut self, node: NodeId) -> Option<N> {
+        let data = self.node_data.get(node)?;
+
+        let inputs = data.inputs.iter().map(|&(_, id)| id).collect_vec();
+        let outputs = data.outputs.iter().map(|&(_, id)| id).collect_vec();
+
+        self.log_mutation(GraphOp::DeleteNode(node));
+
+        // Drop variadic inputs first, so deleting their ports doesn't create
+        // new ones
+
+        self.variadic_inputs.remove(node);
+
+        let changed = self.unlogged(|graph| {
+            let mut changed = Vec::new();
+
+            for port in inputs {
+                changed.extend(graph.remove_input_port(port).expect(INVALID_STATE));
+            }
+
+            for port in outputs {
+                changed.extend(graph.remove_output_port(port).expect(INVALID_STATE));
+            }
+
+            changed
+        });
+
+        self.node_data.remove(node);
+        self.analysis.invalidate();
+        self.node_names.remove(node);
+        self.unlogged(|graph| graph.remove_from_group(node));
+
+        if let Some(stable_ids) = &mut self.stable_ids {
+            stable_ids.untrack_node(node);
+        }
+
+        let value = self.nodes.remove(node).expect(INVALID_STATE).into_inner();
+        self.notify_connections_changed(&changed);
+        self.after_mutation();
+
+        Some(value)
+    }
+
+    /// Remove all nodes, ports and connections. Configuration like validators
+    /// and adapters is kept.
+    pub fn clear(&mut self) {
+        for node in self.node_data.keys().collect_vec() {
+            self.log_mutation(GraphOp::DeleteNode(node));
+        }
+
+        self.node_data.clear();
+        self.nodes.clear();
+        self.connections.clear();
+        self.input_ports.clear();
+        self.output_ports.clear();
+        self.variadic_inputs.clear();
+        self.node_names.clear();
+        self.parameters.clear();
+        self.groups.clear();
+        self.node_groups.clear();
+        self.observers.clear();
+        self.port_names.clear();
+        self.analysis.invalidate();
+
+        if let Some(stable_ids) = &mut self.stable_ids {
+            stable_ids.untrack_all();
+        }
+
+        self.after_mutation();
+    }
+
+    /// Remove every node for which `predicate` returns false, along with its
+    /// ports and connections
+    pub fn retain_nodes(&mut self, mut predicate: impl FnMut(NodeId, &N) -> bool) {
+        let removed = self
+            .nodes
+            .iter_mut()
+            .filter_map(|(id, node)| (!predicate(id, node.get_mut())).then_some(id))
+            .collect_vec();
+
+        for node in removed {
+            self.take_node(node).expect(INVALID_STATE);
+        }
+    }
+
+    /// Remove every node that isn't upstream of one of `exit_nodes`, returning
+    /// the removed ids. Nodes that aren't [pure](Node::is_pure) are kept along
+    /// with everything upstream of them, since their effects are needed even
+    /// if nothing reads their outputs.
+    pub fn prune(&mut self, exit_nodes: &[NodeId]) -> Vec<NodeId> {
+        let mut keep = SecondaryMap::with_capacity(self.node_data.len());
+
+        let mut stack = exit_nodes.to_vec();
+        stack.extend(
+            self.nodes
+                .iter()
+                .filter_map(|(id, node)| (!node.read().is_pure()).then_some(id)),
+        );
+
+        while let Some(node) = stack.pop() {
+            if keep.insert(node, ()).is_none() {
+                stack.extend(self.get_direct_dependencies(node));
+            }
+        }
+
+        let removed = self
+            .node_data
+            .keys()
+            .filter(|&node| !keep.contains_key(node))
+            .collect_vec();
+
+        for &node in removed.iter() {
+            self.take_node(node).expect(INVALID_STATE);
+        }
+
+        removed
+    }
+
+    /// Replace the value of a node, keeping its id, ports and connections.
+    /// The new value is told about its connections through
+    /// [`connections_changed`](Node::connections_changed), and outputs
+    /// [memoized](crate::memo::Memoizer) for the old value aren't reused.
+    /// Returns the previous value.
+    pub fn set_node(&mut self, node: NodeId, value: N) -> N {
+        self.node_data
+            .get_mut(node)
+            .expect("Node does not exist")
+            .revision = NODE_REVISIONS.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(clone_node) = self.mutation_logging() {
+            self.log_mutation(GraphOp::SetNode {
+                node,
+                value: clone_node(&value),
+            });
+        }
+
+        let previous = std::mem::replace(self.nodes[node].get_mut(), value);
+
+        self.notify_connections_changed(&[node]);
+        self.after_mutation();
+
+        previous
+    }
+
     pub fn get_input_port_info(&self, port: impl InputPortReference) -> Option<&Port<N>> {
-        self.input_ports.get(port.resolve(&self)?)
+        self.input_ports.get(port.resolve(self)?)
     }
 
     pub fn get_output_port_info(&self, port: impl OutputPortReference) -> Option<&Port<N>> {
-        self.output_ports.get(port.resolve(&self)?)
+        self.output_ports.get(port.resolve(self)?)
+    }
+
+    /// Mutable access to an input port, for changing its default value or
+    /// [metadata](Port::metadata) in place. Use the dedicated methods for
+    /// changing the name, type or connections, as changing those fields here
+    /// leaves the graph in an inconsistent state.
+    pub fn get_input_port_info_mut(
+        &mut self,
+        port: impl InputPortReference,
+    ) -> Option<&mut Port<N>> {
+        let id = port.resolve(self)?;
+        self.input_ports.get_mut(id)
+    }
+
+    /// Mutable access to an output port, see
+    /// [`get_input_port_info_mut`](Self::get_input_port_info_mut)
+    pub fn get_output_port_info_mut(
+        &mut self,
+        port: impl OutputPortReference,
+    ) -> Option<&mut Port<N>> {
+        let id = port.resolve(self)?;
+        self.output_ports.get_mut(id)
+    }
+
+    pub fn get_input_port_metadata(&self, port: impl InputPortReference) -> Option<&Metadata> {
+        Some(&self.get_input_port_info(port)?.metadata)
+    }
+
+    pub fn get_input_port_metadata_mut(
+        &mut self,
+        port: impl InputPortReference,
+    ) -> Option<&mut Metadata> {
+        Some(&mut self.get_input_port_info_mut(port)?.metadata)
+    }
+
+    pub fn get_output_port_metadata(&self, port: impl OutputPortReference) -> Option<&Metadata> {
+        Some(&self.get_output_port_info(port)?.metadata)
+    }
+
+    pub fn get_output_port_metadata_mut(
+        &mut self,
+        port: impl OutputPortReference,
+    ) -> Option<&mut Metadata> {
+        Some(&mut self.get_output_port_info_mut(port)?.metadata)
     }
 
     pub fn create_node<T: NodeTemplate<N>>(&mut self, node: T) -> NodeId {
@@ -76,30 +467,43 @@ impl<N: Node> Graph<N> {
             // Add node initial ports
 
             for &(name, ty, ref default) in initial_ports.inputs.iter() {
+                let name = self.port_names.intern(name);
                 let id = self.input_ports.insert(Port::new(
                     node_id,
-                    name.to_string(),
+                    name.clone(),
                     ty,
                     Some(default.clone()),
                 ));
 
-                node_data.inputs.push((name.to_string(), id));
+                node_data.inputs.push(name, id);
             }
 
             for &(name, ty) in initial_ports.outputs.iter() {
+                let name = self.port_names.intern(name);
                 let id = self
                     .output_ports
-                    .insert(Port::new(node_id, name.to_string(), ty, None));
+                    .insert(Port::new(node_id, name.clone(), ty, None));
 
-                node_data.outputs.push((name.to_string(), id));
+                node_data.outputs.push(name, id);
             }
 
             node_data
         });
 
-        self.nodes.insert(id, RwLock::new(node));
+        self.nodes.insert(id, NodeCell::new(node));
+        self.analysis.node_added(id);
+
+        if let Some(clone_node) = self.mutation_logging() {
+            let node = clone_node(&self.nodes[id].read());
+            self.log_mutation(GraphOp::CreateNode(node));
+        }
+
+        if let Some(stable_ids) = &mut self.stable_ids {
+            stable_ids.track_node(id, &self.node_data[id]);
+        }
 
         callback.post_create(self, id);
+        self.after_mutation();
 
         id
     }
@@ -122,53 +526,61 @@ impl<N: Node> Graph<N> {
             // First add initial ports
 
             for &(name, ty, ref default) in initial_ports.inputs.iter() {
+                let name = self.port_names.intern(name);
                 let id = self.input_ports.insert(Port::new(
                     node_id,
-                    name.to_string(),
+                    name.clone(),
                     ty,
                     Some(default.clone()),
                 ));
 
-                node_data.inputs.push((name.to_string(), id));
+                node_data.inputs.push(name, id);
             }
 
             for &(name, ty) in initial_ports.outputs.iter() {
+                let name = self.port_names.intern(name);
                 let id = self
                     .output_ports
-                    .insert(Port::new(node_id, name.to_string(), ty, None));
+                    .insert(Port::new(node_id, name.clone(), ty, None));
 
-                node_data.outputs.push((name.to_string(), id));
+                node_data.outputs.push(name, id);
             }
 
             // Then add user ports
 
             for (i, (name, ty, default)) in inputs.into_iter().enumerate() {
-                let id = self.input_ports.insert(Port::new(
-                    node_id,
-                    name.to_string(),
-                    ty,
-                    Some(default),
-                ));
+                let name = self.port_names.intern(name);
+                let id =
+                    self.input_ports
+                        .insert(Port::new(node_id, name.clone(), ty, Some(default)));
 
-                node_data.inputs.push((name.to_string(), id));
+                node_data.inputs.push(name, id);
                 input_ports[i] = id;
             }
 
             for (i, (name, ty)) in outputs.into_iter().enumerate() {
+                let name = self.port_names.intern(name);
                 let id = self
                     .output_ports
-                    .insert(Port::new(node_id, name.to_string(), ty, None));
+                    .insert(Port::new(node_id, name.clone(), ty, None));
 
-                node_data.outputs.push((name.to_string(), id));
+                node_data.outputs.push(name, id);
                 output_ports[i] = id;
             }
 
             node_data
         });
 
-        self.nodes.insert(id, RwLock::new(node));
+        self.nodes.insert(id, NodeCell::new(node));
+        self.analysis.node_added(id);
+        self.log_node_with_ports(id);
+
+        if let Some(stable_ids) = &mut self.stable_ids {
+            stable_ids.track_node(id, &self.node_data[id]);
+        }
 
         callback.post_create(self, id);
+        self.after_mutation();
 
         (id, input_ports, output_ports)
     }
@@ -179,21 +591,46 @@ impl<N: Node> Graph<N> {
         name: &str,
         ty: N::DataType,
         default: N::DataValue,
+    ) -> InputPortId {
+        self.create_input_port_inner(node, name, ty, Some(default))
+    }
+
+    pub(crate) fn create_input_port_inner(
+        &mut self,
+        node: NodeId,
+        name: &str,
+        ty: N::DataType,
+        default: Option<N::DataValue>,
     ) -> InputPortId {
         let data = self.node_data.get_mut(node).expect("Node does not exist");
 
-        if data.inputs.iter().any(|(port_name, _)| port_name == name) {
+        if data.inputs.by_name(name).is_some() {
             panic!("An input port with this name already exists");
         }
 
+        let interned = self.port_names.intern(name);
         let id = self
             .input_ports
-            .insert(Port::new(node, name.to_string(), ty, Some(default)));
+            .insert(Port::new(node, interned.clone(), ty, default));
+
+        data.inputs.push(interned, id);
+
+        if let Some(stable_ids) = &mut self.stable_ids {
+            stable_ids.track_input_port(id);
+        }
 
-        data.inputs.push((name.to_string(), id));
+        if self.mutation_logging().is_some() {
+            self.log_mutation(GraphOp::CreateInputPort {
+                node,
+                name: name.to_string(),
+                ty,
+                default: self.input_ports[id].default.clone(),
+            });
+        }
 
         let node = self.nodes.get(node).expect("Node does not exist");
         node.write().input_port_created(name, ty, id);
+        self.after_mutation();
 
         id
     }
@@ -206,35 +643,83 @@ impl<N: Node> Graph<N> {
     ) -> OutputPortId {
         let data = self.node_data.get_mut(node).expect("Node does not exist");
 
-        if data.outputs.iter().any(|(port_name, _)| port_name == name) {
+        if data.outputs.by_name(name).is_some() {
             panic!("An output port with this name already exists");
         }
 
+        let interned = self.port_names.intern(name);
         let id = self
             .output_ports
-            .insert(Port::new(node, name.to_string(), ty, None));
+            .insert(Port::new(node, interned.clone(), ty, None));
+
+        data.outputs.push(interned, id);
+
+        if let Some(stable_ids) = &mut self.stable_ids {
+            stable_ids.track_output_port(id);
+        }
 
-        data.outputs.push((name.to_string(), id));
+        if self.mutation_logging().is_some() {
+            self.log_mutation(GraphOp::CreateOutputPort {
+                node,
+                name: name.to_string(),
+                ty,
+            });
+        }
 
         let node = self.nodes.get(node).expect("Node does not exist");
         node.write().output_port_created(name, ty, id);
+        self.after_mutation();
 
         id
     }
 
     #[must_use]
     pub fn delete_input_port(&mut self, port: impl InputPortReference) -> Option<()> {
-        let port = port.resolve(&self)?;
+        let port = port.resolve(self)?;
+        let changed = self.remove_input_port(port)?;
+
+        self.notify_connections_changed(&changed);
+        self.after_mutation();
+
+        Some(())
+    }
 
+    /// Delete an input port without notifying nodes, returning the nodes
+    /// whose connections changed
+    fn remove_input_port(&mut self, port: InputPortId) -> Option<Vec<NodeId>> {
+        let port_id = port;
         let mut port = self.input_ports.remove(port)?;
 
+        self.log_mutation(GraphOp::DeleteInputPort(port_id));
+
+        self.node_data
+            .get_mut(port.node)
+            .expect(INVALID_STATE)
+            .inputs
+            .remove(port_id)
+            .expect(INVALID_STATE);
+
+        self.parameters.remove(port_id);
+
+        if let Some(stable_ids) = &mut self.stable_ids {
+            stable_ids.untrack_input_port(port_id);
+        }
+
         // Disconnect everything from port
 
+        let mut changed = Vec::new();
+
         for connection_id in port.incoming_connections.drain(..) {
             let Some(connection) = self.connections.remove(connection_id) else {
                 continue;
             };
 
+            self.analysis.invalidate();
+
+            if let Some(stable_ids) = &mut self.stable_ids {
+                stable_ids.untrack_connection(connection_id);
+            }
+
             let start_port = self
                 .output_ports
                 .get_mut(connection.start_port)
@@ -255,24 +740,63 @@ impl<N: Node> Graph<N> {
             start_node
                 .write()
                 .output_connection_removed(connection.start_port, connection_id);
+
+            changed.extend([start_node_id, port.node]);
         }
 
-        Some(())
+        self.update_variadic_inputs(port.node);
+        self.port_names.release(port.name);
+
+        Some(changed)
     }
 
     #[must_use]
     pub fn delete_output_port(&mut self, port: impl OutputPortReference) -> Option<()> {
-        let port = port.resolve(&self)?;
+        let port = port.resolve(self)?;
+        let changed = self.remove_output_port(port)?;
+
+        self.notify_connections_changed(&changed);
+        self.after_mutation();
+
+        Some(())
+    }
 
+    /// Delete an output port without notifying nodes, returning the nodes
+    /// whose connections changed
+    fn remove_output_port(&mut self, port: OutputPortId) -> Option<Vec<NodeId>> {
+        let port_id = port;
         let mut port = self.output_ports.remove(port)?;
 
+        self.log_mutation(GraphOp::DeleteOutputPort(port_id));
+
+        self.node_data
+            .get_mut(port.node)
+            .expect(INVALID_STATE)
+            .outputs
+            .remove(port_id)
+            .expect(INVALID_STATE);
+
+        if let Some(stable_ids) = &mut self.stable_ids {
+            stable_ids.untrack_output_port(port_id);
+        }
+
+        self.observers.remove_port(port_id);
+
         // Disconnect everything from port
 
+        let mut changed = Vec::new();
+
         for connection_id in port.outgoing_connections.drain(..) {
             let Some(connection) = self.connections.remove(connection_id) else {
                 continue;
             };
 
+            self.analysis.invalidate();
+
+            if let Some(stable_ids) = &mut self.stable_ids {
+                stable_ids.untrack_connection(connection_id);
+            }
+
             let end_port = self
                 .input_ports
                 .get_mut(connection.end_port)
@@ -293,25 +817,170 @@ impl<N: Node> Graph<N> {
             end_node
                 .write()
                 .input_connection_removed(connection.end_port, connection_id);
+
+            self.update_variadic_inputs(end_node_id);
+            changed.extend([end_node_id, port.node]);
+        }
+
+        self.port_names.release(port.name);
+
+        Some(changed)
+    }
+
+    /// Change the name of an input port, keeping its id and connections
+    #[must_use]
+    pub fn rename_input_port(
+        &mut self,
+        port: impl InputPortReference,
+        new_name: &str,
+    ) -> Option<()> {
+        let id = port.resolve(self)?;
+        let port = self.input_ports.get_mut(id)?;
+
+        let data = self.node_data.get_mut(port.node).expect(INVALID_STATE);
+
+        if data
+            .inputs
+            .iter()
+            .any(|&(ref name, other)| other != id && **name == *new_name)
+        {
+            panic!("An input port with this name already exists");
+        }
+
+        let name = self.port_names.intern(new_name);
+        let old_name = std::mem::replace(&mut port.name, name.clone());
+
+        data.inputs.rename(id, name);
+
+        let node = self.nodes.get(port.node).expect(INVALID_STATE);
+        node.write().input_port_renamed(id, &old_name, new_name);
+        self.port_names.release(old_name);
+
+        if self.mutation_logging().is_some() {
+            self.log_mutation(GraphOp::RenameInputPort {
+                port: id,
+                name: new_name.to_string(),
+            });
+        }
+
+        self.after_mutation();
+
+        Some(())
+    }
+
+    /// Change the name of an output port, keeping its id and connections
+    #[must_use]
+    pub fn rename_output_port(
+        &mut self,
+        port: impl OutputPortReference,
+        new_name: &str,
+    ) -> Option<()> {
+        let id = port.resolve(self)?;
+        let port = self.output_ports.get_mut(id)?;
+
+        let data = self.node_data.get_mut(port.node).expect(INVALID_STATE);
+
+        if data
+            .outputs
+            .iter()
+            .any(|&(ref name, other)| other != id && **name == *new_name)
+        {
+            panic!("An output port with this name already exists");
+        }
+
+        let name = self.port_names.intern(new_name);
+        let old_name = std::mem::replace(&mut port.name, name.clone());
+
+        data.outputs.rename(id, name);
+
+        let node = self.nodes.get(port.node).expect(INVALID_STATE);
+        node.write().output_port_renamed(id, &old_name, new_name);
+        self.port_names.release(old_name);
+
+        if self.mutation_logging().is_some() {
+            self.log_mutation(GraphOp::RenameOutputPort {
+                port: id,
+                name: new_name.to_string(),
+            });
+        }
+
+        self.after_mutation();
+
+        Some(())
+    }
+
+    /// Move the input port at `from_index` to `to_index`, shifting the ports
+    /// in between. Index based references resolve against the new order.
+    #[must_use]
+    pub fn move_input_port(
+        &mut self,
+        node: NodeId,
+        from_index: usize,
+        to_index: usize,
+    ) -> Option<()> {
+        let data = self.node_data.get_mut(node)?;
+
+        if from_index >= data.inputs.len() || to_index >= data.inputs.len() {
+            return None;
+        }
+
+        let id = data.inputs[from_index].1;
+        data.inputs.move_port(from_index, to_index);
+
+        self.nodes[node]
+            .write()
+            .input_port_moved(id, from_index, to_index);
+        self.log_mutation(GraphOp::MoveInputPort {
+            node,
+            from: from_index,
+            to: to_index,
+        });
+        self.after_mutation();
+
+        Some(())
+    }
+
+    /// Move the output port at `from_index` to `to_index`, shifting the ports
+    /// in between. Index based references resolve against the new order.
+    #[must_use]
+    pub fn move_output_port(
+        &mut self,
+        node: NodeId,
+        from_index: usize,
+        to_index: usize,
+    ) -> Option<()> {
+        let data = self.node_data.get_mut(node)?;
+
+        if from_index >= data.outputs.len() || to_index >= data.outputs.len() {
+            return None;
         }
 
+        let id = data.outputs[from_index].1;
+        data.outputs.move_port(from_index, to_index);
+
+        self.nodes[node]
+            .write()
+            .output_port_moved(id, from_index, to_index);
+        self.log_mutation(GraphOp::MoveOutputPort {
+            node,
+            from: from_index,
+            to: to_index,
+        });
+        self.after_mutation();
+
         Some(())
     }
 
     pub fn get_input_port(&self, node: NodeId, name: &str) -> Option<InputPortId> {
         let node = self.node_data.get(node)?;
 
-        node.inputs
-            .iter()
-            .find_map(|(port_name, port_id)| (port_name == name).then_some(*port_id))
+        node.inputs.by_name(name)
     }
 
     pub fn get_output_port(&self, node: NodeId, name: &str) -> Option<OutputPortId> {
         let node = self.node_data.get(node)?;
 
-        node.outputs
-            .iter()
-            .find_map(|(port_name, port_id)| (port_name == name).then_some(*port_id))
+        node.outputs.by_name(name)
     }
 
     pub fn get_input_port_at(&self, node: NodeId, index: usize) -> Option<InputPortId> {
@@ -326,10 +995,32 @@ impl<N: Node> Graph<N> {
         Some(node.outputs.get(index)?.1)
     }
 
-    pub fn get_input_ports(&self, node: NodeId) -> Option<&Vec<(String, InputPortId)>> {
+    /// The position of a port in the inputs of its node
+    pub(crate) fn input_port_index(&self, port: InputPortId) -> usize {
+        let node = self.input_ports[port].node;
+
+        self.node_data[node]
+            .inputs
+            .iter()
+            .position(|&(_, other)| other == port)
+            .expect(INVALID_STATE)
+    }
+
+    /// The position of a port in the outputs of its node
+    pub(crate) fn output_port_index(&self, port: OutputPortId) -> usize {
+        let node = self.output_ports[port].node;
+
+        self.node_data[node]
+            .outputs
+            .iter()
+            .position(|&(_, other)| other == port)
+            .expect(INVALID_STATE)
+    }
+
+    pub fn get_input_ports(&self, node: NodeId) -> Option<&PortList<(Arc<str>, InputPortId)>> {
         let node = self.node_data.get(node)?;
 
-        Some(&node.inputs)
+        Some(&*node.inputs)
     }
 
     pub fn set_default_value(
@@ -339,43 +1030,216 @@ impl<N: Node> Graph<N> {
     ) {
         let value: N::DataValue = value.into();
 
+        let id = port.resolve(self).expect("Port does not exist");
+
+        if self.mutation_logging().is_some() {
+            self.log_mutation(GraphOp::SetDefaultValue {
+                port: id,
+                value: value.clone(),
+            });
+        }
+
         let port = self
             .input_ports
-            .get_mut(port.resolve(&self).expect("Port does not exist"))
+            .get_mut(id)
             .expect("Input port does not exist");
 
+        let node = self.nodes.get(port.node).expect(INVALID_STATE);
+        node.write().input_default_changed(id, &value);
+
         port.default = Some(value);
     }
 
-    pub fn get_output_ports(&self, node: NodeId) -> Option<&Vec<(String, OutputPortId)>> {
-        let node = self.node_data.get(node)?;
+    /// Change the type of an input port. Returns the connections whose start
+    /// port can no longer be converted to the new type, which are disconnected
+    /// first if `disconnect_incompatible` is set.
+    pub fn set_input_port_type(
+        &mut self,
+        port: impl InputPortReference,
+        ty: N::DataType,
+        disconnect_incompatible: bool,
+    ) -> Vec<ConnectionId> {
+        let id = port.resolve(self).expect("Port does not exist");
 
-        Some(&node.outputs)
-    }
+        self.log_mutation(GraphOp::SetInputPortType { port: id, ty });
 
-    pub fn get_incoming_connections(
-        &self,
-        port: impl InputPortReference,
-    ) -> impl Iterator<Item = OutputPortId> + '_ {
-        let port = port.resolve(&self).expect("Port does not exist");
         let port = self
             .input_ports
-            .get(port)
+            .get_mut(id)
             .expect("Input port does not exist");
 
-        port.incoming_connections.iter().map(|&conn_id| {
-            self.connections
-                .get(conn_id)
-                .expect(INVALID_STATE)
-                .start_port
-        })
+        port.ty = ty;
+
+        let incompatible = port
+            .incoming_connections
+            .iter()
+            .copied()
+            .filter(|&connection| {
+                let start_port = self.connections[connection].start_port;
+                !is_compatible(self.output_ports[start_port].ty, ty)
+            })
+            .collect::<Vec<_>>();
+
+        if disconnect_incompatible {
+            for &connection in incompatible.iter() {
+                self.disconnect(connection).expect(INVALID_STATE);
+            }
+        }
+
+        self.after_mutation();
+        incompatible
     }
 
-    pub fn get_outgoing_connections(
-        &self,
+    /// Change the type of an output port. Returns the connections whose end
+    /// port can no longer accept the new type, which are disconnected first if
+    /// `disconnect_incompatible` is set.
+    pub fn set_output_port_type(
+        &mut self,
         port: impl OutputPortReference,
-    ) -> impl Iterator<Item = InputPortId> + '_ {
-        let port = port.resolve(&self).expect("Port does not exist");
+        ty: N::DataType,
+        disconnect_incompatible: bool,
+    ) -> Vec<ConnectionId> {
+        let id = port.resolve(self).expect("Port does not exist");
+
+        self.log_mutation(GraphOp::SetOutputPortType { port: id, ty });
+
+        let port = self
+            .output_ports
+            .get_mut(id)
+            .expect("Output port does not exist");
+
+        port.ty = ty;
+
+        let incompatible = port
+            .outgoing_connections
+            .iter()
+            .copied()
+            .filter(|&connection| {
+                let end_port = self.connections[connection].end_port;
+                !is_compatible(ty, self.input_ports[end_port].ty)
+            })
+            .collect::<Vec<_>>();
+
+        if disconnect_incompatible {
+            for &connection in incompatible.iter() {
+                self.disconnect(connection).expect(INVALID_STATE);
+            }
+        }
+
+        self.after_mutation();
+        incompatible
+    }
+
+    /// Limit the number of connections to an input port. Existing connections
+    /// are kept, even if there are more than `max`.
+    pub fn set_max_incoming(&mut self, port: impl InputPortReference, max: Option<usize>) {
+        let id = port.resolve(self).expect("Port does not exist");
+        let port = self
+            .input_ports
+            .get_mut(id)
+            .expect("Input port does not exist");
+
+        port.max_incoming = max;
+        self.log_mutation(GraphOp::SetMaxIncoming { port: id, max });
+        self.after_mutation();
+    }
+
+    /// Limit the number of connections from an output port. Existing
+    /// connections are kept, even if there are more than `max`.
+    pub fn set_max_outgoing(&mut self, port: impl OutputPortReference, max: Option<usize>) {
+        let id = port.resolve(self).expect("Port does not exist");
+        let port = self
+            .output_ports
+            .get_mut(id)
+            .expect("Output port does not exist");
+
+        port.max_outgoing = max;
+        self.log_mutation(GraphOp::SetMaxOutgoing { port: id, max });
+        self.after_mutation();
+    }
+
+    /// Reorder the connections to an input port, which is the order
+    /// [`get_incoming_connections`](Self::get_incoming_connections) and
+    /// walkers read them in. Returns `None` if `order` doesn't contain each
+    /// connection to the port exactly once.
+    #[must_use]
+    pub fn set_incoming_order(
+        &mut self,
+        port: impl InputPortReference,
+        order: &[ConnectionId],
+    ) -> Option<()> {
+        let id = port.resolve(self)?;
+        let port = self.input_ports.get_mut(id)?;
+
+        if order.len() != port.incoming_connections.len()
+            || !order
+                .iter()
+                .all(|id| port.incoming_connections.contains(id))
+            || !order.iter().all_unique()
+        {
+            return None;
+        }
+
+        port.incoming_connections = PortList::from(order);
+
+        if self.mutation_logging().is_some() {
+            self.log_mutation(GraphOp::SetIncomingOrder {
+                port: id,
+                order: order.to_vec(),
+            });
+        }
+
+        self.after_mutation();
+
+        Some(())
+    }
+
+    /// The connections to an input port, in the order they are read
+    pub fn get_incoming_connection_ids(
+        &self,
+        port: impl InputPortReference,
+    ) -> impl Iterator<Item = ConnectionId> + '_ {
+        let port = port.resolve(self).expect("Port does not exist");
+
+        self.input_ports
+            .get(port)
+            .expect("Input port does not exist")
+            .incoming_connections
+            .iter()
+            .copied()
+    }
+
+    pub fn get_output_ports(&self, node: NodeId) -> Option<&PortList<(Arc<str>, OutputPortId)>> {
+        let node = self.node_data.get(node)?;
+
+        Some(&*node.outputs)
+    }
+
+    /// The output ports connected to an input port, in connection order, see
+    /// [`set_incoming_order`](Self::set_incoming_order)
+    pub fn get_incoming_connections(
+        &self,
+        port: impl InputPortReference,
+    ) -> impl Iterator<Item = OutputPortId> + '_ {
+        let port = port.resolve(self).expect("Port does not exist");
+        let port = self
+            .input_ports
+            .get(port)
+            .expect("Input port does not exist");
+
+        port.incoming_connections.iter().map(|&conn_id| {
+            self.connections
+                .get(conn_id)
+                .expect(INVALID_STATE)
+                .start_port
+        })
+    }
+
+    pub fn get_outgoing_connections(
+        &self,
+        port: impl OutputPortReference,
+    ) -> impl Iterator<Item = InputPortId> + '_ {
+        let port = port.resolve(self).expect("Port does not exist");
         let port = self
             .output_ports
             .get(port)
@@ -386,6 +1250,54 @@ impl<N: Node> Graph<N> {
             .map(|&conn_id| self.connections.get(conn_id).expect(INVALID_STATE).end_port)
     }
 
+    /// All nodes for which `predicate` returns true
+    pub fn find_by<'a>(
+        &'a self,
+        predicate: impl Fn(&N) -> bool + 'a,
+    ) -> impl Iterator<Item = NodeId> + 'a {
+        self.nodes
+            .iter()
+            .filter_map(move |(id, node)| predicate(&node.read()).then_some(id))
+    }
+
+    /// All nodes for which `f` returns `Some`, along with the returned data.
+    /// Useful for enum nodes, e.g. to get the values of all constant nodes.
+    pub fn find_map_nodes<'a, T>(
+        &'a self,
+        f: impl Fn(&N) -> Option<T> + 'a,
+    ) -> impl Iterator<Item = (NodeId, T)> + 'a {
+        self.nodes
+            .iter()
+            .filter_map(move |(id, node)| Some((id, f(&node.read())?)))
+    }
+
+    pub fn get_connection(&self, connection: ConnectionId) -> Option<&Connection> {
+        self.connections.get(connection)
+    }
+
+    /// All connections to the input ports of `node`, followed by all
+    /// connections from its output ports
+    pub fn node_connections(
+        &self,
+        node: NodeId,
+    ) -> impl Iterator<Item = (ConnectionId, &Connection)> + '_ {
+        let node = self.node_data.get(node).expect("Node does not exist");
+
+        let incoming = node
+            .inputs
+            .iter()
+            .flat_map(|&(_, id)| self.input_ports[id].incoming_connections.iter());
+
+        let outgoing = node
+            .outputs
+            .iter()
+            .flat_map(|&(_, id)| self.output_ports[id].outgoing_connections.iter());
+
+        incoming
+            .chain(outgoing)
+            .map(|&id| (id, self.connections.get(id).expect(INVALID_STATE)))
+    }
+
     pub fn get_direct_dependencies(&self, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
         let node = self.node_data.get(node).expect("Node does not exist");
 
@@ -412,16 +1324,224 @@ impl<N: Node> Graph<N> {
             .unique()
     }
 
+    pub fn get_direct_dependents(&self, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let node = self.node_data.get(node).expect("Node does not exist");
+
+        node.outputs
+            .iter()
+            .flat_map(|(_, id)| {
+                self.output_ports
+                    .get(*id)
+                    .expect(INVALID_STATE)
+                    .outgoing_connections
+                    .iter()
+                    .map(|&conn_id| {
+                        self.input_ports
+                            .get(self.connections.get(conn_id).expect(INVALID_STATE).end_port)
+                            .expect(INVALID_STATE)
+                            .node
+                    })
+            })
+            .unique()
+    }
+
+    /// All nodes that are either a direct dependency or a direct dependent of
+    /// `node`, each returned once
+    pub fn neighbors(&self, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.get_direct_dependencies(node)
+            .chain(self.get_direct_dependents(node))
+            .unique()
+    }
+
+    /// The number of connections to the input ports of `node`
+    pub fn in_degree(&self, node: NodeId) -> usize {
+        let node = self.node_data.get(node).expect("Node does not exist");
+
+        node.inputs
+            .iter()
+            .map(|&(_, id)| self.input_ports[id].incoming_connections.len())
+            .sum()
+    }
+
+    /// The number of connections from the output ports of `node`
+    pub fn out_degree(&self, node: NodeId) -> usize {
+        let node = self.node_data.get(node).expect("Node does not exist");
+
+        node.outputs
+            .iter()
+            .map(|&(_, id)| self.output_ports[id].outgoing_connections.len())
+            .sum()
+    }
+
+    /// The number of connections to and from `node`
+    pub fn connection_count_of(&self, node: NodeId) -> usize {
+        self.in_degree(node) + self.out_degree(node)
+    }
+
     pub fn can_connect(
         &self,
         start_port: impl OutputPortReference,
         end_port: impl InputPortReference,
     ) -> bool {
+        self.check_connection(start_port, end_port).is_ok()
+    }
+
+    /// Like [`can_connect`](Self::can_connect), but reports why a connection
+    /// is not allowed
+    pub fn check_connection(
+        &self,
+        start_port: impl OutputPortReference,
+        end_port: impl InputPortReference,
+    ) -> Result<(), ConnectError<N::DataType>> {
         let start_port = start_port
-            .resolve(&self)
-            .expect("Start port does not exist");
+            .resolve(self)
+            .ok_or(ConnectError::OutputPortNotFound)?;
+
+        let end_port = end_port
+            .resolve(self)
+            .ok_or(ConnectError::InputPortNotFound)?;
+
+        self.check_connection_acyclic(start_port, end_port, None)?;
+
+        if !self.allow_cycles && self.would_create_cycle(start_port, end_port) {
+            return Err(ConnectError::WouldCreateCycle);
+        }
+
+        Ok(())
+    }
+
+    /// Everything [`check_connection`](Self::check_connection) checks except
+    /// for cycles, which is the expensive part. A `moved` connection doesn't
+    /// count towards the port limits.
+    fn check_connection_acyclic(
+        &self,
+        start_port: OutputPortId,
+        end_port: InputPortId,
+        moved: Option<ConnectionId>,
+    ) -> Result<(), ConnectError<N::DataType>> {
+        let start = self
+            .output_ports
+            .get(start_port)
+            .ok_or(ConnectError::OutputPortNotFound)?;
+
+        let end = self
+            .input_ports
+            .get(end_port)
+            .ok_or(ConnectError::InputPortNotFound)?;
+
+        if start.node == end.node {
+            return Err(ConnectError::SameNode);
+        }
+
+        let is_moved = |id: &ConnectionId| moved == Some(*id);
+        let outgoing = start.outgoing_connections.len()
+            - usize::from(start.outgoing_connections.iter().any(is_moved));
+        let incoming = end.incoming_connections.len()
+            - usize::from(end.incoming_connections.iter().any(is_moved));
+
+        if start.max_outgoing.is_some_and(|max| outgoing >= max) {
+            return Err(ConnectError::OutputFull);
+        }
+
+        if end.max_incoming.is_some_and(|max| incoming >= max) {
+            return Err(ConnectError::InputFull);
+        }
+
+        if !is_compatible(start.ty, end.ty) {
+            return Err(ConnectError::TypeMismatch {
+                from: start.ty,
+                to: end.ty,
+            });
+        }
+
+        self.validators.validate(self, start_port, end_port)
+    }
+
+    /// Returns every input port that `start_port` could be connected to
+    pub fn find_compatible_inputs(
+        &self,
+        start_port: impl OutputPortReference,
+    ) -> impl Iterator<Item = InputPortId> + '_ {
+        let start_port = start_port.resolve(self).expect("Start port does not exist");
+        let start_node = self
+            .output_ports
+            .get(start_port)
+            .expect("Output port does not exist")
+            .node;
+
+        // Connecting to the start node or any of its ancestors creates a cycle
+
+        let blocked = self.collect_reachable(start_node, |node| {
+            self.get_direct_dependencies(node).collect()
+        });
+
+        self.input_ports.iter().filter_map(move |(end_port, end)| {
+            let allowed = (self.allow_cycles || !blocked.contains_key(end.node))
+                && self
+                    .check_connection_acyclic(start_port, end_port, None)
+                    .is_ok();
+
+            allowed.then_some(end_port)
+        })
+    }
+
+    /// Returns every output port that could be connected to `end_port`
+    pub fn find_compatible_outputs(
+        &self,
+        end_port: impl InputPortReference,
+    ) -> impl Iterator<Item = OutputPortId> + '_ {
+        let end_port = end_port.resolve(self).expect("End port does not exist");
+        let end_node = self
+            .input_ports
+            .get(end_port)
+            .expect("Input port does not exist")
+            .node;
+
+        // Connecting from the end node or any of its descendants creates a cycle
+
+        let blocked =
+            self.collect_reachable(end_node, |node| self.get_direct_dependents(node).collect());
+
+        self.output_ports
+            .iter()
+            .filter_map(move |(start_port, start)| {
+                let allowed = (self.allow_cycles || !blocked.contains_key(start.node))
+                    && self
+                        .check_connection_acyclic(start_port, end_port, None)
+                        .is_ok();
+
+                allowed.then_some(start_port)
+            })
+    }
+
+    fn collect_reachable(
+        &self,
+        from: NodeId,
+        next: impl Fn(NodeId) -> Vec<NodeId>,
+    ) -> SecondaryMap<NodeId, ()> {
+        let mut visited = SecondaryMap::new();
+        let mut stack = vec![from];
+
+        while let Some(top) = stack.pop() {
+            if visited.insert(top, ()).is_none() {
+                stack.extend(next(top));
+            }
+        }
 
-        let end_port = end_port.resolve(&self).expect("End port does not exist");
+        visited
+    }
+
+    /// Returns whether connecting these ports would make the start port's node
+    /// depend on itself. Answered from the
+    /// [cached reachability index](Self::cached_reachability) if there is one,
+    /// otherwise by searching from the end port's node.
+    pub fn would_create_cycle(
+        &self,
+        start_port: impl OutputPortReference,
+        end_port: impl InputPortReference,
+    ) -> bool {
+        let start_port = start_port.resolve(self).expect("Start port does not exist");
+        let end_port = end_port.resolve(self).expect("End port does not exist");
 
         let start = self
             .output_ports
@@ -433,7 +1553,65 @@ impl<N: Node> Graph<N> {
             .get(end_port)
             .expect("End port of connection does not exist");
 
-        start.node != end.node && start.ty.can_convert_to(end.ty)
+        match self.analysis.reachability() {
+            Some(index) => index.is_reachable(end.node, start.node),
+            None => GraphAnalyzer::new(self).is_reachable(end.node, start.node),
+        }
+    }
+
+    /// Like [`would_create_cycle`](Self::would_create_cycle), but as if
+    /// `connection` didn't exist
+    fn would_create_cycle_without(
+        &self,
+        start_port: OutputPortId,
+        end_port: InputPortId,
+        connection: ConnectionId,
+    ) -> bool {
+        let start_node = self.output_ports[start_port].node;
+        let end_node = self.input_ports[end_port].node;
+
+        // Ignoring a connection can only make nodes unreachable
+        if self
+            .analysis
+            .reachability()
+            .is_some_and(|index| !index.is_reachable(end_node, start_node))
+        {
+            return false;
+        }
+
+        self.collect_reachable(end_node, |node| {
+            self.node_data[node]
+                .outputs
+                .iter()
+                .flat_map(|&(_, port)| self.output_ports[port].outgoing_connections.iter())
+                .filter(|&&id| id != connection)
+                .map(|&id| self.input_ports[self.connections[id].end_port].node)
+                .collect()
+        })
+        .contains_key(start_node)
+    }
+
+    /// Whether an output of `start` is connected to an input of `end`
+    fn nodes_connected(&self, start: NodeId, end: NodeId) -> bool {
+        self.node_data[start].outputs.iter().any(|&(_, port)| {
+            self.output_ports[port]
+                .outgoing_connections
+                .iter()
+                .any(|&connection| {
+                    self.input_ports[self.connections[connection].end_port].node == end
+                })
+        })
+    }
+
+    /// Allow connections that create cycles. Disabled by default, because the
+    /// [`GraphAnalyzer`] and [`GraphWalker`](walker::GraphWalker) expect the
+    /// graph to be acyclic.
+    pub fn set_allow_cycles(&mut self, allow_cycles: bool) {
+        self.allow_cycles = allow_cycles;
+    }
+
+    pub fn allows_cycles(&self) -> bool {
+        self.allow_cycles
     }
 
     pub fn connect(
@@ -441,53 +1619,265 @@ impl<N: Node> Graph<N> {
         start_port: impl OutputPortReference,
         end_port: impl InputPortReference,
     ) -> ConnectionId {
-        let start_port = start_port
-            .resolve(&self)
-            .expect("Start port does not exist");
+        let start_port = start_port.resolve(self).expect("Start port does not exist");
+
+        let end_port = end_port.resolve(self).expect("End port does not exist`");
+
+        if let Err(error) = self.check_connection(start_port, end_port) {
+            panic!("Attempted to create an invalid connection: {error}");
+        }
 
-        let end_port = end_port.resolve(&self).expect("End port does not exist`");
+        let id = self.insert_connection(start_port, end_port);
+
+        let start_node = self.output_ports[start_port].node;
+        let end_node = self.input_ports[end_port].node;
+
+        self.analysis.connection_added(start_node, end_node);
+        self.notify_connections_changed(&[start_node, end_node]);
+        self.after_mutation();
+
+        id
+    }
 
+    /// Add a connection without checking it, invalidating the analysis cache
+    /// or calling [`Node::connections_changed`], which is left to the caller
+    pub(crate) fn insert_connection(
+        &mut self,
+        start_port: OutputPortId,
+        end_port: InputPortId,
+    ) -> ConnectionId {
         let connection = Connection {
             start_port,
             end_port,
+            metadata: Metadata::new(),
         };
 
         let id = self.connections.insert(connection);
+        self.log_mutation(GraphOp::Connect {
+            start_port: start_port.into(),
+            end_port: end_port.into(),
+        });
 
-        let start = self
-            .output_ports
-            .get_mut(start_port)
-            .expect("Start port of connection does not exist");
+        if let Some(stable_ids) = &mut self.stable_ids {
+            stable_ids.track_connection(id);
+        }
+
+        let start = self.output_ports.get_mut(start_port).expect(INVALID_STATE);
 
         start.outgoing_connections.push(id);
 
-        let start_ty = start.ty;
         let start_node_id = start.node;
-
-        let start_node = self.nodes.get(start.node).expect(INVALID_STATE);
+        let start_node = self.nodes.get(start_node_id).expect(INVALID_STATE);
 
         start_node.write().output_connection_added(start_port, id);
 
-        let end = self
-            .input_ports
-            .get_mut(end_port)
-            .expect("End port of connection does not exist");
+        let end = self.input_ports.get_mut(end_port).expect(INVALID_STATE);
+
+        end.incoming_connections.push(id);
+
+        let end_node_id = end.node;
+        let end_node = self.nodes.get(end_node_id).expect(INVALID_STATE);
+
+        end_node.write().input_connection_added(end_port, id);
 
-        if start_node_id == end.node {
-            panic!("Attempted to create a connection to the same node");
+        self.update_variadic_inputs(end_node_id);
+
+        id
+    }
+
+    #[must_use]
+    pub fn disconnect(&mut self, connection: ConnectionId) -> Option<()> {
+        let Connection {
+            start_port,
+            end_port,
+            ..
+        } = self.connections.remove(connection)?;
+
+        self.log_mutation(GraphOp::Disconnect(connection));
+
+        if let Some(stable_ids) = &mut self.stable_ids {
+            stable_ids.untrack_connection(connection);
         }
 
-        if !start_ty.can_convert_to(end.ty) {
-            panic!("Attempted to create a connection between two ports of non-convertable types");
+        let start = self.output_ports.get_mut(start_port).expect(INVALID_STATE);
+
+        start.outgoing_connections.retain(|id| *id != connection);
+
+        let start_node_id = start.node;
+        let start_node = self.nodes.get(start_node_id).expect(INVALID_STATE);
+
+        start_node
+            .write()
+            .output_connection_removed(start_port, connection);
+
+        let end = self.input_ports.get_mut(end_port).expect(INVALID_STATE);
+
+        end.incoming_connections.retain(|id| *id != connection);
+
+        let end_node_id = end.node;
+        let end_node = self.nodes.get(end_node_id).expect(INVALID_STATE);
+
+        end_node
+            .write()
+            .input_connection_removed(end_port, connection);
+
+        self.analysis
+            .connection_removed(self.nodes_connected(start_node_id, end_node_id));
+        self.update_variadic_inputs(end_node_id);
+        self.notify_connections_changed(&[start_node_id, end_node_id]);
+        self.after_mutation();
+
+        Some(())
+    }
+
+    /// Connect `connection` to `end_port` instead of its current end, keeping
+    /// its id and metadata. The connection is checked like a new one, except
+    /// that it doesn't count towards port limits or cycles itself, and is left
+    /// unchanged if that fails.
+    /// [Connection validators](Self::add_connection_validator) see it in its
+    /// old place.
+    pub fn move_connection_end(
+        &mut self,
+        connection: ConnectionId,
+        end_port: impl InputPortReference,
+    ) -> Result<(), ConnectError<N::DataType>> {
+        let end_port = end_port
+            .resolve(self)
+            .ok_or(ConnectError::InputPortNotFound)?;
+
+        let start_port = self
+            .connections
+            .get(connection)
+            .expect("Connection does not exist")
+            .start_port;
+
+        self.move_connection(connection, start_port, end_port)
+    }
+
+    /// Connect `connection` from `start_port` instead of its current start,
+    /// see [`move_connection_end`](Self::move_connection_end)
+    pub fn move_connection_start(
+        &mut self,
+        connection: ConnectionId,
+        start_port: impl OutputPortReference,
+    ) -> Result<(), ConnectError<N::DataType>> {
+        let start_port = start_port
+            .resolve(self)
+            .ok_or(ConnectError::OutputPortNotFound)?;
+
+        let end_port = self
+            .connections
+            .get(connection)
+            .expect("Connection does not exist")
+            .end_port;
+
+        self.move_connection(connection, start_port, end_port)
+    }
+
+    fn move_connection(
+        &mut self,
+        connection: ConnectionId,
+        start_port: OutputPortId,
+        end_port: InputPortId,
+    ) -> Result<(), ConnectError<N::DataType>> {
+        let Connection {
+            start_port: old_start,
+            end_port: old_end,
+            ..
+        } = *self.connections.get(connection).expect(INVALID_STATE);
+
+        if start_port == old_start && end_port == old_end {
+            return Ok(());
         }
 
-        end.incoming_connections.push(id);
+        self.check_connection_acyclic(start_port, end_port, Some(connection))?;
 
-        let end_node = self.nodes.get(end.node).expect(INVALID_STATE);
+        if !self.allow_cycles && self.would_create_cycle_without(start_port, end_port, connection) {
+            return Err(ConnectError::WouldCreateCycle);
+        }
 
-        end_node.write().input_connection_added(end_port, id);
+        // The end that didn't move keeps its place among the other
+        // connections
+        if start_port != old_start {
+            self.output_ports[old_start]
+                .outgoing_connections
+                .retain(|id| *id != connection);
+            self.output_ports[start_port]
+                .outgoing_connections
+                .push(connection);
+        }
 
-        id
+        if end_port != old_end {
+            self.input_ports[old_end]
+                .incoming_connections
+                .retain(|id| *id != connection);
+            self.input_ports[end_port]
+                .incoming_connections
+                .push(connection);
+        }
+
+        let data = self.connections.get_mut(connection).expect(INVALID_STATE);
+        data.start_port = start_port;
+        data.end_port = end_port;
+
+        let old_start_node = self.output_ports[old_start].node;
+        let old_end_node = self.input_ports[old_end].node;
+
+        self.analysis
+            .connection_removed(self.nodes_connected(old_start_node, old_end_node));
+        self.analysis.connection_added(
+            self.output_ports[start_port].node,
+            self.input_ports[end_port].node,
+        );
+
+        self.log_mutation(GraphOp::Disconnect(connection));
+        self.log_mutation(GraphOp::Connect {
+            start_port: start_port.into(),
+            end_port: end_port.into(),
+        });
+
+        let mut changed = Vec::with_capacity(4);
+
+        if start_port != old_start {
+            let old_node = self.output_ports[old_start].node;
+            let new_node = self.output_ports[start_port].node;
+
+            self.nodes[old_node]
+                .write()
+                .output_connection_removed(old_start, connection);
+            self.nodes[new_node]
+                .write()
+                .output_connection_added(start_port, connection);
+
+            changed.extend([old_node, new_node]);
+        }
+
+        if end_port != old_end {
+            let old_node = self.input_ports[old_end].node;
+            let new_node = self.input_ports[end_port].node;
+
+            self.nodes[old_node]
+                .write()
+                .input_connection_removed(old_end, connection);
+            self.nodes[new_node]
+                .write()
+                .input_connection_added(end_port, connection);
+
+            self.update_variadic_inputs(old_node);
+            self.update_variadic_inputs(new_node);
+
+            changed.extend([old_node, new_node]);
+        }
+
+        // A moved start changes the inputs of the end node and the other way
+        // around
+        changed.push(self.output_ports[start_port].node);
+        changed.push(self.input_ports[end_port].node);
+
+        self.notify_connections_changed(&changed);
+        self.after_mutation();
+
+        Ok(())
     }
 }
 
@@ -507,10 +1897,36 @@ impl<N: Node + PartialEq> Graph<N> {
 
 #[derive(Debug, Clone, Default)]
 pub struct NodeData {
-    inputs: Vec<(String, InputPortId)>,
-    outputs: Vec<(String, OutputPortId)>,
+    inputs: NamedPorts<InputPortId>,
+    outputs: NamedPorts<OutputPortId>,
+    /// Whether the node was created with [`Graph::create_reroute`]
+    reroute: bool,
+    metadata: Metadata,
+    /// Changes every time the node's value is [replaced](Graph::set_node),
+    /// so memoized outputs of the old value aren't reused
+    revision: u64,
 }
 
+/// Source of node revisions, shared by every graph so a revision is never
+/// handed out twice, not even to a clone of a graph or after a restore
+static NODE_REVISIONS: AtomicU64 = AtomicU64::new(1);
+
+/// How many ports or connections fit in a [`PortList`] before it allocates,
+/// with the `smallvec` feature
+pub const INLINE_PORT_CAPACITY: usize = 4;
+
+/// The list type used for the ports of a node and the connections of a port.
+/// With the `smallvec` feature, lists of up to [`INLINE_PORT_CAPACITY`]
+/// elements are stored inline instead of on the heap.
+#[cfg(not(feature = "smallvec"))]
+pub type PortList<T> = Vec<T>;
+
+/// The list type used for the ports of a node and the connections of a port.
+/// With the `smallvec` feature, lists of up to [`INLINE_PORT_CAPACITY`]
+/// elements are stored inline instead of on the heap.
+#[cfg(feature = "smallvec")]
+pub type PortList<T> = smallvec::SmallVec<[T; INLINE_PORT_CAPACITY]>;
+
 #[derive(Debug, Clone)]
 pub struct InitialPorts<N: Node> {
     pub inputs: Vec<(&'static str, N::DataType, N::DataValue)>,
@@ -534,10 +1950,45 @@ pub trait Node: Sized + 'static {
         Default::default()
     }
 
+    /// Whether evaluating this node has no effects besides setting its
+    /// outputs, so its outputs only depend on its inputs. Nodes that print,
+    /// write files and so on should return false, so they are never skipped
+    /// by [memoization](crate::memo::Memoizer) or other optimizations.
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    /// A rough estimate of how long evaluating this node takes, relative to
+    /// other nodes. Used to balance work between threads, see
+    /// [`GraphAnalyzer::schedule`](crate::analyzer::GraphAnalyzer::schedule).
+    fn cost_hint(&self) -> f64 {
+        1.0
+    }
+
+    /// The kind of this node, like the name of its enum variant. Identifies
+    /// unnamed nodes in tracing spans.
+    fn variant_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
     fn input_port_created(&mut self, name: &str, ty: Self::DataType, id: InputPortId) {
         let _ = (name, ty, id);
     }
 
+    fn input_port_renamed(&mut self, id: InputPortId, old_name: &str, new_name: &str) {
+        let _ = (id, old_name, new_name);
+    }
+
+    fn input_port_moved(&mut self, id: InputPortId, from_index: usize, to_index: usize) {
+        let _ = (id, from_index, to_index);
+    }
+
+    /// Called when [`Graph::set_default_value`] changes the default value of
+    /// one of this node's input ports
+    fn input_default_changed(&mut self, port: InputPortId, value: &Self::DataValue) {
+        let _ = (port, value);
+    }
+
     fn input_connection_added(&mut self, port: InputPortId, connection: ConnectionId) {
         let _ = (port, connection);
     }
@@ -550,6 +2001,14 @@ pub trait Node: Sized + 'static {
         let _ = (name, ty, id);
     }
 
+    fn output_port_renamed(&mut self, id: OutputPortId, old_name: &str, new_name: &str) {
+        let _ = (id, old_name, new_name);
+    }
+
+    fn output_port_moved(&mut self, id: OutputPortId, from_index: usize, to_index: usize) {
+        let _ = (id, from_index, to_index);
+    }
+
     fn output_connection_added(&mut self, port: OutputPortId, connection: ConnectionId) {
         let _ = (port, connection);
     }
@@ -557,41 +2016,144 @@ pub trait Node: Sized + 'static {
     fn output_connection_removed(&mut self, port: OutputPortId, connection: ConnectionId) {
         let _ = (port, connection);
     }
+
+    /// Called after a connection to or from this node was added, removed or
+    /// moved, once the graph is up to date. Unlike the callbacks for single
+    /// connections, this can look at what the node is connected to.
+    fn connections_changed(&mut self, id: NodeId, graph: &GraphView<'_, Self>) {
+        let _ = (id, graph);
+    }
 }
 
 pub trait DataType: Debug + Clone + Copy + Eq {
     fn can_convert_to(&self, rhs: Self) -> bool {
         *self == rhs
     }
+
+    /// Whether this is a wildcard type that accepts any connection until one
+    /// pins it to a concrete type, see [`GraphAnalyzer::infer_types`]. All
+    /// generic ports of a node share the same inferred type.
+    fn is_generic(&self) -> bool {
+        false
+    }
+}
+
+/// Whether a connection from a port of type `from` to one of type `to` is
+/// allowed, taking generic types into account
+pub(crate) fn is_compatible<T: DataType>(from: T, to: T) -> bool {
+    from.is_generic() || to.is_generic() || from.can_convert_to(to)
 }
 
 impl DataType for () {}
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Connection {
     start_port: OutputPortId,
     end_port: InputPortId,
+    /// User data for this connection, like blend weights or UI waypoints
+    pub metadata: Metadata,
 }
 
-#[derive(Debug, Clone, Default)]
+impl Connection {
+    pub fn start_port(&self) -> OutputPortId {
+        self.start_port
+    }
+
+    pub fn end_port(&self) -> InputPortId {
+        self.end_port
+    }
+}
+
+/// The reason a connection is not allowed, see [`Graph::check_connection`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectError<T: DataType> {
+    OutputPortNotFound,
+    InputPortNotFound,
+    SameNode,
+    /// The output port already has its maximum number of connections
+    OutputFull,
+    /// The input port already has its maximum number of connections
+    InputFull,
+    TypeMismatch {
+        from: T,
+        to: T,
+    },
+    WouldCreateCycle,
+    /// Rejected by a [custom validator](Graph::add_connection_validator)
+    Rejected(String),
+}
+
+impl<T: DataType> Display for ConnectError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutputPortNotFound => write!(f, "Start port does not exist"),
+            Self::InputPortNotFound => write!(f, "End port does not exist"),
+            Self::SameNode => write!(f, "Cannot connect a node to itself"),
+            Self::OutputFull => write!(f, "Start port cannot have any more connections"),
+            Self::InputFull => write!(f, "End port cannot have any more connections"),
+            Self::TypeMismatch { from, to } => {
+                write!(f, "Cannot convert from {from:?} to {to:?}")
+            }
+            Self::WouldCreateCycle => write!(f, "Connection would create a cycle"),
+            Self::Rejected(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl<T: DataType> std::error::Error for ConnectError<T> {}
+
+#[derive(Debug, Default)]
 pub struct Port<N: Node> {
     pub node: NodeId,
-    pub name: String,
+    /// Shared with every other port of the graph with the same name
+    pub name: Arc<str>,
     pub ty: N::DataType,
     pub default: Option<N::DataValue>,
-    pub incoming_connections: Vec<ConnectionId>,
-    pub outgoing_connections: Vec<ConnectionId>,
+    pub incoming_connections: PortList<ConnectionId>,
+    pub outgoing_connections: PortList<ConnectionId>,
+    /// The maximum number of connections to this (input) port, or `None` for
+    /// no limit
+    pub max_incoming: Option<usize>,
+    /// The maximum number of connections from this (output) port, or `None`
+    /// for no limit
+    pub max_outgoing: Option<usize>,
+    /// User data for this port, like UI hints
+    pub metadata: Metadata,
+}
+
+impl<N: Node> Clone for Port<N> {
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node,
+            name: self.name.clone(),
+            ty: self.ty,
+            default: self.default.clone(),
+            incoming_connections: self.incoming_connections.clone(),
+            outgoing_connections: self.outgoing_connections.clone(),
+            max_incoming: self.max_incoming,
+            max_outgoing: self.max_outgoing,
+            metadata: self.metadata.clone(),
+        }
+    }
 }
 
 impl<N: Node> Port<N> {
-    pub fn new(node: NodeId, name: String, ty: N::DataType, default: Option<N::DataValue>) -> Self {
+    pub fn new(
+        node: NodeId,
+        name: Arc<str>,
+        ty: N::DataType,
+        default: Option<N::DataValue>,
+    ) -> Self {
         Self {
             node,
             name,
             ty,
             default,
-            incoming_connections: Vec::new(),
-            outgoing_connections: Vec::new(),
+            incoming_connections: PortList::new(),
+            outgoing_connections: PortList::new(),
+            max_incoming: None,
+            max_outgoing: None,
+            metadata: Metadata::new(),
         }
     }
 }
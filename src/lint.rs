@@ -0,0 +1,439 @@
+use std::{cmp::Reverse, fmt::Display, hash::Hash, marker::PhantomData};
+
+use slotmap::SecondaryMap;
+
+use crate::{DataType, Graph, Node, NodeId, analyzer::GraphAnalyzer};
+
+/// How serious a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    /// Probably fine, but worth knowing about
+    Info,
+    /// Likely a mistake
+    Warning,
+    /// Will fail or panic when the graph is walked
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found by a [`GraphLint`], attached to the node an editor should
+/// show it on
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The [name](GraphLint::name) of the lint that reported this
+    pub lint: String,
+    pub message: String,
+    pub node: NodeId,
+    /// Other nodes involved, like the other end of a connection
+    pub related: Vec<NodeId>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} ({})", self.severity, self.message, self.lint)
+    }
+}
+
+/// A check for likely mistakes in a graph, see [`Linter`]
+pub trait GraphLint<N: Node> {
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    fn check(&self, graph: &Graph<N>) -> Vec<Diagnostic>;
+}
+
+impl<N: Node, F: Fn(&Graph<N>) -> Vec<Diagnostic>> GraphLint<N> for F {
+    fn check(&self, graph: &Graph<N>) -> Vec<Diagnostic> {
+        self(graph)
+    }
+}
+
+/// Runs a list of lints over a graph
+pub struct Linter<N: Node> {
+    lints: Vec<Box<dyn GraphLint<N>>>,
+}
+
+impl<N: Node> Linter<N> {
+    /// A linter without any lints
+    pub fn new() -> Self {
+        Self { lints: Vec::new() }
+    }
+
+    /// A linter with the lints used by [`Graph::lint`]
+    pub fn builtin() -> Self {
+        let mut linter = Self::new();
+
+        linter.add_lint(UnusedOutputs);
+        linter.add_lint(DisconnectedInputs);
+        linter.add_lint(UnreachableNodes::default());
+        linter.add_lint(SuspiciousConversions::new());
+        linter
+    }
+
+    pub fn add_lint(&mut self, lint: impl GraphLint<N> + 'static) {
+        self.lints.push(Box::new(lint));
+    }
+
+    /// Run every lint, returning their diagnostics with the most severe
+    /// first
+    pub fn run(&self, graph: &Graph<N>) -> Vec<Diagnostic> {
+        let mut diagnostics = self
+            .lints
+            .iter()
+            .flat_map(|lint| lint.check(graph))
+            .collect::<Vec<_>>();
+
+        diagnostics.sort_by_key(|diagnostic| Reverse(diagnostic.severity));
+        diagnostics
+    }
+}
+
+impl<N: Node> Default for Linter<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Node> std::fmt::Debug for Linter<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.lints.iter().map(|lint| lint.name()))
+            .finish()
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Check the graph for likely mistakes with the [built-in](Linter::builtin)
+    /// lints, most severe first. [`DuplicateConstantSubtrees`] needs hashable
+    /// nodes, so it has to be added to a [`Linter`] separately.
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use node_graph::{Graph, InitialPorts, Node, NodeId};
+    /// # #[derive(Debug)]
+    /// # struct Sum;
+    /// # impl Node for Sum {
+    /// #     type DataType = ();
+    /// #     type DataValue = f32;
+    /// #     fn initial_ports(&self) -> InitialPorts<Self> {
+    /// #         InitialPorts {
+    /// #             inputs: vec![("a", (), 0.0), ("b", (), 0.0)],
+    /// #             outputs: vec![("sum", ())],
+    /// #         }
+    /// #     }
+    /// # }
+    /// # #[derive(Default)]
+    /// # struct Editor {
+    /// #     notes: HashMap<NodeId, Vec<String>>,
+    /// # }
+    /// # impl Editor {
+    /// #     fn annotate(&mut self, node: NodeId, note: String) {
+    /// #         self.notes.entry(node).or_default().push(note);
+    /// #     }
+    /// # }
+    /// # let mut graph = Graph::<Sum>::new();
+    /// # graph.create_node(Sum);
+    /// # let mut editor = Editor::default();
+    /// for diagnostic in graph.lint() {
+    ///     editor.annotate(diagnostic.node, diagnostic.to_string());
+    /// }
+    /// ```
+    pub fn lint(&self) -> Vec<Diagnostic> {
+        Linter::builtin().run(self)
+    }
+}
+
+/// Outputs without connections on nodes that do have other outputs
+/// connected, which usually means a wire was forgotten. Nodes without any
+/// connected outputs are exit nodes, whose outputs are read after a walk.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnusedOutputs;
+
+impl<N: Node> GraphLint<N> for UnusedOutputs {
+    fn name(&self) -> &str {
+        "UnusedOutputs"
+    }
+
+    fn check(&self, graph: &Graph<N>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for (node, data) in graph.node_data.iter() {
+            let is_unused = |port| graph.output_ports[port].outgoing_connections.is_empty();
+
+            if data.reroute || data.outputs.iter().all(|&(_, port)| is_unused(port)) {
+                continue;
+            }
+
+            for (name, port) in data.outputs.iter() {
+                if is_unused(*port) {
+                    diagnostics.push(Diagnostic {
+                        severity: Severity::Info,
+                        lint: <Self as GraphLint<N>>::name(self).to_string(),
+                        message: format!("Output {name:?} is never used"),
+                        node,
+                        related: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// Inputs that have no default value and nothing connected to them, and
+/// aren't [parameters](Graph::expose_parameter). Reading them panics during a
+/// walk.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DisconnectedInputs;
+
+impl<N: Node> GraphLint<N> for DisconnectedInputs {
+    fn name(&self) -> &str {
+        "DisconnectedInputs"
+    }
+
+    fn check(&self, graph: &Graph<N>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for (node, data) in graph.node_data.iter() {
+            if data.reroute {
+                continue;
+            }
+
+            for (name, port) in data.inputs.iter() {
+                if graph.input_ports[*port].default.is_none()
+                    && graph.get_sources(*port).next().is_none()
+                    && graph.get_parameter_name(*port).is_none()
+                {
+                    diagnostics.push(Diagnostic {
+                        severity: Severity::Error,
+                        lint: <Self as GraphLint<N>>::name(self).to_string(),
+                        message: format!("Input {name:?} has no default and isn't connected"),
+                        node,
+                        related: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// Nodes that a walk never evaluates: loose nodes, and nodes in cycles that
+/// don't lead to an exit node. With `exit_nodes` set, every node that isn't
+/// upstream of them is reported.
+#[derive(Debug, Clone, Default)]
+pub struct UnreachableNodes {
+    pub exit_nodes: Option<Vec<NodeId>>,
+}
+
+impl<N: Node> GraphLint<N> for UnreachableNodes {
+    fn name(&self) -> &str {
+        "UnreachableNodes"
+    }
+
+    fn check(&self, graph: &Graph<N>) -> Vec<Diagnostic> {
+        let mut evaluated = SecondaryMap::with_capacity(graph.node_data.len());
+
+        for node in graph.cached_execution_path(self.exit_nodes.as_deref()) {
+            evaluated.insert(node, ());
+        }
+
+        graph
+            .node_data
+            .iter()
+            .filter(|&(node, data)| !data.reroute && !evaluated.contains_key(node))
+            .map(|(node, _)| Diagnostic {
+                severity: Severity::Warning,
+                lint: <Self as GraphLint<N>>::name(self).to_string(),
+                message: "Node is never evaluated".to_string(),
+                node,
+                related: Vec::new(),
+            })
+            .collect()
+    }
+}
+
+/// Connections between ports of different types that are only allowed
+/// because of [implicit conversions](DataType::can_convert_to), except for
+/// conversions marked as [intended](Self::allow). Generic ports are ignored.
+#[derive(Debug, Clone)]
+pub struct SuspiciousConversions<T> {
+    allowed: Vec<(T, T)>,
+}
+
+impl<T: DataType> SuspiciousConversions<T> {
+    pub fn new() -> Self {
+        Self {
+            allowed: Vec::new(),
+        }
+    }
+
+    /// Don't report conversions from `from` to `to`
+    pub fn allow(mut self, from: T, to: T) -> Self {
+        self.allowed.push((from, to));
+        self
+    }
+}
+
+impl<T: DataType> Default for SuspiciousConversions<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Node> GraphLint<N> for SuspiciousConversions<N::DataType> {
+    fn name(&self) -> &str {
+        "SuspiciousConversions"
+    }
+
+    fn check(&self, graph: &Graph<N>) -> Vec<Diagnostic> {
+        graph
+            .connections
+            .values()
+            .filter_map(|connection| {
+                let start = &graph.output_ports[connection.start_port];
+                let end = &graph.input_ports[connection.end_port];
+
+                if start.ty == end.ty
+                    || start.ty.is_generic()
+                    || end.ty.is_generic()
+                    || self.allowed.contains(&(start.ty, end.ty))
+                {
+                    return None;
+                }
+
+                Some(Diagnostic {
+                    severity: Severity::Warning,
+                    lint: <Self as GraphLint<N>>::name(self).to_string(),
+                    message: format!(
+                        "Input {:?} converts {:?} from {:?} of {} to {:?}",
+                        end.name,
+                        start.ty,
+                        start.name,
+                        graph.node_title(start.node),
+                        end.ty,
+                    ),
+                    node: end.node,
+                    related: vec![start.node],
+                })
+            })
+            .collect()
+    }
+}
+
+/// [Pure](Node::is_pure) nodes that compute the same value as another node,
+/// because they and everything upstream of them are equal, see
+/// [`Graph::find_duplicates`]. Only the most downstream node of each
+/// duplicated subtree is reported, on every copy but the first.
+pub struct DuplicateConstantSubtrees<N> {
+    _node: PhantomData<fn() -> N>,
+}
+
+impl<N> DuplicateConstantSubtrees<N> {
+    pub fn new() -> Self {
+        Self { _node: PhantomData }
+    }
+}
+
+impl<N> Default for DuplicateConstantSubtrees<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N> std::fmt::Debug for DuplicateConstantSubtrees<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DuplicateConstantSubtrees")
+    }
+}
+
+impl<N: Node + Hash> GraphLint<N> for DuplicateConstantSubtrees<N>
+where
+    N::DataType: Hash,
+    N::DataValue: Hash,
+{
+    fn name(&self) -> &str {
+        "DuplicateConstantSubtrees"
+    }
+
+    fn check(&self, graph: &Graph<N>) -> Vec<Diagnostic> {
+        let analyzer = GraphAnalyzer::new(graph);
+
+        // A subtree is constant if nothing in it has effects
+        let mut pure = SecondaryMap::with_capacity(graph.node_data.len());
+
+        for &node in analyzer.layers().iter().flatten() {
+            let is_pure = graph.nodes[node].read().is_pure()
+                && graph
+                    .get_direct_dependencies(node)
+                    .all(|dependency| pure.get(dependency).copied().unwrap_or(false));
+
+            pure.insert(node, is_pure);
+        }
+
+        let mut groups = graph
+            .find_duplicates()
+            .into_iter()
+            .map(|mut group| {
+                group.retain(|&node| pure.get(node).copied().unwrap_or(false));
+                group.sort_unstable();
+                group
+            })
+            .filter(|group| group.len() > 1)
+            .collect::<Vec<_>>();
+
+        groups.sort_unstable();
+
+        let mut duplicated = SecondaryMap::with_capacity(graph.node_data.len());
+
+        for &node in groups.iter().flatten() {
+            duplicated.insert(node, ());
+        }
+
+        // Copies that only feed other duplicates go away along with them, so
+        // they are covered by the diagnostics further downstream
+        let is_covered = |node: NodeId| {
+            let mut dependents = graph.get_direct_dependents(node).peekable();
+
+            dependents.peek().is_some()
+                && dependents.all(|dependent| duplicated.contains_key(dependent))
+        };
+
+        let mut diagnostics = Vec::new();
+
+        for group in groups {
+            if group[1..].iter().all(|&node| is_covered(node)) {
+                continue;
+            }
+
+            let first = group[0];
+
+            for &node in &group[1..] {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    lint: <Self as GraphLint<N>>::name(self).to_string(),
+                    message: format!("Computes the same value as {}", graph.node_title(first)),
+                    node,
+                    related: vec![first],
+                });
+            }
+        }
+
+        diagnostics
+    }
+}
@@ -1,22 +1,301 @@
+use crate::{
+    ConnectionId, Graph, Node,
+    reference::{InputPortReference, OutputPortReference},
+};
+
+/// What [`Graph::connect_to`] can connect an output port to: an input port,
+/// or an array of input ports to fan out to
+pub trait ConnectTargets<N: Node> {
+    /// A [`ConnectionId`] for a single port, or an array of them
+    type Connections;
+
+    fn connect_from(
+        self,
+        graph: &mut Graph<N>,
+        start: impl OutputPortReference,
+    ) -> Self::Connections;
+}
+
+impl<N: Node, T: InputPortReference> ConnectTargets<N> for T {
+    type Connections = ConnectionId;
+
+    fn connect_from(
+        self,
+        graph: &mut Graph<N>,
+        start: impl OutputPortReference,
+    ) -> Self::Connections {
+        graph.connect(start, self)
+    }
+}
+
+impl<N: Node, T: InputPortReference, const COUNT: usize> ConnectTargets<N> for [T; COUNT] {
+    type Connections = [ConnectionId; COUNT];
+
+    fn connect_from(
+        self,
+        graph: &mut Graph<N>,
+        start: impl OutputPortReference,
+    ) -> Self::Connections {
+        let start = start.resolve(graph).expect("Start port does not exist");
+
+        self.map(|end| graph.connect(start, end))
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Connect `start_port` to `end_ports`, which is a single input port like
+    /// for [`connect`](Self::connect), or an array of input ports of the same
+    /// reference type to fan out to. Returns a connection for each of them.
+    pub fn connect_to<T: ConnectTargets<N>>(
+        &mut self,
+        start_port: impl OutputPortReference,
+        end_ports: T,
+    ) -> T::Connections {
+        end_ports.connect_from(self, start_port)
+    }
+}
+
+/// Connect output ports to input ports, returning the new connections as a
+/// tuple with one element per `=>`. An array of input ports fans out to all
+/// of them, giving an array of connections, see [`Graph::connect_to`].
+///
+/// ```
+/// # use node_graph::{Graph, InitialPorts, Node, connect};
+/// # #[derive(Debug)]
+/// # struct Vector;
+/// # impl Node for Vector {
+/// #     type DataType = ();
+/// #     type DataValue = f32;
+/// #     fn initial_ports(&self) -> InitialPorts<Self> {
+/// #         InitialPorts {
+/// #             inputs: vec![("x", (), 0.0), ("y", (), 0.0)],
+/// #             outputs: vec![("x", ()), ("y", ())],
+/// #         }
+/// #     }
+/// # }
+/// # let mut graph = Graph::<Vector>::new();
+/// # let [a, b, c] = [(); 3].map(|_| graph.create_node(Vector));
+/// let (first, [second, third]) = connect!(graph;
+///     a.output(0) => b.input("x")
+///     a.output(1) => [b.input("y"), c.input("y")]
+/// );
+/// # assert_eq!(graph.connection_count(), 3);
+/// # assert!([first, second, third].iter().all(|&id| graph.get_connection(id).is_some()));
+/// ```
 #[macro_export]
 macro_rules! connect {
     ($graph:expr; $($start:expr => $end:expr)+) => {
-        $(
-            $graph.connect($start, $end);
-        )+
+        ($(
+            $graph.connect_to($start, $end),
+        )+)
     };
 }
 
+/// Connect chains of ports, where each port is connected to the next one.
+/// Returns a tuple with an array of connections for each chain.
 #[macro_export]
 macro_rules! connect_all {
     ($graph:expr; $($start:expr $(=> $end:expr)+;)+) => {
-        $({
+        ($({
             let mut last = $start;
 
-            $(
-                $graph.connect(last, $end);
+            [$({
+                let connection = $graph.connect(last, $end);
                 last = $end;
-            )+
-        })+
+                connection
+            }),+]
+        },)+)
+    };
+}
+
+/// Create nodes and connect them in one block. `name = node;` creates a node
+/// and binds its id to a local variable, and `start.output -> end.input;`
+/// connects two of them, where ports are indices, names, or any other
+/// identifier in parentheses. `start.output -> [a.input, b.input];` fans out
+/// to several inputs.
+///
+/// ```
+/// # use node_graph::{Graph, InitialPorts, Node, graph, ports};
+/// # #[derive(Debug)]
+/// # enum MathNode {
+/// #     Constant(f32),
+/// #     Multiply,
+/// #     Output,
+/// # }
+/// # impl Node for MathNode {
+/// #     type DataType = ();
+/// #     type DataValue = f32;
+/// #     fn initial_ports(&self) -> InitialPorts<Self> {
+/// #         match self {
+/// #             Self::Constant(_) => InitialPorts {
+/// #                 outputs: vec![("value", ())],
+/// #                 ..Default::default()
+/// #             },
+/// #             Self::Multiply => InitialPorts {
+/// #                 inputs: vec![("a", (), 1.0), ("b", (), 1.0)],
+/// #                 outputs: vec![("result", ())],
+/// #             },
+/// #             Self::Output => InitialPorts {
+/// #                 inputs: vec![("value", (), 0.0)],
+/// #                 ..Default::default()
+/// #             },
+/// #         }
+/// #     }
+/// # }
+/// # ports! {
+/// #     MultiplyPort { in A, in B, out Result }
+/// # }
+/// # let mut graph = Graph::<MathNode>::new();
+/// graph! { graph;
+///     constant = MathNode::Constant(5.0);
+///     multiply = MathNode::Multiply;
+///     output = MathNode::Output;
+///     constant.0 -> multiply."a";
+///     constant.0 -> [multiply.(MultiplyPort::B), output.0];
+/// }
+///
+/// let result = graph.get_output_port_at(multiply, 0);
+/// # assert!(result.is_some());
+/// # assert_eq!(graph.connection_count_of(constant), 3);
+/// ```
+#[macro_export]
+macro_rules! graph {
+    ($graph:expr; $($body:tt)*) => {
+        $crate::graph!(@statements $graph; $($body)*);
+    };
+    (@statements $graph:expr;) => {};
+    (@statements $graph:expr; $start:ident . $output:tt -> [$($end:ident . $input:tt),+ $(,)?]; $($rest:tt)*) => {
+        $(
+            $graph.connect($start.output($output), $end.input($input));
+        )+
+        $crate::graph!(@statements $graph; $($rest)*);
+    };
+    (@statements $graph:expr; $start:ident . $output:tt -> $end:ident . $input:tt; $($rest:tt)*) => {
+        $graph.connect($start.output($output), $end.input($input));
+        $crate::graph!(@statements $graph; $($rest)*);
+    };
+    (@statements $graph:expr; $name:ident = $node:expr; $($rest:tt)*) => {
+        let $name = $graph.create_node($node);
+        $crate::graph!(@statements $graph; $($rest)*);
+    };
+}
+
+/// Define an enum naming the ports of a node kind, so they can be used in
+/// place of port names or indices. Ports are numbered per direction in the
+/// order they are listed, which has to match the order the node creates
+/// them in.
+///
+/// ```
+/// # use node_graph::{Graph, InitialPorts, Node, ports};
+/// # #[derive(Debug)]
+/// # enum MathNode {
+/// #     Constant(f32),
+/// #     Multiply,
+/// # }
+/// # impl Node for MathNode {
+/// #     type DataType = ();
+/// #     type DataValue = f32;
+/// #     fn initial_ports(&self) -> InitialPorts<Self> {
+/// #         match self {
+/// #             Self::Constant(_) => InitialPorts {
+/// #                 outputs: vec![("value", ())],
+/// #                 ..Default::default()
+/// #             },
+/// #             Self::Multiply => InitialPorts {
+/// #                 inputs: vec![("a", (), 1.0), ("b", (), 1.0)],
+/// #                 outputs: vec![("result", ())],
+/// #             },
+/// #         }
+/// #     }
+/// # }
+/// # let mut graph = Graph::<MathNode>::new();
+/// # let constant = graph.create_node(MathNode::Constant(5.0));
+/// # let multiply = graph.create_node(MathNode::Multiply);
+/// ports! {
+///     pub MultiplyPort { in A, in B, out Result }
+/// }
+///
+/// graph.connect(constant.output(0), multiply.input(MultiplyPort::A));
+/// # assert_eq!(MultiplyPort::B.input_index(), Some(1));
+/// # assert_eq!(MultiplyPort::Result.output_index(), Some(0));
+/// # assert_eq!(graph.connection_count_of(multiply), 1);
+/// ```
+///
+/// Using an output as an input, or the other way around, refers to a port
+/// that doesn't exist.
+#[macro_export]
+macro_rules! ports {
+    ($(#[$meta:meta])* $vis:vis $name:ident { $($direction:tt $port:ident),* $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($port,)*
+        }
+
+        impl $name {
+            /// Every port along with whether it is an input
+            const PORTS: &'static [(Self, bool)] = &[
+                $((Self::$port, $crate::ports!(@is_input $direction)),)*
+            ];
+
+            const fn index(self, input: bool) -> Option<usize> {
+                let mut index = 0;
+                let mut position = 0;
+
+                while position < Self::PORTS.len() {
+                    let (port, is_input) = Self::PORTS[position];
+
+                    if port as usize == self as usize {
+                        return if is_input == input { Some(index) } else { None };
+                    }
+
+                    if is_input == input {
+                        index += 1;
+                    }
+
+                    position += 1;
+                }
+
+                None
+            }
+
+            /// The position of this port among the inputs of its node, or
+            /// `None` if it is an output
+            pub const fn input_index(self) -> Option<usize> {
+                self.index(true)
+            }
+
+            /// The position of this port among the outputs of its node, or
+            /// `None` if it is an input
+            pub const fn output_index(self) -> Option<usize> {
+                self.index(false)
+            }
+
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $(Self::$port => stringify!($port),)*
+                }
+            }
+        }
+
+        impl<'a> $crate::reference::NodeInputIdentifier<'a> for $name {
+            type Reference = $crate::reference::NodeInputIndexReference;
+
+            fn combine(self, node_id: $crate::NodeId) -> Self::Reference {
+                let index = self.input_index().unwrap_or(usize::MAX);
+                $crate::reference::NodeInputIndexReference::new(node_id, index)
+            }
+        }
+
+        impl<'a> $crate::reference::NodeOutputIdentifier<'a> for $name {
+            type Reference = $crate::reference::NodeOutputIndexReference;
+
+            fn combine(self, node_id: $crate::NodeId) -> Self::Reference {
+                let index = self.output_index().unwrap_or(usize::MAX);
+                $crate::reference::NodeOutputIndexReference::new(node_id, index)
+            }
+        }
     };
+    (@is_input in) => { true };
+    (@is_input out) => { false };
 }
@@ -0,0 +1,228 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+};
+
+use slotmap::SecondaryMap;
+
+use crate::{Graph, InputPortId, Node, NodeId, OutputPortId, walker::OutputCache};
+
+type HashFn<N> = Box<dyn Fn(&<N as Node>::DataValue) -> u64 + Send + Sync>;
+type EqFn<N> = Box<dyn Fn(&<N as Node>::DataValue, &<N as Node>::DataValue) -> bool + Send + Sync>;
+
+/// The outputs of earlier evaluations, keyed by a hash of the inputs they were
+/// computed from. A [walker](crate::walker::GraphWalker::set_memoizer) with a
+/// memoizer skips nodes whose inputs hash the same as last time and reuses
+/// their outputs.
+///
+/// Nodes that aren't [pure](Node::is_pure) are always evaluated. Only input
+/// values are hashed, so a node whose outputs depend on its own state has to
+/// be [invalidated](Self::invalidate) when that state changes, unless it is
+/// replaced with [`Graph::set_node`]. Inputs whose hashes collide count as
+/// the same, unless the memoizer is [checked](Self::checked).
+pub struct Memoizer<N: Node> {
+    hash: HashFn<N>,
+    /// Compares input values on lookup, if checked
+    eq: Option<EqFn<N>>,
+    entries: SecondaryMap<NodeId, MemoEntry<N>>,
+}
+
+pub(crate) struct MemoEntry<N: Node> {
+    pub(crate) key: MemoKey<N::DataValue>,
+    pub(crate) outputs: Vec<(OutputPortId, N::DataValue)>,
+}
+
+/// The hash of the values a node reads, along with the values themselves if
+/// the memoizer is checked
+#[derive(Debug, Clone)]
+pub(crate) struct MemoKey<V> {
+    pub(crate) hash: u64,
+    /// The revision of the node's value, see [`Graph::set_node`]
+    pub(crate) revision: u64,
+    /// The values read from each input port, in port order
+    pub(crate) inputs: Option<Vec<Vec<V>>>,
+}
+
+impl<N: Node> Memoizer<N>
+where
+    N::DataValue: Hash,
+{
+    pub fn new() -> Self {
+        Self::with_hasher(|value| {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        })
+    }
+}
+
+impl<N: Node> Memoizer<N>
+where
+    N::DataValue: Hash + PartialEq,
+{
+    /// A memoizer that also compares the input values when their hashes
+    /// match, see [`checked_by`](Self::checked_by)
+    pub fn checked() -> Self {
+        Self::new().checked_by(PartialEq::eq)
+    }
+}
+
+impl<N: Node> Memoizer<N> {
+    /// Create a memoizer for values that don't implement [`Hash`]
+    pub fn with_hasher(hash: impl Fn(&N::DataValue) -> u64 + Send + Sync + 'static) -> Self {
+        Self {
+            hash: Box::new(hash),
+            eq: None,
+            entries: SecondaryMap::new(),
+        }
+    }
+
+    /// Keep a copy of the inputs of every memoized node and compare them with
+    /// `eq` on lookup, so outputs are never reused for different inputs that
+    /// happen to hash the same
+    pub fn checked_by(
+        mut self,
+        eq: impl Fn(&N::DataValue, &N::DataValue) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.eq = Some(Box::new(eq));
+        self
+    }
+
+    pub fn is_checked(&self) -> bool {
+        self.eq.is_some()
+    }
+
+    /// Forget the outputs of `node`, so it is evaluated again on the next walk
+    pub fn invalidate(&mut self, node: NodeId) {
+        self.entries.remove(node);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn contains(&self, node: NodeId) -> bool {
+        self.entries.contains_key(node)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Hash every value `node` would read, in port order, keeping the values
+    /// if checked
+    pub(crate) fn key(
+        &self,
+        graph: &Graph<N>,
+        output_cache: &OutputCache<N::DataValue>,
+        inputs: &SecondaryMap<InputPortId, N::DataValue>,
+        node: NodeId,
+    ) -> MemoKey<N::DataValue> {
+        let mut hasher = DefaultHasher::new();
+        let mut values = self.eq.as_ref().map(|_| Vec::new());
+
+        for &(_, port) in graph.node_data[node].inputs.iter() {
+            let mut read = graph
+                .get_sources(port)
+                .filter_map(|port| output_cache.get(port))
+                .collect::<Vec<_>>();
+
+            let default = inputs
+                .get(port)
+                .or(graph.input_ports[port].default.as_ref());
+
+            if read.is_empty()
+                && let Some(default) = default
+            {
+                read.push(default);
+            }
+
+            for &value in read.iter() {
+                (self.hash)(value).hash(&mut hasher);
+            }
+
+            // Separate ports so values can't shift from one port to the next
+            u64::MAX.hash(&mut hasher);
+
+            if let Some(values) = &mut values {
+                values.push(read.into_iter().cloned().collect());
+            }
+        }
+
+        MemoKey {
+            hash: hasher.finish(),
+            revision: graph.node_data[node].revision,
+            inputs: values,
+        }
+    }
+
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (NodeId, &MemoEntry<N>)> + '_ {
+        self.entries.iter()
+    }
+
+    pub(crate) fn get(
+        &self,
+        node: NodeId,
+        key: &MemoKey<N::DataValue>,
+    ) -> Option<&[(OutputPortId, N::DataValue)]> {
+        self.entries
+            .get(node)
+            .filter(|entry| {
+                entry.key.hash == key.hash
+                    && entry.key.revision == key.revision
+                    && self.same_inputs(&entry.key, key)
+            })
+            .map(|entry| entry.outputs.as_slice())
+    }
+
+    fn same_inputs(&self, a: &MemoKey<N::DataValue>, b: &MemoKey<N::DataValue>) -> bool {
+        let Some(eq) = &self.eq else {
+            return true;
+        };
+
+        // Entries loaded from an unchecked cache can't be compared
+        let (Some(a), Some(b)) = (&a.inputs, &b.inputs) else {
+            return false;
+        };
+
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(a, b)| a.len() == b.len() && a.iter().zip(b).all(|(a, b)| eq(a, b)))
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        node: NodeId,
+        key: MemoKey<N::DataValue>,
+        outputs: Vec<(OutputPortId, N::DataValue)>,
+    ) {
+        self.entries.insert(node, MemoEntry { key, outputs });
+    }
+}
+
+impl<N: Node> Default for Memoizer<N>
+where
+    N::DataValue: Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Node> Debug for Memoizer<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(
+                self.entries
+                    .iter()
+                    .map(|(node, entry)| (node, entry.key.hash)),
+            )
+            .finish()
+    }
+}
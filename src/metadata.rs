@@ -0,0 +1,381 @@
+use std::collections::BTreeMap;
+
+use crate::{ConnectionId, Graph, Node, NodeId, batch::GraphOp};
+
+/// A single value in a [`Metadata`] map
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MetaValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<MetaValue>),
+}
+
+impl MetaValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a float, converting integers
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Float(value) => Some(*value),
+            Self::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[MetaValue]> {
+        match self {
+            Self::List(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for MetaValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for MetaValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<i32> for MetaValue {
+    fn from(value: i32) -> Self {
+        Self::Int(value.into())
+    }
+}
+
+impl From<f64> for MetaValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<f32> for MetaValue {
+    fn from(value: f32) -> Self {
+        Self::Float(value.into())
+    }
+}
+
+impl From<String> for MetaValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for MetaValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl<T: Into<MetaValue>> From<Vec<T>> for MetaValue {
+    fn from(value: Vec<T>) -> Self {
+        Self::List(value.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Types that can be read back from a [`MetaValue`], see [`Metadata::get_as`]
+pub trait FromMetaValue: Sized {
+    fn from_meta_value(value: &MetaValue) -> Option<Self>;
+}
+
+impl FromMetaValue for MetaValue {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl FromMetaValue for bool {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        value.as_bool()
+    }
+}
+
+impl FromMetaValue for i64 {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        value.as_int()
+    }
+}
+
+impl FromMetaValue for i32 {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        value.as_int()?.try_into().ok()
+    }
+}
+
+impl FromMetaValue for f64 {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        value.as_float()
+    }
+}
+
+impl FromMetaValue for f32 {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        value.as_float().map(|value| value as f32)
+    }
+}
+
+impl FromMetaValue for String {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        value.as_str().map(str::to_string)
+    }
+}
+
+impl<T: FromMetaValue> FromMetaValue for Vec<T> {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        value.as_list()?.iter().map(T::from_meta_value).collect()
+    }
+}
+
+/// The connection metadata key holding the weight of a connection, see
+/// [`GraphWalkContext::get_weighted`](crate::walker::GraphWalkContext::get_weighted)
+pub const CONNECTION_WEIGHT: &str = "weight";
+
+/// The node metadata keys holding the position of a node in an editor, see
+/// [`Graph::node_position`]
+pub const NODE_X: &str = "x";
+pub const NODE_Y: &str = "y";
+
+/// User data attached to part of a graph, like UI hints (slider ranges,
+/// tooltips, units) for a port. The graph itself never reads it.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Metadata {
+    values: BTreeMap<String, MetaValue>,
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&MetaValue> {
+        self.values.get(key)
+    }
+
+    /// Get the value of `key` converted to `T`, or `None` if it is missing or
+    /// has a different type
+    pub fn get_as<T: FromMetaValue>(&self, key: &str) -> Option<T> {
+        T::from_meta_value(self.get(key)?)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut MetaValue> {
+        self.values.get_mut(key)
+    }
+
+    /// Set `key` to `value`, returning the previous value
+    pub fn set(&mut self, key: &str, value: impl Into<MetaValue>) -> Option<MetaValue> {
+        self.values.insert(key.to_string(), value.into())
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<MetaValue> {
+        self.values.remove(key)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    /// Iterate over all entries, sorted by key
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &MetaValue)> {
+        self.values.iter().map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Metadata about the graph as a whole, like the version of the tool that
+    /// created it. Kept by [`clear`](Self::clear).
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Set a graph metadata entry, returning the previous value
+    pub fn set_meta<T: Into<MetaValue>>(&mut self, key: &str, value: T) -> Option<MetaValue> {
+        self.metadata.set(key, value)
+    }
+
+    /// Get a graph metadata entry, or `None` if it is missing or has a
+    /// different type
+    pub fn get_meta<T: FromMetaValue>(&self, key: &str) -> Option<T> {
+        self.metadata.get_as(key)
+    }
+
+    pub fn remove_meta(&mut self, key: &str) -> Option<MetaValue> {
+        self.metadata.remove(key)
+    }
+
+    pub fn get_connection_metadata(&self, connection: ConnectionId) -> Option<&Metadata> {
+        Some(&self.connections.get(connection)?.metadata)
+    }
+
+    pub fn get_connection_metadata_mut(
+        &mut self,
+        connection: ConnectionId,
+    ) -> Option<&mut Metadata> {
+        Some(&mut self.connections.get_mut(connection)?.metadata)
+    }
+
+    /// Set a metadata entry of a connection, returning the previous value.
+    /// Entries under [`CONNECTION_WEIGHT`] are read by
+    /// [`GraphWalkContext::get_weighted`](crate::walker::GraphWalkContext::get_weighted).
+    pub fn set_connection_meta<T: Into<MetaValue>>(
+        &mut self,
+        connection: ConnectionId,
+        key: &str,
+        value: T,
+    ) -> Option<MetaValue> {
+        self.connections
+            .get_mut(connection)
+            .expect("Connection does not exist")
+            .metadata
+            .set(key, value)
+    }
+
+    /// Get a metadata entry of a connection, or `None` if it is missing or
+    /// has a different type
+    pub fn get_connection_meta<T: FromMetaValue>(
+        &self,
+        connection: ConnectionId,
+        key: &str,
+    ) -> Option<T> {
+        self.connections
+            .get(connection)
+            .expect("Connection does not exist")
+            .metadata
+            .get_as(key)
+    }
+
+    pub fn remove_connection_meta(
+        &mut self,
+        connection: ConnectionId,
+        key: &str,
+    ) -> Option<MetaValue> {
+        self.connections
+            .get_mut(connection)
+            .expect("Connection does not exist")
+            .metadata
+            .remove(key)
+    }
+
+    pub fn get_node_metadata(&self, node: NodeId) -> Option<&Metadata> {
+        Some(&self.node_data.get(node)?.metadata)
+    }
+
+    pub fn get_node_metadata_mut(&mut self, node: NodeId) -> Option<&mut Metadata> {
+        Some(&mut self.node_data.get_mut(node)?.metadata)
+    }
+
+    /// Set a metadata entry of a node, returning the previous value
+    pub fn set_node_meta<T: Into<MetaValue>>(
+        &mut self,
+        node: NodeId,
+        key: &str,
+        value: T,
+    ) -> Option<MetaValue> {
+        let value = value.into();
+        let logged = self.mutation_logging().map(|_| value.clone());
+
+        let previous = self
+            .node_data
+            .get_mut(node)
+            .expect("Node does not exist")
+            .metadata
+            .set(key, value);
+
+        if let Some(value) = logged {
+            self.log_mutation(GraphOp::SetNodeMeta {
+                node,
+                key: key.to_string(),
+                value: Some(value),
+            });
+        }
+
+        previous
+    }
+
+    /// Get a metadata entry of a node, or `None` if it is missing or has a
+    /// different type
+    pub fn get_node_meta<T: FromMetaValue>(&self, node: NodeId, key: &str) -> Option<T> {
+        self.node_data
+            .get(node)
+            .expect("Node does not exist")
+            .metadata
+            .get_as(key)
+    }
+
+    pub fn remove_node_meta(&mut self, node: NodeId, key: &str) -> Option<MetaValue> {
+        let value = self
+            .node_data
+            .get_mut(node)
+            .expect("Node does not exist")
+            .metadata
+            .remove(key);
+
+        if value.is_some() {
+            self.log_mutation(GraphOp::SetNodeMeta {
+                node,
+                key: key.to_string(),
+                value: None,
+            });
+        }
+
+        value
+    }
+
+    /// The position of a node in an editor, stored under [`NODE_X`] and
+    /// [`NODE_Y`], or `None` if it has none
+    pub fn node_position(&self, node: NodeId) -> Option<(f64, f64)> {
+        Some((
+            self.get_node_meta(node, NODE_X)?,
+            self.get_node_meta(node, NODE_Y)?,
+        ))
+    }
+
+    pub fn set_node_position(&mut self, node: NodeId, (x, y): (f64, f64)) {
+        self.set_node_meta(node, NODE_X, x);
+        self.set_node_meta(node, NODE_Y, y);
+    }
+}
@@ -0,0 +1,88 @@
+use std::{fmt::Debug, time::Duration};
+
+use crate::{Graph, Node, NodeId};
+
+/// Receives measurements from a [`GraphWalker`](crate::walker::GraphWalker),
+/// like counters for a metrics exporter. Set with
+/// [`GraphWalker::set_metrics`](crate::walker::GraphWalker::set_metrics).
+pub trait WalkMetrics: Debug {
+    /// The walk callback evaluated `node`, taking `duration`
+    fn node_evaluated(&mut self, node: NodeInfo<'_>, duration: Duration) {
+        let _ = (node, duration);
+    }
+
+    /// The [memoizer](crate::memo::Memoizer) had the outputs of `node` for
+    /// its current inputs, so it wasn't evaluated
+    fn cache_hit(&mut self, node: NodeInfo<'_>) {
+        let _ = node;
+    }
+
+    /// The memoizer didn't have the outputs of `node`, so it was evaluated
+    fn cache_miss(&mut self, node: NodeInfo<'_>) {
+        let _ = node;
+    }
+
+    /// A walk over a path of `path_length` nodes finished, taking `duration`
+    /// including the time it spent paused
+    fn walk_finished(&mut self, path_length: usize, duration: Duration) {
+        let _ = (path_length, duration);
+    }
+}
+
+/// The node a measurement is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeInfo<'a> {
+    pub id: NodeId,
+    pub name: Option<&'a str>,
+    /// See [`Node::variant_name`]
+    pub variant: &'static str,
+}
+
+impl<'a> NodeInfo<'a> {
+    pub(crate) fn new<N: Node>(graph: &'a Graph<N>, id: NodeId) -> Self {
+        Self {
+            id,
+            name: graph.get_node_name(id),
+            variant: graph.nodes[id].read().variant_name(),
+        }
+    }
+}
+
+/// Reports walk measurements to the [`metrics`] crate, so they can be
+/// exported to Prometheus and the like. Node measurements are labeled with
+/// the node's [variant](Node::variant_name) to keep the number of series low.
+///
+/// - `node_graph_node_evaluations_total` and
+///   `node_graph_node_evaluation_seconds` per variant
+/// - `node_graph_cache_hits_total` and `node_graph_cache_misses_total` per
+///   variant
+/// - `node_graph_walks_total`, `node_graph_walk_seconds` and
+///   `node_graph_path_length`
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsRecorder;
+
+#[cfg(feature = "metrics")]
+impl WalkMetrics for MetricsRecorder {
+    fn node_evaluated(&mut self, node: NodeInfo<'_>, duration: Duration) {
+        ::metrics::counter!("node_graph_node_evaluations_total", "variant" => node.variant)
+            .increment(1);
+        ::metrics::histogram!("node_graph_node_evaluation_seconds", "variant" => node.variant)
+            .record(duration);
+    }
+
+    fn cache_hit(&mut self, node: NodeInfo<'_>) {
+        ::metrics::counter!("node_graph_cache_hits_total", "variant" => node.variant).increment(1);
+    }
+
+    fn cache_miss(&mut self, node: NodeInfo<'_>) {
+        ::metrics::counter!("node_graph_cache_misses_total", "variant" => node.variant)
+            .increment(1);
+    }
+
+    fn walk_finished(&mut self, path_length: usize, duration: Duration) {
+        ::metrics::counter!("node_graph_walks_total").increment(1);
+        ::metrics::histogram!("node_graph_walk_seconds").record(duration);
+        ::metrics::gauge!("node_graph_path_length").set(path_length as f64);
+    }
+}
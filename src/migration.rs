@@ -0,0 +1,154 @@
+use std::{collections::BTreeMap, fmt::Debug};
+
+use crate::{
+    DataType,
+    document::{GraphDocument, LoadError},
+};
+
+type Migration<T, V> = Box<dyn Fn(&mut GraphDocument<T, V>) -> Result<(), String> + Send + Sync>;
+
+/// Upgrades [`GraphDocument`]s saved by older versions of an application, so
+/// graphs keep loading after node kinds are renamed, ports are remapped or
+/// defaults change. Used by [`Graph::from_document`](crate::Graph::from_document).
+pub trait GraphMigrator<T, V> {
+    /// The document version saved by this version of the application
+    fn current_version(&self) -> u32;
+
+    /// Migrate `document` from `document.version` to the next version. The
+    /// version number is updated afterwards.
+    fn migrate(&self, document: &mut GraphDocument<T, V>) -> Result<(), String>;
+
+    /// Migrate `document` one version at a time until it reaches the current
+    /// version
+    fn migrate_document(&self, document: &mut GraphDocument<T, V>) -> Result<(), LoadError<T>>
+    where
+        T: DataType,
+    {
+        let current = self.current_version();
+
+        if document.version > current {
+            return Err(LoadError::NewerVersion {
+                version: document.version,
+                current,
+            });
+        }
+
+        while document.version < current {
+            self.migrate(document)
+                .map_err(|message| LoadError::Migration {
+                    version: document.version,
+                    message,
+                })?;
+
+            document.version += 1;
+        }
+
+        Ok(())
+    }
+}
+
+/// A [`GraphMigrator`] made of a function per version. Versions without a
+/// migration are left as they are.
+///
+/// ```
+/// # use node_graph::{
+/// #     Graph, InitialPorts, Node, document::DocumentNode, migration::Migrations,
+/// #     registry::NodeRegistry,
+/// # };
+/// # #[derive(Debug)]
+/// # struct Multiply;
+/// # impl Node for Multiply {
+/// #     type DataType = ();
+/// #     type DataValue = f32;
+/// #     fn initial_ports(&self) -> InitialPorts<Self> {
+/// #         InitialPorts {
+/// #             inputs: vec![("a", (), 1.0), ("b", (), 1.0)],
+/// #             outputs: vec![("product", ())],
+/// #         }
+/// #     }
+/// # }
+/// # impl DocumentNode for Multiply {
+/// #     fn kind(&self) -> &str {
+/// #         "Multiply"
+/// #     }
+/// # }
+/// # let mut registry = NodeRegistry::new();
+/// # registry.register("Multiply", |_| Multiply);
+/// # let mut graph = Graph::<Multiply>::new();
+/// # let node = graph.create_node(Multiply);
+/// # graph.set_default_value(node.input(0), 2.0);
+/// # let mut document = graph.to_document(0);
+/// # document.rename_kind("Multiply", "Mult");
+/// # document.rename_input("Mult", "a", "x");
+/// let mut migrations = Migrations::new(2);
+///
+/// migrations.register(0, |document| {
+///     document.rename_kind("Mult", "Multiply");
+///     Ok(())
+/// });
+///
+/// migrations.register(1, |document| {
+///     document.rename_input("Multiply", "x", "a");
+///     Ok(())
+/// });
+///
+/// let graph = Graph::from_document(document, &registry, &migrations)?;
+/// # let node = graph.view().node_ids().next().unwrap();
+/// # assert_eq!(graph.get_input_port_info(node.input("a")).unwrap().default, Some(2.0));
+/// # Ok::<(), node_graph::document::LoadError<()>>(())
+/// ```
+pub struct Migrations<T, V> {
+    current_version: u32,
+    migrations: BTreeMap<u32, Migration<T, V>>,
+}
+
+impl<T, V> Migrations<T, V> {
+    pub fn new(current_version: u32) -> Self {
+        Self {
+            current_version,
+            migrations: BTreeMap::new(),
+        }
+    }
+
+    /// Set the function migrating documents from `version` to `version + 1`,
+    /// replacing any earlier one
+    pub fn register(
+        &mut self,
+        version: u32,
+        migration: impl Fn(&mut GraphDocument<T, V>) -> Result<(), String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        assert!(
+            version < self.current_version,
+            "Migrations must be from a version before the current version"
+        );
+
+        self.migrations.insert(version, Box::new(migration));
+        self
+    }
+
+    pub fn contains(&self, version: u32) -> bool {
+        self.migrations.contains_key(&version)
+    }
+}
+
+impl<T, V> GraphMigrator<T, V> for Migrations<T, V> {
+    fn current_version(&self) -> u32 {
+        self.current_version
+    }
+
+    fn migrate(&self, document: &mut GraphDocument<T, V>) -> Result<(), String> {
+        match self.migrations.get(&document.version) {
+            Some(migration) => migration(document),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T, V> Debug for Migrations<T, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Migrations")
+            .field("current_version", &self.current_version)
+            .field("versions", &self.migrations.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
@@ -0,0 +1,187 @@
+use std::time::SystemTime;
+
+use crate::{Graph, Node, NodeId, batch::GraphOp};
+
+/// A structural change recorded in the mutation log, see
+/// [`Graph::enable_mutation_log`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "N: serde::Serialize, N::DataType: serde::Serialize, N::DataValue: serde::Serialize",
+        deserialize = "N: serde::Deserialize<'de>, N::DataType: serde::Deserialize<'de>, N::DataValue: serde::Deserialize<'de>"
+    ))
+)]
+pub struct LoggedOp<N: Node> {
+    pub op: GraphOp<N>,
+    pub timestamp: SystemTime,
+    /// The [tag](Graph::set_mutation_tag) set when the change was made
+    pub tag: Option<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct MutationLog<N: Node> {
+    entries: Vec<LoggedOp<N>>,
+    tag: Option<String>,
+    /// Copies node values for [`GraphOp::CreateNode`], since only graphs of
+    /// cloneable nodes can be logged
+    clone_node: fn(&N) -> N,
+    /// Nonzero while making changes that are part of a logged change
+    paused: usize,
+}
+
+impl<N: Node + Clone> Graph<N> {
+    /// Start recording every change to the graph's nodes, ports and
+    /// connections, including default values, port order and limits, node
+    /// names, node metadata and groups. Ports created and deleted
+    /// automatically, like initial and variadic ports, aren't recorded, since
+    /// applying the recorded changes recreates them. Neither are changes made
+    /// through mutable references, like
+    /// [`get_node_metadata_mut`](Self::get_node_metadata_mut).
+    ///
+    /// Applying the recorded operations one at a time with
+    /// [`apply_batch`](Self::apply_batch), starting from the same graph,
+    /// repeats the changes with the same ids. Moving a connection is recorded
+    /// as a disconnect followed by a connect though, so it gets a new id. The
+    /// log no longer matches the graph after [compacting](Self::compact).
+    /// [Restoring](Self::restore) a snapshot drops the changes recorded since
+    /// it was taken.
+    pub fn enable_mutation_log(&mut self) {
+        if self.mutation_log.is_none() {
+            self.mutation_log = Some(MutationLog {
+                entries: Vec::new(),
+                tag: None,
+                clone_node: N::clone,
+                paused: 0,
+            });
+        }
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Stop recording changes, returning the changes recorded so far
+    pub fn disable_mutation_log(&mut self) -> Option<Vec<LoggedOp<N>>> {
+        Some(self.mutation_log.take()?.entries)
+    }
+
+    /// The changes recorded since the log was enabled or last taken, oldest
+    /// first, or `None` if the log isn't enabled
+    pub fn mutation_log(&self) -> Option<&[LoggedOp<N>]> {
+        Some(&self.mutation_log.as_ref()?.entries)
+    }
+
+    /// Remove and return the recorded changes, to write them out in parts.
+    /// The log stays enabled.
+    pub fn take_mutation_log(&mut self) -> Vec<LoggedOp<N>> {
+        self.mutation_log
+            .as_mut()
+            .map(|log| std::mem::take(&mut log.entries))
+            .unwrap_or_default()
+    }
+
+    /// Tag changes recorded from now on, like with the user or the editor
+    /// action making them, or stop tagging them with `None`
+    pub fn set_mutation_tag(&mut self, tag: Option<&str>) {
+        if let Some(log) = &mut self.mutation_log {
+            log.tag = tag.map(str::to_string);
+        }
+    }
+
+    /// The function copying nodes into the log, if changes are being
+    /// recorded right now
+    pub(crate) fn mutation_logging(&self) -> Option<fn(&N) -> N> {
+        self.mutation_log
+            .as_ref()
+            .filter(|log| log.paused == 0)
+            .map(|log| log.clone_node)
+    }
+
+    pub(crate) fn log_mutation(&mut self, op: GraphOp<N>) {
+        let Some(log) = &mut self.mutation_log else {
+            return;
+        };
+
+        if log.paused == 0 {
+            log.entries.push(LoggedOp {
+                op,
+                timestamp: SystemTime::now(),
+                tag: log.tag.clone(),
+            });
+        }
+    }
+
+    /// Record the creation of a node that was inserted along with ports
+    /// besides its initial ones. Replaying the log creates those ports after
+    /// the initial ones.
+    pub(crate) fn log_node_with_ports(&mut self, node: NodeId) {
+        let Some(clone_node) = self.mutation_logging() else {
+            return;
+        };
+
+        let value = clone_node(&self.nodes[node].read());
+        let initial = value.initial_ports();
+        let data = &self.node_data[node];
+
+        let inputs = data
+            .inputs
+            .iter()
+            .filter(|(name, _)| !initial.inputs.iter().any(|&(other, ..)| other == &**name))
+            .map(|(name, port)| GraphOp::CreateInputPort {
+                node,
+                name: name.to_string(),
+                ty: self.input_ports[*port].ty,
+                default: self.input_ports[*port].default.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let outputs = data
+            .outputs
+            .iter()
+            .filter(|(name, _)| !initial.outputs.iter().any(|&(other, _)| other == &**name))
+            .map(|(name, port)| GraphOp::CreateOutputPort {
+                node,
+                name: name.to_string(),
+                ty: self.output_ports[*port].ty,
+            })
+            .collect::<Vec<_>>();
+
+        self.log_mutation(GraphOp::CreateNode(value));
+
+        for op in inputs.into_iter().chain(outputs) {
+            self.log_mutation(op);
+        }
+    }
+
+    /// The number of recorded changes, to [roll back](Self::truncate_mutation_log)
+    /// to later
+    pub(crate) fn mutation_log_len(&self) -> usize {
+        self.mutation_log
+            .as_ref()
+            .map_or(0, |log| log.entries.len())
+    }
+
+    /// Forget changes recorded after the log had `len` entries, when they
+    /// were undone
+    pub(crate) fn truncate_mutation_log(&mut self, len: usize) {
+        if let Some(log) = &mut self.mutation_log {
+            log.entries.truncate(len);
+        }
+    }
+
+    /// Run `f` without recording the changes it makes, for changes that
+    /// follow from a recorded one
+    pub(crate) fn unlogged<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        if let Some(log) = &mut self.mutation_log {
+            log.paused += 1;
+        }
+
+        let result = f(self);
+
+        if let Some(log) = &mut self.mutation_log {
+            log.paused -= 1;
+        }
+
+        result
+    }
+}
@@ -0,0 +1,225 @@
+use std::{collections::HashMap, fmt::Display};
+
+use slotmap::SecondaryMap;
+
+use crate::{Graph, INVALID_STATE, Node, NodeId, batch::GraphOp, compact::IdRemapping};
+
+/// What happens when a node is given a name that another node already has,
+/// see [`Graph::set_node_name_policy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NodeNamePolicy {
+    /// Multiple nodes may share a name, [`Graph::get_node_by_name`] returns
+    /// the one that was named first
+    AllowDuplicates,
+    /// Naming a node after another one fails
+    Unique,
+    /// A numbered suffix is added to make the name unique, like
+    /// `Multiply.001`
+    #[default]
+    AutoSuffix,
+}
+
+/// Returned by [`Graph::set_node_name`] under [`NodeNamePolicy::Unique`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NameTaken(pub NodeId);
+
+impl Display for NameTaken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Name is already used by node {:?}", self.0)
+    }
+}
+
+impl std::error::Error for NameTaken {}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NodeNames {
+    names: SecondaryMap<NodeId, String>,
+    by_name: HashMap<String, Vec<NodeId>>,
+}
+
+impl NodeNames {
+    pub(crate) fn remove(&mut self, node: NodeId) -> Option<String> {
+        let name = self.names.remove(node)?;
+
+        let nodes = self.by_name.get_mut(&name).expect(INVALID_STATE);
+        nodes.retain(|&other| other != node);
+
+        if nodes.is_empty() {
+            self.by_name.remove(&name);
+        }
+
+        Some(name)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.names.clear();
+        self.by_name.clear();
+    }
+
+    pub(crate) fn remap(&mut self, remapping: &IdRemapping) {
+        self.names = remapping.remap(std::mem::take(&mut self.names));
+
+        for node in self.by_name.values_mut().flatten() {
+            *node = remapping.get(*node).expect(INVALID_STATE);
+        }
+    }
+
+    /// Check that names belong to existing nodes and that the index by name
+    /// matches
+    pub(crate) fn check_consistency(&self, exists: impl Fn(NodeId) -> bool) -> Result<(), String> {
+        for (node, name) in self.names.iter() {
+            if !exists(node) {
+                return Err(format!("Missing node {node:?} is named {name:?}"));
+            }
+
+            let indexed = self.by_name.get(name).into_iter().flatten();
+
+            if indexed.filter(|&&other| other == node).count() != 1 {
+                return Err(format!("{node:?} is not indexed once under {name:?}"));
+            }
+        }
+
+        let indexed = self.by_name.values().map(Vec::len).sum::<usize>();
+
+        if indexed != self.names.len() {
+            return Err(format!(
+                "{indexed} nodes are indexed by name, but {} are named",
+                self.names.len()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The nodes named `name`, in the order they were named
+    pub(crate) fn nodes_named(&self, name: &str) -> &[NodeId] {
+        self.by_name.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    fn insert(&mut self, node: NodeId, name: String) {
+        self.by_name.entry(name.clone()).or_default().push(node);
+        self.names.insert(node, name);
+    }
+
+    fn is_taken(&self, name: &str, node: NodeId) -> Option<NodeId> {
+        self.by_name
+            .get(name)?
+            .iter()
+            .copied()
+            .find(|&other| other != node)
+    }
+}
+
+impl<N: Node> Graph<N> {
+    pub fn node_name_policy(&self) -> NodeNamePolicy {
+        self.node_name_policy
+    }
+
+    /// Change how duplicate names are handled. Existing names are not changed.
+    pub fn set_node_name_policy(&mut self, policy: NodeNamePolicy) {
+        self.node_name_policy = policy;
+    }
+
+    /// Give a node a name, replacing its previous one. Returns the name the
+    /// node ended up with, which differs from `name` if a suffix was added.
+    pub fn set_node_name(&mut self, node: NodeId, name: &str) -> Result<String, NameTaken> {
+        if !self.node_data.contains_key(node) {
+            panic!("Node does not exist");
+        }
+
+        let name = match self.node_names.is_taken(name, node) {
+            None => name.to_string(),
+            Some(other) => match self.node_name_policy {
+                NodeNamePolicy::AllowDuplicates => name.to_string(),
+                NodeNamePolicy::Unique => return Err(NameTaken(other)),
+                NodeNamePolicy::AutoSuffix => {
+                    // Strip an existing suffix so "Multiply.001" becomes
+                    // "Multiply.002" instead of "Multiply.001.001"
+
+                    let base = match name.rsplit_once('.') {
+                        Some((base, suffix))
+                            if suffix.len() >= 3 && suffix.bytes().all(|b| b.is_ascii_digit()) =>
+                        {
+                            base
+                        }
+                        _ => name,
+                    };
+
+                    (1..)
+                        .map(|index| format!("{base}.{index:03}"))
+                        .find(|name| self.node_names.is_taken(name, node).is_none())
+                        .expect("Ran out of node names")
+                }
+            },
+        };
+
+        self.node_names.remove(node);
+        self.node_names.insert(node, name.clone());
+
+        if self.mutation_logging().is_some() {
+            self.log_mutation(GraphOp::SetNodeName {
+                node,
+                name: Some(name.clone()),
+            });
+        }
+
+        self.after_mutation();
+
+        Ok(name)
+    }
+
+    /// Remove the name of a node, returning it
+    pub fn clear_node_name(&mut self, node: NodeId) -> Option<String> {
+        let name = self.node_names.remove(node);
+
+        if name.is_some() {
+            self.log_mutation(GraphOp::SetNodeName { node, name: None });
+        }
+
+        self.after_mutation();
+
+        name
+    }
+
+    /// Put back a name a node had before, even if another node has it now
+    pub(crate) fn restore_node_name(&mut self, node: NodeId, name: Option<String>) {
+        self.node_names.remove(node);
+
+        if let Some(name) = name {
+            self.node_names.insert(node, name);
+        }
+
+        self.after_mutation();
+    }
+
+    pub fn get_node_name(&self, node: NodeId) -> Option<&str> {
+        self.node_names.names.get(node).map(String::as_str)
+    }
+
+    /// The name of a node, or its [variant name](Node::variant_name) without
+    /// the module path if it has none, for labeling it in drawings
+    pub(crate) fn node_title(&self, node: NodeId) -> String {
+        match self.get_node_name(node) {
+            Some(name) => name.to_string(),
+            None => {
+                let variant = self.nodes[node].read().variant_name();
+                variant.rsplit("::").next().unwrap_or(variant).to_string()
+            }
+        }
+    }
+
+    pub fn get_node_by_name(&self, name: &str) -> Option<NodeId> {
+        self.node_names.by_name.get(name)?.first().copied()
+    }
+
+    /// All nodes with this name, only more than one under
+    /// [`NodeNamePolicy::AllowDuplicates`]
+    pub fn get_nodes_by_name(&self, name: &str) -> impl Iterator<Item = NodeId> + '_ {
+        self.node_names
+            .by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+}
@@ -0,0 +1,115 @@
+use std::{fmt::Debug, sync::Arc};
+
+use slotmap::{SecondaryMap, SlotMap, new_key_type};
+
+use crate::{
+    Graph, INVALID_STATE, Node, OutputPortId, compact::IdRemapping, reference::OutputPortReference,
+};
+
+new_key_type! { pub struct ObserverId; }
+
+/// Shared so snapshots can hold on to the observers
+type Observer<N> = Arc<dyn Fn(&<N as Node>::DataValue) + Send + Sync>;
+
+/// Callbacks watching the values written to output ports, see
+/// [`Graph::watch`]
+pub struct OutputObservers<N: Node> {
+    observers: SlotMap<ObserverId, (OutputPortId, Observer<N>)>,
+    by_port: SecondaryMap<OutputPortId, Vec<ObserverId>>,
+}
+
+impl<N: Node> OutputObservers<N> {
+    pub(crate) fn new() -> Self {
+        Self {
+            observers: SlotMap::with_key(),
+            by_port: SecondaryMap::new(),
+        }
+    }
+
+    pub(crate) fn notify(&self, port: OutputPortId, value: &N::DataValue) {
+        for &observer in self.by_port.get(port).into_iter().flatten() {
+            (self.observers[observer].1)(value);
+        }
+    }
+
+    /// Remove the observers of a deleted port
+    pub(crate) fn remove_port(&mut self, port: OutputPortId) {
+        for observer in self.by_port.remove(port).into_iter().flatten() {
+            self.observers.remove(observer);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.observers.clear();
+        self.by_port.clear();
+    }
+
+    pub(crate) fn remap(&mut self, remapping: &IdRemapping) {
+        for (port, _) in self.observers.values_mut() {
+            *port = remapping.get(*port).expect(INVALID_STATE);
+        }
+
+        self.by_port = remapping.remap(std::mem::take(&mut self.by_port));
+    }
+}
+
+impl<N: Node> Clone for OutputObservers<N> {
+    fn clone(&self) -> Self {
+        Self {
+            observers: self.observers.clone(),
+            by_port: self.by_port.clone(),
+        }
+    }
+}
+
+impl<N: Node> Debug for OutputObservers<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.observers.iter().map(|(id, (port, _))| (id, port)))
+            .finish()
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Call `observer` with every value a walker writes to `port`, without
+    /// involving the node that produces it. Observers are removed along with
+    /// their port.
+    pub fn watch(
+        &mut self,
+        port: impl OutputPortReference,
+        observer: impl Fn(&N::DataValue) + Send + Sync + 'static,
+    ) -> ObserverId {
+        let port = port.resolve(self).expect("Output port does not exist");
+
+        let id = self.observers.observers.insert((port, Arc::new(observer)));
+
+        self.observers
+            .by_port
+            .entry(port)
+            .expect("Output port does not exist")
+            .or_default()
+            .push(id);
+
+        id
+    }
+
+    #[must_use]
+    pub fn unwatch(&mut self, observer: ObserverId) -> Option<()> {
+        let (port, _) = self.observers.observers.remove(observer)?;
+
+        if let Some(observers) = self.observers.by_port.get_mut(port) {
+            observers.retain(|&other| other != observer);
+
+            if observers.is_empty() {
+                self.observers.by_port.remove(port);
+            }
+        }
+
+        Some(())
+    }
+
+    /// Whether any observers are watching `port`
+    pub fn is_watched(&self, port: OutputPortId) -> bool {
+        self.observers.by_port.contains_key(port)
+    }
+}
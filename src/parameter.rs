@@ -0,0 +1,194 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use slotmap::SecondaryMap;
+
+use crate::{
+    Graph, INVALID_STATE, InputPortId, Node, compact::IdRemapping, reference::InputPortReference,
+    walker::GraphWalker,
+};
+
+/// Returned by [`Graph::expose_parameter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExposeParameterError {
+    /// Another input port is already exposed under this name
+    NameTaken(InputPortId),
+    /// The input port is connected, so it has no value of its own to set
+    Connected,
+}
+
+impl Display for ExposeParameterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NameTaken(port) => write!(f, "Parameter name is already used by {port:?}"),
+            Self::Connected => write!(f, "Connected input ports can't be parameters"),
+        }
+    }
+}
+
+impl std::error::Error for ExposeParameterError {}
+
+/// Returned by [`GraphWalker::set_parameters`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownParameter(pub String);
+
+impl Display for UnknownParameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Graph has no parameter named {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownParameter {}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Parameters {
+    names: SecondaryMap<InputPortId, String>,
+    by_name: BTreeMap<String, InputPortId>,
+}
+
+impl Parameters {
+    pub(crate) fn remove(&mut self, port: InputPortId) -> Option<String> {
+        let name = self.names.remove(port)?;
+        self.by_name.remove(&name).expect(INVALID_STATE);
+
+        Some(name)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.names.clear();
+        self.by_name.clear();
+    }
+
+    pub(crate) fn remap(&mut self, remapping: &IdRemapping) {
+        self.names = remapping.remap(std::mem::take(&mut self.names));
+
+        for port in self.by_name.values_mut() {
+            *port = remapping.get(*port).expect(INVALID_STATE);
+        }
+    }
+
+    /// Check that parameters are existing input ports and that the index by
+    /// name matches
+    pub(crate) fn check_consistency(
+        &self,
+        exists: impl Fn(InputPortId) -> bool,
+    ) -> Result<(), String> {
+        for (port, name) in self.names.iter() {
+            if !exists(port) {
+                return Err(format!("Missing input {port:?} is parameter {name:?}"));
+            }
+
+            if self.by_name.get(name) != Some(&port) {
+                return Err(format!("Parameter {name:?} is not indexed as {port:?}"));
+            }
+        }
+
+        if self.by_name.len() != self.names.len() {
+            return Err(format!(
+                "{} parameters are indexed by name, but there are {}",
+                self.by_name.len(),
+                self.names.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Expose a disconnected input port as a parameter of the graph, so its
+    /// value can be set by name with [`GraphWalker::set_parameters`]. Exposing
+    /// a port again renames it.
+    pub fn expose_parameter(
+        &mut self,
+        port: impl InputPortReference,
+        name: &str,
+    ) -> Result<(), ExposeParameterError> {
+        let port = port.resolve(self).expect("Input port does not exist");
+
+        if !self.input_ports[port].incoming_connections.is_empty() {
+            return Err(ExposeParameterError::Connected);
+        }
+
+        match self.parameters.by_name.get(name) {
+            Some(&other) if other != port => return Err(ExposeParameterError::NameTaken(other)),
+            _ => {}
+        }
+
+        self.parameters.remove(port);
+        self.parameters.names.insert(port, name.to_string());
+        self.parameters.by_name.insert(name.to_string(), port);
+        self.after_mutation();
+
+        Ok(())
+    }
+
+    /// Stop exposing a parameter, returning its input port
+    pub fn remove_parameter(&mut self, name: &str) -> Option<InputPortId> {
+        let port = *self.parameters.by_name.get(name)?;
+        self.parameters.remove(port);
+        self.after_mutation();
+
+        Some(port)
+    }
+
+    /// All parameters, sorted by name
+    pub fn parameters(&self) -> impl Iterator<Item = (&str, InputPortId)> + '_ {
+        self.parameters
+            .by_name
+            .iter()
+            .map(|(name, &port)| (name.as_str(), port))
+    }
+
+    pub fn get_parameter(&self, name: &str) -> Option<InputPortId> {
+        self.parameters.by_name.get(name).copied()
+    }
+
+    /// The name an input port is exposed under, if it is a parameter
+    pub fn get_parameter_name(&self, port: InputPortId) -> Option<&str> {
+        self.parameters.names.get(port).map(String::as_str)
+    }
+}
+
+impl<N: Node> GraphWalker<'_, N> {
+    /// Set the value of a parameter for this walk and later ones, see
+    /// [`set_input`](Self::set_input)
+    pub fn set_parameter(
+        &mut self,
+        name: &str,
+        value: N::DataValue,
+    ) -> Result<(), UnknownParameter> {
+        let port = self
+            .graph()
+            .get_parameter(name)
+            .ok_or_else(|| UnknownParameter(name.to_string()))?;
+
+        self.set_input(port, value);
+
+        Ok(())
+    }
+
+    /// Set the values of many parameters at once. Nothing is set if any of
+    /// the names is not a parameter.
+    pub fn set_parameters<S: AsRef<str>>(
+        &mut self,
+        values: impl IntoIterator<Item = (S, N::DataValue)>,
+    ) -> Result<(), UnknownParameter> {
+        let values = values
+            .into_iter()
+            .map(|(name, value)| {
+                let name = name.as_ref();
+
+                match self.graph().get_parameter(name) {
+                    Some(port) => Ok((port, value)),
+                    None => Err(UnknownParameter(name.to_string())),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (port, value) in values {
+            self.set_input(port, value);
+        }
+
+        Ok(())
+    }
+}
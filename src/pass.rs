@@ -0,0 +1,172 @@
+use crate::{Graph, Node, NodeId};
+
+/// A transformation of a graph, like removing unused nodes or folding
+/// constants, see [`PassManager`]
+pub trait GraphPass<N: Node> {
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Transform the graph, reporting what changed. A pass that has nothing
+    /// left to do should return an empty report, so the [`PassManager`] knows
+    /// when to stop.
+    fn run(&self, graph: &mut Graph<N>) -> PassReport;
+}
+
+impl<N: Node, F: Fn(&mut Graph<N>) -> PassReport> GraphPass<N> for F {
+    fn run(&self, graph: &mut Graph<N>) -> PassReport {
+        self(graph)
+    }
+}
+
+/// What a [`GraphPass`] changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PassReport {
+    pub added_nodes: Vec<NodeId>,
+    pub removed_nodes: Vec<NodeId>,
+    /// Changes that didn't add or remove nodes, like rewired connections or
+    /// edited node values
+    pub other_changes: usize,
+}
+
+impl PassReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_changed(&self) -> bool {
+        !self.added_nodes.is_empty() || !self.removed_nodes.is_empty() || self.other_changes > 0
+    }
+
+    /// Add the changes of `other` to this report
+    pub fn merge(&mut self, other: PassReport) {
+        self.added_nodes.extend(other.added_nodes);
+        self.removed_nodes.extend(other.removed_nodes);
+        self.other_changes += other.other_changes;
+    }
+}
+
+/// The result of [`PassManager::run`]
+#[derive(Debug, Clone, Default)]
+pub struct PipelineReport {
+    /// How many times every pass was run
+    pub iterations: usize,
+    /// Whether the last iteration didn't change anything. False if the
+    /// iteration limit was reached first.
+    pub converged: bool,
+    /// The combined changes of each pass, by name, in the order passes were
+    /// added
+    pub passes: Vec<(String, PassReport)>,
+}
+
+impl PipelineReport {
+    /// The combined changes of all passes
+    pub fn total(&self) -> PassReport {
+        let mut total = PassReport::new();
+
+        for (_, report) in self.passes.iter() {
+            total.merge(report.clone());
+        }
+
+        total
+    }
+}
+
+/// Runs a list of passes in order, over and over until none of them change
+/// the graph anymore
+pub struct PassManager<N: Node> {
+    passes: Vec<Box<dyn GraphPass<N>>>,
+    max_iterations: usize,
+}
+
+impl<N: Node> PassManager<N> {
+    pub fn new() -> Self {
+        Self {
+            passes: Vec::new(),
+            max_iterations: 100,
+        }
+    }
+
+    pub fn add_pass(&mut self, pass: impl GraphPass<N> + 'static) {
+        self.passes.push(Box::new(pass));
+    }
+
+    /// Stop after running all passes this many times, even if they still
+    /// change the graph. Defaults to 100.
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations;
+    }
+
+    pub fn run(&self, graph: &mut Graph<N>) -> PipelineReport {
+        let mut report = PipelineReport {
+            iterations: 0,
+            converged: false,
+            passes: self
+                .passes
+                .iter()
+                .map(|pass| (pass.name().to_string(), PassReport::new()))
+                .collect(),
+        };
+
+        while report.iterations < self.max_iterations {
+            report.iterations += 1;
+
+            let mut changed = false;
+
+            for (pass, (_, total)) in self.passes.iter().zip(report.passes.iter_mut()) {
+                let pass_report = pass.run(graph);
+                changed |= pass_report.is_changed();
+                total.merge(pass_report);
+            }
+
+            if !changed {
+                report.converged = true;
+                break;
+            }
+        }
+
+        report
+    }
+}
+
+impl<N: Node> Default for PassManager<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Node> std::fmt::Debug for PassManager<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PassManager")
+            .field(
+                "passes",
+                &self
+                    .passes
+                    .iter()
+                    .map(|pass| pass.name())
+                    .collect::<Vec<_>>(),
+            )
+            .field("max_iterations", &self.max_iterations)
+            .finish()
+    }
+}
+
+/// Removes nodes that aren't upstream of the exit nodes, see
+/// [`Graph::prune`]
+#[derive(Debug, Clone)]
+pub struct DeadNodeElimination {
+    pub exit_nodes: Vec<NodeId>,
+}
+
+impl<N: Node> GraphPass<N> for DeadNodeElimination {
+    fn name(&self) -> &str {
+        "DeadNodeElimination"
+    }
+
+    fn run(&self, graph: &mut Graph<N>) -> PassReport {
+        PassReport {
+            removed_nodes: graph.prune(&self.exit_nodes),
+            ..Default::default()
+        }
+    }
+}
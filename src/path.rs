@@ -0,0 +1,227 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    NodeId,
+    workspace::{GraphInstanceNode, GraphWorkspace},
+};
+
+/// A node addressed by names through instances of other graphs, like
+/// `PostFX/Bloom/Threshold`: the workspace graph `PostFX`, its node named
+/// `Bloom` which is an instance of another graph, and the node named
+/// `Threshold` in that graph. Unlike ids, paths stay the same when graphs are
+/// saved and loaded, so they suit logs and error messages.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct NodePath {
+    segments: Vec<String>,
+}
+
+/// Returned when parsing a [`NodePath`] with an empty segment, like `a//b`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmptySegment;
+
+impl Display for EmptySegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Node paths can't have empty segments")
+    }
+}
+
+impl std::error::Error for EmptySegment {}
+
+/// Returned by [`GraphWorkspace::resolve_path`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvePathError {
+    /// The path is empty or only names a graph
+    NotANode,
+    /// The workspace has no graph with this key, named by the first segment
+    /// or by an instance on the way
+    MissingGraph(String),
+    /// There is no node named by the last segment of this part of the path
+    MissingNode(NodePath),
+    /// This part of the path is followed by more segments, but the node it
+    /// names isn't an instance of a graph
+    NotAnInstance(NodePath),
+}
+
+impl Display for ResolvePathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotANode => write!(f, "Path doesn't name a node"),
+            Self::MissingGraph(graph) => write!(f, "Workspace has no graph {graph:?}"),
+            Self::MissingNode(path) => write!(f, "There is no node at {path}"),
+            Self::NotAnInstance(path) => write!(f, "Node {path} is not an instance of a graph"),
+        }
+    }
+}
+
+impl std::error::Error for ResolvePathError {}
+
+impl NodePath {
+    /// Panics if a segment is empty or contains a `/`
+    pub fn new<S: Into<String>>(segments: impl IntoIterator<Item = S>) -> Self {
+        let segments = segments.into_iter().map(Into::into).collect::<Vec<_>>();
+
+        assert!(
+            segments.iter().all(|segment| is_valid_segment(segment)),
+            "Path segments can't be empty or contain '/'"
+        );
+
+        Self { segments }
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = &str> + '_ {
+        self.segments.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// The last segment
+    pub fn name(&self) -> Option<&str> {
+        self.segments.last().map(String::as_str)
+    }
+
+    /// This path without its last segment
+    pub fn parent(&self) -> Option<NodePath> {
+        let (_, parent) = self.segments.split_last()?;
+
+        Some(Self {
+            segments: parent.to_vec(),
+        })
+    }
+
+    /// This path with `name` added to the end. Panics if `name` is empty or
+    /// contains a `/`.
+    pub fn join(&self, name: &str) -> NodePath {
+        assert!(
+            is_valid_segment(name),
+            "Path segments can't be empty or contain '/'"
+        );
+
+        let mut segments = self.segments.clone();
+        segments.push(name.to_string());
+
+        Self { segments }
+    }
+}
+
+impl Display for NodePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.segments.join("/"))
+    }
+}
+
+impl FromStr for NodePath {
+    type Err = EmptySegment;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self {
+                segments: Vec::new(),
+            });
+        }
+
+        let segments = s.split('/').map(str::to_string).collect::<Vec<_>>();
+
+        if segments.iter().any(String::is_empty) {
+            return Err(EmptySegment);
+        }
+
+        Ok(Self { segments })
+    }
+}
+
+impl From<NodePath> for String {
+    fn from(path: NodePath) -> Self {
+        path.to_string()
+    }
+}
+
+impl TryFrom<String> for NodePath {
+    type Error = EmptySegment;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+fn is_valid_segment(segment: &str) -> bool {
+    !segment.is_empty() && !segment.contains('/')
+}
+
+impl<N: GraphInstanceNode> GraphWorkspace<N> {
+    /// Find the node at `path`, returning the key of the graph it is in along
+    /// with its id. Nodes are found by [name](crate::Graph::get_node_by_name).
+    pub fn resolve_path(&self, path: &NodePath) -> Result<(String, NodeId), ResolvePathError> {
+        let mut segments = path.segments();
+
+        let mut key = segments
+            .next()
+            .ok_or(ResolvePathError::NotANode)?
+            .to_string();
+        let mut graph = self
+            .get(&key)
+            .ok_or_else(|| ResolvePathError::MissingGraph(key.clone()))?;
+
+        let mut node = None;
+        let mut resolved = NodePath::new([key.as_str()]);
+
+        for name in segments {
+            if let Some(node) = node {
+                key = graph
+                    .get_node(node)
+                    .and_then(|node| Some(node.instance_of()?.to_string()))
+                    .ok_or_else(|| ResolvePathError::NotAnInstance(resolved.clone()))?;
+
+                graph = self
+                    .get(&key)
+                    .ok_or_else(|| ResolvePathError::MissingGraph(key.clone()))?;
+            }
+
+            resolved = resolved.join(name);
+            node = Some(
+                graph
+                    .get_node_by_name(name)
+                    .ok_or_else(|| ResolvePathError::MissingNode(resolved.clone()))?,
+            );
+        }
+
+        Ok((key, node.ok_or(ResolvePathError::NotANode)?))
+    }
+
+    /// The path of `nodes[nodes.len() - 1]`, where the first node is in graph
+    /// `key` and every other node is in the graph the node before it is an
+    /// instance of. Returns `None` if a node doesn't exist, isn't named, has
+    /// a name containing `/` or isn't an instance when it should be, or if
+    /// `key` contains `/`.
+    pub fn path_of(&self, key: &str, nodes: &[NodeId]) -> Option<NodePath> {
+        if !is_valid_segment(key) {
+            return None;
+        }
+
+        let mut graph = self.get(key)?;
+        let mut segments = vec![key.to_string()];
+
+        for (index, &node) in nodes.iter().enumerate() {
+            let name = graph.get_node_name(node)?;
+
+            if !is_valid_segment(name) {
+                return None;
+            }
+
+            segments.push(name.to_string());
+
+            if index + 1 < nodes.len() {
+                graph = self.get(graph.get_node(node)?.instance_of()?)?;
+            }
+        }
+
+        Some(NodePath { segments })
+    }
+}
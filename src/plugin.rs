@@ -0,0 +1,108 @@
+use crate::{Node, registry::NodeRegistry};
+
+/// A set of node kinds, adapters and connection validators provided by
+/// another crate, added to a host application's [`NodeRegistry`] with
+/// [`NodeRegistry::add_plugin`]
+pub trait NodePlugin<N: Node> {
+    /// Identifies the plugin, for example in [`NodeRegistry::plugins`]
+    fn name(&self) -> &str;
+
+    fn register(&self, registry: &mut NodeRegistry<N>);
+}
+
+impl<N: Node> NodeRegistry<N> {
+    /// Let `plugin` register everything it provides
+    pub fn add_plugin(&mut self, plugin: &dyn NodePlugin<N>) {
+        plugin.register(self);
+        self.plugins.push(plugin.name().to_string());
+    }
+
+    /// The names of all added plugins, in the order they were added
+    pub fn plugins(&self) -> impl Iterator<Item = &str> + '_ {
+        self.plugins.iter().map(String::as_str)
+    }
+}
+
+/// The symbol [`NodeRegistry::load_plugin_library`] looks for, defined by
+/// [`export_plugin!`](crate::export_plugin)
+#[cfg(feature = "libloading")]
+pub const PLUGIN_ENTRY_POINT: &str = "node_graph_plugin";
+
+#[cfg(feature = "libloading")]
+pub type PluginEntryPoint<N> = fn() -> Box<dyn NodePlugin<N>>;
+
+/// Define the entry point of a plugin library, for
+/// [`NodeRegistry::load_plugin_library`]. Takes the host's node type and an
+/// expression creating the plugin.
+///
+/// ```
+/// # use node_graph::{Node, plugin::NodePlugin, registry::NodeRegistry};
+/// # #[derive(Debug)]
+/// # enum AudioNode {
+/// #     LowPass,
+/// # }
+/// # impl Node for AudioNode {
+/// #     type DataType = ();
+/// #     type DataValue = f32;
+/// # }
+/// # #[derive(Default)]
+/// # struct FiltersPlugin;
+/// # impl NodePlugin<AudioNode> for FiltersPlugin {
+/// #     fn name(&self) -> &str {
+/// #         "filters"
+/// #     }
+/// #     fn register(&self, registry: &mut NodeRegistry<AudioNode>) {
+/// #         registry.register("low_pass", |_| AudioNode::LowPass);
+/// #     }
+/// # }
+/// node_graph::export_plugin!(AudioNode, FiltersPlugin::default());
+/// # fn main() {
+/// #     assert_eq!(node_graph_plugin().name(), "filters");
+/// # }
+/// ```
+#[cfg(feature = "libloading")]
+#[macro_export]
+macro_rules! export_plugin {
+    ($node:ty, $plugin:expr) => {
+        #[unsafe(no_mangle)]
+        pub fn node_graph_plugin() -> ::std::boxed::Box<dyn $crate::plugin::NodePlugin<$node>> {
+            ::std::boxed::Box::new($plugin)
+        }
+    };
+}
+
+#[cfg(feature = "libloading")]
+impl<N: Node> NodeRegistry<N> {
+    /// Load a dynamic library defined with
+    /// [`export_plugin!`](crate::export_plugin) and add its plugin. Returns
+    /// the name of the plugin. The library stays loaded until the registry
+    /// is dropped.
+    ///
+    /// # Safety
+    ///
+    /// The library runs arbitrary code when loaded. It has to be built with
+    /// the same compiler and the same versions of this crate and the crate
+    /// defining `N` as the host, since Rust has no stable ABI. Graphs that
+    /// this registry was [`install`](Self::install)ed into may call code from
+    /// the library, so they must be dropped before the registry.
+    pub unsafe fn load_plugin_library(
+        &mut self,
+        path: impl AsRef<std::ffi::OsStr>,
+    ) -> Result<&str, libloading::Error> {
+        let library = unsafe { libloading::Library::new(path)? };
+
+        let plugin = unsafe {
+            let entry_point = library.get::<PluginEntryPoint<N>>(PLUGIN_ENTRY_POINT.as_bytes())?;
+
+            entry_point()
+        };
+
+        self.add_plugin(plugin.as_ref());
+
+        // The plugin's code is in the library, so it has to go first
+        drop(plugin);
+        self.libraries.push(library);
+
+        Ok(self.plugins.last().expect("Plugin was just added"))
+    }
+}
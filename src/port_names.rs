@@ -0,0 +1,171 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+    sync::Arc,
+};
+
+use crate::{INVALID_STATE, PortList};
+
+/// Port names shared between every port, so ports with the same name on many
+/// nodes all point to one allocation. A name is freed once the last port
+/// using it is gone, see [`release`](Self::release).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NameInterner {
+    names: HashSet<Arc<str>>,
+}
+
+impl NameInterner {
+    pub(crate) fn intern(&mut self, name: &str) -> Arc<str> {
+        if let Some(name) = self.names.get(name) {
+            return name.clone();
+        }
+
+        let name = Arc::<str>::from(name);
+        self.names.insert(name.clone());
+        name
+    }
+
+    /// Give back a name taken from a port that was deleted or renamed,
+    /// forgetting it if no other port uses it anymore
+    pub(crate) fn release(&mut self, name: Arc<str>) {
+        // Held by `name` and the interner only
+        if Arc::strong_count(&name) == 2
+            && self
+                .names
+                .get(&name)
+                .is_some_and(|interned| Arc::ptr_eq(interned, &name))
+        {
+            self.names.remove(&name);
+        }
+    }
+
+    /// Forget every name no port uses anymore, like names that were still
+    /// held by a snapshot when their last port was deleted
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.names.retain(|name| Arc::strong_count(name) > 1);
+        self.names.shrink_to_fit();
+    }
+
+    /// Estimated bytes allocated for the names, including the reference
+    /// counts stored next to them
+    pub(crate) fn memory(&self) -> usize {
+        self.names.capacity() * size_of::<Arc<str>>()
+            + self
+                .names
+                .iter()
+                .map(|name| 2 * size_of::<usize>() + name.len())
+                .sum::<usize>()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.names.clear();
+    }
+}
+
+/// The ports of a node in order, along with an index from their names to
+/// their ids. Dereferences to the list of ports, changes go through methods
+/// that keep the index up to date. Names are shared with the ports
+/// themselves through the graph's [`NameInterner`].
+#[derive(Debug, Clone)]
+pub(crate) struct NamedPorts<K> {
+    ports: PortList<(Arc<str>, K)>,
+    /// The first port with each name
+    index: HashMap<Arc<str>, K>,
+}
+
+impl<K> Default for NamedPorts<K> {
+    fn default() -> Self {
+        Self {
+            ports: PortList::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<K> Deref for NamedPorts<K> {
+    type Target = PortList<(Arc<str>, K)>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.ports
+    }
+}
+
+impl<K: Copy + Eq> NamedPorts<K> {
+    /// Index a list of ports whose names were already interned
+    pub(crate) fn from_list(ports: PortList<(Arc<str>, K)>) -> Self {
+        let mut named = Self {
+            ports,
+            index: HashMap::new(),
+        };
+
+        for (name, id) in named.ports.iter() {
+            named.index.entry(name.clone()).or_insert(*id);
+        }
+
+        named
+    }
+
+    pub(crate) fn by_name(&self, name: &str) -> Option<K> {
+        self.index.get(name).copied()
+    }
+
+    pub(crate) fn push(&mut self, name: Arc<str>, id: K) {
+        self.index.entry(name.clone()).or_insert(id);
+        self.ports.push((name, id));
+    }
+
+    pub(crate) fn remove(&mut self, id: K) -> Option<(Arc<str>, K)> {
+        let index = self.ports.iter().position(|(_, other)| *other == id)?;
+        let (name, id) = self.ports.remove(index);
+
+        self.unindex(&name, id);
+
+        Some((name, id))
+    }
+
+    /// Give the port `id` a new name, returning the old one
+    pub(crate) fn rename(&mut self, id: K, new_name: Arc<str>) -> Arc<str> {
+        let entry = self
+            .ports
+            .iter_mut()
+            .find(|(_, other)| *other == id)
+            .expect(INVALID_STATE);
+
+        let old_name = std::mem::replace(&mut entry.0, new_name.clone());
+
+        self.unindex(&old_name, id);
+        self.index.entry(new_name).or_insert(id);
+
+        old_name
+    }
+
+    /// Move the port at `from` to `to`, shifting the ports in between
+    pub(crate) fn move_port(&mut self, from: usize, to: usize) {
+        let port = self.ports.remove(from);
+        self.ports.insert(to, port);
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.ports.shrink_to_fit();
+        self.index.shrink_to_fit();
+    }
+
+    /// The number of entries the name index has room for
+    pub(crate) fn index_capacity(&self) -> usize {
+        self.index.capacity()
+    }
+
+    /// Remove `name` from the index if it points to `id`, pointing it to the
+    /// next port with that name instead
+    fn unindex(&mut self, name: &str, id: K) {
+        let Some((key, indexed)) = self.index.remove_entry(name) else {
+            return;
+        };
+
+        if indexed != id {
+            self.index.insert(key, indexed);
+        } else if let Some(&(_, next)) = self.ports.iter().find(|(other, _)| **other == *name) {
+            self.index.insert(key, next);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt::Display;
+
+use slotmap::SecondaryMap;
+
+use crate::{Graph, Node, NodeId, analyzer::GraphAnalyzer};
+
+/// A readable listing of a graph, created with [`Graph::pretty`]
+pub struct PrettyGraph<'a, N: Node, F> {
+    graph: &'a Graph<N>,
+    fmt_node: F,
+}
+
+impl<N: Node> Graph<N> {
+    /// Display every node with its ports, defaults and connections, with
+    /// `fmt_node` describing the nodes themselves. Nodes are listed so that
+    /// dependencies come first and are referred to by their position:
+    ///
+    /// ```text
+    /// [0] Constant(2.0)
+    ///     out value: Float -> [1].a
+    /// [1] "result" Add
+    ///     in  a: Float = 0.0 <- [0].value
+    ///     in  b: Float = 1.0
+    ///     out sum: Float
+    /// ```
+    pub fn pretty<F: Fn(&N) -> String>(&self, fmt_node: F) -> PrettyGraph<'_, N, F> {
+        PrettyGraph {
+            graph: self,
+            fmt_node,
+        }
+    }
+}
+
+impl<N: Node, F: Fn(&N) -> String> Display for PrettyGraph<'_, N, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let graph = self.graph;
+
+        // Dependencies first, then nodes in or behind a cycle
+        let mut order = GraphAnalyzer::new(graph)
+            .layers()
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let mut labels = SecondaryMap::<NodeId, usize>::with_capacity(graph.node_count());
+
+        for (label, &node) in order.iter().enumerate() {
+            labels.insert(node, label);
+        }
+
+        for node in graph.node_data.keys() {
+            if !labels.contains_key(node) {
+                labels.insert(node, order.len());
+                order.push(node);
+            }
+        }
+
+        for (label, &node) in order.iter().enumerate() {
+            let value = (self.fmt_node)(&graph.nodes[node].read());
+
+            match graph.get_node_name(node) {
+                Some(name) => writeln!(f, "[{label}] {name:?} {value}")?,
+                None => writeln!(f, "[{label}] {value}")?,
+            }
+
+            for (name, port) in graph.node_data[node].inputs.iter() {
+                let info = &graph.input_ports[*port];
+
+                write!(f, "    in  {name}: {:?}", info.ty)?;
+
+                if let Some(default) = &info.default {
+                    write!(f, " = {default:?}")?;
+                }
+
+                for (index, start) in graph.get_incoming_connections(*port).enumerate() {
+                    let start_info = &graph.output_ports[start];
+                    let separator = if index == 0 { " <- " } else { ", " };
+
+                    write!(
+                        f,
+                        "{separator}[{}].{}",
+                        labels[start_info.node], start_info.name
+                    )?;
+                }
+
+                writeln!(f)?;
+            }
+
+            for (name, port) in graph.node_data[node].outputs.iter() {
+                write!(f, "    out {name}: {:?}", graph.output_ports[*port].ty)?;
+
+                for (index, end) in graph.get_outgoing_connections(*port).enumerate() {
+                    let end_info = &graph.input_ports[end];
+                    let separator = if index == 0 { " -> " } else { ", " };
+
+                    write!(
+                        f,
+                        "{separator}[{}].{}",
+                        labels[end_info.node], end_info.name
+                    )?;
+                }
+
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<N: Node + Display> Display for Graph<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.pretty(N::to_string).fmt(f)
+    }
+}
@@ -67,6 +67,12 @@ impl OutputPortReference for OutputPortId {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct NodeInputIndexReference(NodeId, usize);
 
+impl NodeInputIndexReference {
+    pub const fn new(node: NodeId, index: usize) -> Self {
+        Self(node, index)
+    }
+}
+
 impl InputPortReference for NodeInputIndexReference {
     fn resolve<N: Node>(&self, graph: &Graph<N>) -> Option<InputPortId> {
         graph.get_input_port_at(self.0, self.1)
@@ -76,6 +82,12 @@ impl InputPortReference for NodeInputIndexReference {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct NodeOutputIndexReference(NodeId, usize);
 
+impl NodeOutputIndexReference {
+    pub const fn new(node: NodeId, index: usize) -> Self {
+        Self(node, index)
+    }
+}
+
 impl OutputPortReference for NodeOutputIndexReference {
     fn resolve<N: Node>(&self, graph: &Graph<N>) -> Option<OutputPortId> {
         graph.get_output_port_at(self.0, self.1)
@@ -0,0 +1,241 @@
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc};
+
+use crate::{Graph, InitialPorts, InputPortId, Node, NodeId, OutputPortId, metadata::Metadata};
+
+/// Arguments passed to a node constructor in a [`NodeRegistry`], like values
+/// read from a save file or typed into an editor
+pub type RegistryArgs = Metadata;
+
+type Constructor<N> = Box<dyn Fn(&RegistryArgs) -> N + Send + Sync>;
+type SharedAdapterFactory<N> = Arc<dyn Fn() -> N + Send + Sync>;
+type SharedValidator<N> =
+    Arc<dyn Fn(&Graph<N>, OutputPortId, InputPortId) -> Result<(), String> + Send + Sync>;
+
+/// The ports a node kind is created with, for showing them before the node
+/// exists
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortSignature<T> {
+    pub inputs: Vec<(String, T)>,
+    pub outputs: Vec<(String, T)>,
+}
+
+impl<T> Default for PortSignature<T> {
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+}
+
+impl<N: Node> From<InitialPorts<N>> for PortSignature<N::DataType> {
+    fn from(ports: InitialPorts<N>) -> Self {
+        Self {
+            inputs: ports
+                .inputs
+                .into_iter()
+                .map(|(name, ty, _)| (name.to_string(), ty))
+                .collect(),
+            outputs: ports
+                .outputs
+                .into_iter()
+                .map(|(name, ty)| (name.to_string(), ty))
+                .collect(),
+        }
+    }
+}
+
+/// Describes a node kind in a [`NodeRegistry`]
+#[derive(Debug, Clone)]
+pub struct NodeKindInfo<T> {
+    pub name: String,
+    /// Used to group node kinds, for example in a menu. Empty for no category.
+    pub category: String,
+    pub description: String,
+    pub signature: Option<PortSignature<T>>,
+}
+
+struct NodeKind<N: Node> {
+    info: NodeKindInfo<N::DataType>,
+    constructor: Constructor<N>,
+}
+
+/// Node kinds by name, so nodes can be created from a string, for example
+/// when loading a file, filling a palette in an editor or running a script
+pub struct NodeRegistry<N: Node> {
+    kinds: BTreeMap<String, NodeKind<N>>,
+    adapters: Vec<(N::DataType, N::DataType, SharedAdapterFactory<N>)>,
+    validators: Vec<SharedValidator<N>>,
+    pub(crate) plugins: Vec<String>,
+    // Declared last so it is dropped after everything it could contain code of
+    #[cfg(feature = "libloading")]
+    pub(crate) libraries: Vec<libloading::Library>,
+}
+
+impl<N: Node> NodeRegistry<N> {
+    pub fn new() -> Self {
+        Self {
+            kinds: BTreeMap::new(),
+            adapters: Vec::new(),
+            validators: Vec::new(),
+            plugins: Vec::new(),
+            #[cfg(feature = "libloading")]
+            libraries: Vec::new(),
+        }
+    }
+
+    /// Add a node kind, replacing any earlier kind with the same name.
+    /// Returns its info, so a category, description and signature can be
+    /// filled in.
+    pub fn register(
+        &mut self,
+        name: &str,
+        constructor: impl Fn(&RegistryArgs) -> N + Send + Sync + 'static,
+    ) -> &mut NodeKindInfo<N::DataType> {
+        let kind = NodeKind {
+            info: NodeKindInfo {
+                name: name.to_string(),
+                category: String::new(),
+                description: String::new(),
+                signature: None,
+            },
+            constructor: Box::new(constructor),
+        };
+
+        self.kinds.insert(name.to_string(), kind);
+
+        &mut self
+            .kinds
+            .get_mut(name)
+            .expect("Node kind was just added")
+            .info
+    }
+
+    /// Remove a node kind, returning its info
+    pub fn unregister(&mut self, name: &str) -> Option<NodeKindInfo<N::DataType>> {
+        Some(self.kinds.remove(name)?.info)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.kinds.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NodeKindInfo<N::DataType>> {
+        Some(&self.kinds.get(name)?.info)
+    }
+
+    /// All node kinds, sorted by name
+    pub fn kinds(&self) -> impl Iterator<Item = &NodeKindInfo<N::DataType>> + '_ {
+        self.kinds.values().map(|kind| &kind.info)
+    }
+
+    /// All node kinds in `category`, sorted by name
+    pub fn kinds_in<'a>(
+        &'a self,
+        category: &'a str,
+    ) -> impl Iterator<Item = &'a NodeKindInfo<N::DataType>> + 'a {
+        self.kinds().filter(move |info| info.category == category)
+    }
+
+    /// The categories of all node kinds, sorted and without duplicates
+    pub fn categories(&self) -> Vec<&str> {
+        let mut categories = self
+            .kinds()
+            .map(|info| info.category.as_str())
+            .collect::<Vec<_>>();
+
+        categories.sort_unstable();
+        categories.dedup();
+        categories
+    }
+
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    /// Construct a node of the kind called `name`, or `None` if there is no
+    /// such kind
+    pub fn create(&self, name: &str, args: &RegistryArgs) -> Option<N> {
+        Some((self.kinds.get(name)?.constructor)(args))
+    }
+
+    /// Construct a node of the kind called `name` and add it to `graph`
+    pub fn create_in(
+        &self,
+        graph: &mut Graph<N>,
+        name: &str,
+        args: &RegistryArgs,
+    ) -> Option<NodeId> {
+        Some(graph.create_node(self.create(name, args)?))
+    }
+
+    /// Add an adapter that [`install`](Self::install) copies into graphs, see
+    /// [`AdapterRegistry::register`](crate::adapter::AdapterRegistry::register)
+    pub fn register_adapter(
+        &mut self,
+        from: N::DataType,
+        to: N::DataType,
+        factory: impl Fn() -> N + Send + Sync + 'static,
+    ) {
+        self.adapters.retain(|(f, t, _)| *f != from || *t != to);
+        self.adapters.push((from, to, Arc::new(factory)));
+    }
+
+    /// Add a connection validator that [`install`](Self::install) adds to
+    /// graphs, see [`Graph::add_connection_validator`]
+    pub fn add_connection_validator(
+        &mut self,
+        validator: impl Fn(&Graph<N>, OutputPortId, InputPortId) -> Result<(), String>
+        + Send
+        + Sync
+        + 'static,
+    ) {
+        self.validators.push(Arc::new(validator));
+    }
+
+    /// Give `graph` the adapters and connection validators of this registry.
+    /// Adapters replace ones the graph already has for the same types.
+    pub fn install(&self, graph: &mut Graph<N>) {
+        for (from, to, factory) in self.adapters.iter() {
+            let factory = factory.clone();
+
+            graph.adapters_mut().register(*from, *to, move || factory());
+        }
+
+        for validator in self.validators.iter() {
+            let validator = validator.clone();
+
+            graph.add_connection_validator(move |graph, start_port, end_port| {
+                validator(graph, start_port, end_port)
+            });
+        }
+    }
+}
+
+impl<N: Node> Default for NodeRegistry<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Node> Debug for NodeRegistry<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeRegistry")
+            .field("kinds", &self.kinds().collect::<Vec<_>>())
+            .field(
+                "adapters",
+                &self
+                    .adapters
+                    .iter()
+                    .map(|(from, to, _)| (from, to))
+                    .collect::<Vec<_>>(),
+            )
+            .field("validators", &self.validators.len())
+            .field("plugins", &self.plugins)
+            .finish()
+    }
+}
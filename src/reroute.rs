@@ -0,0 +1,123 @@
+use itertools::Itertools;
+
+use crate::{Graph, INVALID_STATE, Node, NodeId, OutputPortId, reference::InputPortReference};
+
+/// A node type that can contain reroutes, see [`Graph::create_reroute`]
+pub trait RerouteNode: Node {
+    /// The value of a reroute node. It should have no initial ports.
+    fn reroute() -> Self;
+}
+
+impl<N: RerouteNode> Graph<N> {
+    /// Create a reroute, a node with an input `in` and an output `out` of type
+    /// `ty` that only exists to tidy up wiring in editors. Walkers never
+    /// evaluate it: nodes connected to its output read whatever is connected
+    /// to its input, as if they were connected directly. A reroute with a
+    /// disconnected input passes nothing, like a missing connection.
+    pub fn create_reroute(&mut self, ty: N::DataType) -> NodeId {
+        let node = self.create_node(N::reroute());
+        self.node_data[node].reroute = true;
+
+        let input = self.create_input_port_inner(node, "in", ty, None);
+        self.input_ports[input].max_incoming = Some(1);
+
+        self.create_output_port(node, "out", ty);
+
+        node
+    }
+
+    /// Remove all reroutes, connecting what was connected to their inputs
+    /// directly to what was connected to their outputs. Connections keep the
+    /// metadata of the connection from the reroute. Returns the number of
+    /// removed reroutes.
+    ///
+    /// Panics if a reroute passes between ports that can't be connected
+    /// directly, like ports whose types only convert through the reroute's
+    /// type.
+    pub fn flatten_reroutes(&mut self) -> usize {
+        let reroutes = self.reroutes().collect_vec();
+
+        for &node in reroutes.iter() {
+            let data = &self.node_data[node];
+
+            let source = data
+                .inputs
+                .iter()
+                .next()
+                .and_then(|&(_, input)| self.get_incoming_connections(input).next());
+
+            let targets = data
+                .outputs
+                .iter()
+                .flat_map(|&(_, output)| self.output_ports[output].outgoing_connections.iter())
+                .map(|&id| {
+                    let connection = self.connections.get(id).expect(INVALID_STATE);
+
+                    (connection.end_port, connection.metadata.clone())
+                })
+                .collect_vec();
+
+            self.take_node(node).expect(INVALID_STATE);
+
+            let Some(source) = source else {
+                continue;
+            };
+
+            for (target, metadata) in targets {
+                let connection = self.connect(source, target);
+                self.connections[connection].metadata = metadata;
+            }
+        }
+
+        reroutes.len()
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Whether `node` was created with [`create_reroute`](Self::create_reroute)
+    pub fn is_reroute(&self, node: NodeId) -> bool {
+        self.node_data
+            .get(node)
+            .expect("Node does not exist")
+            .reroute
+    }
+
+    pub fn reroutes(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.node_data
+            .iter()
+            .filter_map(|(id, data)| data.reroute.then_some(id))
+    }
+
+    /// The output port whose value `port` passes on: `port` itself, or the
+    /// port connected to the input of its reroute, following chains of
+    /// reroutes. Returns `None` if a reroute on the way is disconnected.
+    pub fn resolve_reroute(&self, mut port: OutputPortId) -> Option<OutputPortId> {
+        loop {
+            let node = self
+                .output_ports
+                .get(port)
+                .expect("Output port does not exist")
+                .node;
+
+            let data = &self.node_data[node];
+
+            if !data.reroute {
+                return Some(port);
+            }
+
+            let &(_, input) = data.inputs.iter().next()?;
+            port = self.get_incoming_connections(input).next()?;
+        }
+    }
+
+    /// Like [`get_incoming_connections`](Self::get_incoming_connections), but
+    /// with [reroutes resolved](Self::resolve_reroute). These are the ports
+    /// whose values an input port reads during a walk.
+    pub fn get_sources(
+        &self,
+        port: impl InputPortReference,
+    ) -> impl Iterator<Item = OutputPortId> + '_ {
+        self.get_incoming_connections(port)
+            .filter_map(|port| self.resolve_reroute(port))
+    }
+}
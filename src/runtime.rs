@@ -0,0 +1,172 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::{
+    Graph, Node, NodeId,
+    async_walker::{AsyncGraphWalker, AsyncNodeContext, NodeOutputs},
+    walker::OutputCache,
+};
+
+type NodeFuture<V> = Pin<Box<dyn Future<Output = NodeOutputs<V>> + Send>>;
+
+/// Evaluates a single node for a [`GraphRuntime`], like the callback passed to
+/// [`AsyncGraphWalker::walk`]
+pub type Evaluator<N> = Arc<
+    dyn Fn(&N, AsyncNodeContext<<N as Node>::DataValue>) -> NodeFuture<<N as Node>::DataValue>
+        + Send
+        + Sync,
+>;
+
+type Mutation<N> = Box<dyn FnOnce(&mut Graph<N>) + Send>;
+
+enum Command<N: Node> {
+    Mutate(Mutation<N>),
+    Evaluate {
+        exit_nodes: Option<Vec<NodeId>>,
+        reply: oneshot::Sender<Arc<OutputCache<N::DataValue>>>,
+    },
+}
+
+/// Sent to [subscribers](GraphRuntime::subscribe) after every evaluation
+#[derive(Debug)]
+pub struct OutputUpdate<V> {
+    /// The exit nodes the evaluation was for, `None` for the whole graph
+    pub exit_nodes: Option<Vec<NodeId>>,
+    pub outputs: Arc<OutputCache<V>>,
+}
+
+impl<V> Clone for OutputUpdate<V> {
+    fn clone(&self) -> Self {
+        Self {
+            exit_nodes: self.exit_nodes.clone(),
+            outputs: self.outputs.clone(),
+        }
+    }
+}
+
+/// A graph owned by a tokio task, which handles mutations and evaluations in
+/// the order they are sent. Handles are cheap to clone, and the task stops
+/// once all of them are dropped.
+pub struct GraphRuntime<N: Node> {
+    commands: mpsc::UnboundedSender<Command<N>>,
+    updates: broadcast::Sender<OutputUpdate<N::DataValue>>,
+}
+
+impl<N: Node> Clone for GraphRuntime<N> {
+    fn clone(&self) -> Self {
+        Self {
+            commands: self.commands.clone(),
+            updates: self.updates.clone(),
+        }
+    }
+}
+
+impl<N: Node> std::fmt::Debug for GraphRuntime<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GraphRuntime")
+            .field("subscribers", &self.updates.receiver_count())
+            .finish()
+    }
+}
+
+impl<N: Node + Send + Sync> GraphRuntime<N>
+where
+    N::DataValue: Send + Sync,
+    N::DataType: Send + Sync,
+    // Not the case with the `cell` feature
+    Graph<N>: Sync,
+{
+    /// Move `graph` into a new task on the current tokio runtime. Nodes are
+    /// evaluated with `evaluator`, up to `concurrency_limit` at once.
+    pub fn spawn(graph: Graph<N>, evaluator: Evaluator<N>, concurrency_limit: usize) -> Self {
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let (updates, _) = broadcast::channel(16);
+
+        tokio::spawn(Self::run(
+            graph,
+            evaluator,
+            concurrency_limit,
+            receiver,
+            updates.clone(),
+        ));
+
+        Self { commands, updates }
+    }
+
+    async fn run(
+        mut graph: Graph<N>,
+        evaluator: Evaluator<N>,
+        concurrency_limit: usize,
+        mut commands: mpsc::UnboundedReceiver<Command<N>>,
+        updates: broadcast::Sender<OutputUpdate<N::DataValue>>,
+    ) {
+        while let Some(command) = commands.recv().await {
+            match command {
+                Command::Mutate(mutation) => mutation(&mut graph),
+                Command::Evaluate { exit_nodes, reply } => {
+                    let mut walker = AsyncGraphWalker::new(&graph, exit_nodes.as_deref());
+                    walker.set_concurrency_limit(concurrency_limit);
+                    walker.walk(|node, context| evaluator(node, context)).await;
+
+                    let outputs = Arc::new(walker.release_cache());
+
+                    // Nobody listening is fine
+                    let _ = updates.send(OutputUpdate {
+                        exit_nodes,
+                        outputs: outputs.clone(),
+                    });
+                    let _ = reply.send(outputs);
+                }
+            }
+        }
+    }
+}
+
+impl<N: Node> GraphRuntime<N> {
+    /// Change the graph, returning whatever `mutation` returns
+    pub async fn mutate<R: Send + 'static>(
+        &self,
+        mutation: impl FnOnce(&mut Graph<N>) -> R + Send + 'static,
+    ) -> R {
+        let (reply, result) = oneshot::channel();
+
+        self.send(Command::Mutate(Box::new(move |graph| {
+            let _ = reply.send(mutation(graph));
+        })));
+
+        result.await.expect("Graph runtime stopped")
+    }
+
+    /// Read from the graph, returning whatever `query` returns
+    pub async fn query<R: Send + 'static>(
+        &self,
+        query: impl FnOnce(&Graph<N>) -> R + Send + 'static,
+    ) -> R {
+        self.mutate(move |graph| query(graph)).await
+    }
+
+    /// Evaluate the nodes needed for `exit_nodes`, or the whole graph when
+    /// `None`. Subscribers receive the outputs as well.
+    pub async fn evaluate(
+        &self,
+        exit_nodes: Option<Vec<NodeId>>,
+    ) -> Arc<OutputCache<N::DataValue>> {
+        let (reply, result) = oneshot::channel();
+
+        self.send(Command::Evaluate { exit_nodes, reply });
+
+        result.await.expect("Graph runtime stopped")
+    }
+
+    /// Receive the outputs of every evaluation from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<OutputUpdate<N::DataValue>> {
+        self.updates.subscribe()
+    }
+
+    fn send(&self, command: Command<N>) {
+        if self.commands.send(command).is_err() {
+            panic!("Graph runtime stopped");
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use slotmap::SecondaryMap;
+
+use crate::{INVALID_STATE, Node, NodeId, analyzer::GraphAnalyzer};
+
+/// An evaluation order for a pool of threads, see
+/// [`GraphAnalyzer::schedule`]. Stages run one after the other, the batches
+/// within a stage run in parallel, and the nodes within a batch run in order
+/// on a single thread.
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    pub stages: Vec<Stage>,
+}
+
+impl Schedule {
+    /// The estimated time to run the schedule, the sum over all stages of
+    /// their most expensive batch
+    pub fn cost(&self) -> f64 {
+        self.stages.iter().map(Stage::cost).sum()
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.stages
+            .iter()
+            .flat_map(|stage| stage.batches.iter())
+            .flat_map(|batch| batch.nodes.iter().copied())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Stage {
+    pub batches: Vec<Batch>,
+}
+
+impl Stage {
+    pub fn cost(&self) -> f64 {
+        self.batches
+            .iter()
+            .map(|batch| batch.cost)
+            .fold(0.0, f64::max)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    pub nodes: Vec<NodeId>,
+    /// The sum of the costs of `nodes`
+    pub cost: f64,
+}
+
+impl<'a, N: Node> GraphAnalyzer<'a, N> {
+    /// Split the graph into stages of batches for `threads` threads, using
+    /// [`Node::cost_hint`] to balance them
+    pub fn schedule(&self, threads: usize) -> Schedule {
+        self.schedule_weighted(threads, |_, node| node.cost_hint())
+    }
+
+    /// Like [`schedule`](Self::schedule), with the cost of each node given by
+    /// `cost`.
+    ///
+    /// Instead of a barrier after every [layer](Self::layers), a node is
+    /// appended to the batch its dependencies are in when they are all in the
+    /// same one, unless that makes an earlier stage take longer. This way
+    /// chains of nodes run without synchronizing. Other nodes are placed in
+    /// the stage after their last dependency, in the cheapest batch.
+    ///
+    /// Nodes that aren't [pure](Node::is_pure) never run in parallel with
+    /// each other. Nodes that are part of, or depend on, a cycle are not
+    /// included, and neither are [reroutes](crate::Graph::create_reroute).
+    pub fn schedule_weighted(&self, threads: usize, cost: impl Fn(NodeId, &N) -> f64) -> Schedule {
+        assert!(threads > 0, "Can't schedule for 0 threads");
+
+        let graph = self.graph;
+
+        let mut schedule = Schedule::default();
+        // The stage and batch every scheduled node is in. Reroutes are where
+        // their dependency is, or nowhere if they are disconnected.
+        let mut placement = SecondaryMap::<NodeId, Option<(usize, usize)>>::new();
+        let mut last_impure = None;
+
+        for layer in self.layers() {
+            let mut layer = layer
+                .into_iter()
+                .filter(|&id| {
+                    if !graph.node_data[id].reroute {
+                        return true;
+                    }
+
+                    let dependency = graph
+                        .get_direct_dependencies(id)
+                        .find_map(|dependency| placement[dependency]);
+
+                    placement.insert(id, dependency);
+
+                    false
+                })
+                .map(|id| {
+                    let node = graph.get_node(id).expect(INVALID_STATE);
+                    (id, cost(id, &node), node.is_pure())
+                })
+                .collect::<Vec<_>>();
+
+            // Expensive nodes first balances batches better, impure nodes are
+            // kept in path order by depending on the previous one
+            layer.sort_by(|(_, a, _), (_, b, _)| b.total_cmp(a));
+
+            for (id, cost, pure) in layer {
+                let mut dependencies = graph
+                    .get_direct_dependencies(id)
+                    .filter_map(|dependency| placement[dependency])
+                    .collect::<Vec<_>>();
+
+                if !pure {
+                    dependencies.extend(last_impure.replace(id).and_then(|node| placement[node]));
+                }
+
+                let first = dependencies.first().copied();
+                let single_batch =
+                    first.filter(|&first| dependencies.iter().all(|&other| other == first));
+
+                if let Some((stage, batch)) = single_batch {
+                    let is_last = stage + 1 == schedule.stages.len();
+                    let stage_cost = schedule.stages[stage].cost();
+                    let batch_data = &mut schedule.stages[stage].batches[batch];
+
+                    if is_last || batch_data.cost + cost <= stage_cost {
+                        batch_data.nodes.push(id);
+                        batch_data.cost += cost;
+                        placement.insert(id, Some((stage, batch)));
+                        continue;
+                    }
+                }
+
+                let stage = dependencies
+                    .iter()
+                    .map(|&(stage, _)| stage + 1)
+                    .max()
+                    .unwrap_or(0);
+
+                if stage == schedule.stages.len() {
+                    schedule.stages.push(Stage::default());
+                }
+
+                let batches = &mut schedule.stages[stage].batches;
+
+                let batch = if batches.len() < threads {
+                    batches.push(Batch::default());
+                    batches.len() - 1
+                } else {
+                    (0..batches.len())
+                        .min_by(|&a, &b| batches[a].cost.total_cmp(&batches[b].cost))
+                        .expect(INVALID_STATE)
+                };
+
+                batches[batch].nodes.push(id);
+                batches[batch].cost += cost;
+                placement.insert(id, Some((stage, batch)));
+            }
+        }
+
+        schedule
+    }
+}
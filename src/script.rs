@@ -0,0 +1,294 @@
+//! Nodes running [rhai](https://rhai.rs) scripts, so graphs can be extended
+//! without recompiling. A script declares its ports in a header of comments,
+//! reads inputs with `get` and writes outputs with `set`:
+//!
+//! ```text
+//! // in a: float = 0.0
+//! // in b: float = 1.0
+//! // out sum: float
+//!
+//! set("sum", get("a") + get("b"));
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+    sync::{Arc, OnceLock},
+};
+
+use parking_lot::Mutex;
+use rhai::{
+    AST, Dynamic, Engine, EvalAltResult, ParseError,
+    packages::{Package, StandardPackage},
+};
+
+use crate::{Graph, Node, NodeId, walker::GraphWalkContext};
+
+/// A node type that can contain [`ScriptNode`]s, describing how its data
+/// types are named in script headers and how its values are passed to
+/// scripts
+pub trait ScriptHost: Node {
+    /// The data type called `name` in a script header
+    fn script_type(name: &str) -> Option<Self::DataType>;
+
+    fn to_dynamic(value: &Self::DataValue) -> Dynamic;
+
+    /// Convert a value from a script into a value of type `ty`, or `None` if
+    /// it doesn't fit
+    fn from_dynamic(value: Dynamic, ty: Self::DataType) -> Option<Self::DataValue>;
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    /// A line of the header could not be read, numbered from 1
+    Header {
+        line: usize,
+        message: String,
+    },
+    Parse(ParseError),
+    Runtime(Box<EvalAltResult>),
+    /// The script set an output it didn't declare, or to a value of the wrong
+    /// type
+    InvalidOutput {
+        name: String,
+        value: String,
+    },
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Header { line, message } => write!(f, "Script header line {line}: {message}"),
+            Self::Parse(error) => write!(f, "Script does not compile: {error}"),
+            Self::Runtime(error) => write!(f, "Script failed: {error}"),
+            Self::InvalidOutput { name, value } => {
+                write!(f, "Script set output {name:?} to invalid value {value}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+impl From<ParseError> for ScriptError {
+    fn from(error: ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<Box<EvalAltResult>> for ScriptError {
+    fn from(error: Box<EvalAltResult>) -> Self {
+        Self::Runtime(error)
+    }
+}
+
+/// A compiled script along with the ports declared in its header. Wrap it
+/// in a variant of the node type, which should have no initial ports, and
+/// add it with [`Graph::create_script_node`].
+pub struct ScriptNode<N: ScriptHost> {
+    source: String,
+    inputs: Vec<(String, N::DataType, N::DataValue)>,
+    outputs: Vec<(String, N::DataType)>,
+    ast: AST,
+}
+
+impl<N: ScriptHost> ScriptNode<N> {
+    /// Read the header of `source` and compile it. Header lines look like
+    /// `// in name: type = default` and `// out name: type`, where the
+    /// default is a script expression. Other comments are ignored, and the
+    /// header ends at the first line that isn't a comment.
+    pub fn new(source: impl Into<String>) -> Result<Self, ScriptError> {
+        let source = source.into();
+        let engine = engine();
+
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let Some(comment) = line.strip_prefix("//") else {
+                break;
+            };
+
+            let header_error = |message: String| ScriptError::Header {
+                line: index + 1,
+                message,
+            };
+
+            let comment = comment.trim();
+
+            if let Some(port) = comment.strip_prefix("in ") {
+                let (port, default) = port
+                    .split_once('=')
+                    .ok_or_else(|| header_error("Input has no default value".to_string()))?;
+
+                let (name, ty) = parse_port::<N>(port).map_err(header_error)?;
+
+                let default = engine
+                    .eval_expression::<Dynamic>(default.trim())
+                    .map_err(|error| header_error(format!("Invalid default value: {error}")))?;
+
+                let default = N::from_dynamic(default.clone(), ty).ok_or_else(|| {
+                    header_error(format!("Default value {default} does not fit type {ty:?}"))
+                })?;
+
+                inputs.push((name, ty, default));
+            } else if let Some(port) = comment.strip_prefix("out ") {
+                outputs.push(parse_port::<N>(port).map_err(header_error)?);
+            }
+        }
+
+        let ast = engine.compile(&source)?;
+
+        Ok(Self {
+            source,
+            inputs,
+            outputs,
+            ast,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn inputs(&self) -> &[(String, N::DataType, N::DataValue)] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[(String, N::DataType)] {
+        &self.outputs
+    }
+
+    /// Run the script, reading inputs from and writing outputs to `context`.
+    /// Outputs are only written if the whole script succeeds.
+    pub fn evaluate(&self, context: &mut GraphWalkContext<'_, '_, N>) -> Result<(), ScriptError> {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|(name, _, _)| (name.clone(), N::to_dynamic(&context.get(name.as_str()))))
+            .collect::<BTreeMap<_, _>>();
+
+        let written = Arc::new(Mutex::new(Vec::<(String, Dynamic)>::new()));
+
+        let mut engine = engine();
+
+        engine.register_fn(
+            "get",
+            move |name: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+                inputs
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("Input {name:?} does not exist").into())
+            },
+        );
+
+        engine.register_fn("set", {
+            let written = written.clone();
+
+            move |name: &str, value: Dynamic| written.lock().push((name.to_string(), value))
+        });
+
+        engine.run_ast(&self.ast)?;
+
+        let mut values = Vec::new();
+
+        for (name, value) in written.lock().drain(..) {
+            let converted = self
+                .outputs
+                .iter()
+                .find(|(output, _)| *output == name)
+                .and_then(|(_, ty)| N::from_dynamic(value.clone(), *ty));
+
+            match converted {
+                Some(converted) => values.push((name, converted)),
+                None => {
+                    return Err(ScriptError::InvalidOutput {
+                        name,
+                        value: value.to_string(),
+                    });
+                }
+            }
+        }
+
+        for (name, value) in values {
+            context.set(name.as_str(), value);
+        }
+
+        Ok(())
+    }
+}
+
+impl<N: ScriptHost> Clone for ScriptNode<N> {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source.clone(),
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+            ast: self.ast.clone(),
+        }
+    }
+}
+
+impl<N: ScriptHost> Debug for ScriptNode<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptNode")
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Parse `name: type`
+fn parse_port<N: ScriptHost>(port: &str) -> Result<(String, N::DataType), String> {
+    let (name, ty) = port
+        .split_once(':')
+        .ok_or_else(|| "Expected `name: type`".to_string())?;
+
+    let (name, ty) = (name.trim(), ty.trim());
+
+    if name.is_empty() {
+        return Err("Port has no name".to_string());
+    }
+
+    let ty = N::script_type(ty).ok_or_else(|| format!("Unknown type {ty:?}"))?;
+
+    Ok((name.to_string(), ty))
+}
+
+/// An engine with the standard library, which is only built once
+fn engine() -> Engine {
+    static PACKAGE: OnceLock<StandardPackage> = OnceLock::new();
+
+    let mut engine = Engine::new_raw();
+    engine.register_global_module(PACKAGE.get_or_init(StandardPackage::new).as_shared_module());
+    engine
+}
+
+impl<N: ScriptHost> Graph<N> {
+    /// Create a node wrapping `script`, with the ports declared in its header
+    pub fn create_script_node(&mut self, script: ScriptNode<N>) -> NodeId
+    where
+        ScriptNode<N>: Into<N>,
+    {
+        let inputs = script.inputs.clone();
+        let outputs = script.outputs.clone();
+
+        let node = self.create_node(script);
+
+        for (name, ty, default) in inputs {
+            self.create_input_port(node, &name, ty, default);
+        }
+
+        for (name, ty) in outputs {
+            self.create_output_port(node, &name, ty);
+        }
+
+        node
+    }
+}
@@ -0,0 +1,134 @@
+use slotmap::{SecondaryMap, SlotMap};
+
+use crate::{
+    Connection, ConnectionId, Graph, InputPortId, Node, NodeData, NodeId, OutputPortId, Port,
+    cell::NodeCell,
+    group::{Group, GroupId},
+    metadata::Metadata,
+    naming::NodeNames,
+    observer::OutputObservers,
+    parameter::Parameters,
+    port_names::NameInterner,
+    stable_id::StableIdMap,
+    variadic::VariadicInput,
+};
+
+/// A copy of the structure of a [`Graph`] at a point in time, created with
+/// [`Graph::snapshot`] and applied again with [`Graph::restore`].
+#[derive(Debug, Clone)]
+pub struct GraphSnapshot<N: Node + Clone> {
+    structure: GraphStructure<N>,
+    nodes: SecondaryMap<NodeId, N>,
+}
+
+/// Everything a snapshot holds except for the node values, which can be
+/// copied for any node type. Batches use it to roll back.
+#[derive(Debug, Clone)]
+pub(crate) struct GraphStructure<N: Node> {
+    node_data: SlotMap<NodeId, NodeData>,
+    connections: SlotMap<ConnectionId, Connection>,
+    input_ports: SlotMap<InputPortId, Port<N>>,
+    output_ports: SlotMap<OutputPortId, Port<N>>,
+    stable_ids: Option<StableIdMap>,
+    variadic_inputs: SecondaryMap<NodeId, Vec<VariadicInput<N>>>,
+    node_names: NodeNames,
+    parameters: Parameters,
+    groups: SlotMap<GroupId, Group>,
+    node_groups: SecondaryMap<NodeId, GroupId>,
+    metadata: Metadata,
+    observers: OutputObservers<N>,
+    port_names: NameInterner,
+    /// Changes recorded after this are dropped on restore
+    mutation_log_len: usize,
+}
+
+impl<N: Node + Clone> Graph<N> {
+    /// Copy the current nodes, ports and connections so they can be restored
+    /// later, along with the graph's [metadata](Self::metadata) and the
+    /// [observers](Self::watch) of the ports. Ids stay valid across a
+    /// snapshot/restore round trip.
+    pub fn snapshot(&self) -> GraphSnapshot<N> {
+        GraphSnapshot {
+            structure: self.snapshot_structure(),
+            nodes: self
+                .nodes
+                .iter()
+                .map(|(id, node)| (id, node.read().clone()))
+                .collect(),
+        }
+    }
+
+    /// Replace the structure of this graph with the one stored in `snapshot`.
+    ///
+    /// Nodes are not notified. Ids of anything created after the snapshot was
+    /// taken become invalid, and may be handed out again by later insertions.
+    /// Changes [recorded](Self::enable_mutation_log) since then are dropped
+    /// from the log.
+    pub fn restore(&mut self, snapshot: GraphSnapshot<N>) {
+        self.restore_structure(snapshot.structure);
+        self.nodes = snapshot
+            .nodes
+            .into_iter()
+            .map(|(id, node)| (id, NodeCell::new(node)))
+            .collect();
+        self.analysis.invalidate();
+        self.after_mutation();
+    }
+}
+
+impl<N: Node> Graph<N> {
+    pub(crate) fn snapshot_structure(&self) -> GraphStructure<N> {
+        GraphStructure {
+            node_data: self.node_data.clone(),
+            connections: self.connections.clone(),
+            input_ports: self.input_ports.clone(),
+            output_ports: self.output_ports.clone(),
+            stable_ids: self.stable_ids.clone(),
+            variadic_inputs: self.variadic_inputs.clone(),
+            node_names: self.node_names.clone(),
+            parameters: self.parameters.clone(),
+            groups: self.groups.clone(),
+            node_groups: self.node_groups.clone(),
+            metadata: self.metadata.clone(),
+            observers: self.observers.clone(),
+            port_names: self.port_names.clone(),
+            mutation_log_len: self.mutation_log_len(),
+        }
+    }
+
+    /// Put back everything but the node values. The caller has to make
+    /// `nodes` match the restored node ids again.
+    pub(crate) fn restore_structure(&mut self, structure: GraphStructure<N>) {
+        let GraphStructure {
+            node_data,
+            connections,
+            input_ports,
+            output_ports,
+            stable_ids,
+            variadic_inputs,
+            node_names,
+            parameters,
+            groups,
+            node_groups,
+            metadata,
+            observers,
+            port_names,
+            mutation_log_len,
+        } = structure;
+
+        self.node_data = node_data;
+        self.connections = connections;
+        self.input_ports = input_ports;
+        self.output_ports = output_ports;
+        self.stable_ids = stable_ids;
+        self.variadic_inputs = variadic_inputs;
+        self.node_names = node_names;
+        self.parameters = parameters;
+        self.groups = groups;
+        self.node_groups = node_groups;
+        self.metadata = metadata;
+        self.observers = observers;
+        self.port_names = port_names;
+        self.truncate_mutation_log(mutation_log_len);
+    }
+}
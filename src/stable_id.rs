@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use slotmap::{Key, SecondaryMap};
+
+use crate::{
+    ConnectionId, Graph, InputPortId, Node, NodeData, NodeId, OutputPortId,
+    compact::{CompactedKey, IdRemapping},
+};
+
+/// An id that, unlike slotmap keys, stays the same across save/load cycles and
+/// between processes. They are handed out by one graph, so replicas edited by
+/// several peers at once identify elements by
+/// [`ElementId`](crate::collab::ElementId) instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StableId(pub u64);
+
+/// A bidirectional mapping between keys of one kind and their [`StableId`]s
+#[derive(Debug, Clone)]
+pub struct StableIds<K: Key> {
+    to_stable: SecondaryMap<K, StableId>,
+    from_stable: HashMap<StableId, K>,
+}
+
+impl<K: Key> StableIds<K> {
+    fn new() -> Self {
+        Self {
+            to_stable: SecondaryMap::new(),
+            from_stable: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: K) -> Option<StableId> {
+        self.to_stable.get(key).copied()
+    }
+
+    pub fn resolve(&self, id: StableId) -> Option<K> {
+        self.from_stable.get(&id).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.to_stable.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.to_stable.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (K, StableId)> + '_ {
+        self.to_stable.iter().map(|(key, &id)| (key, id))
+    }
+
+    fn insert(&mut self, key: K, id: StableId) {
+        if let Some(old) = self.to_stable.insert(key, id) {
+            self.from_stable.remove(&old);
+        }
+
+        if let Some(old_key) = self.from_stable.insert(id, key)
+            && old_key != key
+        {
+            self.to_stable.remove(old_key);
+        }
+    }
+
+    fn remove(&mut self, key: K) {
+        if let Some(id) = self.to_stable.remove(key) {
+            self.from_stable.remove(&id);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.to_stable.clear();
+        self.from_stable.clear();
+    }
+
+    fn remap(&mut self, remapping: &IdRemapping)
+    where
+        K: CompactedKey,
+    {
+        self.to_stable = remapping.remap(std::mem::take(&mut self.to_stable));
+        self.from_stable = self.to_stable.iter().map(|(key, &id)| (id, key)).collect();
+    }
+}
+
+/// Assigns a [`StableId`] to every node, port and connection of a graph, see
+/// [`Graph::enable_stable_ids`]
+#[derive(Debug, Clone)]
+pub struct StableIdMap {
+    next: u64,
+    nodes: StableIds<NodeId>,
+    input_ports: StableIds<InputPortId>,
+    output_ports: StableIds<OutputPortId>,
+    connections: StableIds<ConnectionId>,
+}
+
+impl StableIdMap {
+    fn new() -> Self {
+        Self {
+            next: 0,
+            nodes: StableIds::new(),
+            input_ports: StableIds::new(),
+            output_ports: StableIds::new(),
+            connections: StableIds::new(),
+        }
+    }
+
+    pub fn nodes(&self) -> &StableIds<NodeId> {
+        &self.nodes
+    }
+
+    pub fn input_ports(&self) -> &StableIds<InputPortId> {
+        &self.input_ports
+    }
+
+    pub fn output_ports(&self) -> &StableIds<OutputPortId> {
+        &self.output_ports
+    }
+
+    pub fn connections(&self) -> &StableIds<ConnectionId> {
+        &self.connections
+    }
+
+    /// Replace the stable id of a node, used when loading a graph whose stable
+    /// ids were stored externally. Any other node using `id` loses its mapping.
+    pub fn assign_node(&mut self, node: NodeId, id: StableId) {
+        self.reserve(id);
+        self.nodes.insert(node, id);
+    }
+
+    pub fn assign_input_port(&mut self, port: InputPortId, id: StableId) {
+        self.reserve(id);
+        self.input_ports.insert(port, id);
+    }
+
+    pub fn assign_output_port(&mut self, port: OutputPortId, id: StableId) {
+        self.reserve(id);
+        self.output_ports.insert(port, id);
+    }
+
+    pub fn assign_connection(&mut self, connection: ConnectionId, id: StableId) {
+        self.reserve(id);
+        self.connections.insert(connection, id);
+    }
+
+    /// Never generate `id` or anything below it
+    pub(crate) fn reserve(&mut self, id: StableId) {
+        self.next = self.next.max(id.0 + 1);
+    }
+
+    fn generate(&mut self) -> StableId {
+        let id = StableId(self.next);
+        self.next += 1;
+        id
+    }
+
+    pub(crate) fn track_node(&mut self, node: NodeId, data: &NodeData) {
+        let id = self.generate();
+        self.nodes.insert(node, id);
+
+        for &(_, port) in data.inputs.iter() {
+            self.track_input_port(port);
+        }
+
+        for &(_, port) in data.outputs.iter() {
+            self.track_output_port(port);
+        }
+    }
+
+    pub(crate) fn track_input_port(&mut self, port: InputPortId) {
+        let id = self.generate();
+        self.input_ports.insert(port, id);
+    }
+
+    pub(crate) fn track_output_port(&mut self, port: OutputPortId) {
+        let id = self.generate();
+        self.output_ports.insert(port, id);
+    }
+
+    pub(crate) fn track_connection(&mut self, connection: ConnectionId) {
+        let id = self.generate();
+        self.connections.insert(connection, id);
+    }
+
+    /// Forget all mappings, without reusing any of the ids generated so far
+    pub(crate) fn untrack_all(&mut self) {
+        self.nodes.clear();
+        self.input_ports.clear();
+        self.output_ports.clear();
+        self.connections.clear();
+    }
+
+    pub(crate) fn remap(&mut self, remapping: &IdRemapping) {
+        self.nodes.remap(remapping);
+        self.input_ports.remap(remapping);
+        self.output_ports.remap(remapping);
+        self.connections.remap(remapping);
+    }
+
+    pub(crate) fn untrack_node(&mut self, node: NodeId) {
+        self.nodes.remove(node);
+    }
+
+    pub(crate) fn untrack_input_port(&mut self, port: InputPortId) {
+        self.input_ports.remove(port);
+    }
+
+    pub(crate) fn untrack_output_port(&mut self, port: OutputPortId) {
+        self.output_ports.remove(port);
+    }
+
+    pub(crate) fn untrack_connection(&mut self, connection: ConnectionId) {
+        self.connections.remove(connection);
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Start assigning [`StableId`]s to everything in this graph, including
+    /// everything that already exists. Does nothing if already enabled.
+    pub fn enable_stable_ids(&mut self) {
+        if self.stable_ids.is_some() {
+            return;
+        }
+
+        let mut stable_ids = StableIdMap::new();
+
+        for (id, data) in self.node_data.iter() {
+            stable_ids.track_node(id, data);
+        }
+
+        for id in self.connections.keys() {
+            stable_ids.track_connection(id);
+        }
+
+        self.stable_ids = Some(stable_ids);
+    }
+
+    pub fn disable_stable_ids(&mut self) {
+        self.stable_ids = None;
+    }
+
+    pub fn stable_ids(&self) -> Option<&StableIdMap> {
+        self.stable_ids.as_ref()
+    }
+
+    pub fn stable_ids_mut(&mut self) -> Option<&mut StableIdMap> {
+        self.stable_ids.as_mut()
+    }
+}
@@ -0,0 +1,84 @@
+use std::{mem::size_of, sync::Arc};
+
+use crate::{
+    Connection, ConnectionId, Graph, InputPortId, Node, NodeData, OutputPortId, Port,
+    analyzer::GraphAnalyzer, cell::NodeCell,
+};
+
+/// The size and shape of a graph, see [`Graph::stats`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphStats {
+    pub nodes: usize,
+    pub input_ports: usize,
+    pub output_ports: usize,
+    pub connections: usize,
+    /// Connections per node, which is both the average number of incoming
+    /// and of outgoing connections
+    pub average_degree: f64,
+    /// The length of the longest chain of dependencies, see
+    /// [`GraphAnalyzer::max_depth`]
+    pub max_depth: usize,
+    /// Estimated bytes allocated for the graph's nodes, ports and
+    /// connections, not counting memory owned by the nodes and values
+    /// themselves
+    pub node_memory: usize,
+    pub port_memory: usize,
+    pub connection_memory: usize,
+}
+
+impl GraphStats {
+    /// The sum of all memory estimates
+    pub fn total_memory(&self) -> usize {
+        self.node_memory + self.port_memory + self.connection_memory
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Count the nodes, ports and connections in the graph and estimate how
+    /// much memory they take up
+    pub fn stats(&self) -> GraphStats {
+        // Slot maps store a version next to every value
+        let slot = size_of::<u32>();
+
+        let mut node_memory = self.node_data.capacity() * (size_of::<NodeData>() + slot)
+            + self.nodes.capacity() * (size_of::<NodeCell<N>>() + slot);
+
+        for data in self.node_data.values() {
+            node_memory += data.inputs.capacity() * size_of::<(Arc<str>, InputPortId)>()
+                + data.outputs.capacity() * size_of::<(Arc<str>, OutputPortId)>()
+                + data.inputs.index_capacity() * size_of::<(Arc<str>, InputPortId)>()
+                + data.outputs.index_capacity() * size_of::<(Arc<str>, OutputPortId)>();
+        }
+
+        // Port names are shared, so they're counted once
+        let mut port_memory = (self.input_ports.capacity() + self.output_ports.capacity())
+            * (size_of::<Port<N>>() + slot)
+            + self.port_names.memory();
+
+        for port in self.input_ports.values().chain(self.output_ports.values()) {
+            port_memory += (port.incoming_connections.capacity()
+                + port.outgoing_connections.capacity())
+                * size_of::<ConnectionId>();
+        }
+
+        let connection_memory = self.connections.capacity() * (size_of::<Connection>() + slot);
+
+        let nodes = self.node_data.len();
+        let connections = self.connections.len();
+
+        GraphStats {
+            nodes,
+            input_ports: self.input_ports.len(),
+            output_ports: self.output_ports.len(),
+            connections,
+            average_degree: match nodes {
+                0 => 0.0,
+                _ => connections as f64 / nodes as f64,
+            },
+            max_depth: GraphAnalyzer::new(self).max_depth(),
+            node_memory,
+            port_memory,
+            connection_memory,
+        }
+    }
+}
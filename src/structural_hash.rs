@@ -0,0 +1,110 @@
+use std::{
+    collections::{HashMap, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+};
+
+use slotmap::SecondaryMap;
+
+use crate::{Graph, Node, NodeId, analyzer::GraphAnalyzer};
+
+impl<N: Node + Hash> Graph<N>
+where
+    N::DataType: Hash,
+    N::DataValue: Hash,
+{
+    /// A hash of every node and what it depends on, see
+    /// [`node_hashes`](Self::node_hashes). Graphs that are
+    /// [isomorphic](Self::is_isomorphic_to) hash the same, regardless of ids
+    /// and node names.
+    ///
+    /// Hashes are only stable within one build of a program, so they work as
+    /// keys for in-memory caches but shouldn't be stored.
+    pub fn structural_hash(&self) -> u64 {
+        let mut hashes = self
+            .node_hashes()
+            .into_iter()
+            .map(|(_, hash)| hash)
+            .collect::<Vec<_>>();
+        hashes.sort_unstable();
+
+        let mut hasher = DefaultHasher::new();
+        hashes.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// A hash of each node that covers its value, its ports and defaults, and
+    /// the hashes of the nodes connected to its inputs. Two nodes hash the
+    /// same when they, and everything upstream of them, are equal.
+    ///
+    /// Connections coming from nodes that are part of, or depend on, a cycle
+    /// are left out of the hash.
+    pub fn node_hashes(&self) -> SecondaryMap<NodeId, u64> {
+        let mut hashes = SecondaryMap::with_capacity(self.node_count());
+
+        let layers = GraphAnalyzer::new(self).layers();
+
+        for &node in layers.iter().flatten() {
+            let hash = self.hash_node(node, &hashes);
+            hashes.insert(node, hash);
+        }
+
+        // Nodes in or behind a cycle, which only see hashes computed above
+        let cyclic = self
+            .node_data
+            .keys()
+            .filter(|&node| !hashes.contains_key(node))
+            .map(|node| (node, self.hash_node(node, &hashes)))
+            .collect::<Vec<_>>();
+
+        hashes.extend(cyclic);
+
+        hashes
+    }
+
+    /// Nodes that hash the same, and probably compute the same values. Only
+    /// groups with more than one node are returned.
+    pub fn find_duplicates(&self) -> Vec<Vec<NodeId>> {
+        let mut groups = HashMap::<u64, Vec<NodeId>>::new();
+
+        for (node, hash) in self.node_hashes() {
+            groups.entry(hash).or_default().push(node);
+        }
+
+        groups
+            .into_values()
+            .filter(|nodes| nodes.len() > 1)
+            .collect()
+    }
+
+    fn hash_node(&self, node: NodeId, upstream: &SecondaryMap<NodeId, u64>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        self.nodes[node].read().hash(&mut hasher);
+
+        for (name, port) in self.node_data[node].inputs.iter() {
+            let info = &self.input_ports[*port];
+
+            name.hash(&mut hasher);
+            info.ty.hash(&mut hasher);
+            info.default.hash(&mut hasher);
+
+            let mut sources = self
+                .get_incoming_connections(*port)
+                .filter_map(|start| {
+                    let hash = upstream.get(self.output_ports[start].node)?;
+                    Some((*hash, self.output_port_index(start)))
+                })
+                .collect::<Vec<_>>();
+
+            sources.sort_unstable();
+            sources.hash(&mut hasher);
+        }
+
+        for (name, port) in self.node_data[node].outputs.iter() {
+            name.hash(&mut hasher);
+            self.output_ports[*port].ty.hash(&mut hasher);
+        }
+
+        hasher.finish()
+    }
+}
@@ -0,0 +1,355 @@
+use slotmap::SecondaryMap;
+
+use crate::{ConnectError, Graph, INVALID_STATE, InputPortId, Node, NodeId, OutputPortId};
+
+/// A node that can contain a nested graph, see
+/// [`Graph::collapse_to_subgraph`]
+pub trait SubgraphNode: Node + Clone {
+    /// Create a node wrapping `subgraph`. Its ports are created from the
+    /// boundary of the subgraph, so it should not have any initial ports.
+    fn from_subgraph(subgraph: Subgraph<Self>) -> Self;
+
+    fn as_subgraph(&self) -> Option<&Subgraph<Self>>;
+
+    fn into_subgraph(self) -> Option<Subgraph<Self>>;
+}
+
+/// A nested graph along with the ports connecting it to the graph around it
+#[derive(Debug)]
+pub struct Subgraph<N: Node> {
+    pub graph: Graph<N>,
+    /// One for each input port of the subgraph node, in order
+    pub inputs: Vec<SubgraphInput<N>>,
+    /// One for each output port of the subgraph node, in order
+    pub outputs: Vec<SubgraphOutput<N>>,
+}
+
+impl<N: Node + Clone> Clone for Subgraph<N> {
+    fn clone(&self) -> Self {
+        let mut graph = Graph::new();
+        graph.restore(self.graph.snapshot());
+
+        Self {
+            graph,
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+        }
+    }
+}
+
+/// An input port of a subgraph node, whose value is passed to `targets`
+#[derive(Debug, Clone)]
+pub struct SubgraphInput<N: Node> {
+    pub name: String,
+    pub ty: N::DataType,
+    /// `None` when the port it replaces had no default, like the input of a
+    /// reroute
+    pub default: Option<N::DataValue>,
+    /// Input ports inside the subgraph
+    pub targets: Vec<InputPortId>,
+}
+
+/// An output port of a subgraph node, whose value is taken from `source`
+#[derive(Debug, Clone)]
+pub struct SubgraphOutput<N: Node> {
+    pub name: String,
+    pub ty: N::DataType,
+    /// An output port inside the subgraph
+    pub source: OutputPortId,
+}
+
+impl<N: SubgraphNode> Graph<N> {
+    /// Move `nodes` into a new subgraph node. Connections between the moved
+    /// nodes and the rest of the graph are routed through ports on the new
+    /// node, one per outside output port and one per inside output port.
+    ///
+    /// Nothing changes if reconnecting the subgraph node fails, for example
+    /// because it would create a cycle.
+    pub fn collapse_to_subgraph(
+        &mut self,
+        nodes: &[NodeId],
+    ) -> Result<NodeId, ConnectError<N::DataType>> {
+        let snapshot = self.snapshot();
+
+        let result = self.collapse_to_subgraph_inner(nodes);
+
+        if result.is_err() {
+            self.restore(snapshot);
+        }
+
+        result
+    }
+
+    fn collapse_to_subgraph_inner(
+        &mut self,
+        nodes: &[NodeId],
+    ) -> Result<NodeId, ConnectError<N::DataType>> {
+        let mut selected = SecondaryMap::with_capacity(nodes.len());
+
+        for &node in nodes {
+            if !self.node_data.contains_key(node) {
+                panic!("Node does not exist");
+            }
+
+            selected.insert(node, ());
+        }
+
+        // Find the connections crossing the boundary, as (outer start port,
+        // inner end ports) and (inner start port, outer end ports)
+
+        let mut crossing_inputs = Vec::<(OutputPortId, Vec<InputPortId>)>::new();
+        let mut crossing_outputs = Vec::<(OutputPortId, Vec<InputPortId>)>::new();
+
+        for &node in nodes {
+            for &(_, port) in self.node_data[node].inputs.iter() {
+                for &connection in self.input_ports[port].incoming_connections.iter() {
+                    let start_port = self.connections[connection].start_port;
+
+                    if selected.contains_key(self.output_ports[start_port].node) {
+                        continue;
+                    }
+
+                    match crossing_inputs
+                        .iter_mut()
+                        .find(|(outer, _)| *outer == start_port)
+                    {
+                        Some((_, targets)) => targets.push(port),
+                        None => crossing_inputs.push((start_port, vec![port])),
+                    }
+                }
+            }
+
+            for &(_, port) in self.node_data[node].outputs.iter() {
+                let ends = self.output_ports[port]
+                    .outgoing_connections
+                    .iter()
+                    .map(|&connection| self.connections[connection].end_port)
+                    .filter(|&end_port| !selected.contains_key(self.input_ports[end_port].node))
+                    .collect::<Vec<_>>();
+
+                if !ends.is_empty() {
+                    crossing_outputs.push((port, ends));
+                }
+            }
+        }
+
+        // Copy the nodes into the subgraph, ports keep their index so they
+        // can be found in the copy
+
+        let mut graph = Graph::new();
+        let mapping = graph.paste_nodes(self.copy_nodes(nodes));
+
+        let map_input = |graph: &Graph<N>, port: InputPortId| {
+            let node = self.input_ports[port].node;
+            let index = self.node_data[node]
+                .inputs
+                .iter()
+                .position(|&(_, other)| other == port)
+                .expect(INVALID_STATE);
+
+            graph.node_data[mapping[node]].inputs[index].1
+        };
+
+        let map_output = |graph: &Graph<N>, port: OutputPortId| {
+            let node = self.output_ports[port].node;
+            let index = self.node_data[node]
+                .outputs
+                .iter()
+                .position(|&(_, other)| other == port)
+                .expect(INVALID_STATE);
+
+            graph.node_data[mapping[node]].outputs[index].1
+        };
+
+        let mut inputs = Vec::with_capacity(crossing_inputs.len());
+
+        for (_, targets) in crossing_inputs.iter() {
+            let first = &self.input_ports[targets[0]];
+
+            inputs.push(SubgraphInput {
+                name: unique_name(
+                    &first.name,
+                    inputs.iter().map(|i: &SubgraphInput<N>| &i.name),
+                ),
+                ty: first.ty,
+                default: first.default.clone(),
+                targets: targets
+                    .iter()
+                    .map(|&port| map_input(&graph, port))
+                    .collect(),
+            });
+        }
+
+        let mut outputs = Vec::with_capacity(crossing_outputs.len());
+
+        for &(source, _) in crossing_outputs.iter() {
+            let port = &self.output_ports[source];
+
+            outputs.push(SubgraphOutput {
+                name: unique_name(
+                    &port.name,
+                    outputs.iter().map(|o: &SubgraphOutput<N>| &o.name),
+                ),
+                ty: port.ty,
+                source: map_output(&graph, source),
+            });
+        }
+
+        for &node in nodes {
+            self.take_node(node).expect(INVALID_STATE);
+        }
+
+        let boundary = (
+            inputs
+                .iter()
+                .map(|input| (input.name.clone(), input.ty, input.default.clone()))
+                .collect::<Vec<_>>(),
+            outputs
+                .iter()
+                .map(|output| (output.name.clone(), output.ty))
+                .collect::<Vec<_>>(),
+        );
+
+        let node = self.create_node(N::from_subgraph(Subgraph {
+            graph,
+            inputs,
+            outputs,
+        }));
+
+        for ((name, ty, default), (outer, _)) in boundary.0.into_iter().zip(crossing_inputs) {
+            let port = self.create_input_port_inner(node, &name, ty, default);
+
+            self.check_connection(outer, port)?;
+            self.connect(outer, port);
+        }
+
+        for ((name, ty), (_, ends)) in boundary.1.into_iter().zip(crossing_outputs) {
+            let port = self.create_output_port(node, &name, ty);
+
+            for end in ends {
+                self.check_connection(port, end)?;
+                self.connect(port, end);
+            }
+        }
+
+        Ok(node)
+    }
+
+    /// Replace a subgraph node with the nodes inside of it, reconnecting
+    /// everything that was connected to its ports. Returns a map from the ids
+    /// of nodes in the subgraph to their ids in this graph.
+    ///
+    /// Nothing changes if reconnecting fails.
+    pub fn expand_subgraph(
+        &mut self,
+        node: NodeId,
+    ) -> Result<SecondaryMap<NodeId, NodeId>, ConnectError<N::DataType>> {
+        let snapshot = self.snapshot();
+
+        let result = self.expand_subgraph_inner(node);
+
+        if result.is_err() {
+            self.restore(snapshot);
+        }
+
+        result
+    }
+
+    fn expand_subgraph_inner(
+        &mut self,
+        node: NodeId,
+    ) -> Result<SecondaryMap<NodeId, NodeId>, ConnectError<N::DataType>> {
+        let data = self.node_data.get(node).expect("Node does not exist");
+
+        // Remember what the subgraph node is connected to, by port index
+
+        let inputs = data
+            .inputs
+            .iter()
+            .map(|&(_, port)| {
+                let starts = self.get_incoming_connections(port).collect::<Vec<_>>();
+                let default = self.input_ports[port].default.clone();
+
+                (starts, default)
+            })
+            .collect::<Vec<_>>();
+
+        let outputs = data
+            .outputs
+            .iter()
+            .map(|&(_, port)| self.get_outgoing_connections(port).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let subgraph = self
+            .take_node(node)
+            .expect(INVALID_STATE)
+            .into_subgraph()
+            .expect("Node is not a subgraph");
+
+        let inner_nodes = subgraph.graph.node_data.keys().collect::<Vec<_>>();
+        let mapping = self.paste_nodes(subgraph.graph.copy_nodes(&inner_nodes));
+
+        let inner = &subgraph.graph;
+
+        let map_input = |graph: &Self, port: InputPortId| {
+            let node = inner.input_ports[port].node;
+            let index = inner.node_data[node]
+                .inputs
+                .iter()
+                .position(|&(_, other)| other == port)
+                .expect(INVALID_STATE);
+
+            graph.node_data[mapping[node]].inputs[index].1
+        };
+
+        let map_output = |graph: &Self, port: OutputPortId| {
+            let node = inner.output_ports[port].node;
+            let index = inner.node_data[node]
+                .outputs
+                .iter()
+                .position(|&(_, other)| other == port)
+                .expect(INVALID_STATE);
+
+            graph.node_data[mapping[node]].outputs[index].1
+        };
+
+        for (input, (starts, default)) in subgraph.inputs.iter().zip(inputs) {
+            for &target in input.targets.iter() {
+                let target = map_input(self, target);
+
+                if starts.is_empty()
+                    && let Some(default) = &default
+                {
+                    self.set_default_value(target, default.clone());
+                }
+
+                for &start in starts.iter() {
+                    self.check_connection(start, target)?;
+                    self.connect(start, target);
+                }
+            }
+        }
+
+        for (output, ends) in subgraph.outputs.iter().zip(outputs) {
+            let source = map_output(self, output.source);
+
+            for end in ends {
+                self.check_connection(source, end)?;
+                self.connect(source, end);
+            }
+        }
+
+        Ok(mapping)
+    }
+}
+
+/// `name`, or `name` with the lowest numbered suffix that isn't in `taken`
+fn unique_name<'a>(name: &str, taken: impl Iterator<Item = &'a String> + Clone) -> String {
+    (0..)
+        .map(|index| match index {
+            0 => name.to_string(),
+            _ => format!("{name}_{index}"),
+        })
+        .find(|candidate| !taken.clone().any(|other| other == candidate))
+        .expect("Ran out of port names")
+}
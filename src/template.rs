@@ -0,0 +1,183 @@
+use itertools::Itertools;
+use slotmap::SecondaryMap;
+
+use crate::{Graph, INVALID_STATE, InputPortId, Node, NodeId, OutputPortId, metadata::Metadata};
+
+/// A reusable set of nodes, like a node group asset shared between graphs,
+/// which can be instantiated into graphs any number of times. The template
+/// graph can be built in code or loaded like any other graph.
+#[derive(Debug)]
+pub struct GraphTemplate<N: Node> {
+    graph: Graph<N>,
+    revision: u64,
+}
+
+/// The nodes created by [`GraphTemplate::instantiate`], which link the
+/// instance to its template for [`GraphTemplate::resync`]
+#[derive(Debug, Clone)]
+pub struct InstanceIds {
+    /// The instance node for each template node
+    nodes: SecondaryMap<NodeId, NodeId>,
+    revision: u64,
+}
+
+impl InstanceIds {
+    /// The instance of a node of the template
+    pub fn get(&self, template_node: NodeId) -> Option<NodeId> {
+        self.nodes.get(template_node).copied()
+    }
+
+    /// Pairs of template nodes and their instances
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, NodeId)> + '_ {
+        self.nodes
+            .iter()
+            .map(|(template_node, &node)| (template_node, node))
+    }
+
+    /// The [revision](GraphTemplate::revision) of the template when this
+    /// instance was created or last synced
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+}
+
+/// A connection between an instance and the rest of the graph, by port name
+/// so it can be restored after the instance is recreated
+struct ExternalConnection {
+    template_node: NodeId,
+    port: String,
+    other_port: OtherPort,
+    metadata: Metadata,
+}
+
+enum OtherPort {
+    /// The outside start of a connection to an instance input
+    Output(OutputPortId),
+    /// The outside end of a connection from an instance output
+    Input(InputPortId),
+}
+
+impl<N: Node + Clone> GraphTemplate<N> {
+    pub fn new(graph: Graph<N>) -> Self {
+        Self { graph, revision: 0 }
+    }
+
+    /// Copy `nodes` and the connections between them into a new template
+    pub fn from_nodes(graph: &Graph<N>, nodes: &[NodeId]) -> Self {
+        let mut template = Graph::new();
+        template.paste_nodes(graph.copy_nodes(nodes));
+
+        Self::new(template)
+    }
+
+    pub fn graph(&self) -> &Graph<N> {
+        &self.graph
+    }
+
+    /// Change the template. Instances created before are then
+    /// [outdated](Self::is_outdated).
+    pub fn graph_mut(&mut self) -> &mut Graph<N> {
+        self.revision += 1;
+        &mut self.graph
+    }
+
+    /// Counts changes made with [`graph_mut`](Self::graph_mut)
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Copy the nodes of the template and the connections between them into
+    /// `graph`
+    pub fn instantiate(&self, graph: &mut Graph<N>) -> InstanceIds {
+        let nodes = self.graph.node_data.keys().collect_vec();
+
+        InstanceIds {
+            nodes: graph.paste_nodes(self.graph.copy_nodes(&nodes)),
+            revision: self.revision,
+        }
+    }
+
+    /// Whether the template changed since `instance` was created or synced
+    pub fn is_outdated(&self, instance: &InstanceIds) -> bool {
+        instance.revision != self.revision
+    }
+
+    /// Replace the nodes of `instance` with a new instance of the template,
+    /// restoring connections to the rest of the graph by node and port name.
+    /// Changes made to the instance nodes are lost. Returns the number of
+    /// connections that couldn't be restored, because their port was removed
+    /// from the template or the connection is no longer valid.
+    pub fn resync(&self, graph: &mut Graph<N>, instance: &mut InstanceIds) -> usize {
+        let template_nodes = instance
+            .nodes
+            .iter()
+            .filter(|&(_, &node)| graph.contains_node(node))
+            .map(|(template_node, &node)| (node, template_node))
+            .collect::<SecondaryMap<_, _>>();
+
+        let mut external = Vec::new();
+
+        for (node, &template_node) in template_nodes.iter() {
+            for (connection_id, connection) in graph.node_connections(node) {
+                let start = graph.output_ports[connection.start_port].node;
+                let end = graph.input_ports[connection.end_port].node;
+
+                let (port, other_port) = if end == node && !template_nodes.contains_key(start) {
+                    (
+                        &graph.input_ports[connection.end_port].name,
+                        OtherPort::Output(connection.start_port),
+                    )
+                } else if start == node && !template_nodes.contains_key(end) {
+                    (
+                        &graph.output_ports[connection.start_port].name,
+                        OtherPort::Input(connection.end_port),
+                    )
+                } else {
+                    continue;
+                };
+
+                external.push(ExternalConnection {
+                    template_node,
+                    port: port.to_string(),
+                    other_port,
+                    metadata: graph
+                        .get_connection_metadata(connection_id)
+                        .expect(INVALID_STATE)
+                        .clone(),
+                });
+            }
+        }
+
+        for node in template_nodes.keys() {
+            graph.take_node(node).expect(INVALID_STATE);
+        }
+
+        *instance = self.instantiate(graph);
+
+        let mut dropped = 0;
+
+        for connection in external {
+            let node = instance.get(connection.template_node);
+
+            let ports = node.and_then(|node| match connection.other_port {
+                OtherPort::Output(start) => {
+                    Some((start, graph.get_input_port(node, &connection.port)?))
+                }
+                OtherPort::Input(end) => {
+                    Some((graph.get_output_port(node, &connection.port)?, end))
+                }
+            });
+
+            match ports {
+                Some((start, end)) if graph.check_connection(start, end).is_ok() => {
+                    let id = graph.connect(start, end);
+                    *graph.get_connection_metadata_mut(id).expect(INVALID_STATE) =
+                        connection.metadata;
+                }
+                _ => dropped += 1,
+            }
+        }
+
+        dropped
+    }
+}
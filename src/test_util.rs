@@ -0,0 +1,108 @@
+//! Nodes and graphs shared by the unit tests
+
+use crate::{
+    ConnectionId, Graph, InitialPorts, Node, NodeId,
+    document::DocumentNode,
+    registry::{NodeRegistry, RegistryArgs},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TestNode {
+    Value(i64),
+    Sum,
+}
+
+impl Node for TestNode {
+    type DataType = ();
+    type DataValue = i64;
+
+    fn initial_ports(&self) -> InitialPorts<Self> {
+        match self {
+            Self::Value(_) => InitialPorts {
+                outputs: vec![("value", ())],
+                ..Default::default()
+            },
+            Self::Sum => InitialPorts {
+                inputs: vec![("a", (), 0), ("b", (), 0)],
+                outputs: vec![("sum", ())],
+            },
+        }
+    }
+}
+
+impl DocumentNode for TestNode {
+    fn kind(&self) -> &str {
+        match self {
+            Self::Value(_) => "value",
+            Self::Sum => "sum",
+        }
+    }
+
+    fn args(&self) -> RegistryArgs {
+        let mut args = RegistryArgs::new();
+
+        if let Self::Value(value) = self {
+            args.set("value", *value);
+        }
+
+        args
+    }
+}
+
+/// A registry with every kind of [`TestNode`]
+pub(crate) fn registry() -> NodeRegistry<TestNode> {
+    let mut registry = NodeRegistry::new();
+    registry.register("value", |args| {
+        TestNode::Value(args.get_as("value").unwrap_or_default())
+    });
+    registry.register("sum", |_| TestNode::Sum);
+    registry
+}
+
+/// Connect the first output of `start` to the first input of `end`
+pub(crate) fn link(graph: &mut Graph<TestNode>, start: NodeId, end: NodeId) -> ConnectionId {
+    graph.connect(start.output(0), end.input(0))
+}
+
+/// A graph of `count` sum nodes, with a connection from each node to the
+/// next. Cycles are allowed, so tests can close the chain.
+pub(crate) fn chain(count: usize) -> (Graph<TestNode>, Vec<NodeId>) {
+    let mut graph = Graph::new();
+    graph.set_allow_cycles(true);
+
+    let nodes = (0..count)
+        .map(|_| graph.create_node(TestNode::Sum))
+        .collect::<Vec<_>>();
+
+    for pair in nodes.windows(2) {
+        link(&mut graph, pair[0], pair[1]);
+    }
+
+    (graph, nodes)
+}
+
+/// The ids, values, names and ports of every node, and all connections, to
+/// check that a graph was left exactly as it was
+pub(crate) fn describe(graph: &Graph<TestNode>) -> String {
+    let nodes = graph
+        .node_data
+        .iter()
+        .map(|(id, data)| {
+            (
+                id,
+                graph.nodes[id].read().clone(),
+                graph.get_node_name(id),
+                data.inputs.iter().collect::<Vec<_>>(),
+                data.outputs.iter().collect::<Vec<_>>(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // Undone creations leave free slots behind, which don't matter
+    format!(
+        "{nodes:?}\n{:?}\n{:?}\n{:?}",
+        graph.input_ports.iter().collect::<Vec<_>>(),
+        graph.output_ports.iter().collect::<Vec<_>>(),
+        graph.connections.iter().collect::<Vec<_>>()
+    )
+}
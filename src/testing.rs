@@ -0,0 +1,102 @@
+//! [`proptest`] strategies for random graphs, so evaluators and graph
+//! transformations can be property tested
+
+use std::{fmt::Debug, ops::Range};
+
+use proptest::{collection::vec, prelude::*};
+
+use crate::{Graph, Node};
+
+/// The shape of graphs generated by [`arb_graph`]
+#[derive(Debug, Clone)]
+pub struct GraphConfig {
+    /// How many nodes a graph has
+    pub nodes: Range<usize>,
+    /// How many connections are attempted per node. Connections between
+    /// incompatible ports are skipped, so graphs usually end up with fewer.
+    pub connections_per_node: usize,
+    /// The maximum number of connections to a single input port
+    pub max_fan_in: usize,
+    /// The maximum number of connections from a single output port
+    pub max_fan_out: usize,
+}
+
+impl Default for GraphConfig {
+    fn default() -> Self {
+        Self {
+            nodes: 1..16,
+            connections_per_node: 2,
+            max_fan_in: 1,
+            max_fan_out: 4,
+        }
+    }
+}
+
+/// A connection to attempt, as (end node, input, start node, output). Every
+/// index is wrapped around to a valid one, so they shrink towards the first
+/// nodes and ports.
+type ConnectionSeed = (usize, usize, usize, usize);
+
+/// Random acyclic graphs with nodes from `nodes`. The mix of node types is
+/// up to the node strategy, for example a weighted `prop_oneof!`.
+///
+/// Connections only go from earlier nodes to later ones, so graphs never
+/// contain cycles, and connections that [`Graph::check_connection`] would
+/// reject are left out. Failing graphs shrink by removing nodes and
+/// connections.
+pub fn arb_graph<N, S>(nodes: S, config: GraphConfig) -> impl Strategy<Value = Graph<N>>
+where
+    N: Node + Debug,
+    S: Strategy<Value = N>,
+{
+    let max_connections = config.nodes.end.saturating_sub(1) * config.connections_per_node;
+
+    (
+        vec(nodes, config.nodes.clone()),
+        vec(any::<ConnectionSeed>(), 0..=max_connections),
+    )
+        .prop_map(move |(nodes, connections)| build(nodes, &connections, &config))
+}
+
+fn build<N: Node>(nodes: Vec<N>, connections: &[ConnectionSeed], config: &GraphConfig) -> Graph<N> {
+    let mut graph = Graph::with_capacity(nodes.len(), connections.len());
+
+    let ids = nodes
+        .into_iter()
+        .map(|node| graph.create_node(node))
+        .collect::<Vec<_>>();
+
+    if ids.len() < 2 {
+        return graph;
+    }
+
+    for &(end, input, start, output) in connections {
+        let end = 1 + end % (ids.len() - 1);
+        let start = start % end;
+
+        let input_count = graph.node_data[ids[end]].inputs.len();
+        let output_count = graph.node_data[ids[start]].outputs.len();
+
+        if input_count == 0 || output_count == 0 {
+            continue;
+        }
+
+        let end_port = graph.node_data[ids[end]].inputs[input % input_count].1;
+        let start_port = graph.node_data[ids[start]].outputs[output % output_count].1;
+
+        let is_full = graph.input_ports[end_port].incoming_connections.len() >= config.max_fan_in
+            || graph.output_ports[start_port].outgoing_connections.len() >= config.max_fan_out;
+
+        let is_connected = graph
+            .get_incoming_connections(end_port)
+            .any(|port| port == start_port);
+
+        if is_full || is_connected || graph.check_connection(start_port, end_port).is_err() {
+            continue;
+        }
+
+        graph.connect(start_port, end_port);
+    }
+
+    graph
+}
@@ -0,0 +1,92 @@
+use slotmap::SecondaryMap;
+
+use crate::{InputPortId, Node, NodeId, OutputPortId, walker::WalkStep};
+
+/// The values every node read and wrote during a walk, recorded when
+/// [tracing](crate::walker::GraphWalker::enable_trace) is enabled
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "N::DataValue: serde::Serialize",
+        deserialize = "N::DataValue: serde::Deserialize<'de>"
+    ))
+)]
+pub struct EvaluationTrace<N: Node> {
+    steps: Vec<WalkStep<N>>,
+    by_node: SecondaryMap<NodeId, usize>,
+}
+
+impl<N: Node> Clone for EvaluationTrace<N> {
+    fn clone(&self) -> Self {
+        Self {
+            steps: self.steps.clone(),
+            by_node: self.by_node.clone(),
+        }
+    }
+}
+
+impl<N: Node> EvaluationTrace<N> {
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            by_node: SecondaryMap::new(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, step: WalkStep<N>) {
+        self.by_node.insert(step.node, self.steps.len());
+        self.steps.push(step);
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+        self.by_node.clear();
+    }
+
+    /// Every evaluation, in order
+    pub fn steps(&self) -> &[WalkStep<N>] {
+        &self.steps
+    }
+
+    /// The last evaluation of `node`
+    pub fn get(&self, node: NodeId) -> Option<&WalkStep<N>> {
+        Some(&self.steps[*self.by_node.get(node)?])
+    }
+
+    /// The last value written to an output port, which is the value on every
+    /// connection from it
+    pub fn output_value(&self, node: NodeId, port: OutputPortId) -> Option<&N::DataValue> {
+        self.get(node)?
+            .outputs
+            .iter()
+            .find(|&&(id, _)| id == port)?
+            .1
+            .as_ref()
+    }
+
+    /// The last value an input port was read as
+    pub fn input_value(&self, node: NodeId, port: InputPortId) -> Option<&N::DataValue> {
+        self.get(node)?
+            .inputs
+            .iter()
+            .find(|&&(id, _)| id == port)?
+            .1
+            .as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+impl<N: Node> Default for EvaluationTrace<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,220 @@
+use std::fmt::Display;
+
+use crate::{
+    ConnectError, ConnectionId, DataType, Graph, InputPortId, Node, NodeId, NodeTemplate,
+    OutputPortId,
+    group::GroupId,
+    reference::{InputPortReference, OutputPortReference},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionError {
+    NodeNotFound(NodeId),
+    InputPortNotFound,
+    OutputPortNotFound,
+    ConnectionNotFound(ConnectionId),
+    DuplicateInputPort(String),
+    DuplicateOutputPort(String),
+    SameNode,
+    OutputFull,
+    InputFull,
+    IncompatibleTypes,
+    WouldCreateCycle,
+    Rejected(String),
+    /// The node's name is used by the given node, under
+    /// [`NodeNamePolicy::Unique`](crate::naming::NodeNamePolicy::Unique)
+    NameTaken(NodeId),
+    GroupNotFound(GroupId),
+    /// A new order for the connections to a port doesn't list each of them
+    /// exactly once
+    InvalidConnectionOrder,
+}
+
+impl Display for TransactionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NodeNotFound(id) => write!(f, "Node {id:?} does not exist"),
+            Self::InputPortNotFound => write!(f, "Input port does not exist"),
+            Self::OutputPortNotFound => write!(f, "Output port does not exist"),
+            Self::ConnectionNotFound(id) => write!(f, "Connection {id:?} does not exist"),
+            Self::DuplicateInputPort(name) => {
+                write!(f, "An input port named {name:?} already exists")
+            }
+            Self::DuplicateOutputPort(name) => {
+                write!(f, "An output port named {name:?} already exists")
+            }
+            Self::SameNode => write!(f, "Cannot connect a node to itself"),
+            Self::OutputFull => write!(f, "Output port cannot have any more connections"),
+            Self::InputFull => write!(f, "Input port cannot have any more connections"),
+            Self::IncompatibleTypes => write!(f, "Port types are not convertable"),
+            Self::WouldCreateCycle => write!(f, "Connection would create a cycle"),
+            Self::Rejected(reason) => write!(f, "Connection was rejected: {reason}"),
+            Self::NameTaken(id) => write!(f, "Name is already used by node {id:?}"),
+            Self::GroupNotFound(id) => write!(f, "Group {id:?} does not exist"),
+            Self::InvalidConnectionOrder => {
+                write!(f, "Connection order doesn't list every connection once")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TransactionError {}
+
+impl<T: DataType> From<ConnectError<T>> for TransactionError {
+    fn from(value: ConnectError<T>) -> Self {
+        match value {
+            ConnectError::OutputPortNotFound => Self::OutputPortNotFound,
+            ConnectError::InputPortNotFound => Self::InputPortNotFound,
+            ConnectError::SameNode => Self::SameNode,
+            ConnectError::OutputFull => Self::OutputFull,
+            ConnectError::InputFull => Self::InputFull,
+            ConnectError::TypeMismatch { .. } => Self::IncompatibleTypes,
+            ConnectError::WouldCreateCycle => Self::WouldCreateCycle,
+            ConnectError::Rejected(reason) => Self::Rejected(reason),
+        }
+    }
+}
+
+/// Checked access to a [`Graph`] during [`Graph::transaction`]. Every mutation
+/// is validated before it is applied, so failures are reported as
+/// [`TransactionError`]s instead of panics.
+pub struct GraphTransaction<'a, N: Node> {
+    graph: &'a mut Graph<N>,
+}
+
+impl<'a, N: Node> GraphTransaction<'a, N> {
+    /// Read-only access to the graph, including all changes made so far
+    pub fn graph(&self) -> &Graph<N> {
+        self.graph
+    }
+
+    pub fn create_node<T: NodeTemplate<N>>(&mut self, node: T) -> NodeId {
+        self.graph.create_node(node)
+    }
+
+    pub fn create_input_port(
+        &mut self,
+        node: NodeId,
+        name: &str,
+        ty: N::DataType,
+        default: N::DataValue,
+    ) -> Result<InputPortId, TransactionError> {
+        if !self.graph.node_data.contains_key(node) {
+            return Err(TransactionError::NodeNotFound(node));
+        }
+
+        if self.graph.get_input_port(node, name).is_some() {
+            return Err(TransactionError::DuplicateInputPort(name.to_string()));
+        }
+
+        Ok(self.graph.create_input_port(node, name, ty, default))
+    }
+
+    pub fn create_output_port(
+        &mut self,
+        node: NodeId,
+        name: &str,
+        ty: N::DataType,
+    ) -> Result<OutputPortId, TransactionError> {
+        if !self.graph.node_data.contains_key(node) {
+            return Err(TransactionError::NodeNotFound(node));
+        }
+
+        if self.graph.get_output_port(node, name).is_some() {
+            return Err(TransactionError::DuplicateOutputPort(name.to_string()));
+        }
+
+        Ok(self.graph.create_output_port(node, name, ty))
+    }
+
+    pub fn delete_input_port(
+        &mut self,
+        port: impl InputPortReference,
+    ) -> Result<(), TransactionError> {
+        self.graph
+            .delete_input_port(port)
+            .ok_or(TransactionError::InputPortNotFound)
+    }
+
+    pub fn delete_output_port(
+        &mut self,
+        port: impl OutputPortReference,
+    ) -> Result<(), TransactionError> {
+        self.graph
+            .delete_output_port(port)
+            .ok_or(TransactionError::OutputPortNotFound)
+    }
+
+    pub fn set_default_value(
+        &mut self,
+        port: impl InputPortReference,
+        value: impl Into<N::DataValue>,
+    ) -> Result<(), TransactionError> {
+        let port = port
+            .resolve(self.graph)
+            .filter(|&port| self.graph.input_ports.contains_key(port))
+            .ok_or(TransactionError::InputPortNotFound)?;
+
+        self.graph.set_default_value(port, value);
+
+        Ok(())
+    }
+
+    pub fn connect(
+        &mut self,
+        start_port: impl OutputPortReference,
+        end_port: impl InputPortReference,
+    ) -> Result<ConnectionId, TransactionError> {
+        let start_port = start_port
+            .resolve(self.graph)
+            .ok_or(TransactionError::OutputPortNotFound)?;
+
+        let end_port = end_port
+            .resolve(self.graph)
+            .ok_or(TransactionError::InputPortNotFound)?;
+
+        self.graph.check_connection(start_port, end_port)?;
+
+        Ok(self.graph.connect(start_port, end_port))
+    }
+
+    pub fn disconnect(&mut self, connection: ConnectionId) -> Result<(), TransactionError> {
+        self.graph
+            .disconnect(connection)
+            .ok_or(TransactionError::ConnectionNotFound(connection))
+    }
+}
+
+impl<N: Node + Clone> Graph<N> {
+    /// Run `f` against this graph, applying all of its mutations if it returns
+    /// `Ok`, or none of them if it returns `Err`.
+    ///
+    /// Mutations are applied to the graph as `f` makes them, so it sees its
+    /// own changes through [`GraphTransaction::graph`]. To be able to roll
+    /// back, a full [`snapshot`](Graph::snapshot) is taken before `f` is
+    /// called, including a clone of every node, so prefer
+    /// [`apply_batch`](Graph::apply_batch) for large graphs when the changes
+    /// are known up front.
+    ///
+    /// [`connections_changed`](Node::connections_changed) is held back until
+    /// `f` returns and then called once per affected node, or not at all if
+    /// the transaction is rolled back. Rolling back also removes the
+    /// transaction's entries from the [mutation log](Graph::enable_mutation_log).
+    pub fn transaction<T, E: From<TransactionError>>(
+        &mut self,
+        f: impl FnOnce(&mut GraphTransaction<'_, N>) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let snapshot = self.snapshot();
+        let notifications = self.defer_notifications();
+
+        let result = f(&mut GraphTransaction { graph: self });
+
+        if result.is_err() {
+            self.restore(snapshot);
+        }
+
+        self.resume_notifications(notifications, result.is_ok());
+
+        result
+    }
+}
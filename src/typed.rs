@@ -0,0 +1,134 @@
+use std::{fmt::Debug, hash::Hash, marker::PhantomData};
+
+use crate::{
+    Graph, Node, NodeId,
+    cell::{self, MappedNodeRef, MappedNodeRefMut},
+    reference::{NodeInputIdentifier, NodeOutputIdentifier},
+};
+
+/// Access to one kind of node in a node type that holds several, usually by
+/// matching the enum variant that wraps a `T`
+pub trait Downcast<T>: Node {
+    fn downcast_ref(&self) -> Option<&T>;
+
+    fn downcast_mut(&mut self) -> Option<&mut T>;
+}
+
+/// A [`NodeId`] of a node that is known to be a `T`, see
+/// [`Graph::create_node_typed`]
+pub struct TypedNodeId<T> {
+    id: NodeId,
+    _kind: PhantomData<fn() -> T>,
+}
+
+impl<T> TypedNodeId<T> {
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    pub fn input<'a, I: NodeInputIdentifier<'a>>(&self, identifier: I) -> I::Reference {
+        self.id.input(identifier)
+    }
+
+    pub fn output<'a, I: NodeOutputIdentifier<'a>>(&self, identifier: I) -> I::Reference {
+        self.id.output(identifier)
+    }
+}
+
+// Implemented by hand, deriving would require `T` to implement these as well
+
+impl<T> Clone for TypedNodeId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TypedNodeId<T> {}
+
+impl<T> PartialEq for TypedNodeId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for TypedNodeId<T> {}
+
+impl<T> Hash for TypedNodeId<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> Debug for TypedNodeId<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "TypedNodeId<{}>({:?})",
+            std::any::type_name::<T>(),
+            self.id
+        )
+    }
+}
+
+impl<T> From<TypedNodeId<T>> for NodeId {
+    fn from(node: TypedNodeId<T>) -> Self {
+        node.id
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Like [`create_node`](Self::create_node), but remembers what kind of
+    /// node was created
+    pub fn create_node_typed<T: Into<N>>(&mut self, node: T) -> TypedNodeId<T> {
+        TypedNodeId {
+            id: self.create_node(node),
+            _kind: PhantomData,
+        }
+    }
+
+    /// A typed id for `node`, or `None` if it doesn't exist or isn't a `T`
+    pub fn downcast_id<T>(&self, node: NodeId) -> Option<TypedNodeId<T>>
+    where
+        N: Downcast<T>,
+    {
+        self.get_node(node)?.downcast_ref()?;
+
+        Some(TypedNodeId {
+            id: node,
+            _kind: PhantomData,
+        })
+    }
+
+    /// Borrow a node as a `T`, or `None` if it doesn't exist or is another
+    /// kind of node
+    pub fn get_node_as<T>(&self, node: NodeId) -> Option<MappedNodeRef<'_, T>>
+    where
+        N: Downcast<T>,
+    {
+        cell::try_map(self.get_node(node)?, N::downcast_ref)
+    }
+
+    pub fn get_node_as_mut<T>(&self, node: NodeId) -> Option<MappedNodeRefMut<'_, T>>
+    where
+        N: Downcast<T>,
+    {
+        cell::try_map_mut(self.get_node_mut(node)?, N::downcast_mut)
+    }
+
+    /// Borrow a node created with [`create_node_typed`](Self::create_node_typed).
+    /// Returns `None` if the node was removed or replaced by a different kind
+    /// of node.
+    pub fn get_typed<T>(&self, node: TypedNodeId<T>) -> Option<MappedNodeRef<'_, T>>
+    where
+        N: Downcast<T>,
+    {
+        self.get_node_as(node.id)
+    }
+
+    pub fn get_typed_mut<T>(&self, node: TypedNodeId<T>) -> Option<MappedNodeRefMut<'_, T>>
+    where
+        N: Downcast<T>,
+    {
+        self.get_node_as_mut(node.id)
+    }
+}
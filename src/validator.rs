@@ -0,0 +1,67 @@
+use std::fmt::Debug;
+
+use slotmap::{SlotMap, new_key_type};
+
+use crate::{ConnectError, Graph, InputPortId, Node, OutputPortId};
+
+new_key_type! { pub struct ValidatorId; }
+
+type Validator<N> =
+    Box<dyn Fn(&Graph<N>, OutputPortId, InputPortId) -> Result<(), String> + Send + Sync>;
+
+/// User supplied rules for connections, see [`Graph::add_connection_validator`]
+pub struct ConnectionValidators<N: Node> {
+    validators: SlotMap<ValidatorId, Validator<N>>,
+}
+
+impl<N: Node> ConnectionValidators<N> {
+    pub(crate) fn new() -> Self {
+        Self {
+            validators: SlotMap::with_key(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.validators.is_empty()
+    }
+
+    pub(crate) fn validate(
+        &self,
+        graph: &Graph<N>,
+        start_port: OutputPortId,
+        end_port: InputPortId,
+    ) -> Result<(), ConnectError<N::DataType>> {
+        for validator in self.validators.values() {
+            validator(graph, start_port, end_port).map_err(ConnectError::Rejected)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<N: Node> Debug for ConnectionValidators<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.validators.keys()).finish()
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Add a rule that every new connection has to follow, on top of the
+    /// built-in type and cycle checks. Validators are consulted by
+    /// [`check_connection`](Self::check_connection), and therefore by
+    /// [`can_connect`](Self::can_connect) and [`connect`](Self::connect) too.
+    pub fn add_connection_validator(
+        &mut self,
+        validator: impl Fn(&Graph<N>, OutputPortId, InputPortId) -> Result<(), String>
+        + Send
+        + Sync
+        + 'static,
+    ) -> ValidatorId {
+        self.validators.validators.insert(Box::new(validator))
+    }
+
+    #[must_use]
+    pub fn remove_connection_validator(&mut self, validator: ValidatorId) -> Option<()> {
+        self.validators.validators.remove(validator).map(|_| ())
+    }
+}
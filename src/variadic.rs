@@ -0,0 +1,123 @@
+use crate::{Graph, INVALID_STATE, InputPortId, Node, NodeId};
+
+/// A group of input ports that always ends with exactly one unconnected port,
+/// see [`Graph::create_variadic_input`]
+#[derive(Debug)]
+pub struct VariadicInput<N: Node> {
+    pub base_name: String,
+    pub ty: N::DataType,
+    pub default: N::DataValue,
+    /// The ports in this group, in order
+    pub ports: Vec<InputPortId>,
+}
+
+impl<N: Node> Clone for VariadicInput<N> {
+    fn clone(&self) -> Self {
+        Self {
+            base_name: self.base_name.clone(),
+            ty: self.ty,
+            default: self.default.clone(),
+            ports: self.ports.clone(),
+        }
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Create a group of input ports that grows whenever its last port gets
+    /// connected, and shrinks when trailing ports are disconnected. Ports are
+    /// named `{base_name}_{index}`.
+    ///
+    /// Returns the first port of the group.
+    pub fn create_variadic_input(
+        &mut self,
+        node: NodeId,
+        base_name: &str,
+        ty: N::DataType,
+        default: N::DataValue,
+    ) -> InputPortId {
+        if !self.node_data.contains_key(node) {
+            panic!("Node does not exist");
+        }
+
+        let groups = self
+            .variadic_inputs
+            .entry(node)
+            .expect(INVALID_STATE)
+            .or_default();
+
+        if groups.iter().any(|group| group.base_name == base_name) {
+            panic!("A variadic input with this name already exists");
+        }
+
+        groups.push(VariadicInput {
+            base_name: base_name.to_string(),
+            ty,
+            default,
+            ports: Vec::new(),
+        });
+
+        self.update_variadic_inputs(node);
+
+        *self.variadic_inputs[node]
+            .iter()
+            .find(|group| group.base_name == base_name)
+            .and_then(|group| group.ports.first())
+            .expect("Variadic input has no ports")
+    }
+
+    pub fn get_variadic_input(&self, node: NodeId, base_name: &str) -> Option<&VariadicInput<N>> {
+        self.variadic_inputs
+            .get(node)?
+            .iter()
+            .find(|group| group.base_name == base_name)
+    }
+
+    /// Grow or shrink the variadic inputs of `node` so each ends with exactly
+    /// one unconnected port
+    pub(crate) fn update_variadic_inputs(&mut self, node: NodeId) {
+        let Some(groups) = self.variadic_inputs.get_mut(node) else {
+            return;
+        };
+
+        // Take the groups out while updating, so the port creation and deletion
+        // below doesn't recursively update them again
+
+        let mut groups = std::mem::take(groups);
+
+        for group in groups.iter_mut() {
+            group
+                .ports
+                .retain(|&port| self.input_ports.contains_key(port));
+
+            let is_connected = |graph: &Self, port: InputPortId| {
+                !graph.input_ports[port].incoming_connections.is_empty()
+            };
+
+            while let [.., second_last, last] = group.ports[..]
+                && !is_connected(self, second_last)
+                && !is_connected(self, last)
+            {
+                group.ports.pop();
+                let _ = self.unlogged(|graph| graph.delete_input_port(last));
+            }
+
+            if group
+                .ports
+                .last()
+                .is_none_or(|&port| is_connected(self, port))
+            {
+                let name = (group.ports.len()..)
+                    .map(|index| format!("{}_{index}", group.base_name))
+                    .find(|name| self.get_input_port(node, name).is_none())
+                    .expect("Ran out of port names");
+
+                let port = self.unlogged(|graph| {
+                    graph.create_input_port(node, &name, group.ty, group.default.clone())
+                });
+                group.ports.push(port);
+            }
+        }
+
+        self.variadic_inputs.insert(node, groups);
+    }
+}
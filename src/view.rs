@@ -0,0 +1,288 @@
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use crate::{
+    Connection, ConnectionId, Graph, InputPortId, Node, NodeId, OutputPortId, Port, PortList,
+    analyzer::GraphAnalyzer,
+    cell::NodeRef,
+    metadata::{FromMetaValue, Metadata},
+    reference::{InputPortReference, OutputPortReference},
+    stable_id::StableIdMap,
+};
+
+/// Read-only access to a graph, for code that should only inspect it, like
+/// callbacks, validators and UI threads. Unlike `&Graph`, it can't lock
+/// nodes for writing. Created with [`Graph::view`], and passed to
+/// [`Node::connections_changed`].
+///
+/// A view can be sent to other threads whenever the graph is [`Sync`], which
+/// it is unless the `cell` feature is enabled.
+#[derive(Debug)]
+pub struct GraphView<'a, N: Node> {
+    graph: &'a Graph<N>,
+    /// A node that is already borrowed mutably, so it can't be read
+    locked: Option<NodeId>,
+}
+
+impl<N: Node> Clone for GraphView<'_, N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<N: Node> Copy for GraphView<'_, N> {}
+
+impl<'a, N: Node> GraphView<'a, N> {
+    pub(crate) fn new(graph: &'a Graph<N>, locked: Option<NodeId>) -> Self {
+        Self { graph, locked }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.graph.connection_count()
+    }
+
+    pub fn input_port_count(&self) -> usize {
+        self.graph.input_port_count()
+    }
+
+    pub fn output_port_count(&self) -> usize {
+        self.graph.output_port_count()
+    }
+
+    /// The ids of all nodes, in no particular order
+    pub fn node_ids(&self) -> impl Iterator<Item = NodeId> + 'a {
+        self.graph.node_data.keys()
+    }
+
+    pub fn contains_node(&self, node: NodeId) -> bool {
+        self.graph.contains_node(node)
+    }
+
+    pub fn contains_connection(&self, connection: ConnectionId) -> bool {
+        self.graph.contains_connection(connection)
+    }
+
+    pub fn contains_input_port(&self, port: impl InputPortReference) -> bool {
+        self.graph.contains_input_port(port)
+    }
+
+    pub fn contains_output_port(&self, port: impl OutputPortReference) -> bool {
+        self.graph.contains_output_port(port)
+    }
+
+    /// Read a node. Returns `None` for the node a callback was called on,
+    /// since it is already borrowed.
+    pub fn get_node(&self, node: NodeId) -> Option<NodeRef<'a, N>> {
+        if self.locked == Some(node) {
+            return None;
+        }
+
+        self.graph.get_node(node)
+    }
+
+    pub fn get_connection(&self, connection: ConnectionId) -> Option<&'a Connection> {
+        self.graph.get_connection(connection)
+    }
+
+    pub fn get_input_port_info(&self, port: impl InputPortReference) -> Option<&'a Port<N>> {
+        self.graph.get_input_port_info(port)
+    }
+
+    pub fn get_output_port_info(&self, port: impl OutputPortReference) -> Option<&'a Port<N>> {
+        self.graph.get_output_port_info(port)
+    }
+
+    pub fn get_input_port_metadata(&self, port: impl InputPortReference) -> Option<&'a Metadata> {
+        self.graph.get_input_port_metadata(port)
+    }
+
+    pub fn get_output_port_metadata(&self, port: impl OutputPortReference) -> Option<&'a Metadata> {
+        self.graph.get_output_port_metadata(port)
+    }
+
+    pub fn get_input_port(&self, node: NodeId, name: &str) -> Option<InputPortId> {
+        self.graph.get_input_port(node, name)
+    }
+
+    pub fn get_output_port(&self, node: NodeId, name: &str) -> Option<OutputPortId> {
+        self.graph.get_output_port(node, name)
+    }
+
+    pub fn get_input_port_at(&self, node: NodeId, index: usize) -> Option<InputPortId> {
+        self.graph.get_input_port_at(node, index)
+    }
+
+    pub fn get_output_port_at(&self, node: NodeId, index: usize) -> Option<OutputPortId> {
+        self.graph.get_output_port_at(node, index)
+    }
+
+    pub fn get_input_ports(&self, node: NodeId) -> Option<&'a PortList<(Arc<str>, InputPortId)>> {
+        self.graph.get_input_ports(node)
+    }
+
+    pub fn get_output_ports(&self, node: NodeId) -> Option<&'a PortList<(Arc<str>, OutputPortId)>> {
+        self.graph.get_output_ports(node)
+    }
+
+    /// See [`Graph::get_incoming_connections`]
+    pub fn get_incoming_connections(
+        &self,
+        port: impl InputPortReference,
+    ) -> impl Iterator<Item = OutputPortId> + 'a {
+        self.graph.get_incoming_connections(port)
+    }
+
+    pub fn get_outgoing_connections(
+        &self,
+        port: impl OutputPortReference,
+    ) -> impl Iterator<Item = InputPortId> + 'a {
+        self.graph.get_outgoing_connections(port)
+    }
+
+    /// See [`Graph::node_connections`]
+    pub fn node_connections(
+        &self,
+        node: NodeId,
+    ) -> impl Iterator<Item = (ConnectionId, &'a Connection)> + 'a {
+        self.graph.node_connections(node)
+    }
+
+    pub fn get_direct_dependencies(&self, node: NodeId) -> impl Iterator<Item = NodeId> + 'a {
+        self.graph.get_direct_dependencies(node)
+    }
+
+    pub fn get_direct_dependents(&self, node: NodeId) -> impl Iterator<Item = NodeId> + 'a {
+        self.graph.get_direct_dependents(node)
+    }
+
+    pub fn neighbors(&self, node: NodeId) -> impl Iterator<Item = NodeId> + 'a {
+        self.graph.neighbors(node)
+    }
+
+    pub fn in_degree(&self, node: NodeId) -> usize {
+        self.graph.in_degree(node)
+    }
+
+    pub fn out_degree(&self, node: NodeId) -> usize {
+        self.graph.out_degree(node)
+    }
+
+    /// See [`Graph::get_sources`]
+    pub fn get_sources(
+        &self,
+        port: impl InputPortReference,
+    ) -> impl Iterator<Item = OutputPortId> + 'a {
+        self.graph.get_sources(port)
+    }
+
+    pub fn is_reroute(&self, node: NodeId) -> bool {
+        self.graph.is_reroute(node)
+    }
+
+    pub fn get_node_name(&self, node: NodeId) -> Option<&'a str> {
+        self.graph.get_node_name(node)
+    }
+
+    pub fn get_node_by_name(&self, name: &str) -> Option<NodeId> {
+        self.graph.get_node_by_name(name)
+    }
+
+    pub fn metadata(&self) -> &'a Metadata {
+        self.graph.metadata()
+    }
+
+    pub fn get_meta<T: FromMetaValue>(&self, key: &str) -> Option<T> {
+        self.graph.get_meta(key)
+    }
+
+    pub fn get_connection_metadata(&self, connection: ConnectionId) -> Option<&'a Metadata> {
+        self.graph.get_connection_metadata(connection)
+    }
+
+    pub fn get_node_metadata(&self, node: NodeId) -> Option<&'a Metadata> {
+        self.graph.get_node_metadata(node)
+    }
+
+    pub fn node_position(&self, node: NodeId) -> Option<(f64, f64)> {
+        self.graph.node_position(node)
+    }
+
+    pub fn stable_ids(&self) -> Option<&'a StableIdMap> {
+        self.graph.stable_ids()
+    }
+
+    pub fn allows_cycles(&self) -> bool {
+        self.graph.allows_cycles()
+    }
+
+    /// Analyze the viewed graph. Analyses don't write to nodes either.
+    pub fn analyzer(&self) -> GraphAnalyzer<'a, N> {
+        GraphAnalyzer::new(self.graph)
+    }
+}
+
+impl<N: Node> Graph<N> {
+    /// Read-only access to this graph, which can be handed to code that
+    /// shouldn't change it
+    pub fn view(&self) -> GraphView<'_, N> {
+        GraphView::new(self, None)
+    }
+
+    /// Call [`Node::connections_changed`] on each of `nodes` that still
+    /// exists, or remember them while notifications are
+    /// [deferred](Self::defer_notifications)
+    pub(crate) fn notify_connections_changed(&mut self, nodes: &[NodeId]) {
+        if self.deferred_notifications.depth > 0 {
+            self.deferred_notifications.nodes.extend_from_slice(nodes);
+            return;
+        }
+
+        for &node in nodes.iter().unique() {
+            let Some(cell) = self.nodes.get(node) else {
+                continue;
+            };
+
+            cell.write()
+                .connections_changed(node, &GraphView::new(self, Some(node)));
+        }
+    }
+
+    /// Collect the nodes to notify until the matching
+    /// [`resume_notifications`](Self::resume_notifications), so each is
+    /// notified once. Returns the mark to resume with.
+    pub(crate) fn defer_notifications(&mut self) -> usize {
+        self.deferred_notifications.depth += 1;
+        self.deferred_notifications.nodes.len()
+    }
+
+    /// Notify the nodes collected since
+    /// [`defer_notifications`](Self::defer_notifications) returned `mark`, or
+    /// forget them if `notify` is false. Nodes are only notified once the
+    /// outermost deferral ends.
+    pub(crate) fn resume_notifications(&mut self, mark: usize, notify: bool) {
+        let deferred = &mut self.deferred_notifications;
+        deferred.depth -= 1;
+
+        if !notify {
+            deferred.nodes.truncate(mark);
+        }
+
+        if deferred.depth == 0 {
+            let nodes = std::mem::take(&mut deferred.nodes);
+            self.notify_connections_changed(&nodes);
+        }
+    }
+}
+
+/// Nodes whose connections changed while notifications were deferred, see
+/// [`Graph::defer_notifications`]
+#[derive(Debug, Default)]
+pub(crate) struct DeferredNotifications {
+    depth: usize,
+    nodes: Vec<NodeId>,
+}
@@ -1,31 +1,128 @@
+use std::{collections::HashMap, fmt::Debug, ops::Deref, sync::Arc, time::Instant};
+
 use slotmap::SecondaryMap;
 
 use crate::{
-    Graph, INVALID_STATE, Node, NodeId, OutputPortId,
-    analyzer::GraphAnalyzer,
+    Graph, INVALID_STATE, InputPortId, Node, NodeId, OutputPortId,
+    batch::{BatchError, BatchResult, GraphOp},
+    cache::OutputCacheDiff,
+    memo::Memoizer,
+    metadata::{CONNECTION_WEIGHT, Metadata},
+    metrics::{NodeInfo, WalkMetrics},
     reference::{
         InputPortReference, NodeInputIdentifier, NodeOutputIdentifier, OutputPortReference,
     },
+    trace::EvaluationTrace,
 };
 
 pub type OutputCache<T> = SecondaryMap<OutputPortId, T>;
 
+/// Structural changes requested by nodes during a walk, which can't be
+/// applied right away because the walker borrows the graph. Get them with
+/// [`GraphWalker::finish`] and apply them once the walker is dropped.
+#[derive(Debug, Clone)]
+pub struct MutationQueue<N: Node> {
+    ops: Vec<GraphOp<N>>,
+}
+
+impl<N: Node> MutationQueue<N> {
+    pub fn new() -> Self {
+        Self { ops: Vec::new() }
+    }
+
+    pub fn push(&mut self, op: GraphOp<N>) {
+        self.ops.push(op);
+    }
+
+    pub fn ops(&self) -> &[GraphOp<N>] {
+        &self.ops
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Apply all queued operations as a single [batch](Graph::apply_batch)
+    pub fn apply(self, graph: &mut Graph<N>) -> Result<BatchResult, BatchError> {
+        graph.apply_batch(self.ops)
+    }
+}
+
+impl<N: Node> Default for MutationQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type Watch<'a, N> = Box<dyn FnMut(&<N as Node>::DataValue) + Send + 'a>;
+
+/// Everything besides the output cache a walk context can change
+struct WalkState<'a, N: Node> {
+    mutations: MutationQueue<N>,
+    pause_requested: bool,
+    watches: SecondaryMap<OutputPortId, Vec<Watch<'a, N>>>,
+    /// Values set with [`GraphWalker::set_input`]
+    inputs: SecondaryMap<InputPortId, N::DataValue>,
+}
+
+impl<'a, N: Node> WalkState<'a, N> {
+    fn new() -> Self {
+        Self {
+            mutations: MutationQueue::new(),
+            pause_requested: false,
+            watches: SecondaryMap::new(),
+            inputs: SecondaryMap::new(),
+        }
+    }
+
+    /// Notify watches and graph observers of a value about to be written to
+    /// the output cache
+    fn notify(&mut self, graph: &Graph<N>, port: OutputPortId, value: &N::DataValue) {
+        if let Some(watches) = self.watches.get_mut(port) {
+            for watch in watches.iter_mut() {
+                watch(value);
+            }
+        }
+
+        graph.observers.notify(port, value);
+    }
+}
+
+impl<'a, N: Node> Debug for WalkState<'a, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalkState")
+            .field("mutations", &self.mutations.len())
+            .field("pause_requested", &self.pause_requested)
+            .field("watches", &self.watches.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
 pub struct GraphWalkContext<'a, 'b, N: Node> {
-    graph: &'a Graph<N>,
+    graph: &'b Graph<N>,
     output_cache: &'b mut OutputCache<N::DataValue>,
+    state: &'b mut WalkState<'a, N>,
     node: NodeId,
 }
 
 impl<'a, 'b, N: Node> GraphWalkContext<'a, 'b, N> {
     /// Get the computed output of an input port
     pub fn get<'c>(&self, input: impl NodeInputIdentifier<'c>) -> N::DataValue {
-        let input = input.combine(self.node);
+        let input = input
+            .combine(self.node)
+            .resolve(self.graph)
+            .expect("Input port does not exist");
 
         self.graph
-            .get_incoming_connections(input)
+            .get_sources(input)
             .filter_map(|port| self.output_cache.get(port))
-            .cloned()
             .next()
+            .or_else(|| self.state.inputs.get(input))
+            .cloned()
             .unwrap_or_else(|| {
                 self.graph
                     .get_input_port_info(input)
@@ -36,6 +133,8 @@ impl<'a, 'b, N: Node> GraphWalkContext<'a, 'b, N> {
             })
     }
 
+    /// The computed outputs of everything connected to an input port, in
+    /// connection order, see [`Graph::set_incoming_order`]
     pub fn get_all<'c>(
         &self,
         input: impl NodeInputIdentifier<'c>,
@@ -43,11 +142,93 @@ impl<'a, 'b, N: Node> GraphWalkContext<'a, 'b, N> {
         let input = input.combine(self.node);
 
         self.graph
-            .get_incoming_connections(input)
+            .get_sources(input)
+            .filter_map(|port| self.output_cache.get(port))
+            .cloned()
+    }
+
+    /// The computed outputs of everything connected to an input port, along
+    /// with the metadata of their connections
+    pub fn get_with_metadata<'c>(
+        &self,
+        input: impl NodeInputIdentifier<'c>,
+    ) -> impl Iterator<Item = (N::DataValue, &'b Metadata)> + '_ {
+        let input = input
+            .combine(self.node)
+            .resolve(self.graph)
+            .expect("Input port does not exist");
+
+        let graph = self.graph;
+
+        graph.input_ports[input]
+            .incoming_connections
+            .iter()
+            .filter_map(move |&id| {
+                let connection = graph.connections.get(id).expect(INVALID_STATE);
+                let source = graph.resolve_reroute(connection.start_port)?;
+                let value = self.output_cache.get(source)?;
+
+                Some((value.clone(), &connection.metadata))
+            })
+    }
+
+    /// The computed outputs of everything connected to an input port, each
+    /// with the weight of its connection. Weights are
+    /// [`CONNECTION_WEIGHT`] metadata entries, and default to 1.
+    pub fn get_weighted<'c>(
+        &self,
+        input: impl NodeInputIdentifier<'c>,
+    ) -> impl Iterator<Item = (N::DataValue, f64)> + '_ {
+        self.get_with_metadata(input).map(|(value, metadata)| {
+            let weight = metadata.get_as(CONNECTION_WEIGHT).unwrap_or(1.0);
+
+            (value, weight)
+        })
+    }
+
+    /// Combine the computed outputs of everything connected to an input port
+    pub fn fold_inputs<'c, T>(
+        &self,
+        input: impl NodeInputIdentifier<'c>,
+        init: T,
+        f: impl FnMut(T, N::DataValue) -> T,
+    ) -> T {
+        self.get_all(input).fold(init, f)
+    }
+
+    /// The computed outputs of everything connected to an input port
+    pub fn collect_inputs<'c>(&self, input: impl NodeInputIdentifier<'c>) -> Vec<N::DataValue> {
+        self.get_all(input).collect()
+    }
+
+    /// The number of connections to an input port
+    pub fn connected_count<'c>(&self, input: impl NodeInputIdentifier<'c>) -> usize {
+        self.graph
+            .get_input_port_info(input.combine(self.node))
+            .expect("Input port does not exist")
+            .incoming_connections
+            .len()
+    }
+
+    /// Get the computed outputs connected to a
+    /// [variadic input](Graph::create_variadic_input), in port order
+    pub fn get_variadic(&self, base_name: &str) -> impl Iterator<Item = N::DataValue> + '_ {
+        self.graph
+            .get_variadic_input(self.node, base_name)
+            .expect("Variadic input does not exist")
+            .ports
+            .iter()
+            .flat_map(|&port| self.graph.get_sources(port))
             .filter_map(|port| self.output_cache.get(port))
             .cloned()
     }
 
+    /// Get an output that was already computed, of any node in the graph.
+    /// Returns `None` if the node has not been evaluated yet.
+    pub fn get_output_of(&self, port: impl OutputPortReference) -> Option<N::DataValue> {
+        self.output_cache.get(port.resolve(self.graph)?).cloned()
+    }
+
     /// Set the value of an output port
     pub fn set<'c>(
         &mut self,
@@ -55,14 +236,68 @@ impl<'a, 'b, N: Node> GraphWalkContext<'a, 'b, N> {
         value: impl Into<N::DataValue>,
     ) {
         let value: N::DataValue = value.into();
-        let output = output.combine(self.node);
+        let output = output
+            .combine(self.node)
+            .resolve(self.graph)
+            .expect("Output port does not exist");
 
-        self.output_cache.insert(
-            output
-                .resolve(self.graph)
-                .expect("Output port does not exist"),
-            value,
-        );
+        self.state.notify(self.graph, output, &value);
+
+        self.output_cache.insert(output, value);
+    }
+
+    /// The id of the node being evaluated
+    pub fn node(&self) -> NodeId {
+        self.node
+    }
+
+    /// The name, id and type of every input port of this node, in order
+    pub fn input_ports(&self) -> impl Iterator<Item = (&'b str, InputPortId, N::DataType)> + 'b {
+        let graph = self.graph;
+
+        graph.node_data[self.node]
+            .inputs
+            .iter()
+            .map(move |(name, id)| (&**name, *id, graph.input_ports[*id].ty))
+    }
+
+    /// The name, id and type of every output port of this node, in order
+    pub fn output_ports(&self) -> impl Iterator<Item = (&'b str, OutputPortId, N::DataType)> + 'b {
+        let graph = self.graph;
+
+        graph.node_data[self.node]
+            .outputs
+            .iter()
+            .map(move |(name, id)| (&**name, *id, graph.output_ports[*id].ty))
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.graph.node_data[self.node].inputs.len()
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.graph.node_data[self.node].outputs.len()
+    }
+
+    /// Whether anything is connected to an input port
+    pub fn is_connected<'c>(&self, input: impl NodeInputIdentifier<'c>) -> bool {
+        !self
+            .graph
+            .get_input_port_info(input.combine(self.node))
+            .expect("Input port does not exist")
+            .incoming_connections
+            .is_empty()
+    }
+
+    /// Stop the walk after this node, see [`GraphWalker::resume`]
+    pub fn pause(&mut self) {
+        self.state.pause_requested = true;
+    }
+
+    /// Queue a structural change, applied after the walk, see
+    /// [`GraphWalker::finish`]
+    pub fn enqueue(&mut self, op: GraphOp<N>) {
+        self.state.mutations.push(op);
     }
 
     pub fn can_get(&self, input: impl NodeInputIdentifier<'a>) -> bool {
@@ -74,55 +309,498 @@ impl<'a, 'b, N: Node> GraphWalkContext<'a, 'b, N> {
     }
 }
 
+/// How a walk ended, see [`GraphWalker::walk`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkStatus {
+    /// Every node on the path was evaluated
+    Finished,
+    /// A node [paused](GraphWalkContext::pause) the walk, `next` is the node
+    /// that will be evaluated when it is resumed
+    Paused { next: NodeId },
+    /// The walk halted before evaluating a node with a
+    /// [breakpoint](GraphWalker::add_breakpoint)
+    Breakpoint { node: NodeId },
+}
+
+/// A single node evaluation, see [`GraphWalker::step`]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "N::DataValue: serde::Serialize",
+        deserialize = "N::DataValue: serde::Deserialize<'de>"
+    ))
+)]
+pub struct WalkStep<N: Node> {
+    pub node: NodeId,
+    /// The value of each input port as the node saw it, or `None` for
+    /// disconnected ports without a default
+    pub inputs: Vec<(InputPortId, Option<N::DataValue>)>,
+    /// The value of each output port after evaluating the node, or `None` if
+    /// the node didn't set it
+    pub outputs: Vec<(OutputPortId, Option<N::DataValue>)>,
+}
+
+impl<N: Node> Clone for WalkStep<N> {
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node,
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+        }
+    }
+}
+
+/// The graph a walker evaluates, either borrowed or shared with other
+/// walkers
+#[derive(Debug)]
+enum GraphRef<'a, N: Node> {
+    Borrowed(&'a Graph<N>),
+    Shared(Arc<Graph<N>>),
+}
+
+impl<'a, N: Node> Deref for GraphRef<'a, N> {
+    type Target = Graph<N>;
+
+    fn deref(&self) -> &Graph<N> {
+        match self {
+            Self::Borrowed(graph) => graph,
+            Self::Shared(graph) => graph,
+        }
+    }
+}
+
+/// Evaluates the nodes of a graph in dependency order.
+///
+/// Walkers only need shared access to the graph and keep their outputs in
+/// their own cache, so any number of walkers can evaluate the same graph at
+/// once. When `N: Send + Sync`, a `&Graph<N>` can be walked from multiple
+/// scoped threads, and [`new_shared`](Self::new_shared) creates walkers that
+/// can be moved into spawned threads or tasks.
 #[derive(Debug)]
 pub struct GraphWalker<'a, N: Node> {
-    graph: &'a Graph<N>,
+    graph: GraphRef<'a, N>,
     path: Vec<NodeId>,
     output_cache: SecondaryMap<OutputPortId, N::DataValue>,
+    /// The name of `output_cache`, see [`with_cache`](Self::with_cache)
+    cache_name: String,
+    /// Every other cache by name
+    inactive_caches: HashMap<String, OutputCache<N::DataValue>>,
+    position: usize,
+    breakpoints: SecondaryMap<NodeId, ()>,
+    /// Whether the walk halted at the breakpoint at `position`, so resuming
+    /// doesn't halt at it again
+    at_breakpoint: bool,
+    state: WalkState<'a, N>,
+    trace: Option<EvaluationTrace<N>>,
+    retain_previous: bool,
+    previous_cache: Option<OutputCache<N::DataValue>>,
+    memoizer: Option<Memoizer<N>>,
+    metrics: Option<Box<dyn WalkMetrics + Send + 'a>>,
+    /// When the current walk started, if metrics are reported
+    walk_started: Option<Instant>,
 }
 
 impl<'a, N: Node> GraphWalker<'a, N> {
+    /// The name of the cache a walker starts with
+    pub const DEFAULT_CACHE: &'static str = "default";
+
     /// If `exit_nodes` is left as `None`, exit nodes will automatically be
     /// calculated
     pub fn new(graph: &'a Graph<N>, exit_nodes: Option<&[NodeId]>) -> Self {
-        Self {
-            graph,
-            path: match exit_nodes {
-                Some(exit_nodes) => GraphAnalyzer::new(graph).generate_execution_path(exit_nodes),
-                None => GraphAnalyzer::new(graph).generate_complete_execution_path(),
-            },
-            output_cache: SecondaryMap::with_capacity(graph.node_data.len()),
-        }
+        let path = graph.cached_execution_path(exit_nodes);
+
+        Self::with_graph(GraphRef::Borrowed(graph), path, None)
     }
 
     pub fn from_path(
         graph: &'a Graph<N>,
         path: Vec<NodeId>,
         cache: Option<SecondaryMap<OutputPortId, N::DataValue>>,
+    ) -> Self {
+        Self::with_graph(GraphRef::Borrowed(graph), path, cache)
+    }
+
+    fn with_graph(
+        graph: GraphRef<'a, N>,
+        path: Vec<NodeId>,
+        cache: Option<SecondaryMap<OutputPortId, N::DataValue>>,
     ) -> Self {
         Self {
-            graph,
-            path,
             output_cache: cache
                 .unwrap_or_else(|| SecondaryMap::with_capacity(graph.node_data.len())),
+            graph,
+            path,
+            cache_name: Self::DEFAULT_CACHE.to_string(),
+            inactive_caches: HashMap::new(),
+            position: 0,
+            breakpoints: SecondaryMap::new(),
+            at_breakpoint: false,
+            state: WalkState::new(),
+            trace: None,
+            retain_previous: false,
+            previous_cache: None,
+            memoizer: None,
+            metrics: None,
+            walk_started: None,
+        }
+    }
+
+    /// Evaluate every node on the path, from the start
+    pub fn walk<F: for<'b> Fn(&mut N, &mut GraphWalkContext<'a, 'b, N>)>(
+        &mut self,
+        callback: F,
+    ) -> WalkStatus {
+        self.position = 0;
+        self.at_breakpoint = false;
+
+        if self.retain_previous {
+            self.previous_cache = Some(self.output_cache.clone());
+        }
+
+        if let Some(trace) = &mut self.trace {
+            trace.clear();
+        }
+
+        self.walk_started = self.metrics.as_ref().map(|_| Instant::now());
+
+        self.resume(callback)
+    }
+
+    /// Evaluate the remaining nodes on the path, continuing where a paused
+    /// walk or [stepping](Self::step) left off
+    pub fn resume<F: for<'b> Fn(&mut N, &mut GraphWalkContext<'a, 'b, N>)>(
+        &mut self,
+        callback: F,
+    ) -> WalkStatus {
+        while let Some(&id) = self.path.get(self.position) {
+            if self.breakpoints.contains_key(id) && !std::mem::take(&mut self.at_breakpoint) {
+                self.at_breakpoint = true;
+                return WalkStatus::Breakpoint { node: id };
+            }
+
+            self.evaluate(id, &callback, false);
+            self.position += 1;
+
+            if std::mem::take(&mut self.state.pause_requested)
+                && let Some(&next) = self.path.get(self.position)
+            {
+                return WalkStatus::Paused { next };
+            }
+        }
+
+        if let Some(metrics) = &mut self.metrics
+            && let Some(started) = self.walk_started.take()
+        {
+            metrics.walk_finished(self.path.len(), started.elapsed());
         }
+
+        WalkStatus::Finished
+    }
+
+    /// Evaluate only the next node on the path, returning what it read and
+    /// wrote, or `None` if the walk is finished
+    pub fn step<F: for<'b> Fn(&mut N, &mut GraphWalkContext<'a, 'b, N>)>(
+        &mut self,
+        callback: F,
+    ) -> Option<WalkStep<N>> {
+        let &id = self.path.get(self.position)?;
+
+        let step = self.evaluate(id, &callback, true);
+        self.position += 1;
+        self.at_breakpoint = false;
+        self.state.pause_requested = false;
+
+        step
+    }
+
+    /// Evaluate a node, and return what it read and wrote if `record` is set
+    /// or tracing is enabled
+    fn evaluate<F: for<'b> Fn(&mut N, &mut GraphWalkContext<'a, 'b, N>)>(
+        &mut self,
+        id: NodeId,
+        callback: &F,
+        record: bool,
+    ) -> Option<WalkStep<N>> {
+        if !record && self.trace.is_none() {
+            self.evaluate_node(id, callback);
+            return None;
+        }
+
+        let data = &self.graph.node_data[id];
+
+        let inputs = data
+            .inputs
+            .iter()
+            .map(|&(_, port)| (port, self.input_value(port).cloned()))
+            .collect();
+
+        self.evaluate_node(id, callback);
+
+        let outputs = self.graph.node_data[id]
+            .outputs
+            .iter()
+            .map(|&(_, port)| (port, self.output_cache.get(port).cloned()))
+            .collect();
+
+        let step = WalkStep {
+            node: id,
+            inputs,
+            outputs,
+        };
+
+        if let Some(trace) = &mut self.trace {
+            trace.record(step.clone());
+        }
+
+        record.then_some(step)
     }
 
-    pub fn walk<F: for<'b> Fn(&mut N, &mut GraphWalkContext<'a, 'b, N>)>(&mut self, callback: F) {
-        for &id in self.path.iter() {
-            let mut node = self.graph.get_node_mut(id).expect(INVALID_STATE);
-            let mut context = GraphWalkContext {
-                graph: self.graph,
-                output_cache: &mut self.output_cache,
-                node: id,
-            };
+    /// An iterator that evaluates one node every time it is advanced. Stop
+    /// advancing it to pause, and call [`resume`](Self::resume) or `steps`
+    /// again to continue.
+    pub fn steps<F: for<'b> Fn(&mut N, &mut GraphWalkContext<'a, 'b, N>) + 'a>(
+        &mut self,
+        callback: F,
+    ) -> impl Iterator<Item = WalkStep<N>> + '_ {
+        std::iter::from_fn(move || self.step(&callback))
+    }
 
-            callback(&mut node, &mut context);
+    /// Keep a copy of the output cache every time a new [`walk`](Self::walk)
+    /// starts, so the values of the last walk can be compared to the ones
+    /// before it with [`changed_outputs`](Self::changed_outputs)
+    pub fn retain_previous_cache(&mut self, retain: bool) {
+        self.retain_previous = retain;
+
+        if !retain {
+            self.previous_cache = None;
         }
     }
 
-    pub fn graph(&'a self) -> &'a Graph<N> {
+    /// The output cache as it was before the last walk started
+    pub fn previous_cache(&self) -> Option<&OutputCache<N::DataValue>> {
+        self.previous_cache.as_ref()
+    }
+
+    /// The ports whose value changed during the last walk according to `eq`,
+    /// or `None` if the previous cache isn't retained
+    pub fn changed_outputs(
+        &self,
+        eq: impl Fn(&N::DataValue, &N::DataValue) -> bool,
+    ) -> Option<Vec<OutputPortId>> {
+        Some(OutputCacheDiff::compare(
+            self.previous_cache.as_ref()?,
+            &self.output_cache,
+            eq,
+        ))
+    }
+
+    /// Skip nodes whose inputs are the same as when `memoizer` last saw them,
+    /// reusing their outputs instead. Take the memoizer back with
+    /// [`take_memoizer`](Self::take_memoizer) to use it for the next walk.
+    pub fn set_memoizer(&mut self, memoizer: Memoizer<N>) {
+        self.memoizer = Some(memoizer);
+    }
+
+    pub fn memoizer(&self) -> Option<&Memoizer<N>> {
+        self.memoizer.as_ref()
+    }
+
+    pub fn take_memoizer(&mut self) -> Option<Memoizer<N>> {
+        self.memoizer.take()
+    }
+
+    /// Report evaluations, memoizer hits and misses and finished walks to
+    /// `metrics`. Walks are only reported when they finish by
+    /// [`walk`](Self::walk) or [`resume`](Self::resume), not by stepping.
+    pub fn set_metrics(&mut self, metrics: impl WalkMetrics + Send + 'a) {
+        self.metrics = Some(Box::new(metrics));
+    }
+
+    pub fn metrics(&self) -> Option<&(dyn WalkMetrics + Send + 'a)> {
+        self.metrics.as_deref()
+    }
+
+    pub fn take_metrics(&mut self) -> Option<Box<dyn WalkMetrics + Send + 'a>> {
+        self.walk_started = None;
+        self.metrics.take()
+    }
+
+    /// Record the inputs and outputs of every node evaluated from now on,
+    /// see [`trace`](Self::trace). The trace is cleared when a new
+    /// [`walk`](Self::walk) starts.
+    pub fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(EvaluationTrace::new);
+    }
+
+    pub fn disable_trace(&mut self) {
+        self.trace = None;
+    }
+
+    pub fn trace(&self) -> Option<&EvaluationTrace<N>> {
+        self.trace.as_ref()
+    }
+
+    pub fn take_trace(&mut self) -> Option<EvaluationTrace<N>> {
+        self.trace.take()
+    }
+
+    /// Halt [`walk`](Self::walk) and [`resume`](Self::resume) before
+    /// evaluating `node`. Resuming continues with evaluating it.
+    pub fn add_breakpoint(&mut self, node: NodeId) {
+        self.breakpoints.insert(node, ());
+    }
+
+    pub fn remove_breakpoint(&mut self, node: NodeId) {
+        self.breakpoints.remove(node);
+    }
+
+    /// Call `callback` with every value written to `port` during the walk
+    pub fn add_watch(
+        &mut self,
+        port: OutputPortId,
+        callback: impl FnMut(&N::DataValue) + Send + 'a,
+    ) {
+        self.state
+            .watches
+            .entry(port)
+            .expect("Output port does not exist")
+            .or_default()
+            .push(Box::new(callback));
+    }
+
+    pub fn clear_watches(&mut self) {
+        self.state.watches.clear();
+    }
+
+    /// Use `value` for a disconnected input port instead of its default
+    /// value, in this walk and every later one
+    pub fn set_input(&mut self, port: InputPortId, value: N::DataValue) {
+        self.state.inputs.insert(port, value);
+    }
+
+    /// Go back to the default values of all inputs set with
+    /// [`set_input`](Self::set_input)
+    pub fn clear_inputs(&mut self) {
+        self.state.inputs.clear();
+    }
+
+    /// The index into [`path`](Self::path) of the next node to evaluate
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.path.len()
+    }
+
+    /// The value `port` reads: the value of its first connection, the value
+    /// set with [`set_input`](Self::set_input) or its default value
+    fn input_value(&self, port: InputPortId) -> Option<&N::DataValue> {
         self.graph
+            .get_sources(port)
+            .find_map(|port| self.output_cache.get(port))
+            .or(self.state.inputs.get(port))
+            .or(self.graph.input_ports[port].default.as_ref())
+    }
+
+    fn evaluate_node<F: for<'b> Fn(&mut N, &mut GraphWalkContext<'a, 'b, N>)>(
+        &mut self,
+        id: NodeId,
+        callback: &F,
+    ) {
+        #[cfg(feature = "tracing")]
+        let _span = node_span(&self.graph, id).entered();
+
+        #[cfg(feature = "tracing")]
+        trace_inputs(
+            &self.graph,
+            id,
+            self.graph.node_data[id]
+                .inputs
+                .iter()
+                .map(|&(_, port)| self.input_value(port)),
+        );
+
+        self.evaluate_node_untraced(id, callback);
+
+        #[cfg(feature = "tracing")]
+        trace_outputs(
+            &self.graph,
+            id,
+            self.graph.node_data[id]
+                .outputs
+                .iter()
+                .map(|&(_, port)| self.output_cache.get(port)),
+        );
+    }
+
+    fn evaluate_node_untraced<F: for<'b> Fn(&mut N, &mut GraphWalkContext<'a, 'b, N>)>(
+        &mut self,
+        id: NodeId,
+        callback: &F,
+    ) {
+        let key = self
+            .memoizer
+            .as_ref()
+            .filter(|_| self.graph.nodes[id].read().is_pure())
+            .map(|memoizer| memoizer.key(&self.graph, &self.output_cache, &self.state.inputs, id));
+
+        if let Some(key) = &key
+            && let Some(outputs) = self.memoizer.as_ref().expect(INVALID_STATE).get(id, key)
+        {
+            for (port, value) in outputs {
+                self.state.notify(&self.graph, *port, value);
+                self.output_cache.insert(*port, value.clone());
+            }
+
+            if let Some(metrics) = &mut self.metrics {
+                metrics.cache_hit(NodeInfo::new(&self.graph, id));
+            }
+
+            return;
+        }
+
+        let started = self.metrics.as_ref().map(|_| Instant::now());
+
+        let mut node = self.graph.get_node_mut(id).expect(INVALID_STATE);
+        let mut context = GraphWalkContext {
+            graph: &self.graph,
+            output_cache: &mut self.output_cache,
+            state: &mut self.state,
+            node: id,
+        };
+
+        callback(&mut node, &mut context);
+        drop(node);
+
+        if let Some(metrics) = &mut self.metrics {
+            let info = NodeInfo::new(&self.graph, id);
+
+            metrics.node_evaluated(info, started.expect(INVALID_STATE).elapsed());
+
+            if key.is_some() {
+                metrics.cache_miss(info);
+            }
+        }
+
+        if let Some(key) = key {
+            let outputs = self.graph.node_data[id]
+                .outputs
+                .iter()
+                .filter_map(|&(_, port)| Some((port, self.output_cache.get(port)?.clone())))
+                .collect();
+
+            self.memoizer
+                .as_mut()
+                .expect(INVALID_STATE)
+                .insert(id, key, outputs);
+        }
+    }
+
+    pub fn graph(&self) -> &Graph<N> {
+        &self.graph
     }
 
     pub fn path(&self) -> &[NodeId] {
@@ -131,13 +809,132 @@ impl<'a, N: Node> GraphWalker<'a, N> {
 
     pub fn get<'b>(&'b mut self, node: NodeId) -> GraphWalkContext<'a, 'b, N> {
         GraphWalkContext {
-            graph: self.graph,
+            graph: &self.graph,
             output_cache: &mut self.output_cache,
+            state: &mut self.state,
             node,
         }
     }
 
+    pub(crate) fn output_cache(&self) -> &OutputCache<N::DataValue> {
+        &self.output_cache
+    }
+
+    pub(crate) fn output_cache_mut(&mut self) -> &mut OutputCache<N::DataValue> {
+        &mut self.output_cache
+    }
+
+    pub(crate) fn memoizer_mut(&mut self) -> Option<&mut Memoizer<N>> {
+        self.memoizer.as_mut()
+    }
+
+    /// The graph along with the current cache and the memoizer, for changing
+    /// them based on the graph
+    pub(crate) fn caches_mut(
+        &mut self,
+    ) -> (
+        &Graph<N>,
+        &mut OutputCache<N::DataValue>,
+        Option<&mut Memoizer<N>>,
+    ) {
+        (&self.graph, &mut self.output_cache, self.memoizer.as_mut())
+    }
+
+    /// The value last written to an output port
+    pub fn get_output(&self, port: OutputPortId) -> Option<&N::DataValue> {
+        self.output_cache.get(port)
+    }
+
+    /// Switch to the output cache called `name`, creating it if there is no
+    /// such cache yet. The current cache is kept, so walks can alternate
+    /// between caches, like one for previews and one for full results,
+    /// without recomputing or reallocating them.
+    pub fn with_cache(&mut self, name: &str) -> &mut Self {
+        if name != self.cache_name {
+            let cache = self.inactive_caches.remove(name).unwrap_or_default();
+            let previous = std::mem::replace(&mut self.output_cache, cache);
+
+            self.inactive_caches.insert(
+                std::mem::replace(&mut self.cache_name, name.to_string()),
+                previous,
+            );
+
+            // It was kept for the other cache
+            self.previous_cache = None;
+        }
+
+        self
+    }
+
+    /// The name of the cache walks currently use
+    pub fn cache_name(&self) -> &str {
+        &self.cache_name
+    }
+
+    /// The names of all caches, including the current one
+    pub fn cache_names(&self) -> impl Iterator<Item = &str> + '_ {
+        std::iter::once(self.cache_name.as_str())
+            .chain(self.inactive_caches.keys().map(String::as_str))
+    }
+
+    /// Remove a cache other than the current one, returning its values
+    pub fn remove_cache(&mut self, name: &str) -> Option<OutputCache<N::DataValue>> {
+        self.inactive_caches.remove(name)
+    }
+
     pub fn release_cache(self) -> SecondaryMap<OutputPortId, N::DataValue> {
         self.output_cache
     }
+
+    /// End the walk, returning the output cache and the structural changes
+    /// nodes [queued](GraphWalkContext::enqueue) during it. Apply them with
+    /// [`MutationQueue::apply`] once the walker no longer borrows the graph.
+    pub fn finish(self) -> (OutputCache<N::DataValue>, MutationQueue<N>) {
+        (self.output_cache, self.state.mutations)
+    }
+}
+
+impl<N: Node + Send + Sync> GraphWalker<'static, N> {
+    /// Create a walker that shares ownership of the graph, so it can be moved
+    /// into a spawned thread or task. See [`new`](Self::new) for
+    /// `exit_nodes`.
+    pub fn new_shared(graph: Arc<Graph<N>>, exit_nodes: Option<&[NodeId]>) -> Self {
+        let path = graph.cached_execution_path(exit_nodes);
+
+        Self::with_graph(GraphRef::Shared(graph), path, None)
+    }
+}
+
+/// A span covering the evaluation of `node`, named after the node, or its
+/// [variant](Node::variant_name) if it has no name
+#[cfg(feature = "tracing")]
+pub(crate) fn node_span<N: Node>(graph: &Graph<N>, node: NodeId) -> tracing::Span {
+    let variant = graph.nodes[node].read().variant_name();
+    let name = graph.get_node_name(node).unwrap_or(variant);
+
+    tracing::info_span!("node", name, id = ?node)
+}
+
+/// Log the values read by each input port of `node` at debug level
+#[cfg(feature = "tracing")]
+pub(crate) fn trace_inputs<N: Node>(
+    graph: &Graph<N>,
+    node: NodeId,
+    values: impl IntoIterator<Item = impl Debug>,
+) {
+    for ((name, _), value) in graph.node_data[node].inputs.iter().zip(values) {
+        tracing::debug!(port = &**name, value = ?value, "input");
+    }
+}
+
+/// Log the values written to each output port of `node` at debug level
+#[cfg(feature = "tracing")]
+pub(crate) fn trace_outputs<N: Node>(
+    graph: &Graph<N>,
+    node: NodeId,
+    values: impl IntoIterator<Item = impl Debug>,
+) {
+    for ((name, _), value) in graph.node_data[node].outputs.iter().zip(values) {
+        tracing::debug!(port = &**name, value = ?value, "output");
+    }
 }
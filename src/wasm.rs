@@ -0,0 +1,282 @@
+//! Nodes evaluated by sandboxed WebAssembly modules, for running untrusted
+//! node implementations.
+//!
+//! A module exports its `memory`, an `alloc(len: i32) -> i32` function that
+//! reserves `len` bytes, and an `evaluate(ptr: i32, len: i32) -> i64`
+//! function. The input values are written to memory reserved with `alloc`
+//! and passed to `evaluate`, which returns where it wrote the output values
+//! as `ptr << 32 | len`. Both are lists of values in port order, each
+//! prefixed with its length in bytes as a little-endian `u32`, with the
+//! bytes of a value up to [`WasmHost`].
+//!
+//! Modules can't import anything, every evaluation starts from a fresh
+//! instance, and evaluations are limited in how many instructions they run
+//! and how much memory they use.
+
+use std::fmt::{Debug, Display};
+
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+
+use crate::{Graph, Node, NodeId, walker::GraphWalkContext};
+
+/// A node type that can contain [`WasmNode`]s, describing how its values are
+/// passed to and from modules
+pub trait WasmHost: Node {
+    fn to_bytes(value: &Self::DataValue) -> Vec<u8>;
+
+    /// Read a value of type `ty`, or `None` if `bytes` don't make one
+    fn from_bytes(bytes: &[u8], ty: Self::DataType) -> Option<Self::DataValue>;
+}
+
+#[derive(Debug)]
+pub enum WasmError {
+    /// Compiling, instantiating or running the module failed
+    Wasm(wasmtime::Error),
+    /// The evaluation ran more instructions than its fuel allows
+    OutOfFuel,
+    /// The output values are cut off or don't decode
+    InvalidOutput { name: String },
+}
+
+impl Display for WasmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Wasm(error) => write!(f, "WASM module failed: {error}"),
+            Self::OutOfFuel => write!(f, "WASM module ran out of fuel"),
+            Self::InvalidOutput { name } => {
+                write!(f, "WASM module returned invalid output {name:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WasmError {}
+
+impl From<wasmtime::Error> for WasmError {
+    fn from(error: wasmtime::Error) -> Self {
+        match error.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => Self::OutOfFuel,
+            _ => Self::Wasm(error),
+        }
+    }
+}
+
+/// Compiles modules for [`WasmNode`]s. Nodes using modules from the same
+/// runtime share its compiler settings.
+#[derive(Clone)]
+pub struct WasmRuntime {
+    engine: Engine,
+}
+
+impl WasmRuntime {
+    pub fn new() -> Result<Self, WasmError> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+
+        Ok(Self {
+            engine: Engine::new(&config)?,
+        })
+    }
+}
+
+impl Debug for WasmRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmRuntime").finish_non_exhaustive()
+    }
+}
+
+/// A compiled module along with the ports it reads and writes. Wrap it in a
+/// variant of the node type, which should have no initial ports, and add it
+/// with [`Graph::create_wasm_node`].
+pub struct WasmNode<N: WasmHost> {
+    module: Module,
+    inputs: Vec<(String, N::DataType, N::DataValue)>,
+    outputs: Vec<(String, N::DataType)>,
+    fuel: u64,
+    memory_limit: usize,
+}
+
+impl<N: WasmHost> WasmNode<N> {
+    /// Instructions an evaluation may run by default, roughly
+    pub const DEFAULT_FUEL: u64 = 10_000_000;
+
+    /// Bytes of memory an evaluation may use by default
+    pub const DEFAULT_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+    /// Compile a module from its binary format. It has no ports until they
+    /// are added with [`input`](Self::input) and [`output`](Self::output).
+    pub fn new(runtime: &WasmRuntime, wasm: &[u8]) -> Result<Self, WasmError> {
+        Ok(Self {
+            module: Module::from_binary(&runtime.engine, wasm)?,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            fuel: Self::DEFAULT_FUEL,
+            memory_limit: Self::DEFAULT_MEMORY_LIMIT,
+        })
+    }
+
+    pub fn input(mut self, name: &str, ty: N::DataType, default: N::DataValue) -> Self {
+        self.inputs.push((name.to_string(), ty, default));
+        self
+    }
+
+    pub fn output(mut self, name: &str, ty: N::DataType) -> Self {
+        self.outputs.push((name.to_string(), ty));
+        self
+    }
+
+    pub fn fuel(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = bytes;
+        self
+    }
+
+    pub fn inputs(&self) -> &[(String, N::DataType, N::DataValue)] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[(String, N::DataType)] {
+        &self.outputs
+    }
+
+    /// Run the module, reading inputs from and writing outputs to `context`.
+    /// Outputs are only written if all of them could be read.
+    pub fn evaluate(&self, context: &mut GraphWalkContext<'_, '_, N>) -> Result<(), WasmError> {
+        let mut input = Vec::new();
+
+        for (name, _, _) in self.inputs.iter() {
+            let bytes = N::to_bytes(&context.get(name.as_str()));
+
+            input.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            input.extend_from_slice(&bytes);
+        }
+
+        let output = self.call(&input)?;
+
+        let mut values = Vec::with_capacity(self.outputs.len());
+        let mut rest = output.as_slice();
+
+        for (name, ty) in self.outputs.iter() {
+            let value = read_value(&mut rest).and_then(|bytes| N::from_bytes(bytes, *ty));
+
+            match value {
+                Some(value) => values.push(value),
+                None => return Err(WasmError::InvalidOutput { name: name.clone() }),
+            }
+        }
+
+        for ((name, _), value) in self.outputs.iter().zip(values) {
+            context.set(name.as_str(), value);
+        }
+
+        Ok(())
+    }
+
+    /// Instantiate the module and pass `input` to its `evaluate` function,
+    /// returning the bytes it wrote
+    fn call(&self, input: &[u8]) -> Result<Vec<u8>, WasmError> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.memory_limit)
+            .instances(1)
+            .build();
+
+        let mut store = Store::new(self.module.engine(), limits);
+        store.limiter(|limits: &mut StoreLimits| limits);
+        store.set_fuel(self.fuel)?;
+
+        let instance = Instance::new(&mut store, &self.module, &[])?;
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| wasmtime::Error::msg("Module does not export its memory"))?;
+
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let evaluate = instance.get_typed_func::<(i32, i32), i64>(&mut store, "evaluate")?;
+
+        let input_len = input.len() as i32;
+        let input_ptr = alloc.call(&mut store, input_len)?;
+
+        memory
+            .write(&mut store, input_ptr as u32 as usize, input)
+            .map_err(wasmtime::Error::from)?;
+
+        let packed = evaluate.call(&mut store, (input_ptr, input_len))? as u64;
+
+        let output_ptr = (packed >> 32) as usize;
+        let output_len = packed as u32 as usize;
+
+        // Bounds checked against the memory instead of allocating whatever
+        // length the module claims
+        let output = memory
+            .data(&store)
+            .get(output_ptr..output_ptr + output_len)
+            .ok_or_else(|| wasmtime::Error::msg("Output is outside of memory"))?;
+
+        Ok(output.to_vec())
+    }
+}
+
+/// Split one length-prefixed value off the front of `bytes`
+fn read_value<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let (len, rest) = bytes.split_first_chunk::<4>()?;
+    let len = u32::from_le_bytes(*len) as usize;
+
+    if rest.len() < len {
+        return None;
+    }
+
+    let (value, rest) = rest.split_at(len);
+    *bytes = rest;
+
+    Some(value)
+}
+
+impl<N: WasmHost> Clone for WasmNode<N> {
+    fn clone(&self) -> Self {
+        Self {
+            module: self.module.clone(),
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+            fuel: self.fuel,
+            memory_limit: self.memory_limit,
+        }
+    }
+}
+
+impl<N: WasmHost> Debug for WasmNode<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmNode")
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .field("fuel", &self.fuel)
+            .field("memory_limit", &self.memory_limit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<N: WasmHost> Graph<N> {
+    /// Create a node wrapping `wasm`, with the ports it was given
+    pub fn create_wasm_node(&mut self, wasm: WasmNode<N>) -> NodeId
+    where
+        WasmNode<N>: Into<N>,
+    {
+        let inputs = wasm.inputs.clone();
+        let outputs = wasm.outputs.clone();
+
+        let node = self.create_node(wasm);
+
+        for (name, ty, default) in inputs {
+            self.create_input_port(node, &name, ty, default);
+        }
+
+        for (name, ty) in outputs {
+            self.create_output_port(node, &name, ty);
+        }
+
+        node
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use crate::{
+    Graph, Node, NodeId, OutputPortId,
+    walker::{GraphWalkContext, GraphWalker, OutputCache, WalkStatus},
+};
+
+/// A node type that can contain instances of other graphs of a
+/// [`GraphWorkspace`]
+pub trait GraphInstanceNode: Node {
+    /// The key of the workspace graph this node is an instance of, or `None`
+    /// if it is a regular node
+    fn instance_of(&self) -> Option<&str>;
+}
+
+/// Several graphs stored by key, whose nodes can be instances of other
+/// graphs in the workspace, like materials using shared function graphs
+#[derive(Debug)]
+pub struct GraphWorkspace<N: Node> {
+    graphs: BTreeMap<String, Graph<N>>,
+}
+
+/// Returned by [`GraphWorkspace::evaluation_order`] and
+/// [`GraphWorkspace::evaluate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkspaceError {
+    /// A node is an instance of a graph that isn't in the workspace
+    MissingGraph {
+        graph: String,
+        node: NodeId,
+        instance_of: String,
+    },
+    /// These graphs can't be ordered, because they are instances of
+    /// themselves or depend on graphs that are
+    Cycle(Vec<String>),
+}
+
+impl Display for WorkspaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingGraph {
+                graph,
+                node,
+                instance_of,
+            } => write!(
+                f,
+                "Node {node:?} of graph {graph:?} is an instance of missing graph {instance_of:?}"
+            ),
+            Self::Cycle(graphs) => write!(
+                f,
+                "Graphs {graphs:?} depend on themselves through instances"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WorkspaceError {}
+
+/// The output caches of all graphs after [`GraphWorkspace::evaluate`], so
+/// far. Instance nodes read the outputs of their graph from it.
+#[derive(Debug)]
+pub struct WorkspaceResults<N: Node> {
+    caches: BTreeMap<String, OutputCache<N::DataValue>>,
+}
+
+impl<N: Node> WorkspaceResults<N> {
+    /// The value last written to an output port of graph `key`
+    pub fn get_output(&self, key: &str, port: OutputPortId) -> Option<&N::DataValue> {
+        self.caches.get(key)?.get(port)
+    }
+
+    /// The output cache of graph `key`, if it was evaluated
+    pub fn cache(&self, key: &str) -> Option<&OutputCache<N::DataValue>> {
+        self.caches.get(key)
+    }
+
+    pub fn into_caches(self) -> BTreeMap<String, OutputCache<N::DataValue>> {
+        self.caches
+    }
+}
+
+impl<N: Node> GraphWorkspace<N> {
+    pub fn new() -> Self {
+        Self {
+            graphs: BTreeMap::new(),
+        }
+    }
+
+    /// Add a graph, returning the graph previously stored under `key`
+    pub fn insert(&mut self, key: &str, graph: Graph<N>) -> Option<Graph<N>> {
+        self.graphs.insert(key.to_string(), graph)
+    }
+
+    /// Remove a graph. Instances of it are kept, but the workspace can't be
+    /// evaluated until it is added again.
+    pub fn remove(&mut self, key: &str) -> Option<Graph<N>> {
+        self.graphs.remove(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Graph<N>> {
+        self.graphs.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Graph<N>> {
+        self.graphs.get_mut(key)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.graphs.contains_key(key)
+    }
+
+    /// All graphs, sorted by key
+    pub fn graphs(&self) -> impl Iterator<Item = (&str, &Graph<N>)> + '_ {
+        self.graphs.iter().map(|(key, graph)| (key.as_str(), graph))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> + '_ {
+        self.graphs.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.graphs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graphs.is_empty()
+    }
+}
+
+impl<N: Node> Default for GraphWorkspace<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: GraphInstanceNode> GraphWorkspace<N> {
+    /// The instance nodes of graph `key` and the keys of their graphs
+    pub fn instances(&self, key: &str) -> Vec<(NodeId, String)> {
+        let graph = self.graphs.get(key).expect("Graph does not exist");
+
+        graph
+            .nodes
+            .iter()
+            .filter_map(|(id, node)| Some((id, node.read().instance_of()?.to_string())))
+            .collect()
+    }
+
+    /// The keys of the graphs that graph `key` has instances of, sorted
+    pub fn dependencies(&self, key: &str) -> Vec<String> {
+        self.instances(key)
+            .into_iter()
+            .map(|(_, instance_of)| instance_of)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// The keys of the graphs that have instances of graph `key`, sorted
+    pub fn dependents(&self, key: &str) -> Vec<String> {
+        self.graphs
+            .keys()
+            .filter(|other| self.dependencies(other).iter().any(|dep| dep == key))
+            .cloned()
+            .collect()
+    }
+
+    /// The keys of all graphs, with every graph after the graphs it has
+    /// instances of. Graphs without dependencies between them are sorted by
+    /// key.
+    pub fn evaluation_order(&self) -> Result<Vec<String>, WorkspaceError> {
+        let mut dependencies = BTreeMap::new();
+
+        for key in self.graphs.keys() {
+            for (node, instance_of) in self.instances(key) {
+                if !self.graphs.contains_key(&instance_of) {
+                    return Err(WorkspaceError::MissingGraph {
+                        graph: key.clone(),
+                        node,
+                        instance_of,
+                    });
+                }
+            }
+
+            dependencies.insert(key.clone(), self.dependencies(key));
+        }
+
+        let mut order = Vec::with_capacity(self.graphs.len());
+
+        while !dependencies.is_empty() {
+            let ready = dependencies
+                .iter()
+                .filter(|(_, deps)| deps.iter().all(|dep| order.contains(dep)))
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+
+            if ready.is_empty() {
+                return Err(WorkspaceError::Cycle(dependencies.into_keys().collect()));
+            }
+
+            for key in ready {
+                dependencies.remove(&key);
+                order.push(key);
+            }
+        }
+
+        Ok(order)
+    }
+
+    /// Walk every graph once, in [evaluation order](Self::evaluation_order),
+    /// using `callback` to evaluate each node like [`GraphWalker::walk`]. The
+    /// outputs of all graphs evaluated before are passed along, so instance
+    /// nodes can read the outputs of their graph. Pauses requested by nodes
+    /// are ignored.
+    pub fn evaluate<F>(&self, callback: F) -> Result<WorkspaceResults<N>, WorkspaceError>
+    where
+        F: for<'a, 'b> Fn(&mut N, &mut GraphWalkContext<'a, 'b, N>, &WorkspaceResults<N>),
+    {
+        let mut results = WorkspaceResults {
+            caches: BTreeMap::new(),
+        };
+
+        for key in self.evaluation_order()? {
+            let mut walker = GraphWalker::new(&self.graphs[&key], None);
+            let evaluate_node = |node: &mut N, context: &mut GraphWalkContext<'_, '_, N>| {
+                callback(node, context, &results)
+            };
+
+            let mut status = walker.walk(evaluate_node);
+
+            while status != WalkStatus::Finished {
+                status = walker.resume(evaluate_node);
+            }
+
+            let cache = walker.release_cache();
+            results.caches.insert(key, cache);
+        }
+
+        Ok(results)
+    }
+}